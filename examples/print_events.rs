@@ -54,6 +54,7 @@ fn main() {
                     XmlEvent::Doctype { syntax } => {
                         println!(r#"Doctype("{}")"#, syntax.escape_debug());
                     },
+                    XmlEvent::EntityReference { name } => println!("EntityReference({name})"),
                 }
             },
             Err(e) => {
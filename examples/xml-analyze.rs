@@ -49,7 +49,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             },
             XmlEvent::EndDocument => println!("Document finished"),
             XmlEvent::ProcessingInstruction { .. } => processing_instructions += 1,
-            XmlEvent::Whitespace(_) => {}, // can't happen due to configuration
+            XmlEvent::Whitespace(_) | XmlEvent::EntityReference { .. } => {}, // can't happen due to configuration
             XmlEvent::Characters(s) => {
                 character_blocks += 1;
                 characters += s.len();
@@ -0,0 +1,240 @@
+//! Reformats XML files in place, or checks that they are formatted.
+//!
+//! ```text
+//! xmlfmt [--check] [--indent N | --tabs] [--crlf] [--sort-attributes] [FILE]...
+//! ```
+//!
+//! Without files, it formats the standard input to the standard output.
+#![forbid(unsafe_code)]
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::{env, fs};
+
+use xml::reader::XmlEvent;
+use xml::writer::AttributeOrder;
+use xml::{EmitterConfig, ParserConfig};
+
+const USAGE: &str = "\
+Usage: xmlfmt [OPTIONS] [FILE]...
+
+Reformats the XML files in place. Without files, formats the standard input
+to the standard output.
+
+Options:
+      --check            Don't write the files or the output, exit with 1 if any would change
+      --indent <N>       Indent with N spaces [default: 2]
+      --tabs             Indent with tabs
+      --crlf             Use CRLF line endings, also for line breaks in text
+      --sort-attributes  Write attributes in alphabetical order
+  -h, --help             Print this help
+";
+
+struct Options {
+    check: bool,
+    indent: String,
+    line_ending: &'static str,
+    sort_attributes: bool,
+    files: Vec<String>,
+}
+
+enum Failure {
+    /// Malformed input, with its rendered diagnostic
+    Syntax(String),
+    Io(String),
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        check: false,
+        indent: "  ".into(),
+        line_ending: "\n",
+        sort_attributes: false,
+        files: Vec::new(),
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => options.check = true,
+            "--indent" => {
+                let width = args.next().and_then(|n| n.parse().ok()).ok_or("--indent expects a number")?;
+                options.indent = " ".repeat(width);
+            },
+            "--tabs" => options.indent = "\t".into(),
+            "--crlf" => options.line_ending = "\r\n",
+            "--sort-attributes" => options.sort_attributes = true,
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                std::process::exit(0);
+            },
+            "--" => options.files.extend(args.by_ref()),
+            option if option.starts_with('-') => return Err(format!("unknown option {option}")),
+            _ => options.files.push(arg),
+        }
+    }
+    Ok(options)
+}
+
+/// Whether the document starts with an XML declaration, in UTF-8 or UTF-16
+fn has_declaration(source: &[u8]) -> bool {
+    let source = [&b"\xEF\xBB\xBF"[..], b"\xFF\xFE", b"\xFE\xFF"].iter()
+        .find_map(|bom| source.strip_prefix(*bom))
+        .unwrap_or(source);
+    // UTF-16 ASCII chars have a zero byte
+    let mut chars = source.iter().take(64).filter(|&&b| b != 0).skip_while(|b| b.is_ascii_whitespace());
+    b"<?xml".iter().all(|b| chars.next() == Some(b))
+}
+
+/// Converts line feeds not preceded by a carriage return to CRLF
+fn to_crlf(output: &[u8]) -> Vec<u8> {
+    let mut crlf = Vec::with_capacity(output.len() + output.len() / 32);
+    let mut prev = 0;
+    for &b in output {
+        if b == b'\n' && prev != b'\r' {
+            crlf.push(b'\r');
+        }
+        crlf.push(b);
+        prev = b;
+    }
+    crlf
+}
+
+fn format(source: &[u8], file_name: &str, options: &Options) -> Result<Vec<u8>, Failure> {
+    // a declaration is written only if there was one
+    let has_declaration = has_declaration(source);
+    let mut reader = ParserConfig::new()
+        .ignore_comments(false)
+        .keep_entity_references(true)
+        .create_reader(source);
+    let mut output = Vec::new();
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .preserve_mixed_content(true)
+        .indent_string(options.indent.clone())
+        .line_separator(options.line_ending)
+        .write_document_declaration(false)
+        .attribute_order(if options.sort_attributes { AttributeOrder::Alphabetical } else { AttributeOrder::Unchanged })
+        .create_writer(&mut output);
+
+    let syntax_error = |e: xml::reader::Error| {
+        let source = String::from_utf8_lossy(source);
+        Failure::Syntax(e.diagnostic(&source).file_name(file_name).to_string())
+    };
+    let write_error = |e: xml::writer::Error| Failure::Io(format!("{file_name}: {e}"));
+    loop {
+        let event = reader.next().map_err(syntax_error)?;
+        match &event {
+            XmlEvent::EndDocument => break,
+            XmlEvent::StartDocument { version, encoding, standalone } => {
+                if has_declaration {
+                    // the output is always UTF-8
+                    let encoding = if encoding.eq_ignore_ascii_case("utf-8") { encoding.as_str() } else { "UTF-8" };
+                    let event = xml::writer::XmlEvent::StartDocument { version: *version, encoding: Some(encoding), standalone: *standalone };
+                    writer.write(event).map_err(write_error)?;
+                }
+                continue;
+            },
+            XmlEvent::Doctype { syntax } => {
+                // DOCTYPE is written verbatim, without a line break before the next markup
+                if has_declaration {
+                    writer.write(xml::writer::XmlEvent::Characters(options.line_ending)).map_err(write_error)?;
+                }
+                writer.write(xml::writer::XmlEvent::Doctype(syntax)).map_err(write_error)?;
+                writer.write(xml::writer::XmlEvent::Characters(options.line_ending)).map_err(write_error)?;
+                continue;
+            },
+            _ => {},
+        }
+        if let Some(event) = event.as_writer_event() {
+            writer.write(event).map_err(write_error)?;
+        }
+    }
+    drop(writer);
+    output.extend_from_slice(options.line_ending.as_bytes());
+
+    // line breaks in text are kept as they were parsed, as LF
+    if options.line_ending == "\r\n" {
+        output = to_crlf(&output);
+    }
+    Ok(output)
+}
+
+/// Returns whether the input was formatted already
+fn format_stdin(options: &Options) -> Result<bool, Failure> {
+    let mut source = Vec::new();
+    io::stdin().read_to_end(&mut source).map_err(|e| Failure::Io(format!("<stdin>: {e}")))?;
+    let output = format(&source, "<stdin>", options)?;
+    if options.check {
+        return Ok(output == source);
+    }
+    io::stdout().write_all(&output).map_err(|e| Failure::Io(format!("<stdout>: {e}")))?;
+    Ok(true)
+}
+
+/// Returns whether the file was formatted already
+fn format_file(file_name: &str, options: &Options) -> Result<bool, Failure> {
+    let source = fs::read(file_name).map_err(|e| Failure::Io(format!("{file_name}: {e}")))?;
+    let output = format(&source, file_name, options)?;
+    if output == source {
+        return Ok(true);
+    }
+    if !options.check {
+        fs::write(file_name, output).map_err(|e| Failure::Io(format!("{file_name}: {e}")))?;
+    }
+    Ok(false)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("xmlfmt: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        },
+    };
+
+    let report = |failure: Failure| match failure {
+        Failure::Syntax(diagnostic) => eprint!("{diagnostic}"),
+        Failure::Io(e) => eprintln!("xmlfmt: {e}"),
+    };
+
+    if options.files.is_empty() {
+        return match format_stdin(&options) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => {
+                println!("<stdin>: not formatted");
+                ExitCode::from(1)
+            },
+            Err(failure) => {
+                report(failure);
+                ExitCode::from(2)
+            },
+        };
+    }
+
+    let mut failed = false;
+    let mut unformatted = false;
+    for file_name in &options.files {
+        match format_file(file_name, &options) {
+            Ok(true) => {},
+            Ok(false) => {
+                unformatted = true;
+                if options.check {
+                    println!("{file_name}: not formatted");
+                }
+            },
+            Err(failure) => {
+                failed = true;
+                report(failure);
+            },
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if unformatted && options.check {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,427 @@
+//! Contains a canonicalizer writing [`reader::XmlEvent`]s in a canonical form,
+//! as defined by [Canonical XML 1.0](https://www.w3.org/TR/2001/REC-xml-c14n-20010315),
+//! [Canonical XML 1.1](https://www.w3.org/TR/xml-c14n11/) and
+//! [Exclusive XML Canonicalization](https://www.w3.org/TR/xml-exc-c14n/).
+//!
+//! Documents that are equivalent, such as ones differing only in attribute order, quoting,
+//! namespace declarations or empty element syntax, have the same canonical form:
+//!
+//! * the XML declaration and DOCTYPE are removed, and so is whitespace outside of the root element;
+//! * empty elements are written as start and end tags, CDATA sections as escaped text;
+//! * namespace declarations are written only where they change, sorted by prefix,
+//!   followed by attributes sorted by namespace URI and local name;
+//! * text and attribute values are escaped using character references for `\r`, and in attributes
+//!   also for `\t` and `\n`. The parser has already normalized line endings to `\n`;
+//! * comments are removed, unless [`C14nConfig::with_comments`] is set.
+//!
+//! ```rust
+//! use xml::c14n::{canonicalize, C14nConfig};
+//!
+//! let mut output = Vec::new();
+//! canonicalize(r#"<?xml version="1.0"?>
+//! <doc b='2' a="1"><!-- c --><e xmlns:x="urn:x"/></doc>"#.as_bytes(), &mut output, &C14nConfig::new()).unwrap();
+//! assert_eq!(output, br#"<doc a="1" b="2"><e xmlns:x="urn:x"></e></doc>"#);
+//! ```
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::attribute::OwnedAttribute;
+use crate::name::OwnedName;
+use crate::namespace::{Namespace, NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XML_PREFIX, NS_XML_URI};
+use crate::reader::{self, EventReader, ParserConfig, XmlEvent};
+use crate::uri;
+
+/// A canonicalization algorithm
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Method {
+    /// Canonical XML 1.0, which declares all namespaces in scope on the root of the output
+    C14n10,
+    /// Canonical XML 1.1, which doesn't copy `xml:id` of omitted ancestors, and joins their
+    /// `xml:base` with the one of the element, resolving them like RFC 3986
+    C14n11,
+    /// Exclusive XML Canonicalization 1.0, which declares only namespaces used by elements and attributes
+    Exclusive,
+}
+
+impl Method {
+    /// The identifier of the algorithm, as used in XML Signature
+    #[must_use]
+    pub const fn uri(self, with_comments: bool) -> &'static str {
+        match (self, with_comments) {
+            (Self::C14n10, false) => "http://www.w3.org/TR/2001/REC-xml-c14n-20010315",
+            (Self::C14n10, true) => "http://www.w3.org/TR/2001/REC-xml-c14n-20010315#WithComments",
+            (Self::C14n11, false) => "http://www.w3.org/2006/12/xml-c14n11",
+            (Self::C14n11, true) => "http://www.w3.org/2006/12/xml-c14n11#WithComments",
+            (Self::Exclusive, false) => "http://www.w3.org/2001/10/xml-exc-c14n#",
+            (Self::Exclusive, true) => "http://www.w3.org/2001/10/xml-exc-c14n#WithComments",
+        }
+    }
+
+    /// Recognizes the identifier of an algorithm, and whether it keeps comments
+    #[must_use]
+    pub fn from_uri(uri: &str) -> Option<(Self, bool)> {
+        [Self::C14n10, Self::C14n11, Self::Exclusive].into_iter()
+            .flat_map(|method| [(method, false), (method, true)])
+            .find(|&(method, with_comments)| method.uri(with_comments) == uri)
+    }
+
+    /// `xml:*` attributes that an element gets from ancestors that aren't in the output
+    fn inherits(self, local_name: &str) -> bool {
+        match self {
+            Self::C14n10 => true,
+            Self::C14n11 => matches!(local_name, "lang" | "space"),
+            Self::Exclusive => false,
+        }
+    }
+}
+
+/// Canonicalizer configuration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct C14nConfig {
+    /// The algorithm. Default is [`Method::C14n10`].
+    pub method: Method,
+
+    /// Whether comments are kept. Default is false.
+    pub with_comments: bool,
+
+    /// Prefixes declared like in inclusive canonicalization when using [`Method::Exclusive`],
+    /// the `InclusiveNamespaces PrefixList`. The default namespace is `#default`. Default is empty.
+    pub inclusive_namespaces: Vec<String>,
+}
+
+impl C14nConfig {
+    /// Creates a configuration for Canonical XML 1.0 without comments.
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { method: Method::C14n10, with_comments: false, inclusive_namespaces: Vec::new() }
+    }
+
+    /// Sets the prefixes of [`C14nConfig::inclusive_namespaces`].
+    #[must_use]
+    pub fn inclusive_namespaces<I, S>(mut self, prefixes: I) -> Self where I: IntoIterator<Item = S>, S: Into<String> {
+        self.inclusive_namespaces = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl Default for C14nConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+gen_setters!(C14nConfig,
+    method: val Method,
+    with_comments: val bool
+);
+
+/// A failure to read or canonicalize a document.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The document is malformed or could not be read
+    Reader(reader::Error),
+    /// The output could not be written
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reader(e) => e.fmt(f),
+            Self::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Reader(e) => Some(e),
+            Self::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<reader::Error> for Error {
+    #[cold]
+    fn from(e: reader::Error) -> Self {
+        Self::Reader(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    #[cold]
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A parser configuration that keeps everything canonicalization needs: comments, whitespace and
+/// the original text.
+#[must_use]
+pub fn parser_config() -> ParserConfig {
+    ParserConfig::new()
+        .ignore_comments(false)
+        .trim_whitespace(false)
+        .cdata_to_characters(true)
+        .coalesce_characters(true)
+}
+
+/// Reads a document from the source and writes its canonical form to the sink.
+pub fn canonicalize<R: Read, W: Write>(source: R, sink: W, config: &C14nConfig) -> Result<(), Error> {
+    let mut reader = EventReader::new_with_config(source, parser_config());
+    let mut canonicalizer = Canonicalizer::new(sink, config.clone());
+    loop {
+        let event = reader.next()?;
+        canonicalizer.write(&event)?;
+        if event == XmlEvent::EndDocument {
+            return Ok(());
+        }
+    }
+}
+
+/// An element open in the input
+struct Frame {
+    /// The name as written in the end tag, or `None` if the element isn't in the output
+    output: Option<String>,
+    /// Namespace declarations in effect in the output, as of the nearest output ancestor
+    rendered: BTreeMap<String, String>,
+    /// `xml:*` attributes of omitted ancestors that output descendants have to copy
+    inherited: BTreeMap<String, String>,
+    /// `xml:base` of omitted ancestors, joined for the C14N 1.1 fixup
+    base: Option<String>,
+}
+
+/// Writes events in canonical form.
+///
+/// Events passed to [`write()`](Self::write) are in the output. A document subset, such as a single
+/// element with its descendants, is canonicalized by passing other events to [`skip()`](Self::skip),
+/// so that namespaces and `xml:*` attributes of omitted ancestors are taken into account.
+pub struct Canonicalizer<W: Write> {
+    sink: W,
+    config: C14nConfig,
+    stack: Vec<Frame>,
+    after_root: bool,
+}
+
+impl<W: Write> Canonicalizer<W> {
+    /// Creates a canonicalizer writing to the sink.
+    pub fn new(sink: W, config: C14nConfig) -> Self {
+        Self { sink, config, stack: Vec::new(), after_root: false }
+    }
+
+    /// Returns the sink.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+
+    /// Writes the event to the output.
+    ///
+    /// Canonical XML has no entity references, so an [`XmlEvent::EntityReference`] in the output
+    /// is an [`InvalidData`](io::ErrorKind::InvalidData) error. Read the document without
+    /// [`ParserConfig::keep_entity_references`] to have them expanded.
+    pub fn write(&mut self, event: &XmlEvent) -> io::Result<()> {
+        match event {
+            XmlEvent::StartElement { name, attributes, namespace } => {
+                let parent = self.stack.last();
+                let mut rendered = parent.map(|p| p.rendered.clone()).unwrap_or_default();
+                let declarations = self.declarations(name, attributes, namespace, &rendered);
+
+                let mut attributes: Vec<_> = attributes.iter().map(|a| (a.name.clone(), Cow::Borrowed(a.value.as_str()))).collect();
+                if let Some(parent) = parent.filter(|p| p.output.is_none()) {
+                    for (local_name, value) in &parent.inherited {
+                        if !attributes.iter().any(|(name, _)| is_xml_attribute(name) && name.local_name == *local_name) {
+                            attributes.push((OwnedName::qualified(local_name, NS_XML_URI, Some(NS_XML_PREFIX)), Cow::Borrowed(value)));
+                        }
+                    }
+                    if let Some(base) = &parent.base {
+                        match attributes.iter_mut().find(|(name, _)| is_xml_base(name)) {
+                            Some((_, value)) => *value = Cow::Owned(uri::resolve(base, value)),
+                            None => attributes.push((OwnedName::qualified("base", NS_XML_URI, Some(NS_XML_PREFIX)), Cow::Borrowed(base))),
+                        }
+                    }
+                }
+                attributes.sort_by(|(a, _), (b, _)| {
+                    (a.namespace_ref().unwrap_or(""), &a.local_name).cmp(&(b.namespace_ref().unwrap_or(""), &b.local_name))
+                });
+
+                let qname = name.borrow().to_repr();
+                write!(self.sink, "<{qname}")?;
+                for (prefix, uri) in declarations {
+                    if prefix.is_empty() {
+                        self.sink.write_all(b" xmlns=\"")?;
+                    } else {
+                        write!(self.sink, " xmlns:{prefix}=\"")?;
+                    }
+                    write_escaped(&mut self.sink, &uri, true)?;
+                    self.sink.write_all(b"\"")?;
+                    rendered.insert(prefix, uri);
+                }
+                for (name, value) in attributes {
+                    write!(self.sink, " {}=\"", name.borrow().repr_display())?;
+                    write_escaped(&mut self.sink, &value, true)?;
+                    self.sink.write_all(b"\"")?;
+                }
+                self.sink.write_all(b">")?;
+                self.stack.push(Frame { output: Some(qname), rendered, inherited: BTreeMap::new(), base: None });
+            },
+            XmlEvent::EndElement { .. } => {
+                if let Some(Frame { output: Some(qname), .. }) = self.pop() {
+                    write!(self.sink, "</{qname}>")?;
+                }
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) | XmlEvent::Whitespace(text) => {
+                if let Some(Frame { output: Some(_), .. }) = self.stack.last() {
+                    write_escaped(&mut self.sink, text, false)?;
+                }
+            },
+            XmlEvent::EntityReference { name } => {
+                // canonical XML has no references, but the replacement text is not known here
+                if let Some(Frame { output: Some(_), .. }) = self.stack.last() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                        format!("entity reference &{name}; can't be canonicalized without its replacement text")));
+                }
+            },
+            XmlEvent::Comment(text) => {
+                if self.config.with_comments {
+                    self.write_node(|sink| write!(sink, "<!--{text}-->"))?;
+                }
+            },
+            XmlEvent::ProcessingInstruction { name, data } => {
+                self.write_node(|sink| match data {
+                    Some(data) if !data.is_empty() => write!(sink, "<?{name} {data}?>"),
+                    _ => write!(sink, "<?{name}?>"),
+                })?;
+            },
+            XmlEvent::EndDocument => self.sink.flush()?,
+            XmlEvent::StartDocument { .. } | XmlEvent::Doctype { .. } => {},
+        }
+        Ok(())
+    }
+
+    /// Keeps track of an event that is not in the output.
+    pub fn skip(&mut self, event: &XmlEvent) {
+        match event {
+            XmlEvent::StartElement { attributes, .. } => {
+                let (rendered, mut inherited, mut base) = match self.stack.last() {
+                    Some(parent) if parent.output.is_none() => (parent.rendered.clone(), parent.inherited.clone(), parent.base.clone()),
+                    Some(parent) => (parent.rendered.clone(), BTreeMap::new(), None),
+                    None => (BTreeMap::new(), BTreeMap::new(), None),
+                };
+                for attribute in attributes {
+                    if is_xml_attribute(&attribute.name) && self.config.method.inherits(&attribute.name.local_name) {
+                        inherited.insert(attribute.name.local_name.clone(), attribute.value.clone());
+                    }
+                    if is_xml_base(&attribute.name) && self.config.method == Method::C14n11 {
+                        base = Some(match base {
+                            Some(base) => uri::resolve(&base, &attribute.value),
+                            None => attribute.value.clone(),
+                        });
+                    }
+                }
+                self.stack.push(Frame { output: None, rendered, inherited, base });
+            },
+            XmlEvent::EndElement { .. } => {
+                self.pop();
+            },
+            _ => {},
+        }
+    }
+
+    fn pop(&mut self) -> Option<Frame> {
+        let frame = self.stack.pop();
+        if self.stack.is_empty() {
+            self.after_root = true;
+        }
+        frame
+    }
+
+    /// Comments and processing instructions outside of the root element are on separate lines
+    fn write_node(&mut self, write: impl FnOnce(&mut W) -> io::Result<()>) -> io::Result<()> {
+        match self.stack.last() {
+            Some(Frame { output: None, .. }) => Ok(()),
+            Some(_) => write(&mut self.sink),
+            None if self.after_root => {
+                self.sink.write_all(b"\n")?;
+                write(&mut self.sink)
+            },
+            None => {
+                write(&mut self.sink)?;
+                self.sink.write_all(b"\n")
+            },
+        }
+    }
+
+    /// Namespace declarations of an output element, sorted by prefix
+    fn declarations(&self, name: &OwnedName, attributes: &[OwnedAttribute], namespace: &Namespace, rendered: &BTreeMap<String, String>) -> Vec<(String, String)> {
+        let prefixes: Vec<&str> = match self.config.method {
+            Method::C14n10 | Method::C14n11 => namespace.0.keys().map(String::as_str).collect(),
+            Method::Exclusive => {
+                let mut prefixes: Vec<&str> = std::iter::once(name.prefix_ref().unwrap_or(NS_NO_PREFIX))
+                    .chain(attributes.iter().filter_map(|a| a.name.prefix_ref()))
+                    .chain(self.config.inclusive_namespaces.iter().map(|p| if p == "#default" { NS_NO_PREFIX } else { p.as_str() }))
+                    .collect();
+                prefixes.sort_unstable();
+                prefixes.dedup();
+                prefixes
+            },
+        };
+        prefixes.into_iter()
+            .filter(|&prefix| prefix != NS_XML_PREFIX && prefix != NS_XMLNS_PREFIX)
+            .filter_map(|prefix| {
+                let uri = namespace.get(prefix).unwrap_or("");
+                let previous = rendered.get(prefix).map_or("", String::as_str);
+                // an undeclared prefix, or an empty default namespace that doesn't undo an outer one
+                if uri == previous || (uri.is_empty() && !prefix.is_empty()) {
+                    return None;
+                }
+                Some((prefix.to_owned(), uri.to_owned()))
+            })
+            .collect()
+    }
+}
+
+impl<W: Write> fmt::Debug for Canonicalizer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Canonicalizer").field("config", &self.config).finish_non_exhaustive()
+    }
+}
+
+fn is_xml_attribute(name: &OwnedName) -> bool {
+    name.namespace_ref() == Some(NS_XML_URI)
+}
+
+fn is_xml_base(name: &OwnedName) -> bool {
+    is_xml_attribute(name) && name.local_name == "base"
+}
+
+/// Escapes `&`, `<`, `>` and `\r` in text, and `&`, `<`, `"`, `\t`, `\n` and `\r` in attribute values
+fn write_escaped<W: Write>(sink: &mut W, text: &str, attribute: bool) -> io::Result<()> {
+    let mut rest = text;
+    while let Some(index) = rest.find(|c| match c {
+        '&' | '<' | '\r' => true,
+        '>' => !attribute,
+        '"' | '\t' | '\n' => attribute,
+        _ => false,
+    }) {
+        let bytes = rest.as_bytes();
+        sink.write_all(&bytes[..index])?;
+        sink.write_all(match bytes[index] {
+            b'&' => b"&amp;",
+            b'<' => b"&lt;",
+            b'>' => b"&gt;",
+            b'"' => b"&quot;",
+            b'\t' => b"&#x9;",
+            b'\n' => b"&#xA;",
+            _ => b"&#xD;",
+        })?;
+        rest = &rest[index + 1..];
+    }
+    sink.write_all(rest.as_bytes())
+}
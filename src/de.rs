@@ -0,0 +1,763 @@
+//! Contains a [serde](https://serde.rs) `Deserializer` reading from an [`EventReader`].
+//!
+//! This module is available with the `serde` feature. Elements are mapped to Rust types as follows:
+//!
+//! * a struct is read from an element: its attributes are fields named `@name`, its child elements
+//!   are fields named after the elements' local names, and its text content is the `$text` field;
+//! * a `Vec` field is filled from consecutive child elements with the same name;
+//! * a field named `$value` gets child elements that don't match any other field, which is useful
+//!   with a `Vec` of enums, whose variants are selected by element names;
+//! * numbers, strings and other primitives are read from text content or attribute values, and unit
+//!   enum variants from text as well;
+//! * the name of the root element is not checked.
+//!
+//! ```rust
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! struct Feed {
+//!     title: String,
+//!     #[serde(rename = "entry", default)]
+//!     entries: Vec<Entry>,
+//! }
+//!
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! struct Entry {
+//!     #[serde(rename = "@id")]
+//!     id: u32,
+//!     #[serde(rename = "$value", default)]
+//!     content: Vec<Content>,
+//! }
+//!
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! #[serde(rename_all = "lowercase")]
+//! enum Content {
+//!     Text(String),
+//!     Link { #[serde(rename = "@href")] href: String },
+//! }
+//!
+//! let feed: Feed = xml::de::from_str(r#"<feed>
+//!     <title>News</title>
+//!     <entry id="1"><text>Hello</text><link href="/1"/></entry>
+//!     <entry id="2"/>
+//! </feed>"#).unwrap();
+//!
+//! assert_eq!(feed.entries[0].content[1], Content::Link { href: "/1".into() });
+//! assert_eq!(feed.entries[1], Entry { id: 2, content: vec![] });
+//! ```
+
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+use serde::de::value::StringDeserializer;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+use crate::attribute::OwnedAttribute;
+use crate::common::{is_whitespace_char, Position, TextPosition};
+use crate::reader::{self, EventReader, ParserConfig, XmlEvent};
+
+/// Deserialization failure reason.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The document is malformed or could not be read
+    Reader(reader::Error),
+    /// The document doesn't match the structure of the type
+    Message(String),
+}
+
+/// A deserialization error, with a position in the document.
+#[derive(Debug)]
+pub struct Error {
+    /// Not known yet when the error comes from `Deserialize` impls
+    pos: Option<TextPosition>,
+    kind: ErrorKind,
+}
+
+impl Error {
+    /// Failure reason
+    #[must_use]
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    fn at(pos: TextPosition, msg: impl fmt::Display) -> Self {
+        Self { pos: Some(pos), kind: ErrorKind::Message(msg.to_string()) }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Reader(e) => e.fmt(f),
+            ErrorKind::Message(msg) => write!(f, "{} {msg}", self.position()),
+        }
+    }
+}
+
+impl Position for Error {
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.pos.unwrap_or_else(TextPosition::new)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Reader(e) => Some(e),
+            ErrorKind::Message(_) => None,
+        }
+    }
+}
+
+impl From<reader::Error> for Error {
+    #[cold]
+    fn from(e: reader::Error) -> Self {
+        Self { pos: Some(e.position()), kind: ErrorKind::Reader(e) }
+    }
+}
+
+impl de::Error for Error {
+    #[cold]
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self { pos: None, kind: ErrorKind::Message(msg.to_string()) }
+    }
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Deserializes an instance of `T` from a string of XML.
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
+    from_reader(s.as_bytes())
+}
+
+/// Deserializes an instance of `T` from XML read from the source.
+pub fn from_reader<T: DeserializeOwned, R: Read>(source: R) -> Result<T> {
+    let mut de = Deserializer::from_reader(source);
+    T::deserialize(&mut de).map_err(|e| de.locate(e))
+}
+
+/// A serde `Deserializer` pulling events from an [`EventReader`].
+pub struct Deserializer<R: Read> {
+    reader: EventReader<R>,
+    peeked: Option<XmlEvent>,
+}
+
+impl<R: Read> Deserializer<R> {
+    /// Creates a deserializer reading from the parser.
+    ///
+    /// Comments, processing instructions and whitespace-only text are skipped.
+    pub fn new(reader: EventReader<R>) -> Self {
+        Self { reader, peeked: None }
+    }
+
+    /// Creates a deserializer with a parser configured to trim whitespace and merge CDATA into text.
+    pub fn from_reader(source: R) -> Self {
+        let config = ParserConfig::new()
+            .trim_whitespace(true)
+            .whitespace_to_characters(true)
+            .cdata_to_characters(true)
+            .coalesce_characters(true);
+        Self::new(EventReader::new_with_config(source, config))
+    }
+
+    /// Returns the underlying parser.
+    pub fn into_inner(self) -> EventReader<R> {
+        self.reader
+    }
+
+    fn peek(&mut self) -> Result<&XmlEvent> {
+        if self.peeked.is_none() {
+            loop {
+                match self.reader.next()? {
+                    XmlEvent::StartDocument { .. } | XmlEvent::Doctype { .. } | XmlEvent::Comment(_) |
+                    XmlEvent::ProcessingInstruction { .. } | XmlEvent::Whitespace(_) => {},
+                    XmlEvent::CData(s) => {
+                        self.peeked = Some(XmlEvent::Characters(s));
+                        break;
+                    },
+                    event => {
+                        self.peeked = Some(event);
+                        break;
+                    },
+                }
+            }
+        }
+        Ok(self.peeked.as_ref().expect("peeked"))
+    }
+
+    fn next(&mut self) -> Result<XmlEvent> {
+        self.peek()?;
+        Ok(self.peeked.take().expect("peeked"))
+    }
+
+    fn error(&self, msg: impl fmt::Display) -> Error {
+        Error::at(self.reader.position(), msg)
+    }
+
+    /// Sets the current position on errors returned by `Deserialize` impls
+    fn locate(&self, mut e: Error) -> Error {
+        if e.pos.is_none() {
+            e.pos = Some(self.reader.position());
+        }
+        e
+    }
+
+    /// Reads the next text node
+    fn read_text(&mut self) -> Result<TextDeserializer> {
+        self.peek()?;
+        let pos = self.reader.position();
+        let mut text = String::new();
+        while let XmlEvent::Characters(_) = self.peek()? {
+            if let XmlEvent::Characters(s) = self.next()? {
+                text.push_str(&s);
+            }
+        }
+        Ok(TextDeserializer { text, pos })
+    }
+
+    /// Reads text content up to the end of the current element
+    fn read_element_text(&mut self) -> Result<TextDeserializer> {
+        let text = self.read_text()?;
+        match self.next()? {
+            XmlEvent::EndElement { .. } => Ok(text),
+            XmlEvent::StartElement { name, .. } => Err(self.error(format_args!("expected text, found element <{name}>"))),
+            _ => Err(self.error("unexpected end of document")),
+        }
+    }
+
+    /// Skips the rest of the current element, including its end
+    fn skip_element(&mut self) -> Result<()> {
+        let mut depth = 1_usize;
+        while depth > 0 {
+            match self.next()? {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => depth -= 1,
+                XmlEvent::EndDocument => return Err(self.error("unexpected end of document")),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the next start tag
+    fn open_element(&mut self) -> Result<ElementDeserializer<'_, R>> {
+        match self.next()? {
+            XmlEvent::StartElement { attributes, .. } => {
+                let pos = self.reader.position();
+                Ok(ElementDeserializer { de: self, attributes, pos })
+            },
+            XmlEvent::EndDocument => Err(self.error("no root element")),
+            _ => Err(self.error("expected an element")),
+        }
+    }
+}
+
+impl<R: Read> fmt::Debug for Deserializer<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deserializer").field("position", &self.reader.position()).finish_non_exhaustive()
+    }
+}
+
+macro_rules! forward_to_deserializer {
+    ($this:ident => $target:expr; $($method:ident($($arg:ident: $ty:ty),*))*) => {$(
+        #[inline]
+        fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+            let $this = self;
+            $target.$method($($arg,)* visitor)
+        }
+    )*};
+}
+
+/// Deserializes the root element
+impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    forward_to_deserializer! { this => this.open_element()?;
+        deserialize_any() deserialize_bool() deserialize_i8() deserialize_i16() deserialize_i32()
+        deserialize_i64() deserialize_i128() deserialize_u8() deserialize_u16() deserialize_u32()
+        deserialize_u64() deserialize_u128() deserialize_f32() deserialize_f64() deserialize_char()
+        deserialize_str() deserialize_string() deserialize_bytes() deserialize_byte_buf()
+        deserialize_option() deserialize_unit() deserialize_unit_struct(name: &'static str)
+        deserialize_newtype_struct(name: &'static str) deserialize_seq() deserialize_tuple(len: usize)
+        deserialize_tuple_struct(name: &'static str, len: usize) deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier() deserialize_ignored_any()
+    }
+}
+
+/// An element whose start tag has been read
+struct ElementDeserializer<'d, R: Read> {
+    de: &'d mut Deserializer<R>,
+    attributes: Vec<OwnedAttribute>,
+    /// Position of the start tag
+    pos: TextPosition,
+}
+
+impl<R: Read> ElementDeserializer<'_, R> {
+    fn text(self) -> Result<TextDeserializer> {
+        self.de.read_element_text()
+    }
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for ElementDeserializer<'_, R> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.attributes.is_empty() && !matches!(self.de.peek()?, XmlEvent::StartElement { .. }) {
+            self.deserialize_string(visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    forward_to_deserializer! { this => this.text()?;
+        deserialize_bool() deserialize_i8() deserialize_i16() deserialize_i32() deserialize_i64()
+        deserialize_i128() deserialize_u8() deserialize_u16() deserialize_u32() deserialize_u64()
+        deserialize_u128() deserialize_f32() deserialize_f64() deserialize_char() deserialize_str()
+        deserialize_string() deserialize_bytes() deserialize_byte_buf() deserialize_identifier()
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.de.skip_element()?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Every child is an item
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = visitor.visit_seq(ChildSeq { de: &mut *self.de, mode: Children::Any })?;
+        self.de.skip_element()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_struct("", &[], visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_map(ElementMap {
+            de: self.de,
+            attributes: self.attributes.into_iter(),
+            pos: self.pos,
+            fields,
+            value: None,
+        })
+    }
+
+    /// The variant is the name of the only child element, or the text content for unit variants
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        if let XmlEvent::StartElement { .. } = self.de.peek()? {
+            let value = ChildDeserializer { de: &mut *self.de, mode: Children::Content(&[]), repeated: false }
+                .deserialize_enum(name, variants, visitor)?;
+            self.de.skip_element()?;
+            Ok(value)
+        } else {
+            self.text()?.deserialize_enum(name, variants, visitor)
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+}
+
+/// Which children of an element are deserialized together
+#[derive(Copy, Clone)]
+enum Children<'n> {
+    /// Elements with this local name
+    Named(&'n str),
+    /// Text and elements not named in the fields of a struct
+    Content(&'static [&'static str]),
+    /// All elements and text
+    Any,
+}
+
+impl Children<'_> {
+    fn matches(self, event: &XmlEvent) -> bool {
+        match (self, event) {
+            (Self::Named(n), XmlEvent::StartElement { name, .. }) => name.local_name == n,
+            (Self::Content(fields), XmlEvent::StartElement { name, .. }) => !fields.contains(&name.local_name.as_str()),
+            (Self::Any, XmlEvent::StartElement { .. }) | (Self::Content(_) | Self::Any, XmlEvent::Characters(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Next child of the current element, not read yet
+struct ChildDeserializer<'d, 'n, R: Read> {
+    de: &'d mut Deserializer<R>,
+    mode: Children<'n>,
+    /// Whether a sequence is made of all matching consecutive children
+    repeated: bool,
+}
+
+impl<'d, R: Read> ChildDeserializer<'d, '_, R> {
+    fn open(self) -> Result<Either<ElementDeserializer<'d, R>, TextDeserializer>> {
+        if let XmlEvent::Characters(_) = self.de.peek()? {
+            self.de.read_text().map(Either::Text)
+        } else {
+            self.de.open_element().map(Either::Element)
+        }
+    }
+}
+
+enum Either<E, T> {
+    Element(E),
+    Text(T),
+}
+
+macro_rules! forward_to_either {
+    ($($method:ident($($arg:ident: $ty:ty),*))*) => {$(
+        #[inline]
+        fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+            match self.open()? {
+                Either::Element(e) => e.$method($($arg,)* visitor),
+                Either::Text(t) => t.$method($($arg,)* visitor),
+            }
+        }
+    )*};
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for ChildDeserializer<'_, '_, R> {
+    type Error = Error;
+
+    forward_to_either! {
+        deserialize_any() deserialize_bool() deserialize_i8() deserialize_i16() deserialize_i32()
+        deserialize_i64() deserialize_i128() deserialize_u8() deserialize_u16() deserialize_u32()
+        deserialize_u64() deserialize_u128() deserialize_f32() deserialize_f64() deserialize_char()
+        deserialize_str() deserialize_string() deserialize_bytes() deserialize_byte_buf()
+        deserialize_unit() deserialize_unit_struct(name: &'static str)
+        deserialize_tuple_struct(name: &'static str, len: usize) deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_identifier() deserialize_ignored_any()
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.repeated {
+            visitor.visit_seq(ChildSeq { de: self.de, mode: self.mode })
+        } else {
+            match self.open()? {
+                Either::Element(e) => e.deserialize_seq(visitor),
+                Either::Text(t) => t.deserialize_seq(visitor),
+            }
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        match (self.mode, self.de.peek()?) {
+            (Children::Content(_) | Children::Any, XmlEvent::StartElement { name, .. }) => {
+                let variant = name.local_name.clone();
+                let element = self.de.open_element()?;
+                visitor.visit_enum(VariantElement { variant, element })
+            },
+            _ => match self.open()? {
+                Either::Element(e) => e.deserialize_enum(name, variants, visitor),
+                Either::Text(t) => t.deserialize_enum(name, variants, visitor),
+            },
+        }
+    }
+}
+
+/// Consecutive children as items of a sequence
+struct ChildSeq<'d, 'n, R: Read> {
+    de: &'d mut Deserializer<R>,
+    mode: Children<'n>,
+}
+
+impl<'de, R: Read> de::SeqAccess<'de> for ChildSeq<'_, '_, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if !self.mode.matches(self.de.peek()?) {
+            return Ok(None);
+        }
+        let item = ChildDeserializer { de: &mut *self.de, mode: self.mode, repeated: false };
+        seed.deserialize(item).map(Some).map_err(|e| self.de.locate(e))
+    }
+}
+
+/// Enum variant selected by the element name
+struct VariantElement<'d, R: Read> {
+    variant: String,
+    element: ElementDeserializer<'d, R>,
+}
+
+impl<'de, 'd, R: Read> de::EnumAccess<'de> for VariantElement<'d, R> {
+    type Error = Error;
+    type Variant = ElementDeserializer<'d, R>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant: StringDeserializer<Error> = self.variant.into_deserializer();
+        Ok((seed.deserialize(variant)?, self.element))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for ElementDeserializer<'_, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.de.skip_element()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
+
+/// What the last key returned by [`ElementMap`] refers to
+enum MapValue {
+    Attribute(String),
+    Element(String),
+    Content,
+    Text,
+}
+
+/// Attributes, children and text of an element as fields of a struct or entries of a map
+struct ElementMap<'d, R: Read> {
+    de: &'d mut Deserializer<R>,
+    attributes: std::vec::IntoIter<OwnedAttribute>,
+    /// Position of the start tag, for errors in attribute values
+    pos: TextPosition,
+    /// Names of fields of the struct, empty for maps
+    fields: &'static [&'static str],
+    value: Option<MapValue>,
+}
+
+impl<'de, R: Read> de::MapAccess<'de> for ElementMap<'_, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let has_field = |name: &str| self.fields.contains(&name);
+        let (key, value) = if let Some(attr) = self.attributes.next() {
+            (format!("@{}", attr.name.local_name), MapValue::Attribute(attr.value))
+        } else {
+            match self.de.peek()? {
+                XmlEvent::StartElement { name, .. } => {
+                    let local = &name.local_name;
+                    if !self.fields.is_empty() && !has_field(local) && has_field("$value") {
+                        ("$value".into(), MapValue::Content)
+                    } else {
+                        (local.clone(), MapValue::Element(local.clone()))
+                    }
+                },
+                XmlEvent::Characters(_) if has_field("$value") && !has_field("$text") => ("$value".into(), MapValue::Content),
+                XmlEvent::Characters(_) => ("$text".into(), MapValue::Text),
+                XmlEvent::EndElement { .. } => {
+                    self.de.next()?;
+                    return Ok(None);
+                },
+                _ => return Err(self.de.error("unexpected end of document")),
+            }
+        };
+        self.value = Some(value);
+        let key: StringDeserializer<Error> = key.into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let result = match self.value.take() {
+            Some(MapValue::Attribute(text)) => seed.deserialize(TextDeserializer { text, pos: self.pos }),
+            Some(MapValue::Element(name)) => {
+                seed.deserialize(ChildDeserializer { de: &mut *self.de, mode: Children::Named(&name), repeated: true })
+            },
+            Some(MapValue::Content) => {
+                seed.deserialize(ChildDeserializer { de: &mut *self.de, mode: Children::Content(self.fields), repeated: true })
+            },
+            Some(MapValue::Text) => {
+                let text = self.de.read_text()?;
+                seed.deserialize(text)
+            },
+            None => return Err(self.de.error("value requested before key")),
+        };
+        result.map_err(|e| self.de.locate(e))
+    }
+}
+
+/// Text content or an attribute value
+struct TextDeserializer {
+    text: String,
+    pos: TextPosition,
+}
+
+impl TextDeserializer {
+    fn parse<T: FromStr>(&self, what: &str) -> Result<T> where T::Err: fmt::Display {
+        self.text.trim_matches(is_whitespace_char).parse()
+            .map_err(|e| Error::at(self.pos, format_args!("invalid {what} '{}': {e}", self.text)))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            visitor.$visit(self.parse(stringify!($visit).trim_start_matches("visit_"))?)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for TextDeserializer {
+    type Error = Error;
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.text)
+    }
+
+    /// Accepts the lexical forms of `xs:boolean`
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.text.trim_matches(is_whitespace_char) {
+            "true" | "1" => visitor.visit_bool(true),
+            "false" | "0" => visitor.visit_bool(false),
+            _ => Err(Error::at(self.pos, format_args!("invalid bool '{}'", self.text))),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut chars = self.text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(Error::at(self.pos, format_args!("expected a single character, found '{}'", self.text))),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.text)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.text)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.text.into_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.text.into_bytes())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Whitespace-separated list, like `xs:list`
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let pos = self.pos;
+        let items = self.text.split(is_whitespace_char)
+            .filter(|s| !s.is_empty())
+            .map(|s| Self { text: s.into(), pos })
+            .collect::<Vec<_>>();
+        visitor.visit_seq(de::value::SeqDeserializer::new(items.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::at(self.pos, format_args!("expected an element, found text '{}'", self.text)))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    /// Unit variants only
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        let pos = self.pos;
+        let variant: StringDeserializer<Error> = self.text.trim_matches(is_whitespace_char).to_owned().into_deserializer();
+        visitor.visit_enum(variant).map_err(|mut e| {
+            e.pos.get_or_insert(pos);
+            e
+        })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.text)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for TextDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
@@ -0,0 +1,514 @@
+//! Contains a simple in-memory tree representation of XML documents.
+//!
+//! The tree is built from the events of a regular [`EventReader`], so all
+//! [`ParserConfig`] options (whitespace trimming, comment handling, entity limits, etc.)
+//! apply to it as well. The tree can be modified and then written back through an
+//! [`EventWriter`].
+//!
+//! ```rust
+//! use xml::dom::Document;
+//!
+//! let mut doc: Document = r#"<list><item id="1">one</item></list>"#.parse().unwrap();
+//!
+//! let root = doc.root_mut().unwrap();
+//! assert_eq!(root.get_child("item").unwrap().attribute("id"), Some("1"));
+//!
+//! root.get_child_mut("item").unwrap().set_attribute("id", "2");
+//! root.push_child(xml::dom::Element::new("item").with_text("two"));
+//!
+//! let mut output = Vec::new();
+//! doc.write_to(&mut output).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     r#"<?xml version="1.0" encoding="UTF-8"?><list><item id="2">one</item><item>two</item></list>"#
+//! );
+//! ```
+
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use crate::attribute::OwnedAttribute;
+use crate::common::XmlVersion;
+use crate::name::{Name, OwnedName};
+use crate::namespace::Namespace;
+use crate::reader::{self, EventReader, ParserConfig};
+use crate::writer::{self, EmitterConfig, EventWriter};
+
+pub mod compact;
+
+/// A node of the document tree.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Node {
+    /// An element with its attributes and children.
+    Element(Element),
+
+    /// Character data. Both `Characters` and `Whitespace` reader events become text nodes.
+    Text(String),
+
+    /// Content of a CDATA section.
+    CData(String),
+
+    /// A comment. Comments are only present if the parser was configured not to ignore them.
+    Comment(String),
+
+    /// A processing instruction.
+    ProcessingInstruction {
+        /// Processing instruction target.
+        name: String,
+
+        /// Processing instruction content.
+        data: Option<String>,
+    },
+
+    /// A reference to an entity. References are only present if the parser was configured
+    /// to keep them, see [`ParserConfig::keep_entity_references`](crate::ParserConfig::keep_entity_references).
+    EntityReference(String),
+}
+
+impl Node {
+    /// Returns the element if this node is an element.
+    #[inline]
+    #[must_use]
+    pub fn as_element(&self) -> Option<&Element> {
+        match self {
+            Self::Element(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Returns the element mutably if this node is an element.
+    #[inline]
+    pub fn as_element_mut(&mut self) -> Option<&mut Element> {
+        match self {
+            Self::Element(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Returns the content of text and CDATA nodes.
+    #[inline]
+    #[must_use]
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(s) | Self::CData(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Writes this node and all of its descendants to the writer.
+    pub fn write<W: Write>(&self, writer: &mut EventWriter<W>) -> writer::Result<()> {
+        match self {
+            Self::Element(e) => e.write(writer),
+            Self::Text(s) => writer.write(writer::XmlEvent::Characters(s)),
+            Self::CData(s) => writer.write(writer::XmlEvent::CData(s)),
+            Self::Comment(s) => writer.write(writer::XmlEvent::Comment(s)),
+            Self::ProcessingInstruction { name, data } =>
+                writer.write(writer::XmlEvent::processing_instruction(name, data.as_deref())),
+            Self::EntityReference(name) => writer.write(writer::XmlEvent::EntityReference(name)),
+        }
+    }
+}
+
+impl From<Element> for Node {
+    #[inline]
+    fn from(e: Element) -> Self {
+        Self::Element(e)
+    }
+}
+
+impl From<String> for Node {
+    #[inline]
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<&str> for Node {
+    #[inline]
+    fn from(s: &str) -> Self {
+        Self::Text(s.into())
+    }
+}
+
+/// An XML element.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Element {
+    /// Qualified name of the element.
+    pub name: OwnedName,
+
+    /// Attributes of the element, in document order.
+    pub attributes: Vec<OwnedAttribute>,
+
+    /// Namespace mappings in scope of this element.
+    ///
+    /// For parsed documents this is the complete mapping, including the bindings
+    /// inherited from ancestors. When writing, only the mappings which are not
+    /// already in scope are emitted as `xmlns` attributes.
+    pub namespace: Namespace,
+
+    /// Child nodes, in document order.
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    /// Creates an element with the given name, without attributes or children.
+    pub fn new<'a, N: Into<Name<'a>>>(name: N) -> Self {
+        Self {
+            name: name.into().to_owned(),
+            attributes: Vec::new(),
+            namespace: Namespace::empty(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Sets an attribute and returns the updated element. See [`Element::set_attribute`].
+    #[must_use]
+    pub fn with_attribute<'a, N: Into<Name<'a>>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.set_attribute(name, value);
+        self
+    }
+
+    /// Appends a child node and returns the updated element.
+    #[must_use]
+    pub fn with_child<N: Into<Node>>(mut self, child: N) -> Self {
+        self.push_child(child);
+        self
+    }
+
+    /// Appends a text node and returns the updated element.
+    #[must_use]
+    pub fn with_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.children.push(Node::Text(text.into()));
+        self
+    }
+
+    /// Adds a namespace mapping to this element and returns the updated element.
+    #[must_use]
+    pub fn with_namespace<P: Into<String>, U: Into<String>>(mut self, prefix: P, uri: U) -> Self {
+        self.namespace.force_put(prefix, uri);
+        self
+    }
+
+    /// Returns the value of an attribute with the given local name and no namespace.
+    #[must_use]
+    pub fn attribute(&self, local_name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|a| a.name.namespace.is_none() && a.name.local_name == local_name)
+            .map(|a| &*a.value)
+    }
+
+    /// Returns the value of an attribute with the given local name and namespace URI.
+    #[must_use]
+    pub fn attribute_ns(&self, local_name: &str, namespace: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|a| a.name.namespace_ref() == Some(namespace) && a.name.local_name == local_name)
+            .map(|a| &*a.value)
+    }
+
+    /// Sets the value of an attribute, replacing an existing attribute with the same name.
+    ///
+    /// Attributes are matched by their local name and prefix. New attributes are added
+    /// after existing ones.
+    pub fn set_attribute<'a, N: Into<Name<'a>>, V: Into<String>>(&mut self, name: N, value: V) {
+        let name = name.into();
+        let value = value.into();
+        match self.attributes.iter_mut().find(|a| a.name.local_name == name.local_name && a.name.prefix_ref() == name.prefix) {
+            Some(attr) => attr.value = value,
+            None => self.attributes.push(OwnedAttribute::new(name.to_owned(), value)),
+        }
+    }
+
+    /// Removes an attribute with the given local name and no prefix, returning its value.
+    pub fn remove_attribute(&mut self, local_name: &str) -> Option<String> {
+        let index = self.attributes.iter()
+            .position(|a| a.name.prefix.is_none() && a.name.local_name == local_name)?;
+        Some(self.attributes.remove(index).value)
+    }
+
+    /// Iterates over child elements, skipping other kinds of nodes.
+    pub fn child_elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(Node::as_element)
+    }
+
+    /// Iterates mutably over child elements, skipping other kinds of nodes.
+    pub fn child_elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+        self.children.iter_mut().filter_map(Node::as_element_mut)
+    }
+
+    /// Returns the first child element with the given local name.
+    #[must_use]
+    pub fn get_child(&self, local_name: &str) -> Option<&Element> {
+        self.child_elements().find(|e| e.name.local_name == local_name)
+    }
+
+    /// Returns the first child element with the given local name and namespace URI.
+    #[must_use]
+    pub fn get_child_ns(&self, local_name: &str, namespace: &str) -> Option<&Element> {
+        self.child_elements()
+            .find(|e| e.name.local_name == local_name && e.name.namespace_ref() == Some(namespace))
+    }
+
+    /// Returns the first child element with the given local name, mutably.
+    pub fn get_child_mut(&mut self, local_name: &str) -> Option<&mut Element> {
+        self.child_elements_mut().find(|e| e.name.local_name == local_name)
+    }
+
+    /// Iterates over all descendant elements in document order, not including this element.
+    #[must_use]
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants { stack: vec![self.children.iter()] }
+    }
+
+    /// Returns the concatenated content of all text and CDATA descendants.
+    #[must_use]
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.collect_text(&mut text);
+        text
+    }
+
+    fn collect_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                Node::Element(e) => e.collect_text(out),
+                Node::Text(s) | Node::CData(s) => out.push_str(s),
+                Node::Comment(_) | Node::ProcessingInstruction { .. } | Node::EntityReference(_) => {},
+            }
+        }
+    }
+
+    /// Appends a child node.
+    pub fn push_child<N: Into<Node>>(&mut self, child: N) {
+        self.children.push(child.into());
+    }
+
+    /// Inserts a child node at the given position.
+    ///
+    /// Panics if `index > children.len()`.
+    #[track_caller]
+    pub fn insert_child<N: Into<Node>>(&mut self, index: usize, child: N) {
+        self.children.insert(index, child.into());
+    }
+
+    /// Removes and returns the child node at the given position.
+    ///
+    /// Panics if `index` is out of bounds.
+    #[track_caller]
+    pub fn remove_child(&mut self, index: usize) -> Node {
+        self.children.remove(index)
+    }
+
+    /// Writes this element and all of its descendants to the writer.
+    pub fn write<W: Write>(&self, writer: &mut EventWriter<W>) -> writer::Result<()> {
+        writer.write(writer::XmlEvent::StartElement {
+            name: self.name.borrow(),
+            attributes: self.attributes.iter().map(|a| a.borrow()).collect(),
+            namespace: self.namespace.borrow(),
+        })?;
+        for child in &self.children {
+            child.write(writer)?;
+        }
+        writer.write(writer::XmlEvent::EndElement { name: Some(self.name.borrow()) })
+    }
+
+    /// Builds an element from the rest of the current element of the reader.
+    ///
+    /// `start` is the `StartElement` event just returned by the reader. The reader is left
+    /// after the matching end tag, see [`EventReader::read_subtree_events`].
+    ///
+    /// Panics if `start` is not a `StartElement` event.
+    #[track_caller]
+    pub fn read_from<R: Read>(reader: &mut EventReader<R>, start: reader::XmlEvent) -> reader::Result<Self> {
+        let reader::XmlEvent::StartElement { name, attributes, namespace } = start else {
+            panic!("Element::read_from expects a StartElement event");
+        };
+        let mut stack = vec![Self { name, attributes, namespace, children: Vec::new() }];
+
+        for event in reader.read_subtree_events()? {
+            let node = match event {
+                reader::XmlEvent::StartElement { name, attributes, namespace } => {
+                    stack.push(Self { name, attributes, namespace, children: Vec::new() });
+                    continue;
+                },
+                reader::XmlEvent::EndElement { .. } => match stack.pop() {
+                    Some(element) if stack.is_empty() => return Ok(element),
+                    Some(element) => Node::Element(element),
+                    None => break,
+                },
+                reader::XmlEvent::Characters(s) | reader::XmlEvent::Whitespace(s) => Node::Text(s),
+                reader::XmlEvent::CData(s) => Node::CData(s),
+                reader::XmlEvent::Comment(s) => Node::Comment(s),
+                reader::XmlEvent::ProcessingInstruction { name, data } => Node::ProcessingInstruction { name, data },
+                reader::XmlEvent::EntityReference { name } => Node::EntityReference(name),
+                _ => continue,
+            };
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(node);
+            }
+        }
+        unreachable!("read_subtree_events returns the events up to the matching end tag")
+    }
+}
+
+/// An iterator over descendant elements, created by [`Element::descendants`].
+pub struct Descendants<'a> {
+    stack: Vec<std::slice::Iter<'a, Node>>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = &'a Element;
+
+    fn next(&mut self) -> Option<&'a Element> {
+        loop {
+            let iter = self.stack.last_mut()?;
+            match iter.next() {
+                Some(Node::Element(e)) => {
+                    self.stack.push(e.children.iter());
+                    return Some(e);
+                },
+                Some(_) => {},
+                None => { self.stack.pop(); },
+            }
+        }
+    }
+}
+
+/// A whole XML document.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Document {
+    /// XML version from the document declaration.
+    pub version: XmlVersion,
+
+    /// Encoding from the document declaration.
+    pub encoding: String,
+
+    /// Standalone flag from the document declaration.
+    pub standalone: Option<bool>,
+
+    /// The whole `<!DOCTYPE>` markup, if the document had one.
+    pub doctype: Option<String>,
+
+    /// Top-level nodes: the root element and any comments, processing instructions
+    /// (and whitespace, if not ignored) around it.
+    pub children: Vec<Node>,
+}
+
+impl Document {
+    /// Creates a document with the given root element.
+    #[must_use]
+    pub fn new(root: Element) -> Self {
+        Self {
+            version: XmlVersion::Version10,
+            encoding: "UTF-8".into(),
+            standalone: None,
+            doctype: None,
+            children: vec![Node::Element(root)],
+        }
+    }
+
+    /// Parses a document from the stream using the default parser configuration.
+    ///
+    /// The source should be buffered, see [`EventReader::new`].
+    pub fn parse<R: Read>(source: R) -> reader::Result<Self> {
+        Self::from_reader(EventReader::new(source))
+    }
+
+    /// Parses a document from the stream using the given parser configuration.
+    pub fn parse_with_config<R: Read>(source: R, config: impl Into<ParserConfig>) -> reader::Result<Self> {
+        Self::from_reader(EventReader::new_with_config(source, config))
+    }
+
+    /// Builds a document from the remaining events of the reader.
+    ///
+    /// The reader should not have returned any events yet.
+    pub fn from_reader<R: Read>(mut reader: EventReader<R>) -> reader::Result<Self> {
+        let mut doc = Self {
+            version: XmlVersion::Version10,
+            encoding: "UTF-8".into(),
+            standalone: None,
+            doctype: None,
+            children: Vec::new(),
+        };
+        let mut stack: Vec<Element> = Vec::new();
+
+        loop {
+            let node = match reader.next()? {
+                reader::XmlEvent::StartDocument { version, encoding, standalone } => {
+                    doc.version = version;
+                    doc.encoding = encoding;
+                    doc.standalone = standalone;
+                    continue;
+                },
+                reader::XmlEvent::EndDocument => break,
+                reader::XmlEvent::Doctype { syntax } => {
+                    doc.doctype = Some(syntax);
+                    continue;
+                },
+                reader::XmlEvent::StartElement { name, attributes, namespace } => {
+                    stack.push(Element { name, attributes, namespace, children: Vec::new() });
+                    continue;
+                },
+                reader::XmlEvent::EndElement { .. } => match stack.pop() {
+                    Some(element) => Node::Element(element),
+                    None => continue,
+                },
+                reader::XmlEvent::Characters(s) | reader::XmlEvent::Whitespace(s) => Node::Text(s),
+                reader::XmlEvent::CData(s) => Node::CData(s),
+                reader::XmlEvent::Comment(s) => Node::Comment(s),
+                reader::XmlEvent::ProcessingInstruction { name, data } => Node::ProcessingInstruction { name, data },
+                reader::XmlEvent::EntityReference { name } => Node::EntityReference(name),
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => doc.children.push(node),
+            }
+        }
+        Ok(doc)
+    }
+
+    /// Returns the first top-level element.
+    #[must_use]
+    pub fn root(&self) -> Option<&Element> {
+        self.children.iter().find_map(Node::as_element)
+    }
+
+    /// Returns the first top-level element, mutably.
+    pub fn root_mut(&mut self) -> Option<&mut Element> {
+        self.children.iter_mut().find_map(Node::as_element_mut)
+    }
+
+    /// Writes the document, including the XML declaration, to the writer.
+    pub fn write<W: Write>(&self, writer: &mut EventWriter<W>) -> writer::Result<()> {
+        writer.write(writer::XmlEvent::StartDocument {
+            version: self.version,
+            encoding: Some(&self.encoding),
+            standalone: self.standalone,
+        })?;
+        if let Some(doctype) = &self.doctype {
+            writer.write(writer::XmlEvent::Doctype(doctype))?;
+        }
+        for child in &self.children {
+            child.write(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the document to the sink using the default emitter configuration.
+    pub fn write_to<W: Write>(&self, sink: W) -> writer::Result<()> {
+        self.write_with_config(sink, EmitterConfig::new())
+    }
+
+    /// Writes the document to the sink using the given emitter configuration.
+    pub fn write_with_config<W: Write>(&self, sink: W, config: EmitterConfig) -> writer::Result<()> {
+        self.write(&mut EventWriter::new_with_config(sink, config))
+    }
+}
+
+impl FromStr for Document {
+    type Err = reader::Error;
+
+    /// Parses a document from a string using the default parser configuration.
+    fn from_str(s: &str) -> reader::Result<Self> {
+        Self::parse(s.as_bytes())
+    }
+}
@@ -0,0 +1,704 @@
+//! Contains a compact read-only document representation for large files.
+//!
+//! [`CompactDocument`] stores all nodes in a single `Vec` with `u32` links between them,
+//! all text in one shared buffer, and each distinct name only once. Nodes are referred to by
+//! [`NodeId`]s, which are assigned in document order, and navigated with [`NodeRef`] handles.
+//!
+//! ```rust
+//! use xml::dom::compact::CompactDocument;
+//!
+//! let doc: CompactDocument = "<catalog><book id='a'/><book id='b'>Title</book></catalog>".parse().unwrap();
+//! let catalog = doc.root_element().unwrap();
+//!
+//! let ids: Vec<_> = catalog.children().filter_map(|book| book.attribute("id")).collect();
+//! assert_eq!(ids, ["a", "b"]);
+//!
+//! let second = catalog.last_child().unwrap();
+//! assert_eq!(second.prev_sibling().unwrap().attribute("id"), Some("a"));
+//! assert_eq!(second.text_content(), "Title");
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+use crate::attribute::Attribute;
+use crate::common::XmlVersion;
+use crate::name::{Name, OwnedName};
+use crate::namespace::Namespace;
+use crate::reader::{self, EventReader, ParserConfig, XmlEvent};
+
+const NONE: u32 = u32::MAX;
+
+/// Identifies a node of a [`CompactDocument`].
+///
+/// Identifiers are assigned in document order, so comparing them compares positions
+/// of nodes in the document. The document node always has the smallest identifier.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// Returns the position of the node in the document's node list.
+    #[inline]
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    #[inline]
+    fn from_raw(raw: u32) -> Option<Self> {
+        if raw == NONE { None } else { Some(Self(raw)) }
+    }
+}
+
+/// Kind of a node in a [`CompactDocument`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum NodeKind {
+    /// The document itself, parent of the root element.
+    Document,
+    /// An element.
+    Element,
+    /// Character data, including whitespace.
+    Text,
+    /// Content of a CDATA section.
+    CData,
+    /// A comment.
+    Comment,
+    /// A processing instruction.
+    ProcessingInstruction,
+    /// A reference to an entity that has not been expanded.
+    EntityReference,
+}
+
+#[derive(Copy, Clone)]
+struct NodeData {
+    kind: NodeKind,
+    parent: u32,
+    first_child: u32,
+    last_child: u32,
+    prev_sibling: u32,
+    next_sibling: u32,
+    /// Elements: name index. Others: start of text in the buffer.
+    a: u32,
+    /// Elements: first attribute index. Others: length of text (PI: length of target).
+    b: u32,
+    /// Elements: number of attributes. PIs: length of data, or `NONE`.
+    c: u32,
+    /// Index of the namespace scope.
+    ns: u32,
+}
+
+#[derive(Copy, Clone)]
+struct AttrData {
+    name: u32,
+    start: u32,
+    len: u32,
+}
+
+/// A read-only XML document with a small memory footprint.
+///
+/// See the [module documentation](self) for an overview.
+pub struct CompactDocument {
+    nodes: Vec<NodeData>,
+    attributes: Vec<AttrData>,
+    names: Vec<OwnedName>,
+    namespaces: Vec<Namespace>,
+    text: String,
+
+    version: XmlVersion,
+    encoding: String,
+    standalone: Option<bool>,
+    doctype: Option<String>,
+}
+
+impl CompactDocument {
+    /// Parses a document from the stream using the default parser configuration.
+    ///
+    /// The source should be buffered, see [`EventReader::new`].
+    pub fn parse<R: Read>(source: R) -> reader::Result<Self> {
+        Self::from_reader(EventReader::new(source))
+    }
+
+    /// Parses a document from the stream using the given parser configuration.
+    pub fn parse_with_config<R: Read>(source: R, config: impl Into<ParserConfig>) -> reader::Result<Self> {
+        Self::from_reader(EventReader::new_with_config(source, config))
+    }
+
+    /// Builds a document from the remaining events of the reader.
+    ///
+    /// The reader should not have returned any events yet.
+    pub fn from_reader<R: Read>(mut reader: EventReader<R>) -> reader::Result<Self> {
+        let mut builder = Builder::new();
+        loop {
+            let event = reader.next()?;
+            if let XmlEvent::EndDocument = event {
+                break;
+            }
+            if builder.push(event).is_none() {
+                return Err((&reader, "Document is too large for CompactDocument").into());
+            }
+        }
+        Ok(builder.finish())
+    }
+
+    /// Returns the document node, which is the parent of all top-level nodes.
+    #[inline]
+    #[must_use]
+    pub fn root(&self) -> NodeRef<'_> {
+        NodeRef { doc: self, id: NodeId(0) }
+    }
+
+    /// Returns the first top-level element.
+    #[must_use]
+    pub fn root_element(&self) -> Option<NodeRef<'_>> {
+        self.root().children().find(|n| n.kind() == NodeKind::Element)
+    }
+
+    /// Returns a handle to the node with the given identifier.
+    ///
+    /// Panics if the identifier does not belong to this document.
+    #[inline]
+    #[must_use]
+    #[track_caller]
+    pub fn get(&self, id: NodeId) -> NodeRef<'_> {
+        assert!(id.index() < self.nodes.len(), "NodeId from another document");
+        NodeRef { doc: self, id }
+    }
+
+    /// Total number of nodes, including the document node.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always `false`, because there is at least the document node.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Iterates over all nodes in document order, starting with the document node.
+    pub fn nodes(&self) -> impl Iterator<Item = NodeRef<'_>> {
+        (0..self.nodes.len() as u32).map(move |i| NodeRef { doc: self, id: NodeId(i) })
+    }
+
+    /// XML version from the document declaration.
+    #[inline]
+    #[must_use]
+    pub fn version(&self) -> XmlVersion {
+        self.version
+    }
+
+    /// Encoding from the document declaration.
+    #[inline]
+    #[must_use]
+    pub fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
+    /// Standalone flag from the document declaration.
+    #[inline]
+    #[must_use]
+    pub fn standalone(&self) -> Option<bool> {
+        self.standalone
+    }
+
+    /// The whole `<!DOCTYPE>` markup, if the document had one.
+    #[inline]
+    #[must_use]
+    pub fn doctype(&self) -> Option<&str> {
+        self.doctype.as_deref()
+    }
+
+    #[inline]
+    pub(crate) fn at(&self, index: usize) -> NodeRef<'_> {
+        NodeRef { doc: self, id: NodeId(index as u32) }
+    }
+
+    #[inline]
+    fn node(&self, id: NodeId) -> &NodeData {
+        &self.nodes[id.index()]
+    }
+
+    #[inline]
+    fn str(&self, start: u32, len: u32) -> &str {
+        &self.text[start as usize..(start + len) as usize]
+    }
+}
+
+impl FromStr for CompactDocument {
+    type Err = reader::Error;
+
+    /// Parses a document from a string using the default parser configuration.
+    fn from_str(s: &str) -> reader::Result<Self> {
+        Self::parse(s.as_bytes())
+    }
+}
+
+impl fmt::Debug for CompactDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompactDocument")
+            .field("nodes", &self.nodes.len())
+            .field("attributes", &self.attributes.len())
+            .field("names", &self.names.len())
+            .field("text", &self.text.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A lightweight handle to a node of a [`CompactDocument`].
+#[derive(Copy, Clone)]
+pub struct NodeRef<'a> {
+    doc: &'a CompactDocument,
+    id: NodeId,
+}
+
+impl PartialEq for NodeRef<'_> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.doc, other.doc) && self.id == other.id
+    }
+}
+
+impl Eq for NodeRef<'_> {}
+
+impl<'a> NodeRef<'a> {
+    /// Identifier of this node.
+    #[inline]
+    #[must_use]
+    pub fn id(self) -> NodeId {
+        self.id
+    }
+
+    /// The document this node belongs to.
+    #[inline]
+    #[must_use]
+    pub fn document(self) -> &'a CompactDocument {
+        self.doc
+    }
+
+    /// Kind of this node.
+    #[inline]
+    #[must_use]
+    pub fn kind(self) -> NodeKind {
+        self.data().kind
+    }
+
+    /// Checks whether this node is an element.
+    #[inline]
+    #[must_use]
+    pub fn is_element(self) -> bool {
+        self.kind() == NodeKind::Element
+    }
+
+    #[inline]
+    fn data(self) -> &'a NodeData {
+        self.doc.node(self.id)
+    }
+
+    #[inline]
+    fn link(self, raw: u32) -> Option<Self> {
+        NodeId::from_raw(raw).map(|id| NodeRef { doc: self.doc, id })
+    }
+
+    /// Parent node. Only the document node has no parent.
+    #[inline]
+    #[must_use]
+    pub fn parent(self) -> Option<Self> {
+        self.link(self.data().parent)
+    }
+
+    /// First child node.
+    #[inline]
+    #[must_use]
+    pub fn first_child(self) -> Option<Self> {
+        self.link(self.data().first_child)
+    }
+
+    /// Last child node.
+    #[inline]
+    #[must_use]
+    pub fn last_child(self) -> Option<Self> {
+        self.link(self.data().last_child)
+    }
+
+    /// Previous node with the same parent.
+    #[inline]
+    #[must_use]
+    pub fn prev_sibling(self) -> Option<Self> {
+        self.link(self.data().prev_sibling)
+    }
+
+    /// Next node with the same parent.
+    #[inline]
+    #[must_use]
+    pub fn next_sibling(self) -> Option<Self> {
+        self.link(self.data().next_sibling)
+    }
+
+    /// Iterates over child nodes.
+    #[must_use]
+    pub fn children(self) -> Siblings<'a> {
+        Siblings { next: self.first_child() }
+    }
+
+    /// Iterates over following siblings, not including this node.
+    #[must_use]
+    pub fn following_siblings(self) -> Siblings<'a> {
+        Siblings { next: self.next_sibling() }
+    }
+
+    /// Iterates over the ancestors, starting with the parent and ending with the document node.
+    pub fn ancestors(self) -> impl Iterator<Item = NodeRef<'a>> {
+        std::iter::successors(self.parent(), |n| n.parent())
+    }
+
+    /// Iterates over preceding siblings in reverse document order.
+    pub fn preceding_siblings(self) -> impl Iterator<Item = NodeRef<'a>> {
+        std::iter::successors(self.prev_sibling(), |n| n.prev_sibling())
+    }
+
+    /// Iterates over all descendants in document order, not including this node.
+    pub fn descendants(self) -> impl Iterator<Item = NodeRef<'a>> {
+        let doc = self.doc;
+        let end = self.subtree_end().0;
+        (self.id.0 + 1..end).map(move |i| NodeRef { doc, id: NodeId(i) })
+    }
+
+    /// Returns the identifier just past the last descendant of this node.
+    ///
+    /// Because identifiers are assigned in document order, all descendants of this node
+    /// have identifiers in the range `self.id() < id < self.subtree_end()`.
+    #[must_use]
+    pub fn subtree_end(self) -> NodeId {
+        let mut node = self;
+        loop {
+            if let Some(next) = node.next_sibling() {
+                return next.id;
+            }
+            match node.parent() {
+                Some(parent) => node = parent,
+                None => return NodeId(self.doc.nodes.len() as u32),
+            }
+        }
+    }
+
+    /// Qualified name of an element, or `None` for other nodes.
+    #[must_use]
+    pub fn name(self) -> Option<Name<'a>> {
+        let data = self.data();
+        match data.kind {
+            NodeKind::Element => Some(self.doc.names[data.a as usize].borrow()),
+            _ => None,
+        }
+    }
+
+    /// Iterates over the attributes of an element. Other nodes have no attributes.
+    pub fn attributes(self) -> impl ExactSizeIterator<Item = Attribute<'a>> {
+        let data = self.data();
+        let doc = self.doc;
+        let range = match data.kind {
+            NodeKind::Element => data.b as usize..(data.b + data.c) as usize,
+            _ => 0..0,
+        };
+        doc.attributes[range].iter().map(move |a| Attribute {
+            name: doc.names[a.name as usize].borrow(),
+            value: doc.str(a.start, a.len),
+        })
+    }
+
+    /// Returns the value of an attribute with the given local name and no namespace.
+    #[must_use]
+    pub fn attribute(self, local_name: &str) -> Option<&'a str> {
+        self.attributes()
+            .find(|a| a.name.namespace.is_none() && a.name.local_name == local_name)
+            .map(|a| a.value)
+    }
+
+    /// Returns the value of an attribute with the given local name and namespace URI.
+    #[must_use]
+    pub fn attribute_ns(self, local_name: &str, namespace: &str) -> Option<&'a str> {
+        self.attributes()
+            .find(|a| a.name.namespace == Some(namespace) && a.name.local_name == local_name)
+            .map(|a| a.value)
+    }
+
+    /// Namespace mappings in scope of this node.
+    #[inline]
+    #[must_use]
+    pub fn namespace(self) -> &'a Namespace {
+        &self.doc.namespaces[self.data().ns as usize]
+    }
+
+    /// Content of text, CDATA and comment nodes, data of a processing instruction,
+    /// or name of an entity reference.
+    ///
+    /// Returns `None` for elements, the document node, and processing instructions without data.
+    #[must_use]
+    pub fn text(self) -> Option<&'a str> {
+        let data = self.data();
+        match data.kind {
+            NodeKind::Text | NodeKind::CData | NodeKind::Comment | NodeKind::EntityReference => Some(self.doc.str(data.a, data.b)),
+            NodeKind::ProcessingInstruction if data.c != NONE => Some(self.doc.str(data.a + data.b, data.c)),
+            _ => None,
+        }
+    }
+
+    /// Target of a processing instruction, or `None` for other nodes.
+    #[must_use]
+    pub fn pi_target(self) -> Option<&'a str> {
+        let data = self.data();
+        match data.kind {
+            NodeKind::ProcessingInstruction => Some(self.doc.str(data.a, data.b)),
+            _ => None,
+        }
+    }
+
+    /// Concatenated content of all text and CDATA nodes in this subtree.
+    #[must_use]
+    pub fn text_content(self) -> String {
+        match self.kind() {
+            NodeKind::Text | NodeKind::CData => self.text().unwrap_or_default().into(),
+            NodeKind::Element | NodeKind::Document => self.descendants()
+                .filter(|n| matches!(n.kind(), NodeKind::Text | NodeKind::CData))
+                .filter_map(|n| n.text())
+                .collect(),
+            NodeKind::Comment | NodeKind::ProcessingInstruction | NodeKind::EntityReference => String::new(),
+        }
+    }
+}
+
+impl fmt::Debug for NodeRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}#{}", self.kind(), self.id.0)?;
+        if let Some(name) = self.name() {
+            write!(f, "({name})")?;
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over a run of sibling nodes.
+#[derive(Clone)]
+pub struct Siblings<'a> {
+    next: Option<NodeRef<'a>>,
+}
+
+impl<'a> Iterator for Siblings<'a> {
+    type Item = NodeRef<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<NodeRef<'a>> {
+        let node = self.next?;
+        self.next = node.next_sibling();
+        Some(node)
+    }
+}
+
+struct Builder {
+    doc: CompactDocument,
+    name_index: HashMap<OwnedName, u32>,
+    namespace_index: HashMap<BTreeMap<String, String>, u32>,
+    /// Open elements, starting with the document node
+    stack: Vec<u32>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        let mut namespaces = Vec::with_capacity(4);
+        namespaces.push(Namespace::empty());
+        Self {
+            doc: CompactDocument {
+                nodes: vec![NodeData {
+                    kind: NodeKind::Document,
+                    parent: NONE,
+                    first_child: NONE,
+                    last_child: NONE,
+                    prev_sibling: NONE,
+                    next_sibling: NONE,
+                    a: 0, b: 0, c: 0, ns: 0,
+                }],
+                attributes: Vec::new(),
+                names: Vec::new(),
+                namespaces,
+                text: String::new(),
+                version: XmlVersion::Version10,
+                encoding: "UTF-8".into(),
+                standalone: None,
+                doctype: None,
+            },
+            name_index: HashMap::new(),
+            namespace_index: HashMap::from([(BTreeMap::new(), 0)]),
+            stack: vec![0],
+        }
+    }
+
+    fn intern_name(&mut self, name: OwnedName) -> Option<u32> {
+        if let Some(&idx) = self.name_index.get(&name) {
+            return Some(idx);
+        }
+        let idx = u32::try_from(self.doc.names.len()).ok()?;
+        self.doc.names.push(name.clone());
+        self.name_index.insert(name, idx);
+        Some(idx)
+    }
+
+    fn intern_namespace(&mut self, namespace: Namespace) -> Option<u32> {
+        if let Some(&idx) = self.namespace_index.get(&namespace.0) {
+            return Some(idx);
+        }
+        let idx = u32::try_from(self.doc.namespaces.len()).ok()?;
+        self.namespace_index.insert(namespace.0.clone(), idx);
+        self.doc.namespaces.push(namespace);
+        Some(idx)
+    }
+
+    fn push_text(&mut self, s: &str) -> Option<(u32, u32)> {
+        let start = u32::try_from(self.doc.text.len()).ok()?;
+        let len = u32::try_from(s.len()).ok()?;
+        start.checked_add(len)?;
+        self.doc.text.push_str(s);
+        Some((start, len))
+    }
+
+    fn append_node(&mut self, kind: NodeKind, a: u32, b: u32, c: u32, ns: u32) -> Option<u32> {
+        let id = u32::try_from(self.doc.nodes.len()).ok().filter(|&id| id != NONE)?;
+        let parent = *self.stack.last()?;
+        let prev = self.doc.nodes[parent as usize].last_child;
+        self.doc.nodes.push(NodeData {
+            kind,
+            parent,
+            first_child: NONE,
+            last_child: NONE,
+            prev_sibling: prev,
+            next_sibling: NONE,
+            a, b, c, ns,
+        });
+        if prev == NONE {
+            self.doc.nodes[parent as usize].first_child = id;
+        } else {
+            self.doc.nodes[prev as usize].next_sibling = id;
+        }
+        self.doc.nodes[parent as usize].last_child = id;
+        Some(id)
+    }
+
+    fn current_ns(&self) -> u32 {
+        self.stack.last().map_or(0, |&p| self.doc.nodes[p as usize].ns)
+    }
+
+    /// Returns `None` if the document exceeds 32-bit limits
+    fn push(&mut self, event: XmlEvent) -> Option<()> {
+        match event {
+            XmlEvent::StartDocument { version, encoding, standalone } => {
+                self.doc.version = version;
+                self.doc.encoding = encoding;
+                self.doc.standalone = standalone;
+            },
+            XmlEvent::Doctype { syntax } => self.doc.doctype = Some(syntax),
+            XmlEvent::StartElement { name, attributes, namespace } => {
+                let mut ns = self.current_ns();
+                if self.doc.namespaces[ns as usize] != namespace {
+                    ns = self.intern_namespace(namespace)?;
+                }
+                let name = self.intern_name(name)?;
+                let first_attr = u32::try_from(self.doc.attributes.len()).ok()?;
+                let attr_count = u32::try_from(attributes.len()).ok()?;
+                for attr in attributes {
+                    let (start, len) = self.push_text(&attr.value)?;
+                    let name = self.intern_name(attr.name)?;
+                    self.doc.attributes.push(AttrData { name, start, len });
+                }
+                let id = self.append_node(NodeKind::Element, name, first_attr, attr_count, ns)?;
+                self.stack.push(id);
+            },
+            XmlEvent::EndElement { .. } => {
+                if self.stack.len() > 1 {
+                    self.stack.pop();
+                }
+            },
+            XmlEvent::Characters(s) | XmlEvent::Whitespace(s) => self.push_data(NodeKind::Text, &s)?,
+            XmlEvent::CData(s) => self.push_data(NodeKind::CData, &s)?,
+            XmlEvent::Comment(s) => self.push_data(NodeKind::Comment, &s)?,
+            XmlEvent::EntityReference { name } => self.push_data(NodeKind::EntityReference, &name)?,
+            XmlEvent::ProcessingInstruction { name, data } => {
+                let (start, target_len) = self.push_text(&name)?;
+                let data_len = match data {
+                    Some(data) => self.push_text(&data)?.1,
+                    None => NONE,
+                };
+                let ns = self.current_ns();
+                self.append_node(NodeKind::ProcessingInstruction, start, target_len, data_len, ns)?;
+            },
+            XmlEvent::EndDocument => {},
+        }
+        Some(())
+    }
+
+    fn push_data(&mut self, kind: NodeKind, s: &str) -> Option<()> {
+        let (start, len) = self.push_text(s)?;
+        let ns = self.current_ns();
+        self.append_node(kind, start, len, 0, ns)?;
+        Some(())
+    }
+
+    fn finish(mut self) -> CompactDocument {
+        self.doc.nodes.shrink_to_fit();
+        self.doc.attributes.shrink_to_fit();
+        self.doc.text.shrink_to_fit();
+        self.doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompactDocument, NodeKind};
+
+    #[test]
+    fn node_size() {
+        assert!(std::mem::size_of::<super::NodeData>() <= 40);
+    }
+
+    #[test]
+    fn navigation() {
+        let doc = CompactDocument::parse_with_config("<?pi x?><a><b>1<c/>2</b><!--c--><d x='y'/></a>".as_bytes(),
+            crate::ParserConfig::new().ignore_comments(false)).unwrap();
+        let root = doc.root();
+        assert_eq!(root.kind(), NodeKind::Document);
+        assert_eq!(root.first_child().unwrap().pi_target(), Some("pi"));
+        assert_eq!(root.first_child().unwrap().text(), Some("x"));
+
+        let a = doc.root_element().unwrap();
+        assert_eq!(a.parent(), Some(root));
+        assert_eq!(a.children().count(), 3);
+        assert_eq!(a.descendants().count(), 6);
+        assert_eq!(a.text_content(), "12");
+
+        let d = a.last_child().unwrap();
+        assert_eq!(d.attribute("x"), Some("y"));
+        assert_eq!(d.prev_sibling().unwrap().kind(), NodeKind::Comment);
+        assert_eq!(d.subtree_end().index(), doc.len());
+
+        let c = a.first_child().unwrap().children().nth(1).unwrap();
+        assert_eq!(c.name().unwrap().local_name, "c");
+        assert_eq!(c.ancestors().map(|n| n.kind()).collect::<Vec<_>>(),
+            [NodeKind::Element, NodeKind::Element, NodeKind::Document]);
+        assert_eq!(c.following_siblings().count(), 1);
+        assert_eq!(c.preceding_siblings().count(), 1);
+    }
+
+    #[test]
+    fn interning() {
+        let doc: CompactDocument = r#"<r xmlns:p="urn:p"><p:i a="1"/><p:i a="2"/><i xmlns:q="urn:q" a="3"/><i xmlns:q="urn:q"/></r>"#.parse().unwrap();
+        assert_eq!(doc.names.len(), 4);
+        assert_eq!(doc.namespaces.len(), 3);
+        let r = doc.root_element().unwrap();
+        let last = r.last_child().unwrap();
+        assert_eq!(last.namespace().get("q"), Some("urn:q"));
+        assert_eq!(last.namespace().get("p"), Some("urn:p"));
+        assert_eq!(r.first_child().unwrap().name().unwrap().namespace, Some("urn:p"));
+    }
+}
@@ -15,13 +15,29 @@ pub use crate::reader::{EventReader, ParserConfig};
 pub use crate::util::Encoding;
 pub use crate::writer::{EmitterConfig, EventWriter};
 
+// declared first, so that the macros are available in all other modules
+#[doc(hidden)] // FIXME: not supposed to be public
+pub mod macros;
+
 pub mod attribute;
+pub mod c14n;
 pub mod common;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod dom;
 pub mod escape;
-#[doc(hidden)] // FIXME: not supposed to be public
-pub mod macros;
+pub mod lossless;
 pub mod name;
 pub mod namespace;
+pub mod pipeline;
 pub mod reader;
+pub mod select;
+#[cfg(feature = "serde")]
+pub mod se;
+pub mod typed;
+mod uri;
 mod util;
 pub mod writer;
+pub mod xinclude;
+pub mod xmldsig;
+pub mod xpath;
@@ -0,0 +1,177 @@
+//! Contains a reader and events for rewriting documents without reformatting them.
+//!
+//! Passing [`reader::XmlEvent`]s to an [`EventWriter`] normalizes the markup: quotes, whitespace
+//! inside tags, self-closing tags, character and entity references, and the XML declaration are
+//! all written the writer's way. [`LosslessReader`] keeps the source of each event, and
+//! [`LosslessEvent::write_to`] copies it to the output unless the event has been modified,
+//! so the unmodified parts of a document stay the same byte for byte.
+//!
+//! ```rust
+//! use xml::lossless::LosslessReader;
+//! use xml::reader::XmlEvent;
+//! use xml::EmitterConfig;
+//!
+//! let source = "<?xml version='1.0'?>\n<list  kind = 'todo'><!-- x -->\n  <item done=\"no\">Write &quot;docs&quot;</item><item/>\n</list>\n";
+//! let mut reader = LosslessReader::new(source.as_bytes());
+//! let mut output = Vec::new();
+//! let mut writer = EmitterConfig::new().create_writer(&mut output);
+//!
+//! loop {
+//!     let mut event = reader.next().unwrap();
+//!     if matches!(event.event(), XmlEvent::Characters(_)) {
+//!         if let XmlEvent::Characters(text) = event.event_mut() {
+//!             *text = text.to_uppercase();
+//!         }
+//!     }
+//!     event.write_to(&mut writer).unwrap();
+//!     if *event.event() == XmlEvent::EndDocument {
+//!         break;
+//!     }
+//! }
+//!
+//! assert_eq!(String::from_utf8(output).unwrap(), source.replace("Write &quot;docs&quot;", "WRITE \"DOCS\""));
+//! ```
+
+use std::io::{Read, Write};
+
+use crate::common::{Position, TextPosition};
+use crate::reader::{self, EventReader, ParserConfig, XmlEvent};
+use crate::writer::{self, EventWriter};
+
+/// A reader keeping the source of the events, for writing them back unchanged.
+///
+/// It reads the events of an [`EventReader`] with [`ParserConfig::capture_raw`] set.
+/// The markup skipped by the configuration, such as comments and whitespace,
+/// is kept as the [leading](LosslessEvent::leading) source of the next event.
+pub struct LosslessReader<R: Read> {
+    reader: EventReader<R>,
+    offset: u64,
+}
+
+impl<R: Read> LosslessReader<R> {
+    /// Creates a new reader with the default configuration, consuming the given stream.
+    #[inline]
+    pub fn new(source: R) -> Self {
+        Self::new_with_config(source, ParserConfig::new())
+    }
+
+    /// Creates a new reader with the provided configuration, consuming the given stream.
+    pub fn new_with_config(source: R, config: impl Into<ParserConfig>) -> Self {
+        let mut config = config.into();
+        config.capture_raw = true;
+        Self {
+            reader: EventReader::new_with_config(source, config),
+            offset: 0,
+        }
+    }
+
+    /// Pulls and returns the next event with its source.
+    ///
+    /// Errors and `EndDocument` are returned again by further calls, like [`EventReader::next`].
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> reader::Result<LosslessEvent> {
+        let event = self.reader.next()?;
+        let span = self.reader.span().unwrap_or_default();
+        let source = self.reader.raw_since(self.offset).unwrap_or_default().to_vec();
+        let markup_start = source.len() - span.len().min(source.len() as u64) as usize;
+        self.offset = self.offset.max(span.end);
+        Ok(LosslessEvent { event, source, markup_start, modified: false })
+    }
+
+    /// Unwraps this reader, returning the underlying `EventReader`.
+    pub fn into_inner(self) -> EventReader<R> {
+        self.reader
+    }
+}
+
+impl<R: Read> Position for LosslessReader<R> {
+    /// Returns the position of the last event produced by the reader.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+}
+
+/// An event with the source it has been parsed from.
+///
+/// Mutable access to the event marks it as modified, so it will be written from the event
+/// instead of the source.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LosslessEvent {
+    event: XmlEvent,
+    source: Vec<u8>,
+    markup_start: usize,
+    modified: bool,
+}
+
+impl LosslessEvent {
+    /// The parsed event.
+    #[inline]
+    #[must_use]
+    pub fn event(&self) -> &XmlEvent {
+        &self.event
+    }
+
+    /// The parsed event, for modifying it. The event is considered modified afterwards.
+    #[inline]
+    pub fn event_mut(&mut self) -> &mut XmlEvent {
+        self.modified = true;
+        &mut self.event
+    }
+
+    /// Unwraps the parsed event.
+    #[inline]
+    #[must_use]
+    pub fn into_event(self) -> XmlEvent {
+        self.event
+    }
+
+    /// Whether the event has been modified, or created without a source.
+    #[inline]
+    #[must_use]
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// The source between the previous event and this one, such as whitespace and comments
+    /// skipped by the parser configuration, or a reference to an entity declared in the DTD.
+    #[inline]
+    #[must_use]
+    pub fn leading(&self) -> &[u8] {
+        &self.source[..self.markup_start]
+    }
+
+    /// The source of the event as it was in the input, with its quotes, whitespace, and
+    /// character and entity references.
+    ///
+    /// It's empty for events implied by the markup: the `EndElement` of a self-closing tag,
+    /// a `StartDocument` without an XML declaration, and the events of entities declared in the DTD.
+    #[inline]
+    #[must_use]
+    pub fn markup(&self) -> &[u8] {
+        &self.source[self.markup_start..]
+    }
+
+    /// Writes the leading source, and then the source of the event if it hasn't been modified,
+    /// or the event itself if it has.
+    ///
+    /// The writer keeps track of the elements and namespaces from the source, so the modified events
+    /// can be mixed with the copied ones. The source is copied as-is, so it should be in the
+    /// encoding of the output.
+    pub fn write_to<W: Write>(&self, writer: &mut EventWriter<W>) -> writer::Result<()> {
+        writer.write_verbatim(self.leading())?;
+        match self.event.as_writer_event() {
+            Some(event) if self.modified => writer.write(event),
+            Some(event) => writer.write_event_verbatim(event, self.markup()),
+            None => writer.write_verbatim(self.markup()),
+        }
+    }
+}
+
+impl From<XmlEvent> for LosslessEvent {
+    /// A new event without a source, to be written from the event.
+    #[inline]
+    fn from(event: XmlEvent) -> Self {
+        Self { event, source: Vec::new(), markup_start: 0, modified: true }
+    }
+}
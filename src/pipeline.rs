@@ -0,0 +1,386 @@
+//! Contains composable transformations of [`reader::XmlEvent`] streams.
+//!
+//! [`Pipeline`] is implemented for [`EventReader`](crate::EventReader) and other sources of
+//! events. Each stage keeps track of the open elements, so predicates can look at the element
+//! and its ancestors, and removals and renames apply to whole elements, keeping the output
+//! well-formed. A pipeline ends in an [`EventWriter`]:
+//!
+//! ```rust
+//! use xml::pipeline::Pipeline;
+//! use xml::name::OwnedName;
+//! use xml::reader::XmlEvent;
+//! use xml::{EmitterConfig, EventReader};
+//!
+//! let source = r#"<?xml version="1.0"?><doc><script>alert()</script><span><b>bold</b> text</span><p>Hi</p></doc>"#;
+//! let mut output = Vec::new();
+//! let mut writer = EmitterConfig::new().create_writer(&mut output);
+//!
+//! EventReader::from_str(source)
+//!     .drop_subtree(|e| e.name.local_name == "script")
+//!     .filter_elements(|e| e.name.local_name != "span")
+//!     .rename(|e| (e.name.local_name == "b").then(|| OwnedName::local("strong")))
+//!     .map_text(|text| text.to_uppercase())
+//!     .insert_after(|e| e.name.local_name == "p", [XmlEvent::Characters("!".into())])
+//!     .write_to(&mut writer)
+//!     .unwrap();
+//!
+//! assert_eq!(String::from_utf8(output).unwrap(),
+//!     r#"<?xml version="1.0" encoding="UTF-8"?><doc><strong>BOLD</strong> TEXT<p>HI</p>!</doc>"#);
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Write;
+
+use crate::attribute::OwnedAttribute;
+use crate::name::OwnedName;
+use crate::namespace::{Namespace, NS_NO_PREFIX};
+use crate::reader::{self, XmlEvent};
+use crate::writer::{self, EventWriter};
+
+/// A failure to read or write events.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The source of events failed
+    Reader(reader::Error),
+    /// The events could not be written
+    Writer(writer::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reader(e) => e.fmt(f),
+            Self::Writer(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Reader(e) => Some(e),
+            Self::Writer(e) => Some(e),
+        }
+    }
+}
+
+impl From<reader::Error> for Error {
+    #[cold]
+    fn from(e: reader::Error) -> Self {
+        Self::Reader(e)
+    }
+}
+
+impl From<writer::Error> for Error {
+    #[cold]
+    fn from(e: writer::Error) -> Self {
+        Self::Writer(e)
+    }
+}
+
+/// The element that a predicate of a pipeline stage is called for.
+#[derive(Copy, Clone, Debug)]
+pub struct ElementContext<'a> {
+    /// The name of the element
+    pub name: &'a OwnedName,
+    /// The attributes of the element
+    pub attributes: &'a [OwnedAttribute],
+    /// Namespace mappings in scope of the element
+    pub namespace: &'a Namespace,
+    /// The names of the ancestors of the element, starting with the root
+    pub ancestors: &'a [OwnedName],
+}
+
+impl ElementContext<'_> {
+    /// The number of ancestors. The root element has depth 0.
+    #[inline]
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.ancestors.len()
+    }
+
+    /// Returns the value of an attribute with the given local name and no namespace.
+    #[must_use]
+    pub fn attribute(&self, local_name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|a| a.name.namespace.is_none() && a.name.local_name == local_name)
+            .map(|a| a.value.as_str())
+    }
+}
+
+/// Stages for streams of events.
+///
+/// Stages pass errors through, and stop after an error or `EndDocument`.
+pub trait Pipeline: IntoIterator<Item = reader::Result<XmlEvent>> + Sized {
+    /// Removes the start and end tags of elements for which the predicate returns false, keeping
+    /// their content. The root element is always kept, so that there's one.
+    fn filter_elements<P>(self, predicate: P) -> FilterElements<Self::IntoIter, P>
+    where P: FnMut(&ElementContext<'_>) -> bool {
+        FilterElements { inner: self.into_iter(), predicate, ancestors: Vec::new(), kept: Vec::new() }
+    }
+
+    /// Renames elements for which the function returns a name. Namespace declarations are added
+    /// for the namespace of the new name, and its content keeps its namespaces.
+    ///
+    /// A name with a prefix but no namespace URI uses the namespace bound to the prefix. If the
+    /// prefix is not bound, the stage fails with [`writer::Error::UnboundPrefix`].
+    fn rename<F>(self, rename: F) -> Rename<Self::IntoIter, F>
+    where F: FnMut(&ElementContext<'_>) -> Option<OwnedName> {
+        Rename { inner: self.into_iter(), rename, ancestors: Vec::new(), renamed: Vec::new(), failed: false }
+    }
+
+    /// Replaces text and CDATA content by what the function returns.
+    fn map_text<F>(self, map: F) -> MapText<Self::IntoIter, F>
+    where F: FnMut(&str) -> String {
+        MapText { inner: self.into_iter(), map }
+    }
+
+    /// Removes elements for which the predicate returns true, with all their content.
+    fn drop_subtree<P>(self, predicate: P) -> DropSubtree<Self::IntoIter, P>
+    where P: FnMut(&ElementContext<'_>) -> bool {
+        DropSubtree { inner: self.into_iter(), predicate, ancestors: Vec::new(), dropping: 0 }
+    }
+
+    /// Inserts the events after the end of each element for which the predicate returns true.
+    /// The predicate is not called for the root element, since the events would be outside of it.
+    ///
+    /// # Panics
+    ///
+    /// If the events have unbalanced start and end elements, or document-level events.
+    fn insert_after<P, E>(self, predicate: P, events: E) -> InsertAfter<Self::IntoIter, P>
+    where P: FnMut(&ElementContext<'_>) -> bool, E: IntoIterator<Item = XmlEvent> {
+        let events: Vec<_> = events.into_iter().collect();
+        let mut depth = 0_usize;
+        for event in &events {
+            match event {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => depth = depth.checked_sub(1).expect("unbalanced EndElement"),
+                XmlEvent::StartDocument { .. } | XmlEvent::EndDocument | XmlEvent::Doctype { .. } => panic!("document-level event {event:?}"),
+                _ => {},
+            }
+        }
+        assert_eq!(depth, 0, "unclosed StartElement");
+        InsertAfter { inner: self.into_iter(), predicate, events, ancestors: Vec::new(), matched: Vec::new(), queue: VecDeque::new() }
+    }
+
+    /// Writes all events to the writer, up to `EndDocument`.
+    fn write_to<W: Write>(self, writer: &mut EventWriter<W>) -> Result<(), Error> {
+        for event in self {
+            match event? {
+                XmlEvent::EndDocument => break,
+                event => if let Some(event) = event.as_writer_event() {
+                    writer.write(event)?;
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: IntoIterator<Item = reader::Result<XmlEvent>>> Pipeline for T {}
+
+/// Calls the function with the context of a `StartElement`, and updates the ancestors for
+/// start and end events
+fn track<R>(event: &XmlEvent, ancestors: &mut Vec<OwnedName>, f: impl FnOnce(&ElementContext<'_>) -> R) -> Option<R> {
+    match event {
+        XmlEvent::StartElement { name, attributes, namespace } => {
+            let result = f(&ElementContext { name, attributes, namespace, ancestors });
+            ancestors.push(name.clone());
+            Some(result)
+        },
+        XmlEvent::EndElement { .. } => {
+            ancestors.pop();
+            None
+        },
+        _ => None,
+    }
+}
+
+/// Stage created by [`Pipeline::filter_elements`].
+pub struct FilterElements<I, P> {
+    inner: I,
+    predicate: P,
+    ancestors: Vec<OwnedName>,
+    kept: Vec<bool>,
+}
+
+impl<I, P> Iterator for FilterElements<I, P>
+where I: Iterator<Item = reader::Result<XmlEvent>>, P: FnMut(&ElementContext<'_>) -> bool {
+    type Item = reader::Result<XmlEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = self.inner.next()?;
+            let keep = match &event {
+                Ok(event @ XmlEvent::StartElement { .. }) => {
+                    let root = self.ancestors.is_empty();
+                    let predicate = &mut self.predicate;
+                    let keep = track(event, &mut self.ancestors, |e| root || predicate(e)).unwrap_or(true);
+                    self.kept.push(keep);
+                    keep
+                },
+                Ok(event @ XmlEvent::EndElement { .. }) => {
+                    track(event, &mut self.ancestors, |_| ());
+                    self.kept.pop().unwrap_or(true)
+                },
+                _ => true,
+            };
+            if keep {
+                return Some(event);
+            }
+        }
+    }
+}
+
+/// Stage created by [`Pipeline::rename`].
+pub struct Rename<I, F> {
+    inner: I,
+    rename: F,
+    ancestors: Vec<OwnedName>,
+    renamed: Vec<Option<OwnedName>>,
+    failed: bool,
+}
+
+impl<I, F> Iterator for Rename<I, F>
+where I: Iterator<Item = reader::Result<XmlEvent>>, F: FnMut(&ElementContext<'_>) -> Option<OwnedName> {
+    type Item = reader::Result<XmlEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let mut event = self.inner.next()?;
+        let rename = &mut self.rename;
+        let new_name = event.as_ref().ok().and_then(|event| track(event, &mut self.ancestors, |e| rename(e)));
+        match &mut event {
+            Ok(XmlEvent::StartElement { name, namespace, .. }) => {
+                let mut new_name = new_name.flatten();
+                if let Some(new_name) = &mut new_name {
+                    let prefix = new_name.prefix_ref().unwrap_or(NS_NO_PREFIX);
+                    match new_name.namespace_ref().filter(|uri| !uri.is_empty()) {
+                        Some(uri) => if namespace.get(prefix).unwrap_or("") != uri {
+                            namespace.force_put(prefix, uri);
+                        },
+                        None if prefix.is_empty() => if namespace.get(prefix).is_some_and(|uri| !uri.is_empty()) {
+                            namespace.force_put(prefix, "");
+                        },
+                        None => match namespace.get(prefix) {
+                            Some(uri) if !uri.is_empty() => new_name.namespace = Some(uri.to_owned()),
+                            _ => {
+                                self.failed = true;
+                                return Some(Err(writer::Error::UnboundPrefix(prefix.to_owned()).into()));
+                            },
+                        },
+                    }
+                    name.clone_from(new_name);
+                }
+                self.renamed.push(new_name);
+            },
+            Ok(XmlEvent::EndElement { name }) => {
+                if let Some(new_name) = self.renamed.pop().flatten() {
+                    *name = new_name;
+                }
+            },
+            _ => {},
+        }
+        Some(event)
+    }
+}
+
+/// Stage created by [`Pipeline::map_text`].
+pub struct MapText<I, F> {
+    inner: I,
+    map: F,
+}
+
+impl<I, F> Iterator for MapText<I, F>
+where I: Iterator<Item = reader::Result<XmlEvent>>, F: FnMut(&str) -> String {
+    type Item = reader::Result<XmlEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(match self.inner.next()? {
+            Ok(XmlEvent::Characters(text)) => Ok(XmlEvent::Characters((self.map)(&text))),
+            Ok(XmlEvent::CData(text)) => Ok(XmlEvent::CData((self.map)(&text))),
+            other => other,
+        })
+    }
+}
+
+/// Stage created by [`Pipeline::drop_subtree`].
+pub struct DropSubtree<I, P> {
+    inner: I,
+    predicate: P,
+    ancestors: Vec<OwnedName>,
+    /// Depth within the dropped element, 0 if not in one
+    dropping: usize,
+}
+
+impl<I, P> Iterator for DropSubtree<I, P>
+where I: Iterator<Item = reader::Result<XmlEvent>>, P: FnMut(&ElementContext<'_>) -> bool {
+    type Item = reader::Result<XmlEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = self.inner.next()?;
+            if self.dropping > 0 {
+                match event {
+                    Ok(XmlEvent::StartElement { .. }) => self.dropping += 1,
+                    Ok(XmlEvent::EndElement { .. }) => self.dropping -= 1,
+                    Ok(XmlEvent::EndDocument) | Err(_) => return Some(event),
+                    _ => {},
+                }
+                continue;
+            }
+            if let Ok(XmlEvent::StartElement { name, attributes, namespace }) = &event {
+                if (self.predicate)(&ElementContext { name, attributes, namespace, ancestors: &self.ancestors }) {
+                    self.dropping = 1;
+                    continue;
+                }
+            }
+            if let Ok(event) = &event {
+                track(event, &mut self.ancestors, |_| ());
+            }
+            return Some(event);
+        }
+    }
+}
+
+/// Stage created by [`Pipeline::insert_after`].
+pub struct InsertAfter<I, P> {
+    inner: I,
+    predicate: P,
+    events: Vec<XmlEvent>,
+    ancestors: Vec<OwnedName>,
+    matched: Vec<bool>,
+    queue: VecDeque<XmlEvent>,
+}
+
+impl<I, P> Iterator for InsertAfter<I, P>
+where I: Iterator<Item = reader::Result<XmlEvent>>, P: FnMut(&ElementContext<'_>) -> bool {
+    type Item = reader::Result<XmlEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.queue.pop_front() {
+            return Some(Ok(event));
+        }
+        let event = self.inner.next()?;
+        match &event {
+            Ok(event @ XmlEvent::StartElement { .. }) => {
+                let root = self.ancestors.is_empty();
+                let predicate = &mut self.predicate;
+                let matched = track(event, &mut self.ancestors, |e| !root && predicate(e)).unwrap_or(false);
+                self.matched.push(matched);
+            },
+            Ok(event @ XmlEvent::EndElement { .. }) => {
+                track(event, &mut self.ancestors, |_| ());
+                if self.matched.pop().unwrap_or(false) {
+                    self.queue.extend(self.events.iter().cloned());
+                }
+            },
+            _ => {},
+        }
+        Some(event)
+    }
+}
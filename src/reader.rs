@@ -8,10 +8,14 @@ use std::iter::FusedIterator;
 use std::result;
 
 use crate::common::{Position, TextPosition};
+use crate::writer::EmitterConfig;
 
 pub use self::config::ParserConfig;
-pub use self::error::{Error, ErrorKind};
+pub use self::diagnostic::Diagnostic;
+pub use self::error::{Error, ErrorCode, ErrorKind};
 pub use self::events::XmlEvent;
+pub use self::scope::{XmlScope, XmlSpace};
+pub use self::span::Span;
 
 // back compat
 #[doc(hidden)]
@@ -21,11 +25,14 @@ pub type ParserConfig2 = ParserConfig;
 use self::parser::PullParser;
 
 mod config;
+mod diagnostic;
 mod error;
 mod events;
 mod indexset;
 mod lexer;
 mod parser;
+mod scope;
+mod span;
 
 /// A result type yielded by `XmlReader`.
 pub type Result<T, E = Error> = result::Result<T, E>;
@@ -80,6 +87,8 @@ impl<R: Read> EventReader<R> {
                 XmlEvent::EndDocument => return Err(Error {
                     kind: ErrorKind::UnexpectedEof,
                     pos: self.parser.position(),
+                    syntax: None,
+                    span: None,
                 }),
                 _ => {},
             }
@@ -88,6 +97,84 @@ impl<R: Read> EventReader<R> {
         Ok(())
     }
 
+    /// Reads all XML events until the next end tag at the current level, including it.
+    ///
+    /// Like [`skip`](Self::skip), this is meant to be called after a `StartElement` event,
+    /// and consumes the rest of that element.
+    pub fn read_subtree_events(&mut self) -> Result<Vec<XmlEvent>> {
+        let mut events = Vec::new();
+        let mut depth = 1;
+
+        while depth > 0 {
+            let event = self.next()?;
+            match event {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => depth -= 1,
+                XmlEvent::EndDocument => return Err(Error {
+                    kind: ErrorKind::UnexpectedEof,
+                    pos: self.parser.position(),
+                    syntax: None,
+                    span: None,
+                }),
+                _ => {},
+            }
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
+    /// Reads the rest of the current element, and returns the text inside it, including the
+    /// text of nested elements and CDATA sections.
+    ///
+    /// Like [`skip`](Self::skip), this is meant to be called after a `StartElement` event.
+    pub fn read_inner_text(&mut self) -> Result<String> {
+        let mut text = String::new();
+        let mut depth = 1;
+
+        while depth > 0 {
+            match self.next()? {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => depth -= 1,
+                XmlEvent::Characters(s) | XmlEvent::CData(s) | XmlEvent::Whitespace(s) => text.push_str(&s),
+                XmlEvent::EndDocument => return Err(Error {
+                    kind: ErrorKind::UnexpectedEof,
+                    pos: self.parser.position(),
+                    syntax: None,
+                    span: None,
+                }),
+                _ => {},
+            }
+        }
+
+        Ok(text)
+    }
+
+    /// Reads the rest of the current element, and returns the whole element serialized as XML.
+    ///
+    /// `start` is the `StartElement` event just returned by the reader. The output declares
+    /// the namespaces in scope on the element, so it can be parsed on its own.
+    ///
+    /// # Panics
+    ///
+    /// If `start` is not a `StartElement` event.
+    pub fn read_outer_xml(&mut self, start: &XmlEvent) -> Result<String> {
+        assert!(matches!(start, XmlEvent::StartElement { .. }), "read_outer_xml expects a StartElement event");
+
+        let events = self.read_subtree_events()?;
+        let mut output = Vec::new();
+        let mut writer = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut output);
+        for event in std::iter::once(start).chain(&events) {
+            if let Some(event) = event.as_writer_event() {
+                writer.write(event)?;
+            }
+        }
+        // the events were valid UTF-8 strings
+        Ok(String::from_utf8(output).unwrap_or_default())
+    }
+
     /// Access underlying reader
     ///
     /// Using it directly while the event reader is parsing is not recommended
@@ -116,6 +203,71 @@ impl<R: Read> EventReader<R> {
     pub fn doctype(&self) -> Option<&str> {
         self.parser.doctype()
     }
+
+    /// Returns the `xml:base`, `xml:lang` and `xml:space` in effect for the last event.
+    ///
+    /// After a `StartElement` or `EndElement`, it includes the attributes of that element.
+    #[inline]
+    pub fn scope(&self) -> &XmlScope {
+        self.parser.scope()
+    }
+
+    /// Returns the bytes of the source that produced the last event.
+    ///
+    /// Available with [`ParserConfig::record_spans`], otherwise `None`. Events implied by the
+    /// markup, like the `EndElement` of an empty element, have an empty span at the end of the
+    /// markup, and events from entities declared in the DTD have an empty span after the reference.
+    /// Text spans include the entity references and CDATA sections merged into the text,
+    /// and the whitespace removed by [`ParserConfig::trim_whitespace`] after the text.
+    #[inline]
+    pub fn span(&self) -> Option<Span> {
+        self.parser.span()
+    }
+
+    /// Returns the bytes of each attribute of the last `StartElement` event, from the start of
+    /// its name to the closing quote, in the same order as the attributes.
+    ///
+    /// Empty unless [`ParserConfig::record_spans`] is set. Namespace declarations
+    /// are not attributes, so they have no spans.
+    #[inline]
+    pub fn attribute_spans(&self) -> &[Span] {
+        self.parser.attribute_spans()
+    }
+
+    /// Returns the source bytes of the last event, as they were in the input.
+    ///
+    /// Available with [`ParserConfig::capture_raw`], otherwise `None`.
+    #[inline]
+    pub fn raw(&self) -> Option<&[u8]> {
+        self.parser.raw()
+    }
+
+    /// Returns the errors fixed up so far by [`ParserConfig::recover_from_errors`], in the order
+    /// they were found, with the positions where they were found.
+    #[inline]
+    pub fn warnings(&self) -> &[Error] {
+        self.parser.warnings()
+    }
+
+    /// Returns the errors fixed up so far, and clears them.
+    ///
+    /// See [`warnings`](Self::warnings).
+    #[inline]
+    pub fn take_warnings(&mut self) -> Vec<Error> {
+        self.parser.take_warnings()
+    }
+
+    /// Source bytes from the offset until the end of the last event, if capturing
+    #[inline]
+    pub(crate) fn raw_since(&self, offset: u64) -> Option<&[u8]> {
+        self.parser.raw_since(offset)
+    }
+
+    /// The configuration of the parser, for parsing related documents the same way
+    #[inline]
+    pub(crate) fn config(&self) -> &ParserConfig {
+        self.parser.config()
+    }
 }
 
 impl<B: Read> Position for EventReader<B> {
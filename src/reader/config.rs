@@ -27,6 +27,8 @@ pub struct ParserConfig {
     ///
     /// This option does not affect CDATA events, unless `cdata_to_characters`
     /// option is also set. In that case CDATA content will also be trimmed.
+    ///
+    /// Whitespace is kept in elements with `xml:space="preserve"`.
     pub trim_whitespace: bool,
 
     /// Whether or not should whitespace be converted to characters.
@@ -122,6 +124,36 @@ pub struct ParserConfig {
 
     /// Maximum length of strings reprsenting characters, comments, and processing instructions
     pub max_data_length: usize,
+
+    /// The URI of the document, against which `xml:base` attributes are resolved. Default is empty.
+    ///
+    /// See [`EventReader::scope`].
+    pub base_uri: String,
+
+    /// Whether to record the byte offsets of each event and its attributes. Default is false.
+    ///
+    /// See [`EventReader::span`] and [`EventReader::attribute_spans`].
+    pub record_spans: bool,
+
+    /// Whether to keep the source bytes of each event. Default is false.
+    ///
+    /// See [`EventReader::raw`]. This implies `record_spans`.
+    pub capture_raw: bool,
+
+    /// Whether references to entities declared in the DTD or in `extra_entities` are reported
+    /// as `EntityReference` events instead of being expanded. Default is false.
+    ///
+    /// The predefined entities and character references are always expanded, and so are
+    /// references in attribute values.
+    pub keep_entity_references: bool,
+
+    /// Whether the parser continues after common well-formedness errors. Default is false.
+    ///
+    /// Instead of failing, the parser fixes up mismatched and stray closing tags, unescaped `&` and `<`
+    /// in text, references to unknown entities, unquoted attribute values, and elements left open at
+    /// the end of the document, and reports each fixup in [`EventReader::warnings`].
+    /// The events are a best guess of what was meant, so this is for salvaging broken documents.
+    pub recover_from_errors: bool,
 }
 
 impl ParserConfig {
@@ -160,6 +192,11 @@ impl ParserConfig {
             max_attribute_length: 1 << 30,
             max_data_length: 1 << 30,
             max_name_length: 1 << 18,
+            base_uri: String::new(),
+            record_spans: false,
+            capture_raw: false,
+            keep_entity_references: false,
+            recover_from_errors: false,
         }
     }
 
@@ -265,7 +302,17 @@ gen_setters! { ParserConfig,
     /// Maximum length of strings reprsenting characters, comments, and processing instructions
     max_data_length: val usize,
     /// Allow `<?xml encoding="bogus"?>`
-    ignore_invalid_encoding_declarations: val bool
+    ignore_invalid_encoding_declarations: val bool,
+    /// The URI of the document, for resolving `xml:base`
+    base_uri: into String,
+    /// Record the byte offsets of events and attributes
+    record_spans: val bool,
+    /// Keep the source bytes of each event
+    capture_raw: val bool,
+    /// Report references to declared entities as `EntityReference` events
+    keep_entity_references: val bool,
+    /// Fix up common well-formedness errors instead of failing
+    recover_from_errors: val bool
 }
 
 #[test]
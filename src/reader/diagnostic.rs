@@ -0,0 +1,117 @@
+//! Contains a renderer of errors with the source they refer to.
+
+use std::fmt;
+
+use crate::common::Position;
+use crate::reader::Error;
+
+/// An error rendered with the line of the source where it happened, a caret pointing at
+/// the position, the error code and a hint, for command-line tools and logs:
+///
+/// ```text
+/// error[mismatched-closing-tag]: Unexpected closing tag: b != i
+///  --> feed.xml:1:14
+///   |
+/// 1 | <b><i>bold</b></i>
+///   |              ^
+///   = hint: elements must be closed in the reverse order they were opened
+/// ```
+///
+/// With [`ParserConfig::record_spans`](crate::ParserConfig::record_spans), the part of the
+/// error's [span](Error::span) on that line is underlined instead, like `</b>` above.
+///
+/// Created by [`Error::diagnostic`]. Combined with [`ParserConfig::recover_from_errors`](crate::ParserConfig::recover_from_errors),
+/// all the errors in a document can be reported at once.
+///
+/// ```rust
+/// use xml::reader::XmlEvent;
+/// use xml::ParserConfig;
+///
+/// let source = "<p>\n  Tom &nbsp; Jerry\n</p>";
+/// let mut reader = ParserConfig::new().recover_from_errors(true).create_reader(source.as_bytes());
+/// while reader.next().unwrap() != XmlEvent::EndDocument {}
+///
+/// let warning = &reader.warnings()[0];
+/// assert_eq!(warning.diagnostic(source).file_name("tom.xml").warning(true).to_string(), r#"warning[undefined-entity]: Unexpected entity: nbsp
+///  --> tom.xml:2:12
+///   |
+/// 2 |   Tom &nbsp; Jerry
+///   |            ^
+///   = hint: entities other than `&lt;`, `&gt;`, `&amp;`, `&apos;` and `&quot;` must be declared in the DOCTYPE; did you mean `&amp;`?
+/// "#);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Diagnostic<'a> {
+    error: &'a Error,
+    source: &'a str,
+    file_name: Option<&'a str>,
+    warning: bool,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// Renders the error, which happened when parsing the `source` document.
+    #[inline]
+    #[must_use]
+    pub fn new(error: &'a Error, source: &'a str) -> Self {
+        Self { error, source, file_name: None, warning: false }
+    }
+
+    /// Name of the document to show with the position, like a file name or a URL.
+    #[inline]
+    #[must_use]
+    pub fn file_name(mut self, file_name: &'a str) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    /// Whether to label it as a warning, e.g. for errors fixed up by the parser. Default is false.
+    #[inline]
+    #[must_use]
+    pub fn warning(mut self, warning: bool) -> Self {
+        self.warning = warning;
+        self
+    }
+}
+
+impl Diagnostic<'_> {
+    /// Column and width in chars of the part of the error span on the line, if there is one
+    fn underline(&self, line: &str) -> Option<(usize, usize)> {
+        let span = self.error.span()?;
+        // the line is a slice of the source
+        let line_start = (line.as_ptr() as usize - self.source.as_ptr() as usize) as u64;
+        let start = span.start.saturating_sub(line_start).min(line.len() as u64) as usize;
+        let end = span.end.saturating_sub(line_start).min(line.len() as u64) as usize;
+        let before = line.get(..start)?.chars().count();
+        let len = line.get(start..end)?.chars().count();
+        (len > 0).then_some((before, len))
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pos = self.error.position();
+        let message = self.error.to_string();
+        // the message starts with the position
+        let message = message.strip_prefix(&format!("{pos} ")).unwrap_or(&message);
+        let label = if self.warning { "warning" } else { "error" };
+        writeln!(f, "{label}[{}]: {message}", self.error.code())?;
+
+        let line_number = (pos.row + 1).to_string();
+        let gutter = " ".repeat(line_number.len());
+        writeln!(f, "{gutter}--> {}:{}:{}", self.file_name.unwrap_or("<input>"), pos.row + 1, pos.column + 1)?;
+        if let Some(line) = self.source.lines().nth(pos.row as usize) {
+            let (start, len) = self.underline(line).unwrap_or((pos.column as usize, 1));
+            // tabs are kept, so the carets line up with the text
+            let indent: String = line.chars().take(start)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            writeln!(f, "{gutter} |")?;
+            writeln!(f, "{line_number} | {line}")?;
+            writeln!(f, "{gutter} | {indent}{}", "^".repeat(len))?;
+        }
+        if let Some(hint) = self.error.hint() {
+            writeln!(f, "{gutter} = hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@ use std::error::Error as _;
 use std::{error, fmt, io, str};
 
 use crate::common::{Position, TextPosition};
+use crate::reader::{Diagnostic, Span};
 use crate::util;
 
 /// Failure reason
@@ -25,7 +26,224 @@ pub enum ErrorKind {
     EmitterError(Box<EmitterError>),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// The cause of a syntax error found by the parser, see [`Error::error_code`].
+///
+/// Unlike the messages, the variants and their names don't change between versions,
+/// but new variants may be added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// A closing tag doesn't match the innermost open element
+    MismatchedClosingTag {
+        /// The name in the closing tag
+        found: String,
+        /// The name of the open element, if there is one
+        expected: Option<String>,
+    },
+    /// A reference to an entity that is not predefined or declared in the DTD
+    UndefinedEntity {
+        /// Name of the entity
+        name: String,
+    },
+    /// `&;`
+    EmptyReference,
+    /// `&` not followed by a name and `;`
+    InvalidReference {
+        /// The text after the reference start
+        found: String,
+    },
+    /// A character reference that isn't a number
+    InvalidCharacterReference {
+        /// The reference without `&#` and `;`
+        reference: String,
+    },
+    /// A character that is not allowed in XML, literally or as a reference
+    InvalidCharacter {
+        /// Unicode scalar value of the character
+        code_point: u32,
+    },
+    /// An attribute appears more than once in a tag
+    DuplicateAttribute {
+        /// Name of the attribute
+        name: String,
+    },
+    /// An element name has a prefix without a namespace declaration
+    UnboundElementPrefix {
+        /// Name of the element
+        name: String,
+    },
+    /// An attribute name has a prefix without a namespace declaration
+    UnboundAttributePrefix {
+        /// Name of the attribute
+        name: String,
+    },
+    /// A name with the `xml` or `xmlns` prefix which can't be used for it
+    ReservedPrefix {
+        /// The prefix
+        prefix: String,
+    },
+    /// The `xmlns` prefix is declared
+    RedefinedXmlnsPrefix,
+    /// The `xml` prefix is declared with a different namespace
+    RedefinedXmlPrefix,
+    /// A prefix is declared with an empty namespace
+    UndeclaredPrefix {
+        /// The prefix
+        prefix: String,
+    },
+    /// A reserved namespace is declared as the default namespace
+    InvalidDefaultNamespace {
+        /// The namespace
+        uri: String,
+    },
+    /// A name that is not a valid qualified name
+    InvalidName {
+        /// The name
+        name: String,
+    },
+    /// Something other than a name where a name is expected
+    UnexpectedTokenInName {
+        /// The text instead of the name
+        found: String,
+    },
+    /// A name in the wrong place, like an unknown keyword in the DTD
+    UnexpectedName {
+        /// The name
+        name: String,
+    },
+    /// `<` in an attribute value
+    LessThanInAttributeValue,
+    /// Something that doesn't belong in an opening tag, such as an unquoted attribute value
+    InvalidOpeningTag {
+        /// The text found
+        found: String,
+    },
+    /// Something that doesn't belong in a closing tag
+    InvalidClosingTag {
+        /// The text found
+        found: String,
+    },
+    /// Markup that starts with `<` but is not valid, such as `<!x` or a `<` in text
+    InvalidMarkup {
+        /// The start of the markup
+        found: String,
+    },
+    /// Something that is not allowed where it was found
+    UnexpectedToken {
+        /// The text found
+        found: String,
+    },
+    /// Text before or after the root element
+    TextOutsideRoot {
+        /// The text found
+        found: String,
+    },
+    /// The document has no element
+    NoRootElement,
+    /// The document ended before the root element was closed
+    UnclosedRootElement,
+    /// The document ended inside markup
+    UnexpectedEof,
+    /// The document ended inside a CDATA section
+    UnclosedCdata,
+    /// A processing instruction without a target name
+    UnnamedProcessingInstruction,
+    /// Something that doesn't belong in a processing instruction
+    InvalidProcessingInstruction {
+        /// The start of the processing instruction
+        found: String,
+    },
+    /// `<?xml` after the start of the document
+    MisplacedXmlDeclaration {
+        /// The name of the processing instruction
+        name: String,
+    },
+    /// An unknown name in the XML declaration
+    InvalidXmlDeclaration {
+        /// The name
+        name: String,
+    },
+    /// An XML version other than 1.0 and 1.1
+    UnsupportedVersion {
+        /// The version in the declaration
+        version: String,
+    },
+    /// The `standalone` declaration is not `yes` or `no`
+    InvalidStandalone {
+        /// The value in the declaration
+        value: String,
+    },
+    /// An encoding that is not supported
+    UnsupportedEncoding {
+        /// The name of the encoding
+        encoding: String,
+    },
+    /// The encoding in the XML declaration doesn't match the byte order mark
+    ConflictingEncoding {
+        /// Encoding from the XML declaration
+        declared: Encoding,
+        /// Encoding used by the document
+        detected: Encoding,
+    },
+    /// A markup declaration in the DTD that is not supported
+    UnknownMarkupDeclaration {
+        /// The name of the declaration
+        name: String,
+    },
+    /// Entities expand to too much text, see `ParserConfig::max_entity_expansion_length`
+    EntityTooBig,
+    /// The document exceeds a limit set in `ParserConfig`
+    ExceededConfiguredLimit,
+}
+
+impl ErrorCode {
+    /// A short identifier of the error, such as `undefined-entity` or `mismatched-closing-tag`.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::MismatchedClosingTag { .. } => "mismatched-closing-tag",
+            Self::UndefinedEntity { .. } => "undefined-entity",
+            Self::EmptyReference => "empty-entity",
+            Self::InvalidReference { .. } => "invalid-reference",
+            Self::InvalidCharacterReference { .. } => "invalid-character-reference",
+            Self::InvalidCharacter { .. } => "invalid-character",
+            Self::DuplicateAttribute { .. } => "duplicate-attribute",
+            Self::UnboundElementPrefix { .. } => "unbound-element-prefix",
+            Self::UnboundAttributePrefix { .. } => "unbound-attribute-prefix",
+            Self::ReservedPrefix { .. } => "invalid-name-prefix",
+            Self::RedefinedXmlnsPrefix => "redefined-xmlns-prefix",
+            Self::RedefinedXmlPrefix => "redefined-xml-prefix",
+            Self::UndeclaredPrefix { .. } => "undefined-prefix-declaration",
+            Self::InvalidDefaultNamespace { .. } => "invalid-default-namespace",
+            Self::InvalidName { .. } => "invalid-qualified-name",
+            Self::UnexpectedTokenInName { .. } => "invalid-name",
+            Self::UnexpectedName { .. } => "unexpected-name",
+            Self::LessThanInAttributeValue => "lt-in-attribute",
+            Self::InvalidOpeningTag { .. } => "invalid-opening-tag",
+            Self::InvalidClosingTag { .. } => "invalid-closing-tag",
+            Self::InvalidMarkup { .. } => "invalid-markup",
+            Self::UnexpectedToken { .. } => "unexpected-token",
+            Self::TextOutsideRoot { .. } => "text-outside-root",
+            Self::NoRootElement => "no-root-element",
+            Self::UnclosedRootElement => "unclosed-root-element",
+            Self::UnexpectedEof => "unexpected-eof",
+            Self::UnclosedCdata => "unclosed-cdata",
+            Self::UnnamedProcessingInstruction => "unnamed-processing-instruction",
+            Self::InvalidProcessingInstruction { .. } => "invalid-processing-instruction",
+            Self::MisplacedXmlDeclaration { .. } => "misplaced-xml-declaration",
+            Self::InvalidXmlDeclaration { .. } => "invalid-xml-declaration",
+            Self::UnsupportedVersion { .. } => "unsupported-version",
+            Self::InvalidStandalone { .. } => "invalid-standalone",
+            Self::UnsupportedEncoding { .. } => "unsupported-encoding",
+            Self::ConflictingEncoding { .. } => "conflicting-encoding",
+            Self::UnknownMarkupDeclaration { .. } => "unknown-markup-declaration",
+            Self::EntityTooBig => "entity-too-big",
+            Self::ExceededConfiguredLimit => "exceeded-limit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub(crate) enum SyntaxError {
     CannotRedefineXmlnsPrefix,
@@ -49,7 +267,8 @@ pub(crate) enum SyntaxError {
     InvalidQualifiedName(Box<str>),
     UnboundAttribute(Box<str>),
     UnboundElementPrefix(Box<str>),
-    UnexpectedClosingTag(Box<str>),
+    /// Name of the closing tag, and of the open element if there is one
+    UnexpectedClosingTag(Box<(Box<str>, Option<Box<str>>)>),
     UnexpectedName(Box<str>),
     /// Found <?xml-like PI not at the beginning of a document,
     /// which is an error, see section 2.6 of XML 1.1 spec
@@ -109,7 +328,10 @@ impl SyntaxError {
             Self::UnboundAttribute(ref name) => format!("Attribute {name} prefix is unbound").into(),
             Self::UnboundElementPrefix(ref name) => format!("Element {name} prefix is unbound").into(),
             Self::UndefinedEntity(ref v) => format!("Undefined entity: {v}").into(),
-            Self::UnexpectedClosingTag(ref expected_got) => format!("Unexpected closing tag: {expected_got}").into(),
+            Self::UnexpectedClosingTag(ref names) => match &**names {
+                (found, Some(expected)) => format!("Unexpected closing tag: {found} != {expected}").into(),
+                (found, None) => format!("Unexpected closing tag: {found}").into(),
+            },
             Self::UnexpectedEntity(ref name) => format!("Unexpected entity: {name}").into(),
             Self::UnexpectedName(ref name) => format!("Unexpected name: {name}").into(),
             Self::UnexpectedNameInsideXml(ref name) => format!("Unexpected name inside XML declaration: {name}").into(),
@@ -127,15 +349,96 @@ impl SyntaxError {
             Self::ExceededConfiguredLimit => "This document is larger/more complex than allowed by the parser's configuration".into(),
         }
     }
+
+    /// The public counterpart of the error
+    pub(crate) fn error_code(&self) -> ErrorCode {
+        self.error_code_with(|s| s.to_string())
+    }
+
+    /// Same as `error_code().as_str()`, without copying the text of the error
+    pub(crate) fn code(&self) -> &'static str {
+        self.error_code_with(|_| String::new()).as_str()
+    }
+
+    /// The public counterpart of the error, with its text made by `text`
+    fn error_code_with(&self, text: impl Fn(&dyn fmt::Display) -> String) -> ErrorCode {
+        match self {
+            Self::CannotRedefineXmlnsPrefix => ErrorCode::RedefinedXmlnsPrefix,
+            Self::CannotRedefineXmlPrefix => ErrorCode::RedefinedXmlPrefix,
+            Self::EmptyEntity => ErrorCode::EmptyReference,
+            Self::EntityTooBig => ErrorCode::EntityTooBig,
+            Self::NoRootElement => ErrorCode::NoRootElement,
+            Self::ProcessingInstructionWithoutName => ErrorCode::UnnamedProcessingInstruction,
+            Self::UnbalancedRootElement => ErrorCode::UnclosedRootElement,
+            Self::UnclosedCdata => ErrorCode::UnclosedCdata,
+            Self::UnexpectedEof => ErrorCode::UnexpectedEof,
+            Self::UnexpectedOpeningTag => ErrorCode::LessThanInAttributeValue,
+            Self::CannotUndefinePrefix(prefix) => ErrorCode::UndeclaredPrefix { prefix: text(prefix) },
+            Self::ConflictingEncoding(declared, detected) => ErrorCode::ConflictingEncoding { declared: *declared, detected: *detected },
+            Self::InvalidCharacterEntity(code_point) => ErrorCode::InvalidCharacter { code_point: *code_point },
+            Self::InvalidDefaultNamespace(uri) => ErrorCode::InvalidDefaultNamespace { uri: text(uri) },
+            Self::InvalidNamePrefix(prefix) => ErrorCode::ReservedPrefix { prefix: text(prefix) },
+            Self::InvalidNumericEntity(reference) => ErrorCode::InvalidCharacterReference { reference: text(reference) },
+            Self::InvalidQualifiedName(name) => ErrorCode::InvalidName { name: text(name) },
+            Self::InvalidStandaloneDeclaration(value) => ErrorCode::InvalidStandalone { value: text(value) },
+            Self::InvalidXmlProcessingInstruction(name) => ErrorCode::MisplacedXmlDeclaration { name: text(name) },
+            Self::RedefinedAttribute(name) => ErrorCode::DuplicateAttribute { name: text(name) },
+            Self::UnboundAttribute(name) => ErrorCode::UnboundAttributePrefix { name: text(name) },
+            Self::UnboundElementPrefix(name) => ErrorCode::UnboundElementPrefix { name: text(name) },
+            Self::UndefinedEntity(name) | Self::UnexpectedEntity(name) => ErrorCode::UndefinedEntity { name: text(name) },
+            Self::UnexpectedClosingTag(names) => ErrorCode::MismatchedClosingTag {
+                found: text(&names.0),
+                expected: names.1.as_ref().map(|name| text(name)),
+            },
+            Self::UnexpectedName(name) => ErrorCode::UnexpectedName { name: text(name) },
+            Self::UnexpectedNameInsideXml(name) => ErrorCode::InvalidXmlDeclaration { name: text(name) },
+            Self::UnexpectedProcessingInstruction(buf, token) => ErrorCode::InvalidProcessingInstruction { found: text(&format_args!("<?{buf}{token}")) },
+            Self::UnexpectedQualifiedName(token) => ErrorCode::UnexpectedTokenInName { found: text(token) },
+            Self::UnexpectedToken(token) => ErrorCode::UnexpectedToken { found: text(token) },
+            Self::UnexpectedTokenBefore(before, c) => ErrorCode::InvalidMarkup { found: text(&format_args!("{before}{c}")) },
+            Self::UnexpectedTokenInClosingTag(token) => ErrorCode::InvalidClosingTag { found: text(token) },
+            Self::UnexpectedTokenInEntity(token) => ErrorCode::InvalidReference { found: text(token) },
+            Self::UnexpectedTokenInOpeningTag(token) => ErrorCode::InvalidOpeningTag { found: text(token) },
+            Self::UnexpectedTokenOutsideRoot(token) => ErrorCode::TextOutsideRoot { found: text(token) },
+            Self::UnexpectedXmlVersion(version) => ErrorCode::UnsupportedVersion { version: text(version) },
+            Self::UnknownMarkupDeclaration(name) => ErrorCode::UnknownMarkupDeclaration { name: text(name) },
+            Self::UnsupportedEncoding(encoding) => ErrorCode::UnsupportedEncoding { encoding: text(encoding) },
+            Self::ExceededConfiguredLimit => ErrorCode::ExceededConfiguredLimit,
+        }
+    }
+
+    /// Suggestion for fixing the most likely cause of the error
+    pub(crate) fn hint(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::EmptyEntity | Self::UnexpectedTokenInEntity(_) => "did you mean `&amp;`?",
+            Self::UndefinedEntity(_) | Self::UnexpectedEntity(_) =>
+                "entities other than `&lt;`, `&gt;`, `&amp;`, `&apos;` and `&quot;` must be declared in the DOCTYPE; did you mean `&amp;`?",
+            Self::UnexpectedTokenBefore("<", _) => "did you mean `&lt;`?",
+            Self::UnexpectedOpeningTag => "write `<` in attribute values as `&lt;`",
+            Self::UnexpectedToken(Token::Character(_)) => "attribute values must be quoted",
+            Self::UnexpectedClosingTag(_) => "elements must be closed in the reverse order they were opened",
+            Self::UnbalancedRootElement => "some elements have not been closed",
+            Self::UnboundAttribute(_) | Self::UnboundElementPrefix(_) => "declare the prefix with an `xmlns:prefix` attribute",
+            Self::RedefinedAttribute(_) => "an attribute can appear only once in a tag",
+            Self::UnexpectedTokenOutsideRoot(_) => "the document must have a single root element containing all the text",
+            Self::ConflictingEncoding(..) | Self::UnsupportedEncoding(_) => "the encoding in the XML declaration must match the file",
+            Self::ExceededConfiguredLimit => "the limits can be raised in `ParserConfig`",
+            _ => return None,
+        })
+    }
 }
 
 /// An XML parsing error.
 ///
 /// Consists of a 2D position in a document and a textual message describing the error.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, Debug)]
 pub struct Error {
     pub(crate) pos: TextPosition,
     pub(crate) kind: ErrorKind,
+    /// The cause of `ErrorKind::Syntax` errors from the parser
+    pub(crate) syntax: Option<Box<SyntaxError>>,
+    /// Byte range of the markup with the error, when recording spans
+    pub(crate) span: Option<Span>,
 }
 
 impl fmt::Display for Error {
@@ -153,6 +456,15 @@ impl fmt::Display for Error {
     }
 }
 
+impl PartialEq for Error {
+    /// The cause of syntax errors is not compared, since it's also in the message
+    fn eq(&self, other: &Self) -> bool {
+        self.pos == other.pos && self.kind == other.kind
+    }
+}
+
+impl Eq for Error {}
+
 impl Position for Error {
     #[inline]
     fn position(&self) -> TextPosition { self.pos }
@@ -171,10 +483,71 @@ impl Error {
         &self.kind
     }
 
-    pub(crate) fn syntax(syntax_msg: Cow<'static, str>, pos: TextPosition) -> Self {
+    /// The cause of a syntax error found by the parser, for handling specific errors.
+    ///
+    /// `None` for other kinds of errors, and for syntax errors that don't come from the parser.
+    ///
+    /// ```rust
+    /// use xml::reader::ErrorCode;
+    /// use xml::EventReader;
+    ///
+    /// let error = EventReader::from_str("<a><b></a>").into_iter().find_map(Result::err).unwrap();
+    /// assert_eq!(error.error_code(), Some(ErrorCode::MismatchedClosingTag {
+    ///     found: "a".into(),
+    ///     expected: Some("b".into()),
+    /// }));
+    /// ```
+    #[must_use]
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        self.syntax.as_ref().map(|e| e.error_code())
+    }
+
+    /// Identifier of the kind of error, such as `undefined-entity` or `mismatched-closing-tag`,
+    /// for tools reporting errors. Unlike the message, it doesn't change between versions.
+    ///
+    /// It's [`ErrorCode::as_str`] for syntax errors found by the parser, and `syntax` for other syntax errors.
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match &self.kind {
+            ErrorKind::Syntax(_) => self.syntax.as_ref().map_or("syntax", |e| e.code()),
+            ErrorKind::Io(_) => "io",
+            ErrorKind::Utf8(_) => "invalid-utf8",
+            ErrorKind::UnexpectedEof => "unexpected-eof",
+            ErrorKind::EmitterError(_) => "writer",
+        }
+    }
+
+    /// Byte range of the markup or text the error was found in.
+    ///
+    /// Available for syntax errors found by the parser with [`ParserConfig::record_spans`](crate::ParserConfig::record_spans),
+    /// otherwise `None`. The [position](Position::position) of the error is inside or at the end of it.
+    #[must_use]
+    #[inline]
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// A suggestion for fixing the most likely cause of the error, if there is one.
+    #[must_use]
+    pub fn hint(&self) -> Option<&'static str> {
+        self.syntax.as_ref()?.hint()
+    }
+
+    /// Renders the error with the line of the source document where it happened.
+    ///
+    /// `source` is the whole document that has been parsed.
+    #[inline]
+    #[must_use]
+    pub fn diagnostic<'a>(&'a self, source: &'a str) -> Diagnostic<'a> {
+        Diagnostic::new(self, source)
+    }
+
+    pub(crate) fn syntax(e: SyntaxError, pos: TextPosition) -> Self {
         Self {
-            kind: ErrorKind::Syntax(syntax_msg),
-            pos
+            kind: ErrorKind::Syntax(e.to_cow()),
+            pos,
+            syntax: Some(Box::new(e)),
+            span: None,
         }
     }
 }
@@ -196,6 +569,8 @@ impl<'a, P, M> From<(&'a P, M)> for Error where P: Position, M: Into<Cow<'static
         Self {
             pos: orig.0.position(),
             kind: ErrorKind::Syntax(orig.1.into()),
+            syntax: None,
+            span: None,
         }
     }
 }
@@ -211,6 +586,8 @@ impl From<util::CharReadError> for Error {
                 Utf8(reason) => ErrorKind::Utf8(reason),
                 Io(io_error) => ErrorKind::Io(io_error),
             },
+            syntax: None,
+            span: None,
         }
     }
 }
@@ -221,6 +598,8 @@ impl From<io::Error> for Error {
         Self {
             pos: TextPosition::new(),
             kind: ErrorKind::Io(e),
+            syntax: None,
+            span: None,
         }
     }
 }
@@ -231,13 +610,15 @@ impl From<EmitterError> for Error {
         Self {
             pos: TextPosition::new(),
             kind: ErrorKind::EmitterError(Box::new(e)),
+            syntax: None,
+            span: None,
         }
     }
 }
 
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Self {
-        Self{ kind, pos: TextPosition::new() }
+        Self { kind, pos: TextPosition::new(), syntax: None, span: None }
     }
 }
 
@@ -115,6 +115,15 @@ pub enum XmlEvent {
         /// Everything including `<` and `>`
         syntax: String,
     },
+
+    /// Denotes a reference to an entity, which has not been expanded.
+    ///
+    /// It is emitted instead of the replacement text of entities declared in the DTD
+    /// when `ParserConfig::keep_entity_references` is set.
+    EntityReference {
+        /// Name of the entity, without `&` and `;`.
+        name: String,
+    },
 }
 
 impl fmt::Debug for XmlEvent {
@@ -151,6 +160,8 @@ impl fmt::Debug for XmlEvent {
                 write!(f, "Whitespace({data})"),
             Self::Doctype { syntax } =>
                 write!(f, "Doctype({syntax})"),
+            Self::EntityReference { name } =>
+                write!(f, "EntityReference({name})"),
         }
     }
 }
@@ -221,6 +232,7 @@ impl XmlEvent {
             Self::Characters(data) |
             Self::Whitespace(data) => Some(crate::writer::events::XmlEvent::Characters(data)),
             Self::Doctype { syntax } => Some(crate::writer::events::XmlEvent::Doctype(syntax)),
+            Self::EntityReference { name } => Some(crate::writer::events::XmlEvent::EntityReference(name)),
             Self::EndDocument => None,
         }
     }
@@ -2,12 +2,12 @@
 //!
 //! This module is for internal use. Use `xml::pull` module to do parsing.
 
-use crate::common::{is_name_char, is_whitespace_char, is_xml10_char, is_xml11_char, Position, TextPosition};
+use crate::common::{is_name_char, is_name_start_char, is_whitespace_char, is_xml10_char, is_xml11_char, Position, TextPosition};
 use crate::reader::error::SyntaxError;
 use crate::reader::Error;
-use crate::util::{CharReader, Encoding};
+use crate::util::{CharReadError, CharReader, Encoding};
 use std::collections::VecDeque;
-use std::io::Read;
+use std::io::{self, Read};
 use std::{fmt, result};
 
 use super::ParserConfig;
@@ -209,28 +209,60 @@ macro_rules! dispatch_on_enum_state(
     )
 );
 
+/// Counts, and optionally keeps, the bytes read from the source
+struct CountingReader<'a, R> {
+    inner: &'a mut R,
+    offset: &'a mut u64,
+    raw: Option<&'a mut Vec<u8>>,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        *self.offset += len as u64;
+        if let Some(raw) = &mut self.raw {
+            raw.extend_from_slice(&buf[..len]);
+        }
+        Ok(len)
+    }
+}
+
 /// `Lexer` is a lexer for XML documents, which implements pull API.
 ///
 /// Main method is `next_token` which accepts an `std::io::Read` instance and
 /// tries to read the next lexeme from it.
 ///
-/// When `skip_errors` flag is set, invalid lexemes will be returned as `Chunk`s.
-/// When it is not set, errors will be reported as `Err` objects with a string message.
-/// By default this flag is not set. Use `enable_errors` and `disable_errors` methods
-/// to toggle the behavior.
+/// When `skip_errors` flag is set, invalid lexemes will be returned as `Character`s,
+/// and the errors are kept for `take_skipped_errors`. When it is not set, errors will be
+/// reported as `Err` objects with a string message. By default this flag is set by
+/// `ParserConfig::recover_from_errors`.
 pub(crate) struct Lexer {
     st: State,
     reader: CharReader,
     pos: TextPosition,
     head_pos: TextPosition,
-    char_queue: VecDeque<char>,
+    /// Byte offset of the last token
+    offset: u64,
+    /// Byte offset after the last char, not counting unread chars
+    head_offset: u64,
+    /// Byte offset in the source
+    source_offset: u64,
+    /// Byte spans of the last two chars, for unreading
+    recent: [(u64, u64); 2],
+    /// Whether byte offsets are counted, for spans
+    count_offsets: bool,
+    /// Source bytes, if capturing
+    raw: Option<Vec<u8>>,
+    /// Chars to read again, with the byte offset after them
+    char_queue: VecDeque<(char, u64)>,
     /// Default state to go back to after a tag end (may be `InsideDoctype`)
     normal_state: State,
     inside_token: bool,
     eof_handled: bool,
     reparse_depth: u8,
-    #[cfg(test)]
     skip_errors: bool,
+    /// Errors turned into characters, if `skip_errors` is set
+    skipped_errors: Vec<Error>,
 
     max_entity_expansion_depth: u8,
     max_entity_expansion_length: usize,
@@ -249,14 +281,20 @@ impl Lexer {
             reader: CharReader::new(),
             pos: TextPosition::new(),
             head_pos: TextPosition::new(),
+            offset: 0,
+            head_offset: 0,
+            source_offset: 0,
+            recent: [(0, 0); 2],
+            count_offsets: config.record_spans || config.capture_raw,
+            raw: config.capture_raw.then(Vec::new),
             char_queue: VecDeque::with_capacity(4), // TODO: check size
             st: State::Normal,
             normal_state: State::Normal,
             inside_token: false,
             eof_handled: false,
             reparse_depth: 0,
-            #[cfg(test)]
-            skip_errors: false,
+            skip_errors: config.recover_from_errors,
+            skipped_errors: Vec::new(),
 
             max_entity_expansion_depth: config.max_entity_expansion_depth,
             max_entity_expansion_length: config.max_entity_expansion_length,
@@ -271,10 +309,38 @@ impl Lexer {
         self.reader.encoding = encoding;
     }
 
+    /// Returns the byte offset where the last token starts
+    #[inline]
+    pub(crate) fn offset(&self) -> u64 { self.offset }
+
+    /// Returns the byte offset where the last token ends
+    #[inline]
+    pub(crate) fn head_offset(&self) -> u64 { self.head_offset }
+
+    /// Returns the captured source bytes, starting at the given offset, if capturing
+    pub(crate) fn raw(&self, start: u64, end: u64) -> Option<&[u8]> {
+        let raw = self.raw.as_ref()?;
+        let base = self.source_offset - raw.len() as u64;
+        raw.get(start.checked_sub(base)? as usize..end.checked_sub(base)? as usize)
+    }
+
+    /// Forgets the captured source bytes before the offset
+    pub(crate) fn discard_raw(&mut self, offset: u64) {
+        if let Some(raw) = &mut self.raw {
+            let base = self.source_offset - raw.len() as u64;
+            raw.drain(..(offset.saturating_sub(base) as usize).min(raw.len()));
+        }
+    }
+
     /// Disables error handling so `next_token` will return `Some(Chunk(..))`
     /// upon invalid lexeme with this lexeme content.
     #[cfg(test)] fn disable_errors(&mut self) { self.skip_errors = true; }
 
+    /// Moves the errors turned into characters to the given list
+    pub(crate) fn take_skipped_errors(&mut self, errors: &mut Vec<Error>) {
+        errors.append(&mut self.skipped_errors);
+    }
+
     /// Reset the eof handled flag of the lexer.
     #[inline]
     pub fn reset_eof_handled(&mut self) { self.eof_handled = false; }
@@ -296,11 +362,13 @@ impl Lexer {
 
         if !self.inside_token {
             self.pos = self.head_pos;
+            self.offset = self.head_offset;
             self.inside_token = true;
         }
 
         // Check if we have saved a char or two for ourselves
-        while let Some(c) = self.char_queue.pop_front() {
+        while let Some((c, end)) = self.char_queue.pop_front() {
+            self.advance_offset(self.head_offset, end);
             if let Some(t) = self.dispatch_char(c)? {
                 self.inside_token = false;
                 return Ok(t);
@@ -308,13 +376,26 @@ impl Lexer {
         }
         // if char_queue is empty, all circular reparsing is done
         self.reparse_depth = 0;
-        while let Some(c) = self.reader.next_char_from(b)? {
+        while let Some(c) = self.read_char(b)? {
             if c == '\n' {
                 self.head_pos.new_line();
             } else {
                 self.head_pos.advance(1);
             }
 
+            if self.count_offsets {
+                let end = self.source_offset;
+                let start = if self.head_offset == 0 {
+                    // skip the byte order mark
+                    let start = end.saturating_sub(self.encoded_len(c));
+                    self.offset = start;
+                    start
+                } else {
+                    self.head_offset
+                };
+                self.advance_offset(start, end);
+            }
+
             if let Some(t) = self.dispatch_char(c)? {
                 self.inside_token = false;
                 return Ok(t);
@@ -324,6 +405,30 @@ impl Lexer {
         self.end_of_stream()
     }
 
+    #[inline]
+    fn read_char<B: Read>(&mut self, b: &mut B) -> result::Result<Option<char>, CharReadError> {
+        if !self.count_offsets {
+            return self.reader.next_char_from(b);
+        }
+        let mut source = CountingReader { inner: b, offset: &mut self.source_offset, raw: self.raw.as_mut() };
+        self.reader.next_char_from(&mut source)
+    }
+
+    #[inline]
+    fn advance_offset(&mut self, start: u64, end: u64) {
+        self.recent = [self.recent[1], (start, end)];
+        self.head_offset = end;
+    }
+
+    /// Number of bytes of the char in the source
+    fn encoded_len(&self, c: char) -> u64 {
+        match self.reader.encoding {
+            Encoding::Latin1 | Encoding::Ascii => 1,
+            Encoding::Utf16Be | Encoding::Utf16Le | Encoding::Utf16 => 2 * c.len_utf16() as u64,
+            _ => c.len_utf8() as u64,
+        }
+    }
+
     #[inline(never)]
     fn end_of_stream(&mut self) -> Result<Token> {
         // Handle end of stream
@@ -353,9 +458,8 @@ impl Lexer {
     }
 
     #[cold]
-    #[allow(clippy::needless_pass_by_value)]
     fn error(&self, e: SyntaxError) -> Error {
-        Error::syntax(e.to_cow(), self.position())
+        Error::syntax(e, self.position())
     }
 
     #[inline(never)]
@@ -400,9 +504,15 @@ impl Lexer {
         token
     }
 
+    /// Unreads the last chars, which must have been the last ones read
     fn move_to_with_unread(&mut self, st: State, cs: &[char], token: Token) -> Token {
-        for c in cs.iter().rev().copied() {
-            self.char_queue.push_front(c);
+        debug_assert!(cs.len() <= self.recent.len());
+        let recent = &self.recent[self.recent.len() - cs.len()..];
+        for (c, &(_, end)) in cs.iter().zip(recent).rev() {
+            self.char_queue.push_front((*c, end));
+        }
+        if let Some(&(start, _)) = recent.first() {
+            self.head_offset = start;
         }
         self.move_to_with(st, token)
     }
@@ -420,7 +530,7 @@ impl Lexer {
         self.eof_handled = false;
         self.char_queue.reserve(markup.len());
         for c in markup.chars().rev() {
-            self.char_queue.push_front(c);
+            self.char_queue.push_front((c, self.head_offset));
         }
 
         Ok(())
@@ -430,13 +540,14 @@ impl Lexer {
     fn handle_error(&mut self, chunk: &'static str, c: char) -> Result {
         debug_assert!(!chunk.is_empty());
 
-        #[cfg(test)]
         if self.skip_errors {
+            self.skipped_errors.push(self.error(SyntaxError::UnexpectedTokenBefore(chunk, c)));
             let mut chars = chunk.chars();
             let first = chars.next().unwrap_or('\0');
-            self.char_queue.extend(chars);
-            self.char_queue.push_back(c);
-            return Ok(Some(self.move_to_with(State::Normal, Token::Character(first))));
+            self.char_queue.extend(chars.chain([c]).map(|c| (c, self.head_offset)));
+            // a stray `--` doesn't end the comment
+            let st = if matches!(self.st, State::CommentClosing(_)) { State::InsideComment } else { self.normal_state };
+            return Ok(Some(self.move_to_with(st, Token::Character(first))));
         }
         Err(self.error(SyntaxError::UnexpectedTokenBefore(chunk, c)))
     }
@@ -493,6 +604,8 @@ impl Lexer {
             '?'                        => Ok(Some(self.move_to_with(State::InsideProcessingInstruction, Token::ProcessingInstructionStart))),
             '/'                        => Ok(Some(self.move_to_with(self.normal_state, Token::ClosingTagStart))),
             '!'                        => Ok(self.move_to(State::CommentOrCDataOrDoctypeStarted)),
+            // `a < b` and `a <3` are text when recovering
+            _ if self.skip_errors && !is_name_start_char(c) => self.handle_error("<", c),
             _ if is_whitespace_char(c) => Ok(Some(self.move_to_with_unread(self.normal_state, &[c], Token::OpeningTagStart))),
             _ if is_name_char(c)       => Ok(Some(self.move_to_with_unread(self.normal_state, &[c], Token::OpeningTagStart))),
             _                          => self.handle_error("<", c)
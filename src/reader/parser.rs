@@ -2,14 +2,18 @@
 
 use crate::common::{is_xml10_char, is_xml11_char, is_xml11_char_not_restricted, is_name_char, is_name_start_char, is_whitespace_char};
 use crate::common::{Position, TextPosition, XmlVersion};
+use crate::attribute::OwnedAttribute;
 use crate::name::OwnedName;
-use crate::namespace::NamespaceStack;
+use crate::namespace::{NamespaceStack, NS_XML_URI};
 use crate::reader::config::ParserConfig;
 use crate::reader::error::SyntaxError;
 use crate::reader::error::Error;
 use crate::reader::events::XmlEvent;
 use crate::reader::indexset::AttributesSet;
 use crate::reader::lexer::{Lexer, Token};
+use crate::reader::scope::{XmlScope, XmlSpace};
+use crate::reader::span::Span;
+use crate::uri;
 
 use std::collections::HashMap;
 use std::io::Read;
@@ -38,7 +42,8 @@ gen_takes!(
     element_name -> take_element_name, Option<OwnedName>, None;
 
     attr_name    -> take_attr_name, Option<OwnedName>, None;
-    attributes   -> take_attributes, AttributesSet, AttributesSet::new()
+    attributes   -> take_attributes, AttributesSet, AttributesSet::new();
+    attribute_spans -> take_attribute_spans, Vec<Span>, Vec::new()
 );
 
 mod inside_cdata;
@@ -80,6 +85,22 @@ pub(crate) struct PullParser {
     inside_whitespace: bool,
     read_prefix_separator: bool,
     pop_namespace: bool,
+
+    /// `xml:*` attributes in effect, and the depth of the element that set them
+    scopes: Vec<(usize, XmlScope)>,
+
+    /// Byte ranges of the last event and its attributes, if recording
+    span: Span,
+    attribute_spans: Vec<Span>,
+    /// Byte offsets where the pending text and the last markup start
+    text_start: u64,
+    markup_start: u64,
+    implicit_start_document: bool,
+
+    /// Errors fixed up when recovering
+    warnings: Vec<Error>,
+    /// Depth to close the open elements down to, when recovering
+    close_to_depth: Option<usize>,
 }
 
 // Keeps track when XML declaration can happen
@@ -109,6 +130,7 @@ impl PullParser {
 
         let mut pos = Vec::with_capacity(16);
         pos.push(TextPosition::new());
+        let scope = XmlScope { base_uri: config.base_uri.clone(), ..XmlScope::default() };
 
         Self {
             config,
@@ -128,8 +150,11 @@ impl PullParser {
                 ref_data: String::new(),
                 element_name: None,
                 quote: None,
+                unquoted: false,
                 attr_name: None,
                 attributes: AttributesSet::new(),
+                attr_start: 0,
+                attribute_spans: Vec::new(),
             },
             final_result: None,
             next_event: None,
@@ -140,12 +165,25 @@ impl PullParser {
             inside_whitespace: true,
             read_prefix_separator: false,
             pop_namespace: false,
+            scopes: vec![(0, scope)],
+
+            span: Span::default(),
+            attribute_spans: Vec::new(),
+            text_start: 0,
+            markup_start: 0,
+            implicit_start_document: false,
+
+            warnings: Vec::new(),
+            close_to_depth: None,
         }
     }
 
     /// Checks if this parser ignores the end of stream errors.
     pub fn is_ignoring_end_of_stream(&self) -> bool { self.config.ignore_end_of_stream }
 
+    #[inline]
+    pub(crate) fn config(&self) -> &ParserConfig { &self.config }
+
     /// Retrieves the Doctype from the document if any
     #[inline]
     #[deprecated(note = "there is `XmlEvent::Doctype` now")]
@@ -165,6 +203,7 @@ impl PullParser {
         // emit this declaration as the next event.
         if prev_enc == Encountered::None {
             self.push_pos();
+            self.implicit_start_document = true;
             Some(Ok(XmlEvent::StartDocument {
                 version: DEFAULT_VERSION,
                 encoding: self.lexer.encoding().to_string(),
@@ -314,8 +353,11 @@ struct MarkupData {
     element_name: Option<OwnedName>,  // used for element name
 
     quote: Option<QuoteToken>,  // used to hold opening quote for attribute value
+    unquoted: bool,  // used when recovering an attribute value without quotes
     attr_name: Option<OwnedName>,  // used to hold attribute name
     attributes: AttributesSet,   // used to hold all accumulated attributes
+    attr_start: u64,  // used to hold the byte offset of the attribute name
+    attribute_spans: Vec<Span>,  // used to hold the byte ranges of accumulated attributes
 }
 
 impl PullParser {
@@ -328,13 +370,41 @@ impl PullParser {
             return ev.clone();
         }
 
+        if self.records_spans() {
+            self.lexer.discard_raw(self.span.end);
+        }
+
         if let Some(ev) = self.next_event.take() {
+            if self.records_spans() {
+                // the end of an empty element, or a processing instruction after an implied declaration
+                self.span = match ev {
+                    Ok(XmlEvent::EndElement { .. }) => Span::new(self.span.end, self.span.end),
+                    Ok(XmlEvent::EntityReference { .. }) => Span::new(self.markup_start, self.lexer.head_offset()),
+                    _ => Span::new(self.span.start, self.lexer.head_offset()),
+                };
+                self.attribute_spans.clear();
+            }
             return ev;
         }
 
         if self.pop_namespace {
             self.pop_namespace = false;
             self.nst.pop();
+            if self.scopes.last().is_some_and(|&(depth, _)| depth == self.depth() + 1) {
+                self.scopes.pop();
+            }
+        }
+
+        if self.close_to_depth.is_some() {
+            if let Some(ev) = self.emit_implied_end_element() {
+                self.next_pos();
+                if self.records_spans() {
+                    let end = self.lexer.head_offset();
+                    self.span = Span::new(end, end);
+                    self.attribute_spans.clear();
+                }
+                return ev;
+            }
         }
 
         loop {
@@ -343,16 +413,28 @@ impl PullParser {
 
             // While lexer gives us Ok(maybe_token) -- we loop.
             // Upon having a complete XML-event -- we return from the whole function.
-            match self.lexer.next_token(r) {
+            let token = self.lexer.next_token(r);
+            if self.config.recover_from_errors {
+                self.lexer.take_skipped_errors(&mut self.warnings);
+            }
+            match token {
                 Ok(Token::Eof) => {
                     // Forward pos to the lexer head
                     self.next_pos();
+                    if self.records_spans() {
+                        let end = self.lexer.head_offset();
+                        self.span = Span::new(end, end);
+                        self.attribute_spans.clear();
+                    }
                     return self.handle_eof();
                 },
                 Ok(token) => match self.dispatch_token(token) {
                     None => continue,
                     Some(Ok(xml_event)) => {
                         self.next_pos();
+                        if self.records_spans() {
+                            self.record_span(&xml_event);
+                        }
                         return Ok(xml_event);
                     },
                     Some(Err(xml_error)) => {
@@ -383,6 +465,13 @@ impl PullParser {
             self.final_result = None;
             self.lexer.reset_eof_handled();
             return self.error(SyntaxError::UnbalancedRootElement);
+        } else if self.config.recover_from_errors && self.st == State::OutsideTag {
+            self.warn(SyntaxError::UnbalancedRootElement);
+            self.close_to_depth = Some(0);
+            if let Some(text) = self.take_text_event() {
+                return text;
+            }
+            return self.emit_implied_end_element().unwrap_or(Ok(XmlEvent::EndDocument));
         } else {
             self.error(SyntaxError::UnbalancedRootElement)
         };
@@ -398,9 +487,37 @@ impl PullParser {
     }
 
     #[cold]
-    #[allow(clippy::needless_pass_by_value)]
     fn error(&self, e: SyntaxError) -> Result {
-        Err(Error::syntax(e.to_cow(), self.lexer.position()))
+        Err(self.syntax_error(e))
+    }
+
+    /// Keeps the error as a warning, when recovering from it
+    #[cold]
+    fn warn(&mut self, e: SyntaxError) {
+        let error = self.syntax_error(e);
+        self.warnings.push(error);
+    }
+
+    fn syntax_error(&self, e: SyntaxError) -> Error {
+        let mut error = Error::syntax(e, self.lexer.position());
+        if self.records_spans() {
+            // outside of markup, only the last token is known
+            let start = match self.st {
+                State::OutsideTag | State::DocumentStart => self.lexer.offset(),
+                _ => self.markup_start,
+            };
+            error.span = Some(Span::new(start, self.lexer.head_offset()));
+        }
+        error
+    }
+
+    /// Errors fixed up so far, when recovering
+    pub(crate) fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    pub(crate) fn take_warnings(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.warnings)
     }
 
     #[inline]
@@ -431,8 +548,58 @@ impl PullParser {
         }
     }
 
+    #[inline]
+    fn records_spans(&self) -> bool {
+        self.config.record_spans || self.config.capture_raw
+    }
+
+    fn record_span(&mut self, event: &XmlEvent) {
+        let (start, end) = match event {
+            // text before a kept entity reference ends where the reference starts
+            XmlEvent::Characters(_) | XmlEvent::Whitespace(_) if matches!(self.next_event, Some(Ok(XmlEvent::EntityReference { .. }))) =>
+                (self.text_start, self.markup_start),
+            XmlEvent::Characters(_) | XmlEvent::Whitespace(_) => (self.text_start, self.lexer.offset()),
+            XmlEvent::StartDocument { .. } if std::mem::take(&mut self.implicit_start_document) => (self.markup_start, self.markup_start),
+            _ => (self.markup_start, self.lexer.head_offset()),
+        };
+        self.span = Span::new(start, end);
+        if !matches!(event, XmlEvent::StartElement { .. }) {
+            self.attribute_spans.clear();
+        }
+    }
+
+    /// Byte range of the last event, if recording
+    pub(crate) fn span(&self) -> Option<Span> {
+        self.records_spans().then_some(self.span)
+    }
+
+    /// Byte ranges of the attributes of the last `StartElement`, if recording
+    pub(crate) fn attribute_spans(&self) -> &[Span] {
+        &self.attribute_spans
+    }
+
+    /// Source bytes of the last event, if capturing
+    pub(crate) fn raw(&self) -> Option<&[u8]> {
+        self.lexer.raw(self.span.start, self.span.end)
+    }
+
+    /// Source bytes from the offset until the end of the last event, if capturing.
+    /// The offset must not be before the end of the previous event.
+    pub(crate) fn raw_since(&self, offset: u64) -> Option<&[u8]> {
+        self.lexer.raw(offset.min(self.span.end), self.span.end)
+    }
+
     #[inline(never)]
     fn dispatch_token(&mut self, t: Token) -> Option<Result> {
+        if matches!(self.st, State::OutsideTag | State::DocumentStart) && self.records_spans() {
+            if self.buf.is_empty() {
+                self.text_start = self.lexer.offset();
+            }
+            if matches!(t, Token::OpeningTagStart | Token::ClosingTagStart | Token::CommentStart |
+                Token::CDataStart | Token::DoctypeStart | Token::ProcessingInstructionStart | Token::ReferenceStart) {
+                self.markup_start = self.lexer.offset();
+            }
+        }
         match self.st {
             State::OutsideTag                     => self.outside_tag(t),
             State::InsideOpeningTag(s)            => self.inside_opening_tag(t, s),
@@ -541,9 +708,20 @@ impl PullParser {
     fn read_attribute_value<F>(&mut self, t: Token, on_value: F) -> Option<Result>
       where F: Fn(&mut Self, String) -> Option<Result> {
         match t {
+            // the end of a value without quotes, which is then followed by the token
+            _ if self.data.unquoted && (matches!(t, Token::TagEnd | Token::EmptyTagEnd | Token::ProcessingInstructionEnd) ||
+                matches!(t, Token::Character(c) if is_whitespace_char(c))) => {
+                self.data.unquoted = false;
+                let value = self.take_buf();
+                match on_value(self, value) {
+                    None => self.dispatch_token(t),
+                    result => result,
+                }
+            },
+
             Token::Character(c) if self.data.quote.is_none() && is_whitespace_char(c) => None, // skip leading whitespace
 
-            Token::DoubleQuote | Token::SingleQuote => match self.data.quote {
+            Token::DoubleQuote | Token::SingleQuote if !self.data.unquoted => match self.data.quote {
                 None => {  // Entered attribute value
                     self.data.quote = QuoteToken::from_token(t);
                     None
@@ -567,11 +745,17 @@ impl PullParser {
                 },
             },
 
-            Token::ReferenceStart if self.data.quote.is_some() => {
+            Token::ReferenceStart if self.data.quote.is_some() || self.data.unquoted => {
                 self.state_after_reference = self.st;
                 self.into_state_continue(State::InsideReference)
             },
 
+            Token::OpeningTagStart if self.config.recover_from_errors && (self.data.quote.is_some() || self.data.unquoted) => {
+                self.warn(SyntaxError::UnexpectedOpeningTag);
+                t.push_to_string(&mut self.buf);
+                None
+            },
+
             Token::OpeningTagStart | Token::ProcessingInstructionStart => Some(self.error(SyntaxError::UnexpectedOpeningTag)),
 
             Token::Character(c) if !self.is_valid_xml_char_not_restricted(c) => {
@@ -579,7 +763,7 @@ impl PullParser {
             },
 
             // Every character except " and ' and < is okay
-            _ if self.data.quote.is_some() => {
+            _ if self.data.quote.is_some() || self.data.unquoted => {
                 if self.buf.len() > self.config.max_attribute_length {
                     return Some(self.error(SyntaxError::ExceededConfiguredLimit));
                 }
@@ -587,6 +771,14 @@ impl PullParser {
                 None
             },
 
+            // a value without quotes ends at whitespace or at the end of the tag
+            Token::Character(_) | Token::EqualsSign | Token::ReferenceEnd if self.config.recover_from_errors => {
+                self.warn(SyntaxError::UnexpectedToken(t));
+                self.data.unquoted = true;
+                t.push_to_string(&mut self.buf);
+                None
+            },
+
             _ => Some(self.error(SyntaxError::UnexpectedToken(t))),
         }
     }
@@ -594,6 +786,7 @@ impl PullParser {
     fn emit_start_element(&mut self, emit_end_element: bool) -> Option<Result> {
         let mut name = self.data.take_element_name()?;
         let mut attributes = self.data.take_attributes().into_vec();
+        self.attribute_spans = self.data.take_attribute_spans();
 
         // check whether the name prefix is bound and fix its namespace
         match self.nst.get(name.borrow().prefix_repr()) {
@@ -614,6 +807,8 @@ impl PullParser {
             }
         }
 
+        self.push_scope(&attributes);
+
         if emit_end_element {
             self.pop_namespace = true;
             self.next_event = Some(Ok(XmlEvent::EndElement {
@@ -630,6 +825,38 @@ impl PullParser {
         }))
     }
 
+    /// Applies `xml:*` attributes of an element that is about to be pushed
+    fn push_scope(&mut self, attributes: &[OwnedAttribute]) {
+        let mut xml_attributes = attributes.iter().filter(|a| a.name.namespace_ref() == Some(NS_XML_URI)).peekable();
+        if xml_attributes.peek().is_none() {
+            return;
+        }
+        let mut scope = self.scope().clone();
+        for attribute in xml_attributes {
+            match &*attribute.name.local_name {
+                "base" => scope.base_uri = uri::resolve(&scope.base_uri, &attribute.value),
+                "lang" => scope.language.clone_from(&attribute.value),
+                "space" => match &*attribute.value {
+                    "preserve" => scope.space = XmlSpace::Preserve,
+                    "default" => scope.space = XmlSpace::Default,
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+        self.scopes.push((self.depth() + 1, scope));
+    }
+
+    /// `xml:*` attributes in effect
+    pub(crate) fn scope(&self) -> &XmlScope {
+        &self.scopes[self.scopes.len() - 1].1
+    }
+
+    /// Whether whitespace is trimmed in the current element, unless `xml:space` preserves it
+    fn trims_whitespace(&self) -> bool {
+        self.config.trim_whitespace && self.scope().space == XmlSpace::Default
+    }
+
     fn emit_end_element(&mut self) -> Option<Result> {
         let mut name = self.data.take_element_name()?;
 
@@ -640,14 +867,36 @@ impl PullParser {
             None => return Some(self.error(SyntaxError::UnboundElementPrefix(name.to_string().into()))),
         }
 
-        let op_name = self.est.pop()?;
-
-        if name == op_name {
+        if self.est.last() == Some(&name) {
+            self.est.pop();
             self.pop_namespace = true;
             self.into_state_emit(State::OutsideTag, Ok(XmlEvent::EndElement { name }))
+        } else if self.config.recover_from_errors {
+            // close the elements inside the one being closed, or ignore the tag if it's not open
+            let expected = self.est.last().map(|n| n.borrow().to_repr().into());
+            self.warn(SyntaxError::UnexpectedClosingTag(Box::new((name.borrow().to_repr().into(), expected))));
+            self.st = State::OutsideTag;
+            self.close_to_depth = self.est.iter().rposition(|n| *n == name);
+            self.emit_implied_end_element()
         } else {
-            Some(self.error(SyntaxError::UnexpectedClosingTag(format!("{name} != {op_name}").into())))
+            let op_name = self.est.pop()?;
+            Some(self.error(SyntaxError::UnexpectedClosingTag(Box::new((name.borrow().to_repr().into(), Some(op_name.borrow().to_repr().into()))))))
+        }
+    }
+
+    /// Closes the innermost open element, until the depth to close to is reached
+    fn emit_implied_end_element(&mut self) -> Option<Result> {
+        let depth = self.close_to_depth?;
+        if self.depth() <= depth {
+            self.close_to_depth = None;
+            return None;
+        }
+        let name = self.est.pop()?;
+        if self.depth() == depth {
+            self.close_to_depth = None;
         }
+        self.pop_namespace = true;
+        Some(Ok(XmlEvent::EndElement { name }))
     }
 
     #[inline]
@@ -787,7 +1036,7 @@ mod tests {
 
         expect_event!(r, p, Ok(XmlEvent::StartDocument { .. }));
         expect_event!(r, p, Err(ref e) =>
-            *e == Error::syntax(SyntaxError::UnexpectedOpeningTag.to_cow(), TextPosition { row: 1, column: 24 }));
+            *e == Error::syntax(SyntaxError::UnexpectedOpeningTag, TextPosition { row: 1, column: 24 }));
     }
 
     #[test]
@@ -800,7 +1049,7 @@ mod tests {
 
         expect_event!(r, p, Ok(XmlEvent::StartDocument { .. }));
         expect_event!(r, p, Err(ref e) =>
-            *e == Error::syntax(SyntaxError::UnexpectedOpeningTag.to_cow(),
+            *e == Error::syntax(SyntaxError::UnexpectedOpeningTag,
                 TextPosition { row: 1, column: 18 }));
     }
 
@@ -4,6 +4,7 @@ use crate::namespace;
 use crate::reader::error::SyntaxError;
 
 use crate::reader::lexer::Token;
+use crate::reader::span::Span;
 
 use super::{OpeningTagSubstate, PullParser, QualifiedNameTarget, Result, State};
 
@@ -40,6 +41,7 @@ impl PullParser {
                         return Some(self.error(SyntaxError::ExceededConfiguredLimit));
                     }
                     self.buf.push(c);
+                    self.data.attr_start = self.lexer.offset();
                     self.into_state_continue(State::InsideOpeningTag(OpeningTagSubstate::InsideAttributeName))
                 },
                 _ => Some(self.error(SyntaxError::UnexpectedTokenInOpeningTag(t))),
@@ -102,6 +104,9 @@ impl PullParser {
                             return Some(this.error(SyntaxError::ExceededConfiguredLimit));
                         }
                         this.data.attributes.push(OwnedAttribute { name, value });
+                        if this.records_spans() {
+                            this.data.attribute_spans.push(Span::new(this.data.attr_start, this.lexer.head_offset()));
+                        }
                         this.into_state_continue(State::InsideOpeningTag(OpeningTagSubstate::AfterAttributeValue))
                     },
                 }
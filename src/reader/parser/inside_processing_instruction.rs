@@ -99,6 +99,15 @@ impl PullParser {
                     Some(self.error(SyntaxError::InvalidCharacterEntity(c as u32)))
                 },
 
+                // The whitespace separating the target from the data is not a part of the data
+                Token::Character(c) if self.buf.is_empty() && is_whitespace_char(c) => None,
+
+                // The lexer reports `/` as a closing tag start
+                Token::ClosingTagStart => {
+                    self.buf.push('/');
+                    None
+                },
+
                 // Any other token should be treated as plain characters
                 _ => {
                     if self.buf.len() > self.config.max_data_length {
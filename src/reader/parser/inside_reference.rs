@@ -1,6 +1,7 @@
 use super::{PullParser, Result, State};
 use crate::common::{is_name_char, is_name_start_char, is_whitespace_char};
 use crate::reader::error::SyntaxError;
+use crate::reader::events::XmlEvent;
 use crate::reader::lexer::Token;
 use std::char;
 
@@ -16,7 +17,7 @@ impl PullParser {
             Token::ReferenceEnd => {
                 let name = self.data.take_ref_data();
                 if name.is_empty() {
-                    return Some(self.error(SyntaxError::EmptyEntity));
+                    return self.recover_reference(&name, t, SyntaxError::EmptyEntity);
                 }
 
                 let c = match &*name {
@@ -27,12 +28,15 @@ impl PullParser {
                     "quot" => Some('"'),
                     _ if name.starts_with('#') => match self.numeric_reference_from_str(&name[1..]) {
                         Ok(c) => Some(c),
-                        Err(e) => return Some(self.error(e)),
+                        Err(e) => return self.recover_reference(&name, t, e),
                     },
                     _ => None,
                 };
                 if let Some(c) = c {
                     self.buf.push(c);
+                } else if self.config.keep_entity_references && self.state_after_reference == State::OutsideTag &&
+                    (self.config.extra_entities.contains_key(&name) || self.entities.contains_key(&name)) {
+                    return self.emit_entity_reference(name);
                 } else if let Some(v) = self.config.extra_entities.get(&name) {
                     self.buf.push_str(v);
                 } else if let Some(v) = self.entities.get(&name) {
@@ -47,7 +51,8 @@ impl PullParser {
                         self.buf.push_str(v);
                     }
                 } else {
-                    return Some(self.error(SyntaxError::UnexpectedEntity(name.into())));
+                    let e = SyntaxError::UnexpectedEntity(name.as_str().into());
+                    return self.recover_reference(&name, t, e);
                 }
                 let prev_st = self.state_after_reference;
                 if prev_st == State::OutsideTag && !is_whitespace_char(self.buf.chars().last().unwrap_or('\0')) {
@@ -56,7 +61,45 @@ impl PullParser {
                 self.into_state_continue(prev_st)
             },
 
-            _ => Some(self.error(SyntaxError::UnexpectedTokenInEntity(t))),
+            _ => {
+                let name = self.data.take_ref_data();
+                self.recover_reference(&name, t, SyntaxError::UnexpectedTokenInEntity(t))
+            },
+        }
+    }
+
+    /// Keeps an invalid reference as text when recovering, and continues with the token after it
+    fn recover_reference(&mut self, name: &str, t: Token, e: SyntaxError) -> Option<Result> {
+        if !self.config.recover_from_errors {
+            return Some(self.error(e));
+        }
+        self.warn(e);
+        self.buf.push('&');
+        self.buf.push_str(name);
+        self.st = self.state_after_reference;
+        if self.st == State::OutsideTag {
+            self.inside_whitespace = false;
+        }
+        if t == Token::ReferenceEnd {
+            t.push_to_string(&mut self.buf);
+            None
+        } else {
+            self.dispatch_token(t)
+        }
+    }
+
+    /// Emits the reference after the text before it
+    fn emit_entity_reference(&mut self, name: String) -> Option<Result> {
+        let text = self.take_text_event();
+        // balanced by the reference event, like the markup after text
+        self.push_pos();
+        self.st = State::OutsideTag;
+        let reference = Ok(XmlEvent::EntityReference { name });
+        if text.is_some() {
+            self.next_event = Some(reference);
+            text
+        } else {
+            Some(reference)
         }
     }
 
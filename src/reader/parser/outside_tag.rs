@@ -14,7 +14,7 @@ impl PullParser {
             Token::Character(c) => {
                 if is_whitespace_char(c) {
                     // skip whitespace outside of the root element
-                    if (self.config.trim_whitespace && self.buf.is_empty()) ||
+                    if (self.trims_whitespace() && self.buf.is_empty()) ||
                         (self.depth() == 0 && self.config.ignore_root_level_whitespace) {
                             return None;
                     }
@@ -89,22 +89,7 @@ impl PullParser {
             _ => {
                 // Encountered some markup event, flush the buffer as characters
                 // or a whitespace
-                let mut next_event = if self.buf_has_data() {
-                    let buf = self.take_buf();
-                    if self.inside_whitespace && self.config.trim_whitespace {
-                        // there will be no event emitted for this, but start of buffering has pushed a pos
-                        self.next_pos();
-                        None
-                    } else if self.inside_whitespace && !self.config.whitespace_to_characters {
-                        debug_assert!(buf.chars().all(|ch| ch.is_whitespace()), "ws={buf:?}");
-                        Some(Ok(XmlEvent::Whitespace(buf)))
-                    } else if self.config.trim_whitespace {
-                        Some(Ok(XmlEvent::Characters(buf.trim_matches(is_whitespace_char).into())))
-                    } else {
-                        Some(Ok(XmlEvent::Characters(buf)))
-                    }
-                } else { None };
-                self.inside_whitespace = true;  // Reset inside_whitespace flag
+                let mut next_event = self.take_text_event();
 
                 // pos is popped whenever an event is emitted, so pushes must happen only if there will be an event to balance it
                 // and ignored comments don't pop
@@ -120,7 +105,7 @@ impl PullParser {
                         self.into_state(State::InsideOpeningTag(OpeningTagSubstate::InsideName), next_event)
                     },
 
-                    Token::ClosingTagStart if self.depth() > 0 =>
+                    Token::ClosingTagStart if self.depth() > 0 || self.config.recover_from_errors =>
                         self.into_state(State::InsideClosingTag(ClosingTagSubstate::CTInsideName), next_event),
 
                     Token::CommentStart => {
@@ -154,6 +139,27 @@ impl PullParser {
         }
     }
 
+    /// Flushes the buffer as characters or whitespace, if there is an event for it
+    pub(crate) fn take_text_event(&mut self) -> Option<Result> {
+        let next_event = if self.buf_has_data() {
+            let buf = self.take_buf();
+            if self.inside_whitespace && self.trims_whitespace() {
+                // there will be no event emitted for this, but start of buffering has pushed a pos
+                self.next_pos();
+                None
+            } else if self.inside_whitespace && !self.config.whitespace_to_characters {
+                debug_assert!(buf.chars().all(|ch| ch.is_whitespace()), "ws={buf:?}");
+                Some(Ok(XmlEvent::Whitespace(buf)))
+            } else if self.trims_whitespace() {
+                Some(Ok(XmlEvent::Characters(buf.trim_matches(is_whitespace_char).into())))
+            } else {
+                Some(Ok(XmlEvent::Characters(buf)))
+            }
+        } else { None };
+        self.inside_whitespace = true;  // Reset inside_whitespace flag
+        next_event
+    }
+
     pub fn document_start(&mut self, t: Token) -> Option<Result> {
         debug_assert!(self.encountered < Encountered::Declaration);
 
@@ -0,0 +1,26 @@
+//! Contains the `xml:*` attributes that apply to descendants.
+
+/// Whitespace handling requested by `xml:space`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum XmlSpace {
+    /// `xml:space="default"`, or no `xml:space`: applications may handle whitespace as they see fit.
+    #[default]
+    Default,
+    /// `xml:space="preserve"`: whitespace is significant.
+    /// [`ParserConfig::trim_whitespace`](crate::ParserConfig::trim_whitespace) doesn't apply.
+    Preserve,
+}
+
+/// The `xml:base`, `xml:lang` and `xml:space` in effect, inherited from ancestors.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct XmlScope {
+    /// The base URI, with `xml:base` attributes resolved against
+    /// [`ParserConfig::base_uri`](crate::ParserConfig::base_uri). It's relative if that is.
+    pub base_uri: String,
+
+    /// The language tag from `xml:lang`. Empty if unspecified, or reset by `xml:lang=""`.
+    pub language: String,
+
+    /// The whitespace handling from `xml:space`.
+    pub space: XmlSpace,
+}
@@ -0,0 +1,52 @@
+//! Contains byte ranges of the source document.
+
+use std::fmt;
+use std::ops::Range;
+
+/// A range of bytes in the source document.
+///
+/// Offsets count all bytes read from the source, including a byte order mark, so they can be
+/// used for slicing the original input regardless of its encoding.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Span {
+    /// Offset of the first byte
+    pub start: u64,
+    /// Offset after the last byte
+    pub end: u64,
+}
+
+impl Span {
+    /// Creates a span of bytes from `start` until `end`.
+    #[inline]
+    #[must_use]
+    pub const fn new(start: u64, end: u64) -> Self {
+        Self { start, end }
+    }
+
+    /// Number of bytes in the span.
+    #[inline]
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Whether the span has no bytes, e.g. for events implied by the markup.
+    #[inline]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+
+    /// The span as a range for slicing the source in memory.
+    #[inline]
+    #[must_use]
+    pub const fn range(&self) -> Range<usize> {
+        self.start as usize..self.end as usize
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
@@ -0,0 +1,776 @@
+//! Contains a [serde](https://serde.rs) `Serializer` writing to an [`EventWriter`].
+//!
+//! This module is available with the `serde` feature. It uses the same conventions as
+//! the [`de`](crate::de) module, so serialized values can be read back:
+//!
+//! * a struct is written as an element: fields named `@name` are attributes, and must come before
+//!   other fields, a field named `$text` is text content, and other fields are child elements;
+//! * attributes named `@xmlns` and `@xmlns:prefix` declare namespaces;
+//! * a sequence field is written as repeated elements named after the field;
+//! * a field named `$value` is written without a wrapping element: enum variants become element
+//!   names, structs are named after their type, and primitives are text;
+//! * top-level structs are named after their type, unless a root name is set in [`Serializer::with_root()`].
+//!
+//! Text and attribute values are escaped by the writer. Indentation and other formatting
+//! options come from the [`EmitterConfig`] of the writer.
+//!
+//! ```rust
+//! use serde::Serialize;
+//! use xml::EmitterConfig;
+//!
+//! #[derive(Serialize)]
+//! #[serde(rename = "feed")]
+//! struct Feed {
+//!     #[serde(rename = "@xmlns")]
+//!     xmlns: &'static str,
+//!     title: &'static str,
+//!     #[serde(rename = "entry")]
+//!     entries: Vec<Entry>,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct Entry {
+//!     #[serde(rename = "@id")]
+//!     id: u32,
+//!     #[serde(rename = "$text")]
+//!     text: &'static str,
+//! }
+//!
+//! let feed = Feed {
+//!     xmlns: "http://www.w3.org/2005/Atom",
+//!     title: "News & views",
+//!     entries: vec![Entry { id: 1, text: "First" }, Entry { id: 2, text: "<Second>" }],
+//! };
+//! let config = EmitterConfig::new().perform_indent(true).write_document_declaration(false);
+//! assert_eq!(xml::se::to_string_with_config(&feed, config).unwrap(), r#"<feed xmlns="http://www.w3.org/2005/Atom">
+//!   <title>News &amp; views</title>
+//!   <entry id="1">First</entry>
+//!   <entry id="2">&lt;Second&gt;</entry>
+//! </feed>"#);
+//! ```
+
+use std::fmt;
+use std::io::Write;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::namespace::NS_XMLNS_PREFIX;
+use crate::writer::{self, EmitterConfig, EventWriter, XmlEvent};
+
+/// A serialization error.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The writer failed
+    Writer(writer::Error),
+    /// The value can't be represented in XML using the conventions of this module
+    Message(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Writer(e) => e.fmt(f),
+            Self::Message(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Writer(e) => Some(e),
+            Self::Message(_) => None,
+        }
+    }
+}
+
+impl From<writer::Error> for Error {
+    #[cold]
+    fn from(e: writer::Error) -> Self {
+        Self::Writer(e)
+    }
+}
+
+impl ser::Error for Error {
+    #[cold]
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+fn unsupported<T>(what: &str) -> Result<T> {
+    Err(Error::Message(format!("{what} can't be serialized as XML")))
+}
+
+/// Serializes the value as a document written to the writer.
+pub fn to_writer<W: Write, T: ?Sized + Serialize>(writer: &mut EventWriter<W>, value: &T) -> Result<()> {
+    value.serialize(&mut Serializer::new(writer))
+}
+
+/// Serializes the value as a string, using the default [`EmitterConfig`].
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String> {
+    to_string_with_config(value, EmitterConfig::new())
+}
+
+/// Serializes the value as a string, using the given configuration.
+pub fn to_string_with_config<T: ?Sized + Serialize>(value: &T, config: EmitterConfig) -> Result<String> {
+    let mut writer = EventWriter::new_with_config(Vec::new(), config);
+    to_writer(&mut writer, value)?;
+    String::from_utf8(writer.into_inner()).map_err(|e| Error::Message(e.to_string()))
+}
+
+/// A serde `Serializer` writing a document as events to an [`EventWriter`].
+pub struct Serializer<'w, W: Write> {
+    writer: &'w mut EventWriter<W>,
+    root: Option<String>,
+}
+
+impl<'w, W: Write> Serializer<'w, W> {
+    /// Creates a serializer naming the root element after the type of the value.
+    pub fn new(writer: &'w mut EventWriter<W>) -> Self {
+        Self { writer, root: None }
+    }
+
+    /// Creates a serializer using the given name for the root element.
+    ///
+    /// A root name is required for values other than structs and enums.
+    pub fn with_root(writer: &'w mut EventWriter<W>, root: impl Into<String>) -> Self {
+        Self { writer, root: Some(root.into()) }
+    }
+
+    fn root(&mut self, type_name: Option<&str>) -> Result<ElementSerializer<'_, W>> {
+        match self.root.as_deref().or(type_name) {
+            Some(name) => Ok(ElementSerializer { writer: self.writer, name: name.to_owned(), repeat: false }),
+            None => Err(Error::Message("serializing this value requires a root element name".into())),
+        }
+    }
+}
+
+impl<W: Write> fmt::Debug for Serializer<'_, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Serializer").field("root", &self.root).finish_non_exhaustive()
+    }
+}
+
+macro_rules! forward_to_root {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ret:ty, $type_name:expr;)*) => {$(
+        #[inline]
+        fn $method(self, $($arg: $ty),*) -> Result<$ret> {
+            let type_name: Option<&str> = $type_name;
+            self.root(type_name)?.$method($($arg),*)
+        }
+    )*};
+}
+
+/// Serializes the root element
+impl<'s, W: Write> ser::Serializer for &'s mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'s, W>;
+    type SerializeTuple = SeqSerializer<'s, W>;
+    type SerializeTupleStruct = SeqSerializer<'s, W>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = StructSerializer<'s, W>;
+    type SerializeStruct = StructSerializer<'s, W>;
+    type SerializeStructVariant = StructSerializer<'s, W>;
+
+    forward_to_root! {
+        serialize_bool(v: bool) -> (), None;
+        serialize_i8(v: i8) -> (), None;
+        serialize_i16(v: i16) -> (), None;
+        serialize_i32(v: i32) -> (), None;
+        serialize_i64(v: i64) -> (), None;
+        serialize_i128(v: i128) -> (), None;
+        serialize_u8(v: u8) -> (), None;
+        serialize_u16(v: u16) -> (), None;
+        serialize_u32(v: u32) -> (), None;
+        serialize_u64(v: u64) -> (), None;
+        serialize_u128(v: u128) -> (), None;
+        serialize_f32(v: f32) -> (), None;
+        serialize_f64(v: f64) -> (), None;
+        serialize_char(v: char) -> (), None;
+        serialize_str(v: &str) -> (), None;
+        serialize_bytes(v: &[u8]) -> (), None;
+        serialize_unit() -> (), None;
+        serialize_unit_struct(name: &'static str) -> (), Some(name);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str) -> (), Some(name);
+        serialize_seq(len: Option<usize>) -> Self::SerializeSeq, None;
+        serialize_tuple(len: usize) -> Self::SerializeTuple, None;
+        serialize_tuple_struct(name: &'static str, len: usize) -> Self::SerializeTupleStruct, Some(name);
+        serialize_tuple_variant(name: &'static str, index: u32, variant: &'static str, len: usize) -> Self::SerializeTupleVariant, Some(name);
+        serialize_map(len: Option<usize>) -> Self::SerializeMap, None;
+        serialize_struct(name: &'static str, len: usize) -> Self::SerializeStruct, Some(name);
+        serialize_struct_variant(name: &'static str, index: u32, variant: &'static str, len: usize) -> Self::SerializeStructVariant, Some(name);
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, name: &'static str, value: &T) -> Result<()> {
+        self.root(Some(name))?.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, name: &'static str, index: u32, variant: &'static str, value: &T) -> Result<()> {
+        self.root(Some(name))?.serialize_newtype_variant(name, index, variant, value)
+    }
+}
+
+/// Writes a value as an element, or as repeated elements if it's a sequence
+struct ElementSerializer<'w, W: Write> {
+    writer: &'w mut EventWriter<W>,
+    name: String,
+    /// Whether items of sequences are written as elements of the same name
+    repeat: bool,
+}
+
+impl<W: Write> ElementSerializer<'_, W> {
+    fn text_element(self, text: &str) -> Result<()> {
+        self.writer.write(XmlEvent::start_element(self.name.as_str()))?;
+        if !text.is_empty() {
+            self.writer.write(XmlEvent::Characters(text))?;
+        }
+        self.writer.write(XmlEvent::end_element())?;
+        Ok(())
+    }
+}
+
+macro_rules! serialize_as_text {
+    ($($method:ident($ty:ty),)*) => {$(
+        #[inline]
+        fn $method(self, v: $ty) -> Result<()> {
+            self.text_element(&v.to_string())
+        }
+    )*};
+}
+
+impl<'w, W: Write> ser::Serializer for ElementSerializer<'w, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'w, W>;
+    type SerializeTuple = SeqSerializer<'w, W>;
+    type SerializeTupleStruct = SeqSerializer<'w, W>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = StructSerializer<'w, W>;
+    type SerializeStruct = StructSerializer<'w, W>;
+    type SerializeStructVariant = StructSerializer<'w, W>;
+
+    serialize_as_text! {
+        serialize_bool(bool), serialize_i8(i8), serialize_i16(i16), serialize_i32(i32), serialize_i64(i64),
+        serialize_i128(i128), serialize_u8(u8), serialize_u16(u16), serialize_u32(u32), serialize_u64(u64),
+        serialize_u128(u128), serialize_f32(f32), serialize_f64(f64), serialize_char(char),
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.text_element(v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        unsupported("bytes")
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.text_element("")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.text_element("")
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<()> {
+        self.text_element(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    /// The variant is a child element
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<()> {
+        self.writer.write(XmlEvent::start_element(self.name.as_str()))?;
+        value.serialize(ElementSerializer { writer: &mut *self.writer, name: variant.into(), repeat: false })?;
+        self.writer.write(XmlEvent::end_element())?;
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer<'w, W>> {
+        if self.repeat {
+            Ok(SeqSerializer { writer: self.writer, item_name: Some(self.name), wrapped: false })
+        } else {
+            self.writer.write(XmlEvent::start_element(self.name.as_str()))?;
+            Ok(SeqSerializer { writer: self.writer, item_name: None, wrapped: true })
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'w, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer<'w, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
+        unsupported("tuple variants")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<StructSerializer<'w, W>> {
+        Ok(StructSerializer::new(self.writer, self.name, 0))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<StructSerializer<'w, W>> {
+        Ok(StructSerializer::new(self.writer, self.name, 0))
+    }
+
+    /// The variant is a child element
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<StructSerializer<'w, W>> {
+        self.writer.write(XmlEvent::start_element(self.name.as_str()))?;
+        Ok(StructSerializer::new(self.writer, variant.into(), 1))
+    }
+}
+
+/// Writes a `$value` field: enum variants and structs as elements named after them, and primitives as text
+struct ContentSerializer<'w, W: Write> {
+    writer: &'w mut EventWriter<W>,
+}
+
+impl<'w, W: Write> ContentSerializer<'w, W> {
+    fn text(self, text: &str) -> Result<()> {
+        self.writer.write(XmlEvent::Characters(text))?;
+        Ok(())
+    }
+
+    fn element(self, name: &str) -> ElementSerializer<'w, W> {
+        ElementSerializer { writer: self.writer, name: name.into(), repeat: false }
+    }
+}
+
+macro_rules! serialize_content_as_text {
+    ($($method:ident($ty:ty),)*) => {$(
+        #[inline]
+        fn $method(self, v: $ty) -> Result<()> {
+            self.text(&v.to_string())
+        }
+    )*};
+}
+
+impl<'w, W: Write> ser::Serializer for ContentSerializer<'w, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'w, W>;
+    type SerializeTuple = SeqSerializer<'w, W>;
+    type SerializeTupleStruct = SeqSerializer<'w, W>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = StructSerializer<'w, W>;
+    type SerializeStructVariant = StructSerializer<'w, W>;
+
+    serialize_content_as_text! {
+        serialize_bool(bool), serialize_i8(i8), serialize_i16(i16), serialize_i32(i32), serialize_i64(i64),
+        serialize_i128(i128), serialize_u8(u8), serialize_u16(u16), serialize_u32(u32), serialize_u64(u64),
+        serialize_u128(u128), serialize_f32(f32), serialize_f64(f64), serialize_char(char),
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.text(v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        unsupported("bytes")
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
+        self.element(name).serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<()> {
+        self.element(variant).serialize_unit()
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<()> {
+        value.serialize(self.element(variant))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer<'w, W>> {
+        Ok(SeqSerializer { writer: self.writer, item_name: None, wrapped: false })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'w, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer<'w, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
+        unsupported("tuple variants")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        unsupported("maps without an element name")
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<StructSerializer<'w, W>> {
+        Ok(StructSerializer::new(self.writer, name.into(), 0))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<StructSerializer<'w, W>> {
+        Ok(StructSerializer::new(self.writer, variant.into(), 0))
+    }
+}
+
+/// Writes items of a sequence.
+///
+/// Returned by the [`Serializer`] for sequences and tuples.
+pub struct SeqSerializer<'w, W: Write> {
+    writer: &'w mut EventWriter<W>,
+    /// Name of the element of every item, or `None` to write items as content
+    item_name: Option<String>,
+    /// Whether the sequence is inside an element that has to be closed
+    wrapped: bool,
+}
+
+impl<W: Write> SeqSerializer<'_, W> {
+    fn item<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        match &self.item_name {
+            Some(name) => value.serialize(ElementSerializer { writer: &mut *self.writer, name: name.clone(), repeat: false }),
+            None => value.serialize(ContentSerializer { writer: &mut *self.writer }),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.wrapped {
+            self.writer.write(XmlEvent::end_element())?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for SeqSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for SeqSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for SeqSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Writes fields of a struct or entries of a map as attributes, text and child elements.
+///
+/// Returned by the [`Serializer`] for structs and maps.
+pub struct StructSerializer<'w, W: Write> {
+    writer: &'w mut EventWriter<W>,
+    name: String,
+    /// Attributes collected until the start tag is written
+    attributes: Vec<(String, String)>,
+    started: bool,
+    /// Number of enclosing elements to close after this one
+    outer: usize,
+    key: Option<String>,
+}
+
+impl<'w, W: Write> StructSerializer<'w, W> {
+    fn new(writer: &'w mut EventWriter<W>, name: String, outer: usize) -> Self {
+        Self { writer, name, attributes: Vec::new(), started: false, outer, key: None }
+    }
+
+    fn start(&mut self) -> Result<()> {
+        if !self.started {
+            self.started = true;
+            let mut start = XmlEvent::start_element(self.name.as_str());
+            for (name, value) in &self.attributes {
+                start = match name.split_once(':') {
+                    None if name == NS_XMLNS_PREFIX => start.default_ns(value.as_str()),
+                    Some((NS_XMLNS_PREFIX, prefix)) => start.ns(prefix, value.as_str()),
+                    _ => start.attr(name.as_str(), value),
+                };
+            }
+            self.writer.write(start)?;
+        }
+        Ok(())
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        if let Some(attribute) = key.strip_prefix('@') {
+            if self.started {
+                return Err(Error::Message(format!("attribute '{attribute}' must come before content fields")));
+            }
+            if let Some(value) = value.serialize(TextSerializer)? {
+                self.attributes.push((attribute.into(), value));
+            }
+            return Ok(());
+        }
+        self.start()?;
+        match key {
+            "$text" => {
+                if let Some(text) = value.serialize(TextSerializer)? {
+                    self.writer.write(XmlEvent::Characters(&text))?;
+                }
+                Ok(())
+            },
+            "$value" => value.serialize(ContentSerializer { writer: &mut *self.writer }),
+            name => value.serialize(ElementSerializer { writer: &mut *self.writer, name: name.into(), repeat: true }),
+        }
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.start()?;
+        for _ in 0..=self.outer {
+            self.writer.write(XmlEvent::end_element())?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for StructSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for StructSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeMap for StructSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(TextSerializer)? {
+            Some(key) => {
+                self.key = Some(key);
+                Ok(())
+            },
+            None => unsupported("missing map keys"),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().ok_or_else(|| Error::Message("map value without a key".into()))?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Converts a value to the text of an attribute or a text node, or `None` to omit it
+struct TextSerializer;
+
+macro_rules! serialize_to_string {
+    ($($method:ident($ty:ty),)*) => {$(
+        #[inline]
+        fn $method(self, v: $ty) -> Result<Option<String>> {
+            Ok(Some(v.to_string()))
+        }
+    )*};
+}
+
+impl ser::Serializer for TextSerializer {
+    type Ok = Option<String>;
+    type Error = Error;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = Impossible<Option<String>, Error>;
+    type SerializeMap = Impossible<Option<String>, Error>;
+    type SerializeStruct = Impossible<Option<String>, Error>;
+    type SerializeStructVariant = Impossible<Option<String>, Error>;
+
+    serialize_to_string! {
+        serialize_bool(bool), serialize_i8(i8), serialize_i16(i16), serialize_i32(i32), serialize_i64(i64),
+        serialize_i128(i128), serialize_u8(u8), serialize_u16(u16), serialize_u32(u32), serialize_u64(u64),
+        serialize_u128(u128), serialize_f32(f32), serialize_f64(f64), serialize_char(char), serialize_str(&str),
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Option<String>> {
+        unsupported("bytes")
+    }
+
+    fn serialize_none(self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Option<String>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<String>> {
+        Ok(Some(String::new()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<String>> {
+        Ok(Some(String::new()))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Option<String>> {
+        Ok(Some(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Option<String>> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<Option<String>> {
+        unsupported("attributes or text with enum variants holding data")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<ListSerializer> {
+        Ok(ListSerializer { items: Vec::new() })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
+        unsupported("attributes or text with enum variants holding data")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        unsupported("attributes or text with maps")
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        unsupported("attributes or text with structs")
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant> {
+        unsupported("attributes or text with structs")
+    }
+}
+
+/// Whitespace-separated list, like `xs:list`
+struct ListSerializer {
+    items: Vec<String>,
+}
+
+impl ListSerializer {
+    fn item<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.items.extend(value.serialize(TextSerializer)?);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Option<String>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Option<String>> {
+        Ok(Some(self.items.join(" ")))
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Option<String>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Option<String>> {
+        Ok(Some(self.items.join(" ")))
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Option<String>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Option<String>> {
+        Ok(Some(self.items.join(" ")))
+    }
+}
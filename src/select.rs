@@ -0,0 +1,569 @@
+//! Contains a CSS selector engine for querying documents in memory.
+//!
+//! Selectors are matched against elements of a [`CompactDocument`](crate::dom::compact::CompactDocument).
+//! The supported syntax is:
+//!
+//! * type selectors: `name`, `*`, and with namespaces `prefix|name`, `*|name`, `|name` (no namespace);
+//! * `#id` and `.class`, testing the `id` and `class` attributes;
+//! * attribute selectors: `[attr]`, `[attr=val]`, `[attr~=val]`, `[attr|=val]`, `[attr^=val]`,
+//!   `[attr$=val]`, `[attr*=val]`, also with namespace prefixes;
+//! * pseudo-classes: `:nth-child()`, `:nth-last-child()`, `:nth-of-type()`, `:nth-last-of-type()`,
+//!   `:first-child`, `:last-child`, `:only-child`, `:first-of-type`, `:last-of-type`, `:only-of-type`,
+//!   `:root`, `:empty`, and `:not()`;
+//! * combinators: descendant (whitespace), child `>`, next sibling `+` and subsequent sibling `~`,
+//!   and selector lists separated by commas.
+//!
+//! As in CSS, unprefixed type selectors match elements in any namespace, unless a default namespace
+//! is bound, and unprefixed attribute names match only attributes without a namespace.
+//!
+//! ```rust
+//! use xml::dom::compact::CompactDocument;
+//! use xml::namespace::Namespace;
+//! use xml::select::Selector;
+//!
+//! let doc: CompactDocument = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+//!     <entry><link rel="alternate" href="/1.html"/><link href="/1"/></entry>
+//!     <entry><link href="/2"/></entry>
+//! </feed>"#.parse().unwrap();
+//!
+//! let selector = Selector::parse("entry:nth-child(odd) > link:not([rel])").unwrap();
+//! let hrefs: Vec<_> = selector.select(doc.root()).map(|e| e.attribute("href").unwrap()).collect();
+//! assert_eq!(hrefs, ["/1"]);
+//!
+//! let mut ns = Namespace::empty();
+//! ns.put("atom", "http://www.w3.org/2005/Atom");
+//! let selector = Selector::parse_with(&ns, "atom|entry atom|link[href^='/2']").unwrap();
+//! assert_eq!(selector.select(doc.root()).count(), 1);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::common::{is_name_char, is_whitespace_char};
+use crate::dom::compact::{NodeKind, NodeRef};
+use crate::name::Name;
+use crate::namespace::{Namespace, NS_NO_PREFIX};
+
+/// An error in a selector.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The selector is not syntactically valid.
+    Syntax {
+        /// Byte offset in the selector.
+        position: usize,
+        /// What was wrong.
+        message: &'static str,
+    },
+
+    /// The prefix is not bound in the namespace mappings.
+    UnboundPrefix(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("selector error: ")?;
+        match self {
+            Self::Syntax { position, message } => write!(f, "{message} at offset {position}"),
+            Self::UnboundPrefix(p) => write!(f, "prefix '{p}' is not bound"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A compiled list of CSS selectors.
+#[derive(Clone, Debug)]
+pub struct Selector {
+    alternatives: Vec<Complex>,
+}
+
+impl Selector {
+    /// Parses the selector, which must not use namespace prefixes.
+    pub fn parse(selector: &str) -> Result<Self, Error> {
+        Self::parse_with(&Namespace::empty(), selector)
+    }
+
+    /// Parses the selector, resolving prefixes using the namespace mappings.
+    ///
+    /// If the default namespace is bound, unprefixed type selectors only match elements in that namespace.
+    pub fn parse_with(namespaces: &Namespace, selector: &str) -> Result<Self, Error> {
+        Parser { src: selector, pos: 0, namespaces }.parse_list()
+    }
+
+    /// Checks whether the node is an element matching any selector in the list.
+    #[must_use]
+    pub fn matches(&self, node: NodeRef<'_>) -> bool {
+        node.is_element() && self.alternatives.iter().any(|c| c.matches(node, c.compounds.len() - 1))
+    }
+
+    /// Returns descendants of the node matching the selector, in document order.
+    pub fn select<'s, 'a: 's>(&'s self, root: NodeRef<'a>) -> impl Iterator<Item = NodeRef<'a>> + 's {
+        root.descendants().filter(move |&n| self.matches(n))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Self::parse(s)
+    }
+}
+
+/// Compound selectors joined by combinators, matched from right to left
+#[derive(Clone, Debug)]
+struct Complex {
+    compounds: Vec<Compound>,
+    /// `combinators[i]` is between `compounds[i]` and `compounds[i + 1]`
+    combinators: Vec<Combinator>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling,
+}
+
+type Compound = Vec<Simple>;
+
+#[derive(Clone, Debug)]
+enum Simple {
+    Type(NameTest),
+    Attribute(NameTest, Option<(AttributeOp, String)>),
+    /// Matches the element at position `a*n + b` among its siblings
+    Nth { a: i64, b: i64, from_end: bool, of_type: bool },
+    Root,
+    Empty,
+    Not(Vec<Compound>),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum AttributeOp {
+    Equals,
+    Includes,
+    DashMatch,
+    Prefix,
+    Suffix,
+    Substring,
+}
+
+#[derive(Clone, Debug)]
+enum NamespaceTest {
+    Any,
+    None,
+    Uri(String),
+}
+
+#[derive(Clone, Debug)]
+struct NameTest {
+    namespace: NamespaceTest,
+    /// `None` for `*`
+    local: Option<String>,
+}
+
+impl NameTest {
+    fn matches(&self, name: Name<'_>) -> bool {
+        let namespace = name.namespace.filter(|ns| !ns.is_empty());
+        let ns_matches = match &self.namespace {
+            NamespaceTest::Any => true,
+            NamespaceTest::None => namespace.is_none(),
+            NamespaceTest::Uri(uri) => namespace == Some(uri.as_str()),
+        };
+        ns_matches && self.local.as_ref().map_or(true, |l| *l == name.local_name)
+    }
+}
+
+impl Complex {
+    fn matches(&self, node: NodeRef<'_>, index: usize) -> bool {
+        if !self.compounds[index].iter().all(|s| s.matches(node)) {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+        let mut candidates: Box<dyn Iterator<Item = NodeRef<'_>>> = match self.combinators[index - 1] {
+            Combinator::Child => Box::new(node.parent().into_iter()),
+            Combinator::Descendant => Box::new(node.ancestors()),
+            Combinator::NextSibling => Box::new(node.preceding_siblings().find(|n| n.is_element()).into_iter()),
+            Combinator::SubsequentSibling => Box::new(node.preceding_siblings()),
+        };
+        candidates.any(|n| n.is_element() && self.matches(n, index - 1))
+    }
+}
+
+impl Simple {
+    fn matches(&self, node: NodeRef<'_>) -> bool {
+        match self {
+            Self::Type(test) => node.name().is_some_and(|n| test.matches(n)),
+            Self::Attribute(test, op) => node.attributes().any(|attr| {
+                test.matches(attr.name) && op.as_ref().map_or(true, |(op, expected)| op.matches(attr.value, expected))
+            }),
+            Self::Nth { a, b, from_end, of_type } => {
+                fn expanded<'a>(n: &NodeRef<'a>) -> Option<(Option<&'a str>, &'a str)> {
+                    n.name().map(|n| (n.namespace, n.local_name))
+                }
+                let name = expanded(&node);
+                let same = |n: &NodeRef<'_>| n.is_element() && (!of_type || expanded(n) == name);
+                let index = if *from_end {
+                    node.following_siblings().filter(same).count()
+                } else {
+                    node.preceding_siblings().filter(same).count()
+                } as i64 + 1;
+                match *a {
+                    0 => index == *b,
+                    a => (index - b) % a == 0 && (index - b) / a >= 0,
+                }
+            },
+            Self::Root => node.parent().is_some_and(|p| p.kind() == NodeKind::Document),
+            Self::Empty => node.children().all(|c| matches!(c.kind(), NodeKind::Comment | NodeKind::ProcessingInstruction)),
+            Self::Not(compounds) => !compounds.iter().any(|c| c.iter().all(|s| s.matches(node))),
+        }
+    }
+}
+
+impl AttributeOp {
+    fn matches(self, value: &str, expected: &str) -> bool {
+        match self {
+            Self::Equals => value == expected,
+            Self::Includes => !expected.is_empty() && value.split(is_whitespace_char).any(|v| v == expected),
+            Self::DashMatch => value == expected || value.strip_prefix(expected).is_some_and(|rest| rest.starts_with('-')),
+            Self::Prefix => !expected.is_empty() && value.starts_with(expected),
+            Self::Suffix => !expected.is_empty() && value.ends_with(expected),
+            Self::Substring => !expected.is_empty() && value.contains(expected),
+        }
+    }
+}
+
+struct Parser<'s, 'n> {
+    src: &'s str,
+    pos: usize,
+    namespaces: &'n Namespace,
+}
+
+impl Parser<'_, '_> {
+    fn error<T>(&self, message: &'static str) -> Result<T, Error> {
+        Err(Error::Syntax { position: self.pos, message })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char, message: &'static str) -> Result<(), Error> {
+        if self.eat(c) { Ok(()) } else { self.error(message) }
+    }
+
+    /// Returns whether any whitespace was skipped
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(is_whitespace_char) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn parse_list(&mut self) -> Result<Selector, Error> {
+        let mut alternatives = Vec::new();
+        loop {
+            self.skip_whitespace();
+            alternatives.push(self.parse_complex()?);
+            if self.pos == self.src.len() {
+                return Ok(Selector { alternatives });
+            }
+            self.expect(',', "expected a combinator or ','")?;
+        }
+    }
+
+    fn parse_complex(&mut self) -> Result<Complex, Error> {
+        let mut complex = Complex { compounds: vec![self.parse_compound()?], combinators: Vec::new() };
+        loop {
+            let whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::NextSibling,
+                Some('~') => Combinator::SubsequentSibling,
+                None | Some(',' | ')') => return Ok(complex),
+                Some(_) if whitespace => Combinator::Descendant,
+                Some(_) => return self.error("expected a combinator"),
+            };
+            if combinator != Combinator::Descendant {
+                self.pos += 1;
+                self.skip_whitespace();
+            }
+            complex.combinators.push(combinator);
+            complex.compounds.push(self.parse_compound()?);
+        }
+    }
+
+    fn parse_compound(&mut self) -> Result<Compound, Error> {
+        let mut compound = Vec::new();
+        if let Some(test) = self.parse_type()? {
+            compound.push(Simple::Type(test));
+        }
+        loop {
+            let start = self.pos;
+            match self.peek() {
+                Some('#') => {
+                    self.pos += 1;
+                    let id = self.parse_ident()?;
+                    compound.push(attribute_equals("id", AttributeOp::Equals, id));
+                },
+                Some('.') => {
+                    self.pos += 1;
+                    let class = self.parse_ident()?;
+                    compound.push(attribute_equals("class", AttributeOp::Includes, class));
+                },
+                Some('[') => {
+                    self.pos += 1;
+                    compound.push(self.parse_attribute()?);
+                },
+                Some(':') => {
+                    self.pos += 1;
+                    self.parse_pseudo_class(&mut compound)?;
+                },
+                _ => break,
+            }
+            debug_assert!(self.pos > start);
+        }
+        if compound.is_empty() {
+            return self.error("expected a selector");
+        }
+        Ok(compound)
+    }
+
+    fn parse_ident(&mut self) -> Result<String, Error> {
+        let mut ident = String::new();
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(c) => {
+                            ident.push(c);
+                            self.pos += c.len_utf8();
+                        },
+                        None => return self.error("unexpected end after '\\'"),
+                    }
+                },
+                Some(c) if is_name_char(c) && c != '.' && c != ':' => {
+                    ident.push(c);
+                    self.pos += c.len_utf8();
+                },
+                _ => break,
+            }
+        }
+        if ident.is_empty() {
+            return self.error("expected a name");
+        }
+        Ok(ident)
+    }
+
+    /// Parses `*` or a name, for use as a local name or a namespace prefix
+    fn parse_name_or_star(&mut self) -> Result<Option<String>, Error> {
+        if self.eat('*') { Ok(None) } else { self.parse_ident().map(Some) }
+    }
+
+    fn resolve(&self, prefix: &str) -> Result<NamespaceTest, Error> {
+        match self.namespaces.get(prefix) {
+            Some("") => Ok(NamespaceTest::None),
+            Some(uri) => Ok(NamespaceTest::Uri(uri.into())),
+            None => Err(Error::UnboundPrefix(prefix.into())),
+        }
+    }
+
+    /// Parses `ns|name`, where `name` and `ns` may be `*` and `ns|` may be omitted or empty
+    fn parse_qualified_name(&mut self, default_namespace: NamespaceTest) -> Result<NameTest, Error> {
+        let namespace_separator = |p: &Self| p.src[p.pos..].starts_with('|') && !p.src[p.pos..].starts_with("|=");
+        if namespace_separator(self) {
+            self.pos += 1;
+            return Ok(NameTest { namespace: NamespaceTest::None, local: self.parse_name_or_star()? });
+        }
+        let first = self.parse_name_or_star()?;
+        if namespace_separator(self) {
+            self.pos += 1;
+            let namespace = match first {
+                None => NamespaceTest::Any,
+                Some(prefix) => self.resolve(&prefix)?,
+            };
+            return Ok(NameTest { namespace, local: self.parse_name_or_star()? });
+        }
+        Ok(NameTest { namespace: default_namespace, local: first })
+    }
+
+    fn parse_type(&mut self) -> Result<Option<NameTest>, Error> {
+        match self.peek() {
+            Some('*' | '|' | '\\') => {},
+            Some(c) if is_name_char(c) && c != '.' && c != ':' && c != '-' => {},
+            _ => return Ok(None),
+        }
+        let default = match self.namespaces.get(NS_NO_PREFIX) {
+            Some(uri) if !uri.is_empty() => NamespaceTest::Uri(uri.into()),
+            _ => NamespaceTest::Any,
+        };
+        self.parse_qualified_name(default).map(Some)
+    }
+
+    fn parse_attribute(&mut self) -> Result<Simple, Error> {
+        self.skip_whitespace();
+        let name = self.parse_qualified_name(NamespaceTest::None)?;
+        if name.local.is_none() {
+            return self.error("expected an attribute name");
+        }
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(Simple::Attribute(name, None));
+        }
+        let op = match self.peek() {
+            Some('=') => AttributeOp::Equals,
+            Some('~') => AttributeOp::Includes,
+            Some('|') => AttributeOp::DashMatch,
+            Some('^') => AttributeOp::Prefix,
+            Some('$') => AttributeOp::Suffix,
+            Some('*') => AttributeOp::Substring,
+            _ => return self.error("expected an attribute operator or ']'"),
+        };
+        self.pos += 1;
+        if op != AttributeOp::Equals {
+            self.expect('=', "expected '='")?;
+        }
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.pos += 1;
+                let Some(len) = self.src[self.pos..].find(quote) else {
+                    return self.error("unterminated string");
+                };
+                let value = self.src[self.pos..self.pos + len].to_owned();
+                self.pos += len + 1;
+                value
+            },
+            _ => self.parse_ident()?,
+        };
+        self.skip_whitespace();
+        self.expect(']', "expected ']'")?;
+        Ok(Simple::Attribute(name, Some((op, value))))
+    }
+
+    fn parse_pseudo_class(&mut self, compound: &mut Compound) -> Result<(), Error> {
+        let start = self.pos;
+        let name = self.parse_ident()?;
+        let nth = |a, b, from_end, of_type| Simple::Nth { a, b, from_end, of_type };
+        match name.as_str() {
+            "first-child" => compound.push(nth(0, 1, false, false)),
+            "last-child" => compound.push(nth(0, 1, true, false)),
+            "only-child" => compound.extend([nth(0, 1, false, false), nth(0, 1, true, false)]),
+            "first-of-type" => compound.push(nth(0, 1, false, true)),
+            "last-of-type" => compound.push(nth(0, 1, true, true)),
+            "only-of-type" => compound.extend([nth(0, 1, false, true), nth(0, 1, true, true)]),
+            "root" => compound.push(Simple::Root),
+            "empty" => compound.push(Simple::Empty),
+            "nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type" => {
+                self.expect('(', "expected '('")?;
+                self.skip_whitespace();
+                let (a, b) = self.parse_nth()?;
+                self.skip_whitespace();
+                self.expect(')', "expected ')'")?;
+                compound.push(nth(a, b, name.contains("last"), name.ends_with("of-type")));
+            },
+            "not" => {
+                self.expect('(', "expected '('")?;
+                let mut compounds = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    compounds.push(self.parse_compound()?);
+                    self.skip_whitespace();
+                    if self.eat(')') {
+                        break;
+                    }
+                    self.expect(',', "expected ',' or ')'")?;
+                }
+                compound.push(Simple::Not(compounds));
+            },
+            _ => {
+                self.pos = start;
+                return self.error("unsupported pseudo-class");
+            },
+        }
+        Ok(())
+    }
+
+    /// Parses the `an+b` syntax, `odd` and `even`
+    fn parse_nth(&mut self) -> Result<(i64, i64), Error> {
+        let rest = &self.src[self.pos..];
+        let len = rest.find(')').unwrap_or(rest.len());
+        let arg: String = rest[..len].chars().filter(|c| !is_whitespace_char(*c)).collect();
+        let parsed = match arg.to_ascii_lowercase().as_str() {
+            "odd" => Some((2, 1)),
+            "even" => Some((2, 0)),
+            arg => match arg.split_once('n') {
+                Some((a, b)) => {
+                    let a = match a {
+                        "" | "+" => Some(1),
+                        "-" => Some(-1),
+                        a => a.parse().ok(),
+                    };
+                    let b = match b {
+                        "" => Some(0),
+                        b if b.starts_with(['+', '-']) => b.parse().ok(),
+                        _ => None,
+                    };
+                    a.zip(b)
+                },
+                None => arg.parse().ok().map(|b| (0, b)),
+            },
+        };
+        match parsed {
+            Some(nth) => {
+                self.pos += len;
+                Ok(nth)
+            },
+            None => self.error("expected an+b, odd or even"),
+        }
+    }
+}
+
+fn attribute_equals(name: &str, op: AttributeOp, value: String) -> Simple {
+    Simple::Attribute(NameTest { namespace: NamespaceTest::None, local: Some(name.into()) }, Some((op, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Selector};
+
+    #[test]
+    fn nth() {
+        let parse = |s: &str| {
+            let Some(super::Simple::Nth { a, b, .. }) = Selector::parse(&format!(":nth-child({s})")).unwrap()
+                .alternatives[0].compounds[0].first().cloned() else { panic!() };
+            (a, b)
+        };
+        assert_eq!(parse("odd"), (2, 1));
+        assert_eq!(parse(" even "), (2, 0));
+        assert_eq!(parse("-n + 3"), (-1, 3));
+        assert_eq!(parse("2n-1"), (2, -1));
+        assert_eq!(parse("n"), (1, 0));
+        assert_eq!(parse("+5"), (0, 5));
+        assert!(Selector::parse(":nth-child(2x)").is_err());
+        assert!(Selector::parse(":nth-child(n3)").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Selector::parse("p|a").unwrap_err(), Error::UnboundPrefix("p".into()));
+        for s in ["", "a >", "a,", "[x", "[x=]", "a:hover", "a b)", ":not(a b)", "[*]"] {
+            assert!(matches!(Selector::parse(s), Err(Error::Syntax { .. })), "{s}");
+        }
+    }
+}
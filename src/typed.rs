@@ -0,0 +1,583 @@
+//! Contains the [`FromXml`] and [`ToXml`] traits mapping Rust types to XML elements.
+//!
+//! Unlike the serde-based [`de`](crate::de) and [`se`](crate::se) modules, these traits know about
+//! attributes, text content and namespaces, so the mapping is declared precisely on each field.
+//! The traits are usually derived, with the `derive` feature enabled:
+//!
+//! * a struct is an element, named after the type or `#[xml(rename = "name")]`;
+//! * fields are child elements named after the fields, and `Option` and `Vec` fields are optional
+//!   and repeated elements;
+//! * `#[xml(attr)]` fields are attributes, and the `#[xml(text)]` field is the text content;
+//! * `#[xml(flatten)]` fields are types whose attributes and children are merged into this element;
+//! * `#[xml(ns = "uri")]` sets the namespace of a type's element and its child elements, or of
+//!   a single field. Namespaced attributes need a prefix, e.g. `#[xml(attr, rename = "x:id", ns = "uri")]`;
+//! * `#[xml(default)]` fields are set to `Default::default()` when missing;
+//! * enums with only unit variants are text values, and other enums are a choice of elements named after
+//!   the variants, which must be unit or newtype variants.
+//!
+//! Text values, such as strings and numbers, implement [`FromXmlText`] and [`ToXmlText`].
+//! Unknown attributes and elements are ignored. Errors report the position of the element,
+//! attribute or text that didn't match.
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")] {
+//! use xml::typed::{FromXml, ToXml};
+//!
+//! #[derive(FromXml, ToXml, Debug, PartialEq)]
+//! #[xml(rename = "feed", ns = "http://www.w3.org/2005/Atom")]
+//! struct Feed {
+//!     title: String,
+//!     #[xml(rename = "entry")]
+//!     entries: Vec<Entry>,
+//! }
+//!
+//! #[derive(FromXml, ToXml, Debug, PartialEq)]
+//! struct Entry {
+//!     #[xml(attr)]
+//!     id: u32,
+//!     #[xml(attr, rename = "xml:lang")]
+//!     lang: Option<String>,
+//!     #[xml(text)]
+//!     text: String,
+//! }
+//!
+//! let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>News</title><entry id="1" xml:lang="en">Hello</entry></feed>"#;
+//! let feed: Feed = xml::typed::from_str(xml).unwrap();
+//! assert_eq!(feed.entries[0], Entry { id: 1, lang: Some("en".into()), text: "Hello".into() });
+//!
+//! let config = xml::EmitterConfig::new().write_document_declaration(false);
+//! assert_eq!(xml::typed::to_string_with_config(&feed, config).unwrap(), xml);
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{Read, Write};
+
+use crate::attribute::{Attribute, OwnedAttribute};
+use crate::common::{is_whitespace_char, Position, TextPosition};
+use crate::name::{Name, OwnedName};
+use crate::namespace::{Namespace, NS_EMPTY_URI, NS_NO_PREFIX, NS_XML_PREFIX};
+use crate::reader::{self, EventReader, ParserConfig};
+use crate::writer::{self, EmitterConfig, EventWriter};
+
+/// Derives [`FromXml`](trait@FromXml), or [`FromXmlText`] for enums with only unit variants.
+#[cfg(feature = "derive")]
+pub use xml_derive::FromXml;
+/// Derives [`ToXml`](trait@ToXml), or [`ToXmlText`] for enums with only unit variants.
+#[cfg(feature = "derive")]
+pub use xml_derive::ToXml;
+
+/// Failure reason of reading or writing a typed value.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The document is malformed or could not be read
+    Reader(reader::Error),
+    /// The writer failed
+    Writer(writer::Error),
+    /// The document doesn't match the structure of the type
+    Message(String),
+}
+
+/// An error of reading or writing a typed value, with a position in the document when reading.
+#[derive(Debug)]
+pub struct Error {
+    /// Not known yet when the error comes from text conversions
+    pos: Option<TextPosition>,
+    kind: ErrorKind,
+}
+
+impl Error {
+    /// Creates an error with a message, which gets the position of the element, attribute or text being read.
+    #[cold]
+    pub fn custom(msg: impl fmt::Display) -> Self {
+        Self { pos: None, kind: ErrorKind::Message(msg.to_string()) }
+    }
+
+    /// Failure reason
+    #[must_use]
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    fn or_at(mut self, pos: TextPosition) -> Self {
+        self.pos.get_or_insert(pos);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.kind, self.pos) {
+            (ErrorKind::Reader(e), _) => e.fmt(f),
+            (ErrorKind::Writer(e), _) => e.fmt(f),
+            (ErrorKind::Message(msg), Some(pos)) => write!(f, "{pos} {msg}"),
+            (ErrorKind::Message(msg), None) => f.write_str(msg),
+        }
+    }
+}
+
+impl Position for Error {
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.pos.unwrap_or_else(TextPosition::new)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Reader(e) => Some(e),
+            ErrorKind::Writer(e) => Some(e),
+            ErrorKind::Message(_) => None,
+        }
+    }
+}
+
+impl From<reader::Error> for Error {
+    #[cold]
+    fn from(e: reader::Error) -> Self {
+        Self { pos: Some(e.position()), kind: ErrorKind::Reader(e) }
+    }
+}
+
+impl From<writer::Error> for Error {
+    #[cold]
+    fn from(e: writer::Error) -> Self {
+        Self { pos: None, kind: ErrorKind::Writer(e) }
+    }
+}
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A type read from an element.
+///
+/// The element is read in steps: [`start()`](FromXml::start) creates a builder, which then receives
+/// the attributes, child elements and text of the element, and [`finish()`](FromXml::finish) makes
+/// the value out of it. Flattened fields forward these steps to the builder of their type.
+pub trait FromXml: Sized {
+    /// Partially read value
+    type Builder;
+
+    /// Checks whether the element is read as this type, when it's in a field expecting elements named
+    /// `field`, or is the root element if `field` is `None`.
+    fn matches(name: &OwnedName, field: Option<Name<'_>>) -> bool {
+        field.map_or(true, |field| is_named(name, field))
+    }
+
+    /// Starts reading the element.
+    fn start(element: &Element) -> Result<Self::Builder>;
+
+    /// Reads an attribute of the element. Returns `false` if the attribute isn't used.
+    fn read_attribute(builder: &mut Self::Builder, attribute: &OwnedAttribute) -> Result<bool> {
+        let _ = (builder, attribute);
+        Ok(false)
+    }
+
+    /// Reads a child element using [`Child::read()`]. Returns `false` if the child isn't used, and it will be skipped.
+    fn read_child<R: Read>(builder: &mut Self::Builder, child: &mut Child<'_, R>) -> Result<bool> {
+        let _ = (builder, child);
+        Ok(false)
+    }
+
+    /// Reads a piece of text content. Returns `false` if the text isn't used.
+    fn read_text(builder: &mut Self::Builder, text: &str) -> Result<bool> {
+        let _ = (builder, text);
+        Ok(false)
+    }
+
+    /// Makes the value after the end of the element.
+    fn finish(builder: Self::Builder, element: &Element) -> Result<Self>;
+}
+
+/// A type written as an element.
+pub trait ToXml {
+    /// Name of the element for this value, when it's in a field writing elements named `field`,
+    /// or is the root element if `field` is `None`.
+    fn element_name(&self, field: Option<Name<'static>>) -> Option<Name<'static>> {
+        field
+    }
+
+    /// Adds attributes of the element to its start tag.
+    fn write_attributes<'a>(&'a self, start: &mut StartTag<'a>) -> Result<()> {
+        let _ = start;
+        Ok(())
+    }
+
+    /// Writes child elements and text of the element.
+    fn write_content<W: Write>(&self, writer: &mut EventWriter<W>) -> Result<()> {
+        let _ = writer;
+        Ok(())
+    }
+}
+
+/// A type read from text content or an attribute value.
+pub trait FromXmlText: Sized {
+    /// Parses the text. Errors get the position of the text or attribute.
+    fn from_xml_text(text: &str) -> Result<Self>;
+}
+
+/// A type written as text content or an attribute value.
+pub trait ToXmlText {
+    /// The text, which will be escaped by the writer.
+    fn to_xml_text(&self) -> Cow<'_, str>;
+}
+
+/// Text values are elements with only text content.
+impl<T: FromXmlText> FromXml for T {
+    type Builder = String;
+
+    fn start(_: &Element) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn read_child<R: Read>(_: &mut String, child: &mut Child<'_, R>) -> Result<bool> {
+        Err(child.element().error(format_args!("expected text, found element <{}>", child.name().borrow().repr_display())))
+    }
+
+    fn read_text(builder: &mut String, text: &str) -> Result<bool> {
+        builder.push_str(text);
+        Ok(true)
+    }
+
+    fn finish(builder: String, _: &Element) -> Result<Self> {
+        T::from_xml_text(&builder)
+    }
+}
+
+impl<T: ToXmlText + ?Sized> ToXml for T {
+    fn write_content<W: Write>(&self, writer: &mut EventWriter<W>) -> Result<()> {
+        write_text(writer, self)
+    }
+}
+
+impl FromXmlText for String {
+    fn from_xml_text(text: &str) -> Result<Self> {
+        Ok(text.to_owned())
+    }
+}
+
+impl FromXmlText for bool {
+    /// Accepts the lexical forms of `xs:boolean`
+    fn from_xml_text(text: &str) -> Result<Self> {
+        match text.trim_matches(is_whitespace_char) {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            _ => Err(Error::custom(format_args!("invalid bool '{text}'"))),
+        }
+    }
+}
+
+macro_rules! impl_parsed_text {
+    ($($what:literal => $($ty:ty),+;)*) => {$($(
+        impl FromXmlText for $ty {
+            fn from_xml_text(text: &str) -> Result<Self> {
+                text.trim_matches(is_whitespace_char).parse()
+                    .map_err(|e| Error::custom(format_args!(concat!("invalid ", $what, " '{}': {}"), text, e)))
+            }
+        }
+    )+)*};
+}
+
+impl_parsed_text! {
+    "char" => char;
+    "integer" => i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize;
+    "number" => f32, f64;
+}
+
+impl ToXmlText for str {
+    fn to_xml_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl ToXmlText for String {
+    fn to_xml_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<T: ToXmlText + ?Sized> ToXmlText for &T {
+    fn to_xml_text(&self) -> Cow<'_, str> {
+        (**self).to_xml_text()
+    }
+}
+
+impl ToXmlText for bool {
+    fn to_xml_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(if *self { "true" } else { "false" })
+    }
+}
+
+macro_rules! impl_displayed_text {
+    ($($ty:ty),*) => {$(
+        impl ToXmlText for $ty {
+            fn to_xml_text(&self) -> Cow<'_, str> {
+                Cow::Owned(self.to_string())
+            }
+        }
+    )*};
+}
+
+impl_displayed_text!(char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+/// An element being read.
+#[derive(Debug, Clone)]
+pub struct Element {
+    name: OwnedName,
+    pos: TextPosition,
+}
+
+impl Element {
+    /// Name of the element
+    #[must_use]
+    #[inline]
+    pub fn name(&self) -> &OwnedName {
+        &self.name
+    }
+
+    /// Creates an error at the position of the element's start tag.
+    #[cold]
+    pub fn error(&self, msg: impl fmt::Display) -> Error {
+        Error::custom(msg).or_at(self.pos)
+    }
+}
+
+impl Position for Element {
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.pos
+    }
+}
+
+/// A child element passed to [`FromXml::read_child()`], before its content has been read.
+pub struct Child<'r, R: Read> {
+    reader: &'r mut EventReader<R>,
+    element: Element,
+    attributes: Vec<OwnedAttribute>,
+    read: bool,
+}
+
+impl<R: Read> Child<'_, R> {
+    /// Name of the element
+    #[must_use]
+    #[inline]
+    pub fn name(&self) -> &OwnedName {
+        &self.element.name
+    }
+
+    /// The element's name and position
+    #[must_use]
+    #[inline]
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    /// Reads the element as `T`, up to and including its end tag.
+    pub fn read<T: FromXml>(&mut self) -> Result<T> {
+        if self.read {
+            return Err(self.element.error("the element has already been read"));
+        }
+        self.read = true;
+        read_element(self.reader, &self.element, &std::mem::take(&mut self.attributes))
+    }
+}
+
+impl<R: Read> fmt::Debug for Child<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Child").field("element", &self.element).finish_non_exhaustive()
+    }
+}
+
+fn read_element<T: FromXml, R: Read>(reader: &mut EventReader<R>, element: &Element, attributes: &[OwnedAttribute]) -> Result<T> {
+    let mut builder = T::start(element).map_err(|e| e.or_at(element.pos))?;
+    for attribute in attributes {
+        T::read_attribute(&mut builder, attribute).map_err(|e| e.or_at(element.pos))?;
+    }
+    loop {
+        let event = reader.next()?;
+        let pos = reader.position();
+        match event {
+            reader::XmlEvent::StartElement { name, attributes, .. } => {
+                let mut child = Child { reader: &mut *reader, element: Element { name, pos }, attributes, read: false };
+                let used = T::read_child(&mut builder, &mut child).map_err(|e| e.or_at(pos))?;
+                if !used && !child.read {
+                    skip_element(reader)?;
+                }
+            },
+            reader::XmlEvent::EndElement { .. } => break,
+            reader::XmlEvent::Characters(text) | reader::XmlEvent::CData(text) => {
+                T::read_text(&mut builder, &text).map_err(|e| e.or_at(pos))?;
+            },
+            reader::XmlEvent::EndDocument => return Err(Error::custom("unexpected end of document").or_at(pos)),
+            _ => {},
+        }
+    }
+    T::finish(builder, element).map_err(|e| e.or_at(element.pos))
+}
+
+/// Skips the rest of the current element, including its end
+fn skip_element<R: Read>(reader: &mut EventReader<R>) -> Result<()> {
+    let mut depth = 1_usize;
+    while depth > 0 {
+        match reader.next()? {
+            reader::XmlEvent::StartElement { .. } => depth += 1,
+            reader::XmlEvent::EndElement { .. } => depth -= 1,
+            reader::XmlEvent::EndDocument => return Err(Error::custom("unexpected end of document").or_at(reader.position())),
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+fn is_named(name: &OwnedName, expected: Name<'_>) -> bool {
+    name.local_name == expected.local_name && name.namespace.as_deref() == expected.namespace
+}
+
+/// Reads an instance of `T` from a string of XML.
+pub fn from_str<T: FromXml>(s: &str) -> Result<T> {
+    from_reader(s.as_bytes())
+}
+
+/// Reads an instance of `T` from XML read from the source, with whitespace trimmed from text.
+pub fn from_reader<T: FromXml, R: Read>(source: R) -> Result<T> {
+    let config = ParserConfig::new()
+        .trim_whitespace(true)
+        .cdata_to_characters(true)
+        .coalesce_characters(true);
+    read(&mut EventReader::new_with_config(source, config))
+}
+
+/// Reads an instance of `T` from the next element of the parser, skipping the document prolog.
+pub fn read<T: FromXml, R: Read>(reader: &mut EventReader<R>) -> Result<T> {
+    loop {
+        match reader.next()? {
+            reader::XmlEvent::StartElement { name, attributes, .. } => {
+                let element = Element { name, pos: reader.position() };
+                if !T::matches(&element.name, None) {
+                    return Err(element.error(format_args!("unexpected element <{}>", element.name.borrow().repr_display())));
+                }
+                return read_element(reader, &element, &attributes);
+            },
+            reader::XmlEvent::EndDocument => return Err(Error::custom("no root element").or_at(reader.position())),
+            _ => {},
+        }
+    }
+}
+
+/// The start tag of an element being written, passed to [`ToXml::write_attributes()`].
+#[derive(Debug)]
+pub struct StartTag<'a> {
+    name: Name<'a>,
+    attributes: Vec<(Name<'a>, Cow<'a, str>)>,
+    namespace: Namespace,
+}
+
+impl<'a> StartTag<'a> {
+    fn new(name: Name<'a>) -> Self {
+        let mut start = Self { name, attributes: Vec::new(), namespace: Namespace::empty() };
+        start.bind(name);
+        if name.namespace.is_none() && name.prefix.is_none() {
+            // written as `xmlns=""` if it's inside an element with a default namespace
+            start.namespace.put(NS_NO_PREFIX, NS_EMPTY_URI);
+        }
+        start
+    }
+
+    /// Adds an attribute. The prefix of a namespaced name is declared on the element.
+    pub fn attr(&mut self, name: Name<'a>, value: impl Into<Cow<'a, str>>) {
+        self.bind(name);
+        self.attributes.push((name, value.into()));
+    }
+
+    /// Declares the namespace of the name, unless it's the reserved `xml` prefix
+    fn bind(&mut self, name: Name<'_>) {
+        if let Some(uri) = name.namespace {
+            match name.prefix {
+                Some(NS_XML_PREFIX) => {},
+                prefix => { self.namespace.put(prefix.unwrap_or(NS_NO_PREFIX), uri); },
+            }
+        }
+    }
+}
+
+/// Writes the value as text content, if it's not empty.
+pub fn write_text<W: Write, T: ToXmlText + ?Sized>(writer: &mut EventWriter<W>, value: &T) -> Result<()> {
+    let text = value.to_xml_text();
+    if !text.is_empty() {
+        writer.write(writer::XmlEvent::characters(&text))?;
+    }
+    Ok(())
+}
+
+/// Writes the value as an element, which is named `name` unless the type chooses a name of its own.
+pub fn write_element<W: Write, T: ToXml + ?Sized>(writer: &mut EventWriter<W>, name: Name<'static>, value: &T) -> Result<()> {
+    let name = value.element_name(Some(name)).unwrap_or(name);
+    write_named(writer, name, value)
+}
+
+fn write_named<W: Write, T: ToXml + ?Sized>(writer: &mut EventWriter<W>, name: Name<'_>, value: &T) -> Result<()> {
+    let mut start = StartTag::new(name);
+    value.write_attributes(&mut start)?;
+    let attributes: Vec<_> = start.attributes.iter().map(|(name, value)| Attribute::new(*name, value)).collect();
+    writer.write(writer::XmlEvent::StartElement {
+        name: start.name,
+        attributes: Cow::Owned(attributes),
+        namespace: Cow::Owned(start.namespace),
+    })?;
+    value.write_content(writer)?;
+    writer.write(writer::XmlEvent::end_element())?;
+    Ok(())
+}
+
+/// Writes the value as the root element of a document.
+pub fn to_writer<W: Write, T: ToXml + ?Sized>(writer: &mut EventWriter<W>, value: &T) -> Result<()> {
+    match value.element_name(None) {
+        Some(name) => write_named(writer, name, value),
+        None => Err(Error::custom("writing this value requires a root element name")),
+    }
+}
+
+/// Writes the value as a document to a string, using the default [`EmitterConfig`].
+pub fn to_string<T: ToXml + ?Sized>(value: &T) -> Result<String> {
+    to_string_with_config(value, EmitterConfig::new())
+}
+
+/// Writes the value as a document to a string, using the given configuration.
+pub fn to_string_with_config<T: ToXml + ?Sized>(value: &T, config: EmitterConfig) -> Result<String> {
+    let mut writer = EventWriter::new_with_config(Vec::new(), config);
+    to_writer(&mut writer, value)?;
+    String::from_utf8(writer.into_inner()).map_err(Error::custom)
+}
+
+/// Used by the derived code
+#[doc(hidden)]
+pub mod __private {
+    use super::{is_named, Error, FromXmlText};
+    use crate::attribute::OwnedAttribute;
+    use crate::name::{Name, OwnedName};
+
+    pub use std::io::{Read, Write};
+    pub use std::option::Option::{self, None, Some};
+    pub use std::result::Result::{self, Err, Ok};
+    pub use std::string::String;
+    pub use std::vec::Vec;
+
+    #[must_use]
+    pub fn is(name: &OwnedName, expected: Name<'_>) -> bool {
+        is_named(name, expected)
+    }
+
+    pub fn attribute<T: FromXmlText>(attribute: &OwnedAttribute) -> Result<T, Error> {
+        T::from_xml_text(&attribute.value).map_err(|e| match e.kind {
+            super::ErrorKind::Message(msg) => Error::custom(format_args!("attribute '{}': {msg}", attribute.name)),
+            _ => e,
+        })
+    }
+
+    pub fn optional_text<T: FromXmlText>(text: &str) -> Result<Option<T>, Error> {
+        if text.is_empty() { Ok(None) } else { T::from_xml_text(text).map(Some) }
+    }
+}
@@ -0,0 +1,136 @@
+//! Resolution of relative URI references, as in [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986#section-5.2)
+
+/// Resolves the reference against the base URI, which may itself be relative
+pub(crate) fn resolve(base: &str, reference: &str) -> String {
+    let (scheme, rest) = split_scheme(reference);
+    if scheme.is_some() {
+        let (authority, path, suffix) = split(rest);
+        return format!("{}{authority}{}{suffix}", &reference[..reference.len() - rest.len()], remove_dot_segments(path));
+    }
+    let base = strip_fragment(base);
+    let (_, base_rest) = split_scheme(base);
+    let base_scheme = &base[..base.len() - base_rest.len()];
+    if reference.starts_with("//") {
+        let (authority, path, suffix) = split(reference);
+        return format!("{base_scheme}{authority}{}{suffix}", remove_dot_segments(path));
+    }
+    let (base_authority, base_path, _) = split(base_rest);
+    let (_, path, suffix) = split(reference);
+    let path = if path.is_empty() {
+        if suffix.is_empty() {
+            return base.to_owned();
+        }
+        if suffix.starts_with('#') {
+            return format!("{}{suffix}", strip_fragment(base));
+        }
+        base_path.to_owned()
+    } else if path.starts_with('/') {
+        remove_dot_segments(path)
+    } else {
+        let merged = match base_path.rfind('/') {
+            Some(slash) => format!("{}{path}", &base_path[..=slash]),
+            None if !base_authority.is_empty() => format!("/{path}"),
+            None => path.to_owned(),
+        };
+        remove_dot_segments(&merged)
+    };
+    format!("{base_scheme}{base_authority}{path}{suffix}")
+}
+
+/// Splits `scheme:` off, if there is one
+fn split_scheme(uri: &str) -> (Option<&str>, &str) {
+    let Some(colon) = uri.find(':') else { return (None, uri) };
+    let scheme = &uri[..colon];
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if valid { (Some(scheme), &uri[colon + 1..]) } else { (None, uri) }
+}
+
+/// Splits a URI without scheme into `//authority`, path, and `?query#fragment`
+fn split(uri: &str) -> (&str, &str, &str) {
+    let authority_len = match uri.strip_prefix("//") {
+        Some(rest) => rest.find(['/', '?', '#']).map_or(uri.len(), |end| end + 2),
+        None => 0,
+    };
+    let (authority, rest) = uri.split_at(authority_len);
+    let path_len = rest.find(['?', '#']).unwrap_or(rest.len());
+    let (path, suffix) = rest.split_at(path_len);
+    (authority, path, suffix)
+}
+
+fn strip_fragment(uri: &str) -> &str {
+    uri.split('#').next().unwrap_or_default()
+}
+
+/// Removes `.` and `..` segments. Leading `..` segments of relative paths are kept.
+fn remove_dot_segments(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+    let mut parts = path.split('/').skip(usize::from(absolute)).peekable();
+    while let Some(segment) = parts.next() {
+        let last = parts.peek().is_none();
+        match segment {
+            "." => {
+                if last {
+                    segments.push("");
+                }
+            },
+            ".." => {
+                if segments.last().map_or(true, |&s| s == "..") && !absolute {
+                    segments.push("..");
+                } else {
+                    segments.pop();
+                }
+                if last {
+                    segments.push("");
+                }
+            },
+            segment => segments.push(segment),
+        }
+    }
+    let joined = segments.join("/");
+    if absolute { format!("/{joined}") } else { joined }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve;
+
+    #[test]
+    fn rfc3986_examples() {
+        let base = "http://a/b/c/d;p?q";
+        for (reference, expected) in [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("g/../h", "http://a/b/c/h"),
+        ] {
+            assert_eq!(resolve(base, reference), expected, "{reference}");
+        }
+    }
+
+    #[test]
+    fn relative_bases() {
+        assert_eq!(resolve("", "doc.xml"), "doc.xml");
+        assert_eq!(resolve("docs/index.xml", "chapter.xml"), "docs/chapter.xml");
+        assert_eq!(resolve("docs/index.xml", "../img/a.png"), "img/a.png");
+        assert_eq!(resolve("index.xml", "../a.xml"), "../a.xml");
+        assert_eq!(resolve("docs/sub/", "x.xml#frag"), "docs/sub/x.xml#frag");
+        assert_eq!(resolve("/srv/docs/index.xml", "file:///etc/a.xml"), "file:///etc/a.xml");
+    }
+}
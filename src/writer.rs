@@ -3,24 +3,27 @@
 //! The most important type in this module is `EventWriter` which allows writing an XML document
 //! to some output stream.
 
-pub use self::config::EmitterConfig;
+pub use self::config::{AttributeOrder, EmitterConfig};
 pub use self::emitter::EmitterError as Error;
 pub use self::emitter::Result;
 pub use self::events::XmlEvent;
 
 use self::emitter::Emitter;
+use self::formatter::{xml_space, Formatter};
 
 use std::io::prelude::*;
 
 mod config;
 mod emitter;
 pub mod events;
+mod formatter;
 
 /// A wrapper around an `std::io::Write` instance which emits XML document according to provided
 /// events.
 pub struct EventWriter<W> {
     sink: W,
     emitter: Emitter,
+    formatter: Option<Formatter>,
 }
 
 impl<W: Write> EventWriter<W> {
@@ -35,9 +38,12 @@ impl<W: Write> EventWriter<W> {
     /// configuration.
     #[inline]
     pub fn new_with_config(sink: W, config: EmitterConfig) -> Self {
+        let formatter = (config.perform_indent && config.preserve_mixed_content)
+            .then(|| Formatter::new(config.collapse_whitespace_only_elements));
         Self {
             sink,
             emitter: Emitter::new(config),
+            formatter,
         }
     }
 
@@ -48,14 +54,60 @@ impl<W: Write> EventWriter<W> {
     /// correspond to a separate closing element or it may cause writing an empty element.
     /// Another example is that `XmlEvent::CData` may be represented as characters in
     /// the output stream.
+    ///
+    /// With [`EmitterConfig::preserve_mixed_content`], the events of elements may be written
+    /// by later calls, or by [`flush`](Self::flush).
     pub fn write<'a, E>(&mut self, event: E) -> Result<()> where E: Into<XmlEvent<'a>> {
-        match event.into() {
+        let event = event.into();
+        if let Some(formatter) = &mut self.formatter {
+            let decides_layout = match &event {
+                XmlEvent::StartElement { attributes, .. } =>
+                    !self.emitter.keeps_layout() || xml_space(attributes) == Some("default"),
+                _ => false,
+            };
+            if formatter.is_buffering() || decides_layout {
+                formatter.push(event);
+                return self.write_buffered();
+            }
+        }
+        self.write_event(event)
+    }
+
+    /// Writes the buffered events whose layout is known
+    fn write_buffered(&mut self) -> Result<()> {
+        while let Some(event) = self.formatter.as_mut().and_then(Formatter::take) {
+            self.write_event(event.as_event())?;
+            match event.layout() {
+                Some(true) => self.emitter.keep_layout(),
+                Some(false) => self.emitter.reset_layout(),
+                None => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the events buffered by [`EmitterConfig::preserve_mixed_content`], and flushes
+    /// the underlying writer.
+    ///
+    /// Elements which are still open are indented, as if they contained no text. Use this method
+    /// to write an unfinished document. Writing the document can be continued afterwards.
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(formatter) = &mut self.formatter {
+            formatter.flush();
+            self.write_buffered()?;
+        }
+        self.sink.flush()?;
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: XmlEvent<'_>) -> Result<()> {
+        match event {
             XmlEvent::StartDocument { version, encoding, standalone } =>
                 self.emitter.emit_start_document(&mut self.sink, version, encoding.unwrap_or("UTF-8"), standalone),
             XmlEvent::ProcessingInstruction { name, data } =>
                 self.emitter.emit_processing_instruction(&mut self.sink, name, data),
             XmlEvent::StartElement { name, attributes, namespace } => {
-                self.emitter.namespace_stack_mut().push_empty().checked_target().extend(namespace.as_ref());
+                self.emitter.push_namespace(&namespace);
                 self.emitter.emit_start_element(&mut self.sink, name, &attributes)
             },
             XmlEvent::EndElement { name } => {
@@ -68,6 +120,30 @@ impl<W: Write> EventWriter<W> {
             XmlEvent::Characters(content) => self.emitter.emit_characters(&mut self.sink, content),
             XmlEvent::RawCharacters(content) => self.emitter.emit_raw_characters(&mut self.sink, content),
             XmlEvent::Doctype(content) => self.emitter.emit_raw_characters(&mut self.sink, content),
+            XmlEvent::EntityReference(name) => self.emitter.emit_entity_reference(&mut self.sink, name),
+        }
+    }
+
+    /// Writes bytes copied from a source document, such as the markup between events
+    pub(crate) fn write_verbatim(&mut self, source: &[u8]) -> Result<()> {
+        self.emitter.emit_verbatim(&mut self.sink, source)
+    }
+
+    /// Writes the markup of the event copied from a source document instead of the event,
+    /// and keeps track of the open elements and namespaces as if the event was written
+    pub(crate) fn write_event_verbatim(&mut self, event: XmlEvent<'_>, source: &[u8]) -> Result<()> {
+        match event {
+            XmlEvent::StartDocument { .. } => self.emitter.emit_verbatim_start_document(&mut self.sink, source),
+            XmlEvent::StartElement { name, namespace, .. } => {
+                self.emitter.push_namespace(&namespace);
+                self.emitter.emit_verbatim_start_element(&mut self.sink, name, source)
+            },
+            XmlEvent::EndElement { name } => {
+                let r = self.emitter.emit_verbatim_end_element(&mut self.sink, name, source);
+                self.emitter.namespace_stack_mut().try_pop();
+                r
+            },
+            _ => self.emitter.emit_verbatim(&mut self.sink, source),
         }
     }
 
@@ -77,6 +153,9 @@ impl<W: Write> EventWriter<W> {
     /// documents. Use this method with care. Valid use cases for this method include accessing
     /// methods like `Write::flush`, which do not emit new data but rather change the state
     /// of the stream itself.
+    ///
+    /// With [`EmitterConfig::preserve_mixed_content`], the output may not include the last
+    /// events yet. Call [`flush`](Self::flush) first to write them.
     pub fn inner_mut(&mut self) -> &mut W {
         &mut self.sink
     }
@@ -91,7 +170,14 @@ impl<W: Write> EventWriter<W> {
     /// Note that this is a destructive operation: unwrapping a writer and then wrapping
     /// it again with `EventWriter::new()` will create a fresh writer whose state will be
     /// blank; for example, accumulated namespaces will be reset.
-    pub fn into_inner(self) -> W {
+    ///
+    /// Events buffered by [`EmitterConfig::preserve_mixed_content`] are written first, ignoring
+    /// errors. Call [`flush`](Self::flush) first to handle them.
+    pub fn into_inner(mut self) -> W {
+        if let Some(formatter) = &mut self.formatter {
+            formatter.flush();
+            let _ = self.write_buffered();
+        }
         self.sink
     }
 }
@@ -1,13 +1,19 @@
 //! Contains emitter configuration structure.
 
+use crate::attribute::Attribute;
+use crate::name::{Name, OwnedName};
 use crate::writer::EventWriter;
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::iter;
 use std::io::Write;
 
 /// Emitter configuration structure.
 ///
 /// This structure contains various options which control XML document emitter behavior.
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
 pub struct EmitterConfig {
     /// Line separator used to separate lines in formatted output. Default is `"\n"`.
     pub line_separator: Cow<'static, str>,
@@ -24,7 +30,8 @@ pub struct EmitterConfig {
     ///
     /// Sometimes, however, automatic indentation is undesirable, e.g. when you want to keep
     /// existing layout when processing an existing XML document. Also the indentiation algorithm
-    /// is not thoroughly tested. Hence by default it is disabled.
+    /// is not thoroughly tested. Hence by default it is disabled. See `preserve_mixed_content`
+    /// for indenting documents with text between elements.
     pub perform_indent: bool,
 
     /// Whether or not characters in output events will be escaped. Default is true.
@@ -81,6 +88,153 @@ pub struct EmitterConfig {
     /// this option is also true, the same element would appear `<a />`. If this option is false,
     /// then the same element would appear `<a/>`.
     pub pad_self_closing: bool,
+
+    /// Whether or not to check that the written document is well-formed. Default is false.
+    ///
+    /// When enabled, the emitter returns an error instead of writing an element or attribute
+    /// name which is not a valid XML name, a duplicate attribute, a namespace prefix which is
+    /// not bound, a character not allowed in XML documents, a processing instruction containing
+    /// `?>`, or an element or text after the root element has been closed.
+    pub check_well_formedness: bool,
+
+    /// Whether or not to assign prefixes to namespaces of element and attribute names which
+    /// have a namespace URI but no prefix. Default is false.
+    ///
+    /// A prefix already bound to the URI is reused, and elements in the default namespace stay
+    /// unprefixed. Otherwise the prefix from `preferred_prefixes` is used, or `ns0`, `ns1` and so on
+    /// if there is none, and it is declared on the element. Declaring the namespaces on an
+    /// ancestor element, or with `hoist_namespace_declarations`, avoids repeating the declarations
+    /// on every element using them.
+    ///
+    /// Names with both a prefix and a namespace URI keep their prefix, which is declared on
+    /// the element if it's bound to a different URI. If the element binds it already,
+    /// another prefix is chosen as for names without a prefix.
+    ///
+    /// When this option is enabled, names of `EndElement` events without a prefix or with
+    /// a namespace URI are compared with the start element by their namespace and local name only.
+    pub auto_namespace_prefixes: bool,
+
+    /// Prefixes for namespace URIs, used by `auto_namespace_prefixes` and
+    /// `hoist_namespace_declarations`. Empty by default.
+    pub preferred_prefixes: BTreeMap<String, String>,
+
+    /// Whether or not to declare all namespaces of `preferred_prefixes` on the root element,
+    /// or on the element chosen with `hoist_namespace_declarations_to`. Default is false.
+    ///
+    /// The elements using these namespaces don't need to declare them again, so generated
+    /// documents with many namespaced elements are more compact. Namespaces without a preferred
+    /// prefix are not known yet when the declarations are written, so they are still declared
+    /// on every element using them.
+    pub hoist_namespace_declarations: bool,
+
+    /// The element on which `hoist_namespace_declarations` declares the namespaces, instead of
+    /// the root element, matched by its namespace URI and local name. Default is `None`.
+    ///
+    /// Declarations are written on each matching element, unless its ancestor has
+    /// declared them already.
+    pub hoist_namespace_declarations_to: Option<OwnedName>,
+
+    /// Whether or not to keep the layout of elements containing text when indenting.
+    /// Default is false.
+    ///
+    /// The content of an element is written with indentation only if it contains markup and
+    /// whitespace, and the whitespace between its child elements is replaced by the indentation.
+    /// The content of elements with text, such as `<p>Some <b>bold</b> text</p>`, and of elements
+    /// with `xml:space="preserve"`, is written as it is, so the indentation never alters
+    /// significant whitespace. Elements with `xml:space="default"` inside such content are
+    /// indented again, unless they contain text.
+    ///
+    /// Which one it is becomes known only at the first text or at the end of an element, so the
+    /// events are kept in memory until then, which may be until the end of the root element.
+    /// An error of a kept event is returned by the call writing the event which made it known.
+    /// `EventWriter::flush` writes the kept events of an unfinished document.
+    ///
+    /// This option is only meaningful if `perform_indent` is true.
+    pub preserve_mixed_content: bool,
+
+    /// Whether or not to write elements containing only whitespace as empty elements.
+    /// Default is false.
+    ///
+    /// By default, the whitespace of such elements is kept, as it may be their significant content.
+    /// This option is only meaningful if `preserve_mixed_content` is true.
+    pub collapse_whitespace_only_elements: bool,
+
+    /// Column after which attributes are written one per line when indenting. Default is `None`.
+    ///
+    /// If a start tag including its indentation would be longer than this, each of its attributes
+    /// and namespace declarations is written on its own line, indented one level deeper than
+    /// the element. This option is only meaningful if `perform_indent` is true.
+    pub wrap_attributes_at: Option<usize>,
+
+    /// Whether or not comments are written on their own lines when indenting. Default is true.
+    ///
+    /// If this option is false, a comment is written right after the preceding markup, which
+    /// keeps comments annotating an element on the same line as it:
+    /// `<port>80</port><!-- http -->`. This option is only meaningful if `perform_indent` is true.
+    pub indent_comments: bool,
+
+    /// Order in which attributes are written. Default is `AttributeOrder::Unchanged`.
+    ///
+    /// Sorting makes the output independent of the order of attributes in the events,
+    /// for deterministic diffs of generated files.
+    pub attribute_order: AttributeOrder,
+
+    /// Whether or not namespace declarations are written after the attributes instead of before
+    /// them. Default is false.
+    ///
+    /// The declarations are written in the alphabetical order of their prefixes, with
+    /// the default namespace first.
+    pub namespace_declarations_last: bool,
+}
+
+/// Order of attributes in start tags, see [`EmitterConfig::attribute_order`].
+#[derive(Copy, Clone, Debug, Default)]
+#[non_exhaustive]
+pub enum AttributeOrder {
+    /// In the order of the attributes of the event.
+    #[default]
+    Unchanged,
+    /// Sorted alphabetically by their qualified names, like `id`, `xlink:href`, `xml:lang`.
+    Alphabetical,
+    /// Attributes in namespaces first, grouped by namespace URI, and then the attributes without
+    /// a namespace. Both are sorted alphabetically by their qualified names.
+    NamespaceFirst,
+    /// Sorted by the given comparison function. Attributes comparing as equal keep their order.
+    Custom(fn(&Attribute<'_>, &Attribute<'_>) -> Ordering),
+}
+
+impl PartialEq for AttributeOrder {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // the same function may have different addresses, so this may be false for equal orders
+            (Self::Custom(a), Self::Custom(b)) => *a as usize == *b as usize,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for AttributeOrder {}
+
+impl AttributeOrder {
+    /// Compares attributes by this order.
+    #[must_use]
+    pub fn compare(&self, a: &Attribute<'_>, b: &Attribute<'_>) -> Ordering {
+        let has_namespace = |attr: &Attribute<'_>| attr.name.namespace.is_some() || attr.name.prefix.is_some();
+        match self {
+            Self::Unchanged => Ordering::Equal,
+            Self::Alphabetical => qualified_name_chars(a.name).cmp(qualified_name_chars(b.name)),
+            Self::NamespaceFirst => has_namespace(b).cmp(&has_namespace(a))
+                .then_with(|| a.name.namespace.cmp(&b.name.namespace))
+                .then_with(|| qualified_name_chars(a.name).cmp(qualified_name_chars(b.name))),
+            Self::Custom(compare) => compare(a, b),
+        }
+    }
+}
+
+/// Characters of `prefix:local_name`, for comparing names without allocating
+fn qualified_name_chars(name: Name<'_>) -> impl Iterator<Item = char> + '_ {
+    let prefix = name.prefix.into_iter().flat_map(|p| p.chars().chain(iter::once(':')));
+    prefix.chain(name.local_name.chars())
 }
 
 impl EmitterConfig {
@@ -110,6 +264,17 @@ impl EmitterConfig {
             keep_element_names_stack: true,
             autopad_comments: true,
             pad_self_closing: true,
+            check_well_formedness: false,
+            auto_namespace_prefixes: false,
+            preferred_prefixes: BTreeMap::new(),
+            hoist_namespace_declarations: false,
+            hoist_namespace_declarations_to: None,
+            preserve_mixed_content: false,
+            collapse_whitespace_only_elements: false,
+            wrap_attributes_at: None,
+            indent_comments: true,
+            attribute_order: AttributeOrder::Unchanged,
+            namespace_declarations_last: false,
         }
     }
 
@@ -135,6 +300,36 @@ impl EmitterConfig {
     pub fn create_writer<W: Write>(self, sink: W) -> EventWriter<W> {
         EventWriter::new_with_config(sink, self)
     }
+
+    /// Adds a prefix for a namespace URI and returns an updated config object.
+    ///
+    /// See `preferred_prefixes`. An example:
+    ///
+    /// ```rust
+    /// use xml::writer::{EmitterConfig, XmlEvent};
+    /// use xml::name::Name;
+    ///
+    /// let mut output = Vec::new();
+    /// let mut writer = EmitterConfig::new()
+    ///     .write_document_declaration(false)
+    ///     .auto_namespace_prefixes(true)
+    ///     .add_preferred_prefix("http://www.w3.org/2005/Atom", "atom")
+    ///     .create_writer(&mut output);
+    ///
+    /// let atom = |local_name| Name { local_name, namespace: Some("http://www.w3.org/2005/Atom"), prefix: None };
+    /// writer.write(XmlEvent::start_element(atom("feed"))).unwrap();
+    /// writer.write(XmlEvent::start_element(Name { local_name: "item", namespace: Some("urn:x"), prefix: None })).unwrap();
+    /// writer.write(XmlEvent::end_element()).unwrap();
+    /// writer.write(XmlEvent::end_element()).unwrap();
+    ///
+    /// assert_eq!(String::from_utf8(output).unwrap(),
+    ///     r#"<atom:feed xmlns:atom="http://www.w3.org/2005/Atom"><ns0:item xmlns:ns0="urn:x" /></atom:feed>"#);
+    /// ```
+    #[must_use]
+    pub fn add_preferred_prefix<S: Into<String>, T: Into<String>>(mut self, uri: S, prefix: T) -> Self {
+        self.preferred_prefixes.insert(uri.into(), prefix.into());
+        self
+    }
 }
 
 impl Default for EmitterConfig {
@@ -153,5 +348,15 @@ gen_setters!(EmitterConfig,
     cdata_to_characters: val bool,
     keep_element_names_stack: val bool,
     autopad_comments: val bool,
-    pad_self_closing: val bool
+    pad_self_closing: val bool,
+    check_well_formedness: val bool,
+    auto_namespace_prefixes: val bool,
+    hoist_namespace_declarations: val bool,
+    hoist_namespace_declarations_to: into Option<OwnedName>,
+    preserve_mixed_content: val bool,
+    collapse_whitespace_only_elements: val bool,
+    wrap_attributes_at: val Option<usize>,
+    indent_comments: val bool,
+    attribute_order: val AttributeOrder,
+    namespace_declarations_last: val bool
 );
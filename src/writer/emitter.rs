@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::io::prelude::*;
 use std::{fmt, io, result};
@@ -7,9 +9,9 @@ use crate::common;
 use crate::common::XmlVersion;
 use crate::escape::{AttributeEscapes, Escaped, PcDataEscapes};
 use crate::name::{Name, OwnedName};
-use crate::namespace::{NamespaceStack, NS_EMPTY_URI, NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XML_PREFIX};
+use crate::namespace::{Namespace, NamespaceStack, NS_EMPTY_URI, NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XMLNS_URI, NS_XML_PREFIX, NS_XML_URI};
 
-use crate::writer::config::EmitterConfig;
+use crate::writer::config::{AttributeOrder, EmitterConfig};
 
 /// An error which may be returned by `XmlWriter` when writing XML events.
 #[derive(Debug)]
@@ -31,6 +33,27 @@ pub enum EmitterError {
     /// End element name is not specified when it is needed, for example, when automatic
     /// closing is not enabled in configuration.
     EndElementNameIsNotSpecified,
+
+    /// An element, attribute or processing instruction name is not a valid XML name,
+    /// or a processing instruction target is `xml`.
+    ///
+    /// This and the following errors are returned only if `check_well_formedness` is enabled.
+    InvalidName(String),
+
+    /// An element has more than one attribute with the same local name and namespace.
+    DuplicateAttribute(String),
+
+    /// A namespace prefix of an element or attribute name is not bound to a namespace URI.
+    UnboundPrefix(String),
+
+    /// A character which is not allowed in XML documents.
+    InvalidCharacter(char),
+
+    /// Processing instruction data contains `?>`, which would end it early.
+    InvalidProcessingInstructionData,
+
+    /// An element or non-whitespace text after the root element has been closed.
+    ContentAfterRootElement,
 }
 
 impl Clone for EmitterError {
@@ -42,6 +65,12 @@ impl Clone for EmitterError {
             Self::LastElementNameNotAvailable => Self::LastElementNameNotAvailable,
             Self::EndElementNameIsNotEqualToLastStartElementName => Self::EndElementNameIsNotEqualToLastStartElementName,
             Self::EndElementNameIsNotSpecified => Self::EndElementNameIsNotSpecified,
+            Self::InvalidName(name) => Self::InvalidName(name.clone()),
+            Self::DuplicateAttribute(name) => Self::DuplicateAttribute(name.clone()),
+            Self::UnboundPrefix(prefix) => Self::UnboundPrefix(prefix.clone()),
+            Self::InvalidCharacter(c) => Self::InvalidCharacter(*c),
+            Self::InvalidProcessingInstructionData => Self::InvalidProcessingInstructionData,
+            Self::ContentAfterRootElement => Self::ContentAfterRootElement,
         }
     }
 }
@@ -63,6 +92,12 @@ impl fmt::Display for EmitterError {
             Self::LastElementNameNotAvailable => f.write_str("last element name is not available"),
             Self::EndElementNameIsNotEqualToLastStartElementName => f.write_str("end element name is not equal to last start element name"),
             Self::EndElementNameIsNotSpecified => f.write_str("end element name is not specified and can't be inferred"),
+            Self::InvalidName(name) => write!(f, "invalid name: {name:?}"),
+            Self::DuplicateAttribute(name) => write!(f, "duplicate attribute: {name}"),
+            Self::UnboundPrefix(prefix) => write!(f, "namespace prefix is not bound: {prefix}"),
+            Self::InvalidCharacter(c) => write!(f, "invalid character: {c:?}"),
+            Self::InvalidProcessingInstructionData => f.write_str("processing instruction data contains '?>'"),
+            Self::ContentAfterRootElement => f.write_str("content after the root element"),
         }
     }
 }
@@ -90,9 +125,21 @@ pub struct Emitter {
     indent_stack: Vec<IndentFlags>,
 
     element_names: Vec<OwnedName>,
+    element_sources: Vec<ElementSource>,
 
     start_document_emitted: bool,
     just_wrote_start_element: bool,
+
+    version: XmlVersion,
+    root_element_closed: bool,
+
+    /// Prefixes assigned by `auto_namespace_prefixes`, reused for the same URIs
+    auto_prefixes: BTreeMap<String, String>,
+    next_auto_prefix: usize,
+
+    /// Indentation levels from which the content is written without indenting it, or indented
+    /// again inside such content, innermost last
+    layout_levels: Vec<(usize, bool)>,
 }
 
 impl Emitter {
@@ -109,13 +156,33 @@ impl Emitter {
             indent_stack,
 
             element_names: Vec::new(),
+            element_sources: Vec::new(),
 
             start_document_emitted: false,
             just_wrote_start_element: false,
+
+            version: XmlVersion::Version10,
+            root_element_closed: false,
+
+            auto_prefixes: BTreeMap::new(),
+            next_auto_prefix: 0,
+
+            layout_levels: Vec::new(),
         }
     }
 }
 
+/// How an open element has been written
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum ElementSource {
+    /// From the event
+    Written,
+    /// Copied from a source document
+    Verbatim,
+    /// Copied from a source document, where it was a self-closing tag or an entity reference
+    VerbatimClosed,
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 enum IndentFlags {
     WroteNothing,
@@ -130,6 +197,18 @@ impl Emitter {
         &mut self.nst
     }
 
+    /// Adds the namespace declarations of an element being started
+    pub fn push_namespace(&mut self, namespace: &Namespace) {
+        // the empty default namespace is declared only to undo a default namespace in scope
+        let undeclares_default = namespace.get(NS_NO_PREFIX) == Some(NS_EMPTY_URI) &&
+            self.nst.get(NS_NO_PREFIX).is_some_and(|uri| uri != NS_EMPTY_URI);
+        self.nst.push_empty().checked_target()
+            .extend(namespace.into_iter().filter(|&(prefix, uri)| (prefix, uri) != (NS_NO_PREFIX, NS_EMPTY_URI)));
+        if undeclares_default {
+            self.nst.put(NS_NO_PREFIX, NS_EMPTY_URI);
+        }
+    }
+
     #[inline]
     fn wrote_text(&self) -> bool {
         self.indent_stack.last().map_or(false, |&e| e == IndentFlags::WroteText)
@@ -162,8 +241,42 @@ impl Emitter {
         Ok(())
     }
 
+    #[inline]
+    fn indenting(&self) -> bool {
+        self.config.perform_indent && self.layout_levels.iter().rev()
+            .find(|&&(level, _)| level <= self.indent_level)
+            .map_or(true, |&(_, keep)| !keep)
+    }
+
+    /// Whether the content of the current element is written without indentation
+    #[inline]
+    pub fn keeps_layout(&self) -> bool {
+        self.layout_levels.last().is_some_and(|&(_, keep)| keep)
+    }
+
+    /// Writes the content of the last started element without indentation,
+    /// see `EmitterConfig::preserve_mixed_content`
+    pub fn keep_layout(&mut self) {
+        if !self.keeps_layout() {
+            self.layout_levels.push((self.indent_level, true));
+        }
+    }
+
+    /// Indents the content of the last started element, inside content written without indentation
+    pub fn reset_layout(&mut self) {
+        if self.keeps_layout() {
+            self.layout_levels.push((self.indent_level, false));
+        }
+    }
+
+    /// Whether the whitespace between elements is replaced by the indentation
+    #[inline]
+    fn replaces_whitespace(&self) -> bool {
+        self.config.preserve_mixed_content && self.indent_level > 0 && self.indenting()
+    }
+
     fn before_markup<W: Write>(&mut self, target: &mut W) -> Result<()> {
-        if self.config.perform_indent && !self.wrote_text() &&
+        if self.indenting() && !self.wrote_text() &&
            (self.indent_level > 0 || self.wrote_markup()) {
             let indent_level = self.indent_level;
             self.write_newline(target, indent_level)?;
@@ -190,7 +303,7 @@ impl Emitter {
     }
 
     fn before_end_element<W: Write>(&self, target: &mut W) -> Result<()> {
-        if self.config.perform_indent && self.indent_level > 0 && self.wrote_markup() &&
+        if self.indenting() && self.indent_level > 0 && self.wrote_markup() &&
            !self.wrote_text() {
             let indent_level = self.indent_level;
             self.write_newline(target, indent_level - 1)
@@ -204,9 +317,84 @@ impl Emitter {
             self.indent_level -= 1;
             self.indent_stack.pop();
         }
+        while self.layout_levels.last().is_some_and(|&(level, _)| self.indent_level < level) {
+            self.layout_levels.pop();
+        }
+        if self.element_sources.is_empty() {
+            self.root_element_closed = true;
+        }
         self.set_wrote_markup();
     }
 
+    fn check_name(name: &str) -> Result<()> {
+        let mut chars = name.chars();
+        let valid = chars.next().is_some_and(|c| c != ':' && common::is_name_start_char(c)) &&
+            chars.all(|c| c != ':' && common::is_name_char(c));
+        if valid { Ok(()) } else { Err(EmitterError::InvalidName(name.into())) }
+    }
+
+    fn check_qualified_name(&self, name: Name<'_>, attributes: &[Attribute<'_>]) -> Result<()> {
+        Self::check_name(name.local_name)?;
+        if let Some(prefix) = name.prefix {
+            Self::check_name(prefix)?;
+            let bound = prefix == NS_XML_PREFIX || prefix == NS_XMLNS_PREFIX ||
+                self.nst.get(prefix).is_some() ||
+                // declared with an attribute instead of the namespace of the event
+                attributes.iter().any(|a| a.name.prefix == Some(NS_XMLNS_PREFIX) && a.name.local_name == prefix);
+            if !bound {
+                return Err(EmitterError::UnboundPrefix(prefix.into()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Namespace URI of an attribute, which has been checked to have a bound prefix
+    fn attribute_namespace<'a>(&'a self, name: Name<'a>, attributes: &'a [Attribute<'_>]) -> Option<&'a str> {
+        match name.prefix? {
+            NS_XML_PREFIX => Some(NS_XML_URI),
+            NS_XMLNS_PREFIX => Some(NS_XMLNS_URI),
+            prefix => attributes.iter()
+                .find(|a| a.name.prefix == Some(NS_XMLNS_PREFIX) && a.name.local_name == prefix)
+                .map(|a| a.value)
+                .or_else(|| self.nst.get(prefix)),
+        }
+    }
+
+    fn check_chars(&self, content: &str) -> Result<()> {
+        let is_valid_char = match self.version {
+            XmlVersion::Version10 => common::is_xml10_char,
+            XmlVersion::Version11 => common::is_xml11_char,
+        };
+        match content.chars().find(|&c| !is_valid_char(c)) {
+            Some(c) => Err(EmitterError::InvalidCharacter(c)),
+            None => Ok(()),
+        }
+    }
+
+    fn check_start_element(&self, name: Name<'_>, attributes: &[Attribute<'_>]) -> Result<()> {
+        if self.root_element_closed {
+            return Err(EmitterError::ContentAfterRootElement);
+        }
+        self.check_qualified_name(name, attributes)?;
+        for (i, attr) in attributes.iter().enumerate() {
+            self.check_qualified_name(attr.name, attributes)?;
+            self.check_chars(attr.value)?;
+            let namespace = self.attribute_namespace(attr.name, attributes);
+            if attributes[..i].iter().any(|a| a.name.local_name == attr.name.local_name &&
+                                                self.attribute_namespace(a.name, attributes) == namespace) {
+                return Err(EmitterError::DuplicateAttribute(attr.name.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_text(&self, content: &str) -> Result<()> {
+        if self.root_element_closed && !common::is_whitespace_str(content) {
+            return Err(EmitterError::ContentAfterRootElement);
+        }
+        self.check_chars(content)
+    }
+
     fn after_text(&mut self) {
         self.set_wrote_text();
     }
@@ -219,6 +407,7 @@ impl Emitter {
             return Err(EmitterError::DocumentStartAlreadyEmitted);
         }
         self.start_document_emitted = true;
+        self.version = version;
 
         self.before_markup(target)?;
         let result = {
@@ -261,6 +450,19 @@ impl Emitter {
                                                  target: &mut W,
                                                  name: &str,
                                                  data: Option<&str>) -> Result<()> {
+        if self.config.check_well_formedness {
+            Self::check_name(name)?;
+            // reserved for the XML declaration
+            if name.eq_ignore_ascii_case("xml") {
+                return Err(EmitterError::InvalidName(name.into()));
+            }
+            if let Some(data) = data {
+                if data.contains("?>") {
+                    return Err(EmitterError::InvalidProcessingInstructionData);
+                }
+                self.check_chars(data)?;
+            }
+        }
         self.check_document_started(target)?;
         self.fix_non_empty_element(target)?;
 
@@ -296,23 +498,130 @@ impl Emitter {
         self.fix_non_empty_element(target)?;
         self.before_start_element(target)?;
         write!(target, "<{}", name.repr_display())?;
-        self.emit_current_namespace_attributes(target)?;
-        self.emit_attributes(target, attributes)?;
+        let mut sorted = Vec::new();
+        let attributes = if self.config.attribute_order == AttributeOrder::Unchanged {
+            attributes
+        } else {
+            sorted.extend_from_slice(attributes);
+            sorted.sort_by(|a, b| self.config.attribute_order.compare(a, b));
+            &sorted
+        };
+        let separator = self.attribute_separator(name, attributes)?;
+        if self.config.namespace_declarations_last {
+            self.emit_attributes(target, &separator, attributes)?;
+            self.emit_current_namespace_attributes(target, &separator)?;
+        } else {
+            self.emit_current_namespace_attributes(target, &separator)?;
+            self.emit_attributes(target, &separator, attributes)?;
+        }
         self.after_start_element();
         Ok(())
     }
 
+    /// Finds or declares a prefix for a namespace URI of a name without a prefix,
+    /// or returns `None` if the name can stay unprefixed
+    fn namespace_prefix(&mut self, uri: &str, is_attribute: bool) -> Option<String> {
+        if uri == NS_EMPTY_URI {
+            return None;
+        }
+        if uri == NS_XML_URI {
+            return Some(NS_XML_PREFIX.into());
+        }
+        // unprefixed attributes are not in the default namespace
+        if !is_attribute && self.nst.get(NS_NO_PREFIX) == Some(uri) {
+            return None;
+        }
+        if let Some((prefix, _)) = self.nst.iter().find(|&(p, u)| p != NS_NO_PREFIX && u == uri) {
+            return Some(prefix.into());
+        }
+
+        let known = self.config.preferred_prefixes.get(uri).or_else(|| self.auto_prefixes.get(uri));
+        let prefix = match known {
+            Some(prefix) if self.nst.get(prefix).is_none() => prefix.clone(),
+            _ => loop {
+                let prefix = format!("ns{}", self.next_auto_prefix);
+                self.next_auto_prefix += 1;
+                if self.nst.get(&prefix).is_none() {
+                    self.auto_prefixes.insert(uri.into(), prefix.clone());
+                    break prefix;
+                }
+            },
+        };
+        self.nst.put(prefix.clone(), uri);
+        Some(prefix)
+    }
+
+    fn resolve_name(&mut self, name: Name<'_>, is_attribute: bool) -> OwnedName {
+        let mut owned = name.to_owned();
+        match (name.namespace, name.prefix) {
+            (Some(uri), None) => owned.prefix = self.namespace_prefix(uri, is_attribute),
+            (Some(uri), Some(prefix)) if self.nst.get(prefix) != Some(uri) => {
+                // the prefix is redeclared on this element, unless the element binds it already
+                let redeclared = self.nst.put(prefix, uri);
+                if !redeclared {
+                    owned.prefix = self.namespace_prefix(uri, is_attribute);
+                }
+            },
+            _ => {},
+        }
+        owned
+    }
+
+    /// Whether `hoist_namespace_declarations` declares the namespaces on this element
+    fn is_hoisting_target(&self, name: Name<'_>) -> bool {
+        match &self.config.hoist_namespace_declarations_to {
+            None => self.element_sources.is_empty(),
+            Some(target) => {
+                let namespace = name.namespace
+                    .or_else(|| self.nst.get(name.prefix.unwrap_or(NS_NO_PREFIX)))
+                    .filter(|ns| !ns.is_empty());
+                target.local_name == name.local_name && target.namespace.as_deref() == namespace
+            },
+        }
+    }
+
     #[track_caller]
     pub fn emit_start_element<W>(&mut self, target: &mut W,
                                  name: Name<'_>,
                                  attributes: &[Attribute<'_>]) -> Result<()>
         where W: Write
     {
+        if self.config.hoist_namespace_declarations && self.is_hoisting_target(name) {
+            for (uri, prefix) in &self.config.preferred_prefixes {
+                if self.nst.get(prefix) != Some(uri) {
+                    self.nst.put(prefix.clone(), uri.clone());
+                }
+            }
+        }
+
+        if self.config.auto_namespace_prefixes &&
+           (name.namespace.is_some() || attributes.iter().any(|a| a.name.namespace.is_some())) {
+            let name = self.resolve_name(name, false);
+            let attribute_names: Vec<_> = attributes.iter().map(|a| self.resolve_name(a.name, true)).collect();
+            let attributes: Vec<_> = attributes.iter().zip(&attribute_names)
+                .map(|(a, name)| Attribute::new(name.borrow(), a.value))
+                .collect();
+            return self.emit_resolved_start_element(target, name.borrow(), &attributes);
+        }
+        self.emit_resolved_start_element(target, name, attributes)
+    }
+
+    #[track_caller]
+    fn emit_resolved_start_element<W>(&mut self, target: &mut W,
+                                      name: Name<'_>,
+                                      attributes: &[Attribute<'_>]) -> Result<()>
+        where W: Write
+    {
+        if self.config.check_well_formedness {
+            self.check_start_element(name, attributes)?;
+        }
+
         if self.config.keep_element_names_stack {
             self.element_names.push(name.to_owned());
         }
 
         self.emit_start_element_initial(target, name, attributes)?;
+        self.element_sources.push(ElementSource::Written);
         self.just_wrote_start_element = true;
 
         if !self.config.normalize_empty_elements {
@@ -322,8 +631,29 @@ impl Emitter {
         Ok(())
     }
 
+    /// Whitespace before each attribute, which puts them on their own lines if the start tag
+    /// would be too long
+    fn attribute_separator(&self, name: Name<'_>, attributes: &[Attribute<'_>]) -> Result<Cow<'static, str>> {
+        let Some(width) = self.config.wrap_attributes_at.filter(|_| self.config.perform_indent) else {
+            return Ok(" ".into());
+        };
+        let mut tag = Vec::new();
+        self.emit_current_namespace_attributes(&mut tag, " ")?;
+        self.emit_attributes(&mut tag, " ", attributes)?;
+        let column = self.indent_level * self.config.indent_string.chars().count() +
+            name.to_repr().chars().count() + String::from_utf8_lossy(&tag).chars().count() + 2;
+        if column <= width {
+            return Ok(" ".into());
+        }
+        let mut separator = self.config.line_separator.to_string();
+        for _ in 0..=self.indent_level {
+            separator.push_str(&self.config.indent_string);
+        }
+        Ok(separator.into())
+    }
+
     #[track_caller]
-    pub fn emit_current_namespace_attributes<W>(&self, target: &mut W) -> Result<()>
+    pub fn emit_current_namespace_attributes<W>(&self, target: &mut W, separator: &str) -> Result<()>
         where W: Write
     {
         for (prefix, uri) in self.nst.peek() {
@@ -332,21 +662,19 @@ impl Emitter {
                 NS_XMLNS_PREFIX | NS_XML_PREFIX => Ok(()),
                 //// there is already a namespace binding with this prefix in scope
                 //prefix if self.nst.get(prefix) == Some(uri) => Ok(()),
-                // emit xmlns only if it is overridden
-                NS_NO_PREFIX => if uri != NS_EMPTY_URI {
-                    write!(target, " xmlns=\"{uri}\"")
-                } else { Ok(()) },
+                // the empty namespace is only pushed when it overrides the default namespace
+                NS_NO_PREFIX => write!(target, "{separator}xmlns=\"{uri}\""),
                 // everything else
-                prefix => write!(target, " xmlns:{prefix}=\"{uri}\""),
+                prefix => write!(target, "{separator}xmlns:{prefix}=\"{uri}\""),
             }?;
         }
         Ok(())
     }
 
-    pub fn emit_attributes<W: Write>(&self, target: &mut W,
+    pub fn emit_attributes<W: Write>(&self, target: &mut W, separator: &str,
                                       attributes: &[Attribute<'_>]) -> Result<()> {
-        for attr in attributes {            
-            write!(target, " {}=\"", attr.name.repr_display())?;
+        for attr in attributes {
+            write!(target, "{separator}{}=\"", attr.name.repr_display())?;
             if self.config.perform_escaping {
                 write!(target, "{}", Escaped::<AttributeEscapes>::new(attr.value))?;
             } else {
@@ -359,6 +687,15 @@ impl Emitter {
 
     pub fn emit_end_element<W: Write>(&mut self, target: &mut W,
                                       name: Option<Name<'_>>) -> Result<()> {
+        if self.element_sources.pop() == Some(ElementSource::VerbatimClosed) {
+            // the copied markup has closed the element already
+            if self.config.keep_element_names_stack {
+                self.element_names.pop();
+            }
+            self.after_end_element();
+            return Ok(());
+        }
+
         let owned_name = if self.config.keep_element_names_stack {
             Some(self.element_names.pop().ok_or(EmitterError::LastElementNameNotAvailable)?)
         } else {
@@ -368,7 +705,12 @@ impl Emitter {
         // Check that last started element name equals to the provided name, if there are both
         if let Some(ref last_name) = owned_name {
             if let Some(ref name) = name {
-                if last_name.borrow() != *name {
+                let matches = if self.config.auto_namespace_prefixes && (name.prefix.is_none() || name.namespace.is_some()) {
+                    last_name.local_name == name.local_name && last_name.namespace.as_deref() == name.namespace
+                } else {
+                    last_name.borrow() == *name
+                };
+                if !matches {
                     return Err(EmitterError::EndElementNameIsNotEqualToLastStartElementName);
                 }
             }
@@ -396,6 +738,9 @@ impl Emitter {
     }
 
     pub fn emit_cdata<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
+        if self.config.check_well_formedness {
+            self.check_text(content)?;
+        }
         self.fix_non_empty_element(target)?;
         if self.config.cdata_to_characters {
             self.emit_characters(target, content)
@@ -420,6 +765,12 @@ impl Emitter {
     }
 
     pub fn emit_characters<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
+        if self.config.check_well_formedness {
+            self.check_text(content)?;
+        }
+        if self.replaces_whitespace() && common::is_whitespace_str(content) {
+            return Ok(());
+        }
         self.check_document_started(target)?;
         self.fix_non_empty_element(target)?;
 
@@ -443,7 +794,26 @@ impl Emitter {
         Ok(())
     }
 
+    pub fn emit_entity_reference<W: Write>(&mut self, target: &mut W, name: &str) -> Result<()> {
+        if self.config.check_well_formedness {
+            if self.root_element_closed {
+                return Err(EmitterError::ContentAfterRootElement);
+            }
+            Self::check_name(name)?;
+        }
+        self.check_document_started(target)?;
+        self.fix_non_empty_element(target)?;
+
+        write!(target, "&{name};")?;
+
+        self.after_text();
+        Ok(())
+    }
+
     pub fn emit_comment<W: Write>(&mut self, target: &mut W, content: &str) -> Result<()> {
+        if self.config.check_well_formedness {
+            self.check_chars(content)?;
+        }
         self.fix_non_empty_element(target)?;
 
         // TODO: add escaping dashes at the end of the comment
@@ -475,10 +845,58 @@ impl Emitter {
             Ok(())
         };
 
-        self.before_markup(target)?;
+        if self.config.indent_comments {
+            self.before_markup(target)?;
+        }
         let result = write(target);
         self.after_markup();
 
         result
     }
+
+    /// Copies markup from a source document
+    pub fn emit_verbatim<W: Write>(&mut self, target: &mut W, source: &[u8]) -> Result<()> {
+        if !source.is_empty() {
+            self.fix_non_empty_element(target)?;
+            target.write_all(source)?;
+        }
+        Ok(())
+    }
+
+    pub fn emit_verbatim_start_document<W: Write>(&mut self, target: &mut W, source: &[u8]) -> Result<()> {
+        if self.start_document_emitted {
+            return Err(EmitterError::DocumentStartAlreadyEmitted);
+        }
+        self.start_document_emitted = true;
+        self.emit_verbatim(target, source)
+    }
+
+    pub fn emit_verbatim_start_element<W: Write>(&mut self, target: &mut W, name: Name<'_>, source: &[u8]) -> Result<()> {
+        self.emit_verbatim(target, source)?;
+        if self.config.keep_element_names_stack {
+            self.element_names.push(name.to_owned());
+        }
+        self.element_sources.push(if source.is_empty() || source.ends_with(b"/>") {
+            ElementSource::VerbatimClosed
+        } else {
+            ElementSource::Verbatim
+        });
+        self.indent_stack.push(IndentFlags::WroteNothing);
+        self.after_start_element();
+        Ok(())
+    }
+
+    pub fn emit_verbatim_end_element<W: Write>(&mut self, target: &mut W, name: Option<Name<'_>>, source: &[u8]) -> Result<()> {
+        if self.element_sources.last() == Some(&ElementSource::Written) {
+            // the start tag has been generated, so the end tag has to match it
+            return self.emit_end_element(target, name);
+        }
+        self.element_sources.pop();
+        if self.config.keep_element_names_stack {
+            self.element_names.pop();
+        }
+        self.emit_verbatim(target, source)?;
+        self.after_end_element();
+        Ok(())
+    }
 }
@@ -103,6 +103,11 @@ pub enum XmlEvent<'a> {
 
     /// Syntax of the `DOCTYPE`, everyhing including `<` and `>`
     Doctype(&'a str),
+
+    /// A reference to an entity, written as `&name;`.
+    ///
+    /// The entity should be declared in the `DOCTYPE`, otherwise the document is not well-formed.
+    EntityReference(&'a str),
 }
 
 impl<'a> XmlEvent<'a> {
@@ -174,6 +179,13 @@ impl<'a> XmlEvent<'a> {
     pub const fn comment(data: &'a str) -> Self {
         XmlEvent::Comment(data)
     }
+
+    /// Returns a reference to the entity with the given name.
+    #[inline]
+    #[must_use]
+    pub const fn entity_reference(name: &'a str) -> Self {
+        XmlEvent::EntityReference(name)
+    }
 }
 
 impl<'a> From<&'a str> for XmlEvent<'a> {
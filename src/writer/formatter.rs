@@ -0,0 +1,231 @@
+//! Contains the buffer of events deciding how elements are indented, for
+//! `EmitterConfig::preserve_mixed_content`.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use crate::attribute::{Attribute, OwnedAttribute};
+use crate::common::{is_whitespace_str, XmlVersion};
+use crate::name::OwnedName;
+use crate::namespace::{Namespace, NS_XML_PREFIX, NS_XML_URI};
+use crate::writer::XmlEvent;
+
+/// What is known about the content of an element
+#[derive(Copy, Clone, Default, Debug)]
+pub(crate) struct Content {
+    /// Whether the content is written as is, if known already
+    keep_layout: Option<bool>,
+    /// Child elements, comments or processing instructions
+    has_markup: bool,
+    has_whitespace: bool,
+    /// The element has `xml:space="default"`, so its layout doesn't depend on its parent
+    space_default: bool,
+}
+
+/// Returns the value of the `xml:space` attribute
+pub(crate) fn xml_space<'a>(attributes: &[Attribute<'a>]) -> Option<&'a str> {
+    attributes.iter()
+        .find(|a| a.name.local_name == "space" &&
+            (a.name.prefix == Some(NS_XML_PREFIX) || a.name.namespace == Some(NS_XML_URI)))
+        .map(|a| a.value)
+}
+
+/// A writer event kept until the layout of the elements before it is known
+#[derive(Clone, Debug)]
+pub(crate) enum BufferedEvent {
+    StartDocument { version: XmlVersion, encoding: Option<String>, standalone: Option<bool> },
+    ProcessingInstruction { name: String, data: Option<String> },
+    StartElement { name: OwnedName, attributes: Vec<OwnedAttribute>, namespace: Namespace, content: Content },
+    EndElement { name: Option<OwnedName> },
+    CData(String),
+    Comment(String),
+    Characters(String),
+    RawCharacters(String),
+    Doctype(String),
+    EntityReference(String),
+}
+
+impl BufferedEvent {
+    fn new(event: XmlEvent<'_>) -> Self {
+        match event {
+            XmlEvent::StartDocument { version, encoding, standalone } =>
+                Self::StartDocument { version, encoding: encoding.map(From::from), standalone },
+            XmlEvent::ProcessingInstruction { name, data } =>
+                Self::ProcessingInstruction { name: name.into(), data: data.map(From::from) },
+            XmlEvent::StartElement { name, attributes, namespace } => Self::StartElement {
+                name: name.to_owned(),
+                attributes: attributes.iter().map(|a| a.to_owned()).collect(),
+                namespace: namespace.into_owned(),
+                content: Content::default(),
+            },
+            XmlEvent::EndElement { name } => Self::EndElement { name: name.map(|n| n.to_owned()) },
+            XmlEvent::CData(content) => Self::CData(content.into()),
+            XmlEvent::Comment(content) => Self::Comment(content.into()),
+            XmlEvent::Characters(content) => Self::Characters(content.into()),
+            XmlEvent::RawCharacters(content) => Self::RawCharacters(content.into()),
+            XmlEvent::Doctype(content) => Self::Doctype(content.into()),
+            XmlEvent::EntityReference(name) => Self::EntityReference(name.into()),
+        }
+    }
+
+    pub fn as_event(&self) -> XmlEvent<'_> {
+        match self {
+            Self::StartDocument { version, encoding, standalone } =>
+                XmlEvent::StartDocument { version: *version, encoding: encoding.as_deref(), standalone: *standalone },
+            Self::ProcessingInstruction { name, data } =>
+                XmlEvent::ProcessingInstruction { name, data: data.as_deref() },
+            Self::StartElement { name, attributes, namespace, .. } => XmlEvent::StartElement {
+                name: name.borrow(),
+                attributes: attributes.iter().map(OwnedAttribute::borrow).collect::<Vec<_>>().into(),
+                namespace: Cow::Borrowed(namespace),
+            },
+            Self::EndElement { name } => XmlEvent::EndElement { name: name.as_ref().map(OwnedName::borrow) },
+            Self::CData(content) => XmlEvent::CData(content),
+            Self::Comment(content) => XmlEvent::Comment(content),
+            Self::Characters(content) => XmlEvent::Characters(content),
+            Self::RawCharacters(content) => XmlEvent::RawCharacters(content),
+            Self::Doctype(content) => XmlEvent::Doctype(content),
+            Self::EntityReference(name) => XmlEvent::EntityReference(name),
+        }
+    }
+
+    /// Whether the content of the element is written as is, if it's a start element
+    pub fn layout(&self) -> Option<bool> {
+        match self {
+            Self::StartElement { content, .. } => content.keep_layout,
+            _ => None,
+        }
+    }
+
+    fn content_mut(&mut self) -> Option<&mut Content> {
+        match self {
+            Self::StartElement { content, .. } => Some(content),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps the events of elements until it's known whether their content contains text
+pub(crate) struct Formatter {
+    events: VecDeque<BufferedEvent>,
+    /// Number of events taken from the buffer
+    taken: usize,
+    /// Positions of the start events of the open elements, counting the taken events
+    open: Vec<usize>,
+    collapse_whitespace_only_elements: bool,
+}
+
+impl Formatter {
+    pub fn new(collapse_whitespace_only_elements: bool) -> Self {
+        Self {
+            events: VecDeque::new(),
+            taken: 0,
+            open: Vec::new(),
+            collapse_whitespace_only_elements,
+        }
+    }
+
+    /// Whether there are events which can't be written yet
+    #[inline]
+    pub fn is_buffering(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    fn content_mut(&mut self, position: usize) -> Option<&mut Content> {
+        self.events.get_mut(position.checked_sub(self.taken)?)?.content_mut()
+    }
+
+    fn parent_mut(&mut self) -> Option<&mut Content> {
+        let position = *self.open.last()?;
+        self.content_mut(position)
+    }
+
+    /// The element contains text, so its content and all its descendants are written as is,
+    /// except for elements with `xml:space="default"`
+    fn keep_parent_layout(&mut self) {
+        let Some(index) = self.open.last().and_then(|p| p.checked_sub(self.taken)) else { return };
+        if self.events[index].content_mut().map_or(true, |c| c.keep_layout.is_some()) {
+            return;
+        }
+        // depth in an element with `xml:space="default"`
+        let mut depth = 0;
+        for (i, event) in self.events.range_mut(index..).enumerate() {
+            match event {
+                BufferedEvent::StartElement { content, .. } if depth > 0 || (i > 0 && content.space_default) => depth += 1,
+                // also children which closed before the text, and were going to be indented
+                BufferedEvent::StartElement { content, .. } => content.keep_layout = Some(true),
+                BufferedEvent::EndElement { .. } if depth > 0 => depth -= 1,
+                _ => {},
+            }
+        }
+    }
+
+    pub fn push(&mut self, event: XmlEvent<'_>) {
+        let space = match &event {
+            XmlEvent::StartElement { attributes, .. } => xml_space(attributes),
+            _ => None,
+        };
+        let preserve = space == Some("preserve");
+        let mut event = BufferedEvent::new(event);
+        match &mut event {
+            BufferedEvent::StartElement { content, .. } => {
+                content.space_default = space == Some("default");
+                let parent_keeps_layout = match self.parent_mut() {
+                    Some(parent) => {
+                        parent.has_markup = true;
+                        parent.keep_layout == Some(true)
+                    },
+                    None => false,
+                };
+                if preserve || (parent_keeps_layout && !content.space_default) {
+                    content.keep_layout = Some(true);
+                }
+                self.open.push(self.taken + self.events.len());
+            },
+            BufferedEvent::EndElement { .. } => {
+                let collapse = self.collapse_whitespace_only_elements;
+                if let Some(content) = self.open.pop().and_then(|position| self.content_mut(position)) {
+                    content.keep_layout.get_or_insert(!content.has_markup && content.has_whitespace && !collapse);
+                }
+            },
+            BufferedEvent::Characters(text) if is_whitespace_str(text) => {
+                if let Some(parent) = self.parent_mut() {
+                    parent.has_whitespace = true;
+                }
+            },
+            BufferedEvent::Characters(_) | BufferedEvent::CData(_) |
+            BufferedEvent::RawCharacters(_) | BufferedEvent::EntityReference(_) => self.keep_parent_layout(),
+            BufferedEvent::Comment(_) | BufferedEvent::ProcessingInstruction { .. } => {
+                if let Some(parent) = self.parent_mut() {
+                    parent.has_markup = true;
+                }
+            },
+            BufferedEvent::StartDocument { .. } | BufferedEvent::Doctype(_) => {},
+        }
+        self.events.push_back(event);
+    }
+
+    /// Decides that the elements of unknown layout are indented, so that all events can be taken
+    pub fn flush(&mut self) {
+        for event in &mut self.events {
+            if let Some(content) = event.content_mut() {
+                content.keep_layout.get_or_insert(false);
+            }
+        }
+    }
+
+    /// Takes the next event which can be written, up to the first element of unknown layout
+    pub fn take(&mut self) -> Option<BufferedEvent> {
+        let event = self.events.front()?;
+        if matches!(event, BufferedEvent::StartElement { content: Content { keep_layout: None, .. }, .. }) {
+            return None;
+        }
+        self.taken += 1;
+        let event = self.events.pop_front();
+        if self.events.is_empty() {
+            // the open elements have been written, and their content is written as is
+            self.open.clear();
+        }
+        event
+    }
+}
@@ -0,0 +1,558 @@
+//! Contains [`XIncludeReader`], which processes [XInclude 1.0](https://www.w3.org/TR/xinclude/)
+//! `xi:include` elements while reading a document.
+//!
+//! Included documents and text are loaded through a [`Resolver`], with `href` resolved against
+//! the base URI of the `xi:include` element. XML is parsed with the configuration of the including
+//! reader, and can be narrowed down with the `element()` XPointer scheme or a shorthand `id`
+//! pointer (matching `xml:id` or `id` attributes). If a resource can't be loaded, the content of
+//! `xi:fallback` is used instead. Included elements get an `xml:base` attribute, so that relative
+//! URIs in them keep working.
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use std::io;
+//!
+//! use xml::reader::XmlEvent;
+//! use xml::xinclude::XIncludeReader;
+//! use xml::EventReader;
+//!
+//! let files = HashMap::from([
+//!     ("chapters/intro.xml", "<chapter><title>Intro</title></chapter>"),
+//! ]);
+//! let resolver = |uri: &str| files.get(uri).map(|text| text.as_bytes().to_vec())
+//!     .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound));
+//!
+//! let source = r#"<book xmlns:xi="http://www.w3.org/2001/XInclude">
+//!     <xi:include href="chapters/intro.xml"/>
+//!     <xi:include href="chapters/missing.xml"><xi:fallback><chapter/></xi:fallback></xi:include>
+//! </book>"#;
+//! let mut reader = XIncludeReader::new(EventReader::new(source.as_bytes()), resolver);
+//!
+//! let mut chapters = Vec::new();
+//! loop {
+//!     match reader.next().unwrap() {
+//!         XmlEvent::StartElement { name, attributes, .. } if name.local_name == "chapter" => {
+//!             chapters.push(attributes.iter().map(|a| a.value.clone()).collect::<Vec<_>>());
+//!         },
+//!         XmlEvent::EndDocument => break,
+//!         _ => {},
+//!     }
+//! }
+//! assert_eq!(chapters, [vec!["chapters/intro.xml".to_owned()], vec![]]);
+//! ```
+//!
+//! References to the including document itself (`xpointer` without `href`) are not supported.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+
+use crate::attribute::OwnedAttribute;
+use crate::common::{Position, TextPosition};
+use crate::name::OwnedName;
+use crate::namespace::{NS_XML_PREFIX, NS_XML_URI};
+use crate::reader::{self, EventReader, ParserConfig, XmlEvent};
+use crate::uri;
+use crate::util::{CharReader, Encoding};
+
+/// The XInclude namespace URI
+pub const NS_XINCLUDE: &str = "http://www.w3.org/2001/XInclude";
+
+/// Loads included resources.
+///
+/// It's implemented for closures taking the URI.
+pub trait Resolver {
+    /// Returns the content of the resource. The URI has been resolved against the base URI,
+    /// and is relative if [`ParserConfig::base_uri`] is.
+    ///
+    /// Errors make the `xi:fallback` be used, if there is one.
+    fn load(&mut self, uri: &str) -> io::Result<Vec<u8>>;
+}
+
+impl<F: FnMut(&str) -> io::Result<Vec<u8>>> Resolver for F {
+    fn load(&mut self, uri: &str) -> io::Result<Vec<u8>> {
+        self(uri)
+    }
+}
+
+/// Loads files, with URIs that are paths or `file:` URIs. Other schemes aren't supported.
+///
+/// Use it with the path of the document as the [`ParserConfig::base_uri`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FileResolver;
+
+impl Resolver for FileResolver {
+    fn load(&mut self, uri: &str) -> io::Result<Vec<u8>> {
+        let path = uri.strip_prefix("file://").unwrap_or(uri);
+        if path.contains("://") {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("can't load '{uri}'")));
+        }
+        fs::read(path)
+    }
+}
+
+/// A failure to read the document or to process an `xi:include`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The including document is malformed or could not be read
+    Reader(reader::Error),
+    /// An included document is malformed
+    Included {
+        /// The URI of the included document
+        uri: String,
+        /// The error in the included document
+        error: reader::Error,
+    },
+    /// A resource could not be loaded, and there's no `xi:fallback`
+    Resource {
+        /// The URI of the resource
+        uri: String,
+        /// The error of the [`Resolver`], or why the content can't be included
+        error: io::Error,
+    },
+    /// An `xi:include` element is invalid, or includes itself
+    Include {
+        /// The position of the `xi:include` in the including document
+        pos: TextPosition,
+        /// The description of the problem
+        msg: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reader(e) => e.fmt(f),
+            Self::Included { uri, error } => write!(f, "in '{uri}': {error}"),
+            Self::Resource { uri, error } => write!(f, "can't include '{uri}': {error}"),
+            Self::Include { pos, msg } => write!(f, "{pos} {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Reader(e) | Self::Included { error: e, .. } => Some(e),
+            Self::Resource { error, .. } => Some(error),
+            Self::Include { .. } => None,
+        }
+    }
+}
+
+impl From<reader::Error> for Error {
+    #[cold]
+    fn from(e: reader::Error) -> Self {
+        Self::Reader(e)
+    }
+}
+
+/// XInclude processing configuration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct XIncludeConfig {
+    /// Whether included elements get an `xml:base` attribute. Default is true.
+    pub fixup_base_uris: bool,
+}
+
+impl XIncludeConfig {
+    /// Creates a configuration adding `xml:base` attributes.
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { fixup_base_uris: true }
+    }
+}
+
+impl Default for XIncludeConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+gen_setters!(XIncludeConfig,
+    fixup_base_uris: val bool
+);
+
+/// A wrapper around [`EventReader`] that replaces `xi:include` elements by the events
+/// of what they include.
+pub struct XIncludeReader<R: Read, S: Resolver> {
+    reader: EventReader<R>,
+    resolver: S,
+    config: XIncludeConfig,
+    queue: VecDeque<XmlEvent>,
+    /// Position of the `xi:include` being processed
+    pos: TextPosition,
+}
+
+impl<R: Read, S: Resolver> XIncludeReader<R, S> {
+    /// Creates a reader loading resources relative to the document.
+    #[inline]
+    pub fn new(reader: EventReader<R>, resolver: S) -> Self {
+        Self::new_with_config(reader, resolver, XIncludeConfig::new())
+    }
+
+    /// Creates a reader with the provided configuration.
+    pub fn new_with_config(reader: EventReader<R>, resolver: S, config: XIncludeConfig) -> Self {
+        Self {
+            reader,
+            resolver,
+            config,
+            queue: VecDeque::new(),
+            pos: TextPosition::new(),
+        }
+    }
+
+    /// Pulls the next event, from the document or an included resource.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<XmlEvent, Error> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return Ok(event);
+            }
+            let event = self.reader.next()?;
+            match &event {
+                XmlEvent::StartElement { name, .. } if is_xinclude(name, "include") => {
+                    self.pos = self.reader.position();
+                    let base = self.reader.scope().base_uri.clone();
+                    let mut events = vec![event];
+                    let mut depth = 1;
+                    while depth > 0 {
+                        let event = self.reader.next()?;
+                        match event {
+                            XmlEvent::StartElement { .. } => depth += 1,
+                            XmlEvent::EndElement { .. } => depth -= 1,
+                            _ => {},
+                        }
+                        events.push(event);
+                    }
+                    let mut open = vec![(self.reader.config().base_uri.clone(), None)];
+                    let included = self.include(&events, &base, &mut open)?;
+                    self.queue.extend(included);
+                },
+                XmlEvent::StartElement { name, .. } if is_xinclude(name, "fallback") => {
+                    self.pos = self.reader.position();
+                    return Err(self.error("xi:fallback outside of xi:include".into()));
+                },
+                _ => return Ok(event),
+            }
+        }
+    }
+
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> EventReader<R> {
+        self.reader
+    }
+
+    /// Processes includes in events of an included document or `xi:fallback`
+    fn expand(&mut self, events: &[XmlEvent], base: &str, open: &mut Vec<(String, Option<String>)>) -> Result<Vec<XmlEvent>, Error> {
+        let mut output = Vec::with_capacity(events.len());
+        let mut bases = vec![base.to_owned()];
+        let mut i = 0;
+        while i < events.len() {
+            let parent = bases.last().map_or("", String::as_str);
+            match &events[i] {
+                XmlEvent::StartElement { name, attributes, .. } if is_xinclude(name, "include") => {
+                    let end = element_end(events, i);
+                    let included = self.include(&events[i..=end], &element_base(parent, attributes), open)?;
+                    output.extend(included);
+                    i = end + 1;
+                    continue;
+                },
+                XmlEvent::StartElement { name, .. } if is_xinclude(name, "fallback") => {
+                    return Err(self.error("xi:fallback outside of xi:include".into()));
+                },
+                XmlEvent::StartElement { attributes, .. } => bases.push(element_base(parent, attributes)),
+                XmlEvent::EndElement { .. } => {
+                    bases.pop();
+                },
+                _ => {},
+            }
+            output.push(events[i].clone());
+            i += 1;
+        }
+        Ok(output)
+    }
+
+    /// Returns what the `xi:include` element in `events`, with the base URI `base`, includes
+    fn include(&mut self, events: &[XmlEvent], base: &str, open: &mut Vec<(String, Option<String>)>) -> Result<Vec<XmlEvent>, Error> {
+        let XmlEvent::StartElement { attributes, .. } = &events[0] else {
+            return Ok(Vec::new());
+        };
+        let attribute = |local_name: &str| attributes.iter()
+            .find(|a| a.name.namespace.is_none() && a.name.local_name == local_name)
+            .map(|a| a.value.as_str());
+        let href = attribute("href").unwrap_or_default();
+        let xpointer = attribute("xpointer");
+        let text = match attribute("parse").unwrap_or("xml") {
+            "xml" => false,
+            "text" => true,
+            parse => return Err(self.error(format!("invalid parse attribute '{parse}'"))),
+        };
+        if href.contains('#') {
+            return Err(self.error(format!("href '{href}' has a fragment identifier")));
+        }
+        if text && xpointer.is_some() {
+            return Err(self.error("xpointer is not allowed with parse=\"text\"".into()));
+        }
+        if href.is_empty() {
+            return Err(self.error(match xpointer {
+                Some(_) => "including parts of the same document is not supported".into(),
+                None => "missing href".into(),
+            }));
+        }
+
+        let mut fallback = None;
+        let mut i = 1;
+        while i < events.len() - 1 {
+            if let XmlEvent::StartElement { name, .. } = &events[i] {
+                let end = element_end(events, i);
+                if is_xinclude(name, "fallback") && fallback.is_none() {
+                    fallback = Some(&events[i + 1..end]);
+                } else if name.namespace_ref() == Some(NS_XINCLUDE) {
+                    return Err(self.error(format!("unexpected xi:{} in xi:include", name.local_name)));
+                }
+                i = end;
+            }
+            i += 1;
+        }
+
+        let uri = uri::resolve(base, href);
+        // the URI of the resource relative to the base of the parent, for xml:base fixup
+        let relative = match attributes.iter().find(|a| is_xml_base(&a.name)) {
+            Some(own_base) => uri::resolve(&own_base.value, href),
+            None => href.to_owned(),
+        };
+
+        let result = if text {
+            self.load_text(&uri, attribute("encoding"))
+        } else {
+            self.load_xml(&uri, &relative, xpointer, open)
+        };
+        match (result, fallback) {
+            (Err(Error::Resource { .. }), Some(fallback)) => self.expand(fallback, base, open),
+            (result, _) => result,
+        }
+    }
+
+    fn load_text(&mut self, uri: &str, encoding: Option<&str>) -> Result<Vec<XmlEvent>, Error> {
+        let data = self.resolver.load(uri).map_err(|error| resource(uri, error))?;
+        let encoding = match encoding {
+            Some(encoding) => encoding.parse().map_err(|e| resource(uri, io::Error::new(io::ErrorKind::InvalidInput, e)))?,
+            None => Encoding::Unknown,
+        };
+        let mut chars = CharReader { encoding };
+        let mut source = &data[..];
+        let mut text = String::with_capacity(data.len());
+        while let Some(c) = chars.next_char_from(&mut source)
+            .map_err(|e| resource(uri, io::Error::new(io::ErrorKind::InvalidData, e.to_string())))? {
+            text.push(c);
+        }
+        Ok(if text.is_empty() { Vec::new() } else { vec![XmlEvent::Characters(text)] })
+    }
+
+    fn load_xml(&mut self, uri: &str, relative: &str, xpointer: Option<&str>, open: &mut Vec<(String, Option<String>)>) -> Result<Vec<XmlEvent>, Error> {
+        let key = (uri.to_owned(), xpointer.map(ToOwned::to_owned));
+        if open.contains(&key) {
+            return Err(self.error(format!("'{uri}' includes itself")));
+        }
+        let data = self.resolver.load(uri).map_err(|error| resource(uri, error))?;
+
+        let config = ParserConfig { override_encoding: None, base_uri: uri.to_owned(), ..self.reader.config().clone() };
+        let mut reader = EventReader::new_with_config(&data[..], config);
+        let mut events = Vec::new();
+        loop {
+            match reader.next() {
+                Ok(XmlEvent::EndDocument) => break,
+                Ok(XmlEvent::StartDocument { .. } | XmlEvent::Doctype { .. }) => {},
+                Ok(event) => events.push(event),
+                Err(error) => return Err(Error::Included { uri: uri.to_owned(), error }),
+            }
+        }
+
+        let (start, end) = match xpointer {
+            Some(pointer) => {
+                let start = select(&events, pointer).ok_or_else(|| {
+                    resource(uri, io::Error::new(io::ErrorKind::NotFound, format!("xpointer '{pointer}' doesn't match")))
+                })?;
+                (start, element_end(&events, start))
+            },
+            None => (0, events.len().saturating_sub(1)),
+        };
+        let parent_base = base_at(&events, start, uri);
+        open.push(key);
+        let mut included = self.expand(events.get(start..=end).unwrap_or_default(), &parent_base, open)?;
+        open.pop();
+
+        if self.config.fixup_base_uris {
+            let fixup = base_at(&events, start, relative);
+            let mut depth = 0;
+            for event in &mut included {
+                match event {
+                    XmlEvent::StartElement { attributes, .. } if depth == 0 => {
+                        depth += 1;
+                        let value = element_base(&fixup, attributes);
+                        match attributes.iter_mut().find(|a| is_xml_base(&a.name)) {
+                            Some(base) => base.value = value,
+                            None => attributes.push(OwnedAttribute::new(OwnedName::qualified("base", NS_XML_URI, Some(NS_XML_PREFIX)), value)),
+                        }
+                    },
+                    XmlEvent::StartElement { .. } => depth += 1,
+                    XmlEvent::EndElement { .. } => depth -= 1,
+                    _ => {},
+                }
+            }
+        }
+        Ok(included)
+    }
+
+    fn error(&self, msg: String) -> Error {
+        Error::Include { pos: self.pos, msg }
+    }
+}
+
+impl<R: Read, S: Resolver> Position for XIncludeReader<R, S> {
+    /// Returns the position of the last event read from the including document.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+}
+
+fn resource(uri: &str, error: io::Error) -> Error {
+    Error::Resource { uri: uri.to_owned(), error }
+}
+
+fn is_xinclude(name: &OwnedName, local_name: &str) -> bool {
+    name.local_name == local_name && name.namespace_ref() == Some(NS_XINCLUDE)
+}
+
+fn is_xml_base(name: &OwnedName) -> bool {
+    name.local_name == "base" && name.namespace_ref() == Some(NS_XML_URI)
+}
+
+/// The base URI of an element with these attributes
+fn element_base(parent: &str, attributes: &[OwnedAttribute]) -> String {
+    match attributes.iter().find(|a| is_xml_base(&a.name)) {
+        Some(base) => uri::resolve(parent, &base.value),
+        None => parent.to_owned(),
+    }
+}
+
+/// The base URI of the parent of the event at `index`
+fn base_at(events: &[XmlEvent], index: usize, document: &str) -> String {
+    let mut bases = vec![document.to_owned()];
+    for event in &events[..index] {
+        match event {
+            XmlEvent::StartElement { attributes, .. } => {
+                let base = element_base(bases.last().map_or("", String::as_str), attributes);
+                bases.push(base);
+            },
+            XmlEvent::EndElement { .. } => {
+                bases.pop();
+            },
+            _ => {},
+        }
+    }
+    bases.pop().unwrap_or_default()
+}
+
+/// The index of the end of the element starting at `start`
+fn element_end(events: &[XmlEvent], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, event) in events.iter().enumerate().skip(start) {
+        match event {
+            XmlEvent::StartElement { .. } => depth += 1,
+            XmlEvent::EndElement { .. } => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            },
+            _ => {},
+        }
+    }
+    events.len() - 1
+}
+
+/// The start of the element selected by a shorthand pointer or `element()` scheme parts
+fn select(events: &[XmlEvent], pointer: &str) -> Option<usize> {
+    let pointer = pointer.trim();
+    if !pointer.contains('(') {
+        return find_id(events, pointer);
+    }
+    let mut rest = pointer;
+    while !rest.is_empty() {
+        let open = rest.find('(')?;
+        let scheme = rest[..open].trim();
+        let mut data = String::new();
+        let mut depth = 1;
+        let mut chars = rest[open + 1..].char_indices();
+        let close = loop {
+            match chars.next()? {
+                (_, '^') => data.push(chars.next()?.1),
+                (i, ')') if depth == 1 => break open + 1 + i,
+                (_, c) => {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {},
+                    }
+                    data.push(c);
+                },
+            }
+        };
+        rest = rest[close + 1..].trim_start();
+        if scheme == "element" {
+            if let Some(start) = select_element(events, &data) {
+                return Some(start);
+            }
+        }
+    }
+    None
+}
+
+/// Evaluates `element()` scheme data: an id, a child sequence like `/1/2`, or both
+fn select_element(events: &[XmlEvent], data: &str) -> Option<usize> {
+    let mut steps = data.split('/');
+    let id = steps.next()?;
+    let mut current = if id.is_empty() { None } else { Some(find_id(events, id)?) };
+    for step in steps {
+        let n: usize = step.parse().ok().filter(|&n| n > 0)?;
+        let (from, to) = match current {
+            Some(parent) => (parent + 1, element_end(events, parent)),
+            None => (0, events.len()),
+        };
+        let mut i = from;
+        let mut count = 0;
+        current = loop {
+            if i >= to {
+                return None;
+            }
+            if let XmlEvent::StartElement { .. } = events[i] {
+                count += 1;
+                if count == n {
+                    break Some(i);
+                }
+                i = element_end(events, i);
+            }
+            i += 1;
+        };
+    }
+    current
+}
+
+fn find_id(events: &[XmlEvent], id: &str) -> Option<usize> {
+    events.iter().position(|event| match event {
+        XmlEvent::StartElement { attributes, .. } => attributes.iter().any(|a| {
+            let is_id = match a.name.namespace_ref() {
+                None => a.name.local_name == "id",
+                Some(ns) => ns == NS_XML_URI && a.name.local_name == "id",
+            };
+            is_id && a.value == id
+        }),
+        _ => false,
+    })
+}
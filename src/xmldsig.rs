@@ -0,0 +1,506 @@
+//! Contains verification and generation of enveloped
+//! [XML Signatures](https://www.w3.org/TR/xmldsig-core1/).
+//!
+//! References are computed over the whole document (`URI=""`) or over an element selected by its
+//! `Id`, `ID`, `id` or `xml:id` attribute (`URI="#id"`), using the enveloped-signature transform
+//! and the canonicalization algorithms of the [`c14n`](crate::c14n) module.
+//!
+//! No cryptography is implemented here. Digests and signatures are computed by implementations of
+//! [`DigestMethod`], [`Signer`] and [`Verifier`], which can wrap any crypto library:
+//!
+//! ```rust
+//! use xml::xmldsig::{algorithm, sign, verify, DigestMethod, SignatureConfig, Signer, Verifier};
+//!
+//! /// Not a real digest, only for illustration
+//! struct Checksum;
+//!
+//! impl DigestMethod for Checksum {
+//!     fn algorithm(&self) -> &str { "urn:example:checksum" }
+//!     fn digest(&self, data: &[u8]) -> Vec<u8> {
+//!         vec![data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))]
+//!     }
+//! }
+//!
+//! /// Not a real signature either
+//! struct Key(u8);
+//!
+//! impl Signer for Key {
+//!     fn algorithm(&self) -> &str { "urn:example:xor" }
+//!     fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+//!         Ok(Checksum.digest(data).into_iter().map(|b| b ^ self.0).collect())
+//!     }
+//! }
+//!
+//! impl Verifier for Key {
+//!     fn algorithm(&self) -> &str { "urn:example:xor" }
+//!     fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+//!         self.sign(data).unwrap() == signature
+//!     }
+//! }
+//!
+//! let mut signed = Vec::new();
+//! sign("<order id='1'>5 apples</order>".as_bytes(), &mut signed, &SignatureConfig::new(), &Checksum, &Key(42)).unwrap();
+//! assert_eq!(verify(&signed[..], &[&Checksum], &Key(42)).unwrap(), [""]);
+//!
+//! let tampered = String::from_utf8(signed).unwrap().replace("5 apples", "6 apples");
+//! assert!(verify(tampered.as_bytes(), &[&Checksum], &Key(42)).is_err());
+//! ```
+//!
+//! Only same-document references are supported, and `KeyInfo` is neither written nor read:
+//! the verifier decides which key to trust. A successful verification returns the URIs
+//! of the references, which the application must check to cover the data it uses.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+
+use crate::c14n::{parser_config, C14nConfig, Canonicalizer, Method};
+use crate::dom::{Element, Node};
+use crate::name::OwnedName;
+use crate::namespace::NS_XML_URI;
+use crate::reader::{self, EventReader, XmlEvent};
+use crate::writer::{self, EmitterConfig, EventWriter};
+
+mod base64;
+
+/// The XML Signature namespace URI
+pub const NS_XMLDSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+
+/// The namespace of `InclusiveNamespaces` in exclusive canonicalization transforms
+pub const NS_EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+/// Identifiers of common algorithms. Canonicalization algorithms are [`Method::uri`].
+pub mod algorithm {
+    /// The enveloped-signature transform
+    pub const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+
+    /// SHA-1 digest
+    pub const SHA1: &str = "http://www.w3.org/2000/09/xmldsig#sha1";
+    /// SHA-256 digest
+    pub const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+    /// SHA-384 digest
+    pub const SHA384: &str = "http://www.w3.org/2001/04/xmldsig-more#sha384";
+    /// SHA-512 digest
+    pub const SHA512: &str = "http://www.w3.org/2001/04/xmlenc#sha512";
+
+    /// HMAC with SHA-256
+    pub const HMAC_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#hmac-sha256";
+    /// RSA PKCS#1 v1.5 with SHA-1
+    pub const RSA_SHA1: &str = "http://www.w3.org/2000/09/xmldsig#rsa-sha1";
+    /// RSA PKCS#1 v1.5 with SHA-256
+    pub const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+    /// RSA PKCS#1 v1.5 with SHA-512
+    pub const RSA_SHA512: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512";
+    /// ECDSA with SHA-256
+    pub const ECDSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha256";
+}
+
+/// A message digest algorithm, such as SHA-256
+pub trait DigestMethod {
+    /// The identifier of the algorithm, used in `DigestMethod` elements
+    fn algorithm(&self) -> &str;
+
+    /// Computes the digest of the data
+    fn digest(&self, data: &[u8]) -> Vec<u8>;
+}
+
+/// Computes signature values with a private or secret key
+pub trait Signer {
+    /// The identifier of the algorithm, used in `SignatureMethod` elements
+    fn algorithm(&self) -> &str;
+
+    /// Signs the data, which is the canonical form of `SignedInfo`
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// Checks signature values with a public or secret key
+pub trait Verifier {
+    /// The identifier of the algorithm. Signatures using other algorithms are rejected.
+    fn algorithm(&self) -> &str;
+
+    /// Checks that the signature is valid for the data, which is the canonical form of `SignedInfo`
+    fn verify(&self, data: &[u8], signature: &[u8]) -> bool;
+}
+
+/// A failure to verify or generate a signature.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The document is malformed or could not be read
+    Reader(reader::Error),
+    /// The signed document could not be written
+    Writer(writer::Error),
+    /// The `Signature` element is missing or its structure is invalid
+    Malformed(String),
+    /// The signature uses an algorithm, transform or reference that is not supported
+    Unsupported(String),
+    /// The digest of the reference with this URI doesn't match its `DigestValue`
+    DigestMismatch(String),
+    /// The `SignatureValue` doesn't match `SignedInfo`
+    InvalidSignature,
+    /// The [`Signer`] failed
+    Signer(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reader(e) => e.fmt(f),
+            Self::Writer(e) => e.fmt(f),
+            Self::Malformed(msg) => write!(f, "malformed signature: {msg}"),
+            Self::Unsupported(msg) => write!(f, "unsupported signature: {msg}"),
+            Self::DigestMismatch(uri) => write!(f, "digest of reference '{uri}' doesn't match"),
+            Self::InvalidSignature => f.write_str("invalid signature value"),
+            Self::Signer(e) => write!(f, "signing failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Reader(e) => Some(e),
+            Self::Writer(e) => Some(e),
+            Self::Signer(e) => Some(&**e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reader::Error> for Error {
+    #[cold]
+    fn from(e: reader::Error) -> Self {
+        Self::Reader(e)
+    }
+}
+
+impl From<writer::Error> for Error {
+    #[cold]
+    fn from(e: writer::Error) -> Self {
+        Self::Writer(e)
+    }
+}
+
+/// Signing configuration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SignatureConfig {
+    /// The algorithm used for `SignedInfo` and the reference. Default is [`Method::Exclusive`].
+    pub canonicalization: Method,
+
+    /// The URI of the signed data: `""` for the whole document, or `#` followed by the `Id` of
+    /// an element. The `Signature` is appended to the root or that element. Default is `""`.
+    pub reference: String,
+}
+
+impl SignatureConfig {
+    /// Creates a configuration signing the whole document with exclusive canonicalization.
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { canonicalization: Method::Exclusive, reference: String::new() }
+    }
+}
+
+impl Default for SignatureConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+gen_setters!(SignatureConfig,
+    canonicalization: val Method,
+    reference: into String
+);
+
+/// Verifies the first `Signature` element of the document, which must be enveloped.
+///
+/// Digests of references are computed with the `digests` matching their `DigestMethod`, and
+/// `SignedInfo` is checked by the `verifier`, whose algorithm must match the `SignatureMethod`.
+/// Returns the URIs of the references, in order.
+pub fn verify<R: Read>(source: R, digests: &[&dyn DigestMethod], verifier: &dyn Verifier) -> Result<Vec<String>, Error> {
+    let events = read_events(source)?;
+    let signature = find_element(&events, 0, NS_XMLDSIG, "Signature")
+        .ok_or_else(|| Error::Malformed("no Signature element".into()))?;
+    let signed_info = find_element(&events, *signature.start() + 1, NS_XMLDSIG, "SignedInfo")
+        .filter(|signed_info| signature.contains(signed_info.end()))
+        .ok_or_else(|| missing("SignedInfo"))?;
+    let signed_info_element = build_element(&events[signed_info.clone()]);
+    let signature_element = build_element(&events[signature.clone()]);
+
+    let method = child(&signed_info_element, "SignatureMethod")?;
+    let algorithm = attribute(method, "Algorithm")?;
+    if algorithm != verifier.algorithm() {
+        return Err(Error::Unsupported(format!("signature method {algorithm}")));
+    }
+    if method.child_elements().next().is_some() {
+        return Err(Error::Unsupported("SignatureMethod parameters".into()));
+    }
+
+    let mut uris = Vec::new();
+    for reference in signed_info_element.child_elements().filter(|e| is_dsig(e, "Reference")) {
+        let uri = attribute(reference, "URI")?;
+        let subset = select(&events, uri)?;
+        let mut config = C14nConfig::new();
+        if let Some(transforms) = reference.get_child_ns("Transforms", NS_XMLDSIG) {
+            let transforms: Vec<_> = transforms.child_elements().filter(|e| is_dsig(e, "Transform")).collect();
+            for (n, transform) in transforms.iter().enumerate() {
+                let algorithm = attribute(transform, "Algorithm")?;
+                if algorithm != algorithm::ENVELOPED_SIGNATURE && n + 1 < transforms.len() {
+                    return Err(Error::Unsupported(format!("transform {algorithm} before other transforms")));
+                }
+                if algorithm != algorithm::ENVELOPED_SIGNATURE {
+                    // comments are never in same-document references, even with #WithComments
+                    config = c14n_config(transform)?.with_comments(false);
+                }
+            }
+        }
+        let data = canonicalize(&events, subset, Some(signature.clone()), config);
+
+        let method = child(reference, "DigestMethod")?;
+        let algorithm = attribute(method, "Algorithm")?;
+        let digest = digests.iter().find(|d| d.algorithm() == algorithm)
+            .ok_or_else(|| Error::Unsupported(format!("digest method {algorithm}")))?;
+        let expected = base64::decode(&child(reference, "DigestValue")?.text())
+            .ok_or_else(|| Error::Malformed("DigestValue is not base64".into()))?;
+        if digest.digest(&data) != expected {
+            return Err(Error::DigestMismatch(uri.to_owned()));
+        }
+        uris.push(uri.to_owned());
+    }
+    if uris.is_empty() {
+        return Err(missing("Reference"));
+    }
+
+    let config = c14n_config(child(&signed_info_element, "CanonicalizationMethod")?)?;
+    let data = canonicalize(&events, Some(signed_info), None, config);
+    let value = base64::decode(&child(&signature_element, "SignatureValue")?.text())
+        .ok_or_else(|| Error::Malformed("SignatureValue is not base64".into()))?;
+    if !verifier.verify(&data, &value) {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(uris)
+}
+
+/// Reads a document from the source and writes it to the sink with an enveloped `Signature`
+/// of [`SignatureConfig::reference`], computed using the `digest` and the `signer`.
+pub fn sign<R: Read, W: Write>(source: R, sink: W, config: &SignatureConfig, digest: &dyn DigestMethod, signer: &dyn Signer) -> Result<(), Error> {
+    let events = read_events(source)?;
+    let target = match select(&events, &config.reference)? {
+        Some(target) => target,
+        None => find_root(&events).ok_or_else(|| Error::Malformed("no root element".into()))?,
+    };
+    let subset = if config.reference.is_empty() { None } else { Some(target.clone()) };
+    let c14n = C14nConfig::new().method(config.canonicalization);
+    let digest_value = base64::encode(&digest.digest(&canonicalize(&events, subset, None, c14n.clone())));
+
+    // SignedInfo is canonicalized in the context of the output, so it's written without
+    // a SignatureValue first, and then read back
+    let mut unsigned = Vec::new();
+    {
+        let mut writer = EmitterConfig::new().create_writer(&mut unsigned);
+        for (i, event) in events.iter().enumerate() {
+            if i == *target.end() {
+                write_signature(&mut writer, config, digest.algorithm(), &digest_value, signer.algorithm())?;
+            }
+            write_event(&mut writer, event)?;
+        }
+    }
+    let preceding = events[..*target.end()].iter().filter(|e| matches!(e, XmlEvent::StartElement { .. })).count();
+    let events = read_events(&unsigned[..])?;
+    let signature_start = events.iter().enumerate()
+        .filter(|(_, e)| matches!(e, XmlEvent::StartElement { .. }))
+        .nth(preceding).map(|(i, _)| i)
+        .ok_or_else(|| missing("Signature"))?;
+    let signed_info = find_element(&events, signature_start, NS_XMLDSIG, "SignedInfo").ok_or_else(|| missing("SignedInfo"))?;
+    let signature_value = find_element(&events, *signed_info.end(), NS_XMLDSIG, "SignatureValue").ok_or_else(|| missing("SignatureValue"))?;
+
+    let signature = signer.sign(&canonicalize(&events, Some(signed_info), None, c14n)).map_err(Error::Signer)?;
+    let signature = base64::encode(&signature);
+
+    let mut writer = EmitterConfig::new().create_writer(sink);
+    for (i, event) in events.iter().enumerate() {
+        write_event(&mut writer, event)?;
+        if i == *signature_value.start() {
+            writer.write(writer::XmlEvent::characters(&signature))?;
+        }
+    }
+    Ok(())
+}
+
+fn read_events<R: Read>(source: R) -> Result<Vec<XmlEvent>, reader::Error> {
+    let mut reader = EventReader::new_with_config(source, parser_config());
+    let mut events = Vec::new();
+    loop {
+        let event = reader.next()?;
+        let end = event == XmlEvent::EndDocument;
+        events.push(event);
+        if end {
+            return Ok(events);
+        }
+    }
+}
+
+fn write_event<W: Write>(writer: &mut EventWriter<W>, event: &XmlEvent) -> writer::Result<()> {
+    match event {
+        // the output is always UTF-8
+        XmlEvent::StartDocument { version, standalone, .. } => writer.write(writer::XmlEvent::StartDocument {
+            version: *version,
+            encoding: Some("UTF-8"),
+            standalone: *standalone,
+        }),
+        event => match event.as_writer_event() {
+            Some(event) => writer.write(event),
+            None => Ok(()),
+        },
+    }
+}
+
+fn write_signature<W: Write>(writer: &mut EventWriter<W>, config: &SignatureConfig, digest: &str, digest_value: &str, signature: &str) -> writer::Result<()> {
+    let c14n = config.canonicalization.uri(false);
+    let algorithm = |writer: &mut EventWriter<W>, name: &str, algorithm: &str| {
+        writer.write(writer::XmlEvent::start_element(name).attr("Algorithm", algorithm))?;
+        writer.write(writer::XmlEvent::end_element())
+    };
+    writer.write(writer::XmlEvent::start_element("ds:Signature").ns("ds", NS_XMLDSIG))?;
+    writer.write(writer::XmlEvent::start_element("ds:SignedInfo"))?;
+    algorithm(writer, "ds:CanonicalizationMethod", c14n)?;
+    algorithm(writer, "ds:SignatureMethod", signature)?;
+    writer.write(writer::XmlEvent::start_element("ds:Reference").attr("URI", &config.reference))?;
+    writer.write(writer::XmlEvent::start_element("ds:Transforms"))?;
+    algorithm(writer, "ds:Transform", algorithm::ENVELOPED_SIGNATURE)?;
+    algorithm(writer, "ds:Transform", c14n)?;
+    writer.write(writer::XmlEvent::end_element())?;
+    algorithm(writer, "ds:DigestMethod", digest)?;
+    writer.write(writer::XmlEvent::start_element("ds:DigestValue"))?;
+    writer.write(writer::XmlEvent::characters(digest_value))?;
+    writer.write(writer::XmlEvent::end_element())?;
+    writer.write(writer::XmlEvent::end_element())?;
+    writer.write(writer::XmlEvent::end_element())?;
+    writer.write(writer::XmlEvent::start_element("ds:SignatureValue"))?;
+    writer.write(writer::XmlEvent::end_element())?;
+    writer.write(writer::XmlEvent::end_element())
+}
+
+/// Canonicalizes the events within `subset` (all if `None`), except those within `exclude`
+fn canonicalize(events: &[XmlEvent], subset: Option<RangeInclusive<usize>>, exclude: Option<RangeInclusive<usize>>, config: C14nConfig) -> Vec<u8> {
+    let mut canonicalizer = Canonicalizer::new(Vec::new(), config);
+    for (i, event) in events.iter().enumerate() {
+        if subset.as_ref().map_or(true, |s| s.contains(&i)) && !exclude.as_ref().is_some_and(|e| e.contains(&i)) {
+            canonicalizer.write(event).expect("writing to a Vec can't fail");
+        } else {
+            canonicalizer.skip(event);
+        }
+    }
+    canonicalizer.into_inner()
+}
+
+/// The events of the element selected by a same-document URI, or `None` for the whole document
+fn select(events: &[XmlEvent], uri: &str) -> Result<Option<RangeInclusive<usize>>, Error> {
+    if uri.is_empty() {
+        return Ok(None);
+    }
+    let Some(id) = uri.strip_prefix('#').filter(|id| !id.contains('(')) else {
+        return Err(Error::Unsupported(format!("reference URI '{uri}'")));
+    };
+    let mut found = events.iter().enumerate().filter(|(_, event)| match event {
+        XmlEvent::StartElement { attributes, .. } => attributes.iter().any(|a| is_id(&a.name) && a.value == id),
+        _ => false,
+    });
+    match (found.next(), found.next()) {
+        (Some((start, _)), None) => Ok(Some(start..=element_end(events, start))),
+        // a duplicate could make the application use different data than what was signed
+        (Some(_), Some(_)) => Err(Error::Malformed(format!("duplicate Id '{id}'"))),
+        (None, _) => Err(Error::Malformed(format!("no element with Id '{id}'"))),
+    }
+}
+
+fn is_id(name: &OwnedName) -> bool {
+    match name.namespace_ref() {
+        None => matches!(&*name.local_name, "Id" | "ID" | "id"),
+        Some(ns) => ns == NS_XML_URI && name.local_name == "id",
+    }
+}
+
+fn find_root(events: &[XmlEvent]) -> Option<RangeInclusive<usize>> {
+    let start = events.iter().position(|e| matches!(e, XmlEvent::StartElement { .. }))?;
+    Some(start..=element_end(events, start))
+}
+
+/// The events of the first element with this name, starting at the index `from`
+fn find_element(events: &[XmlEvent], from: usize, namespace: &str, local_name: &str) -> Option<RangeInclusive<usize>> {
+    let start = from + events[from..].iter().position(|e| matches!(e,
+        XmlEvent::StartElement { name, .. } if name.local_name == local_name && name.namespace_ref() == Some(namespace)))?;
+    Some(start..=element_end(events, start))
+}
+
+/// The index of the end of the element starting at `start`
+fn element_end(events: &[XmlEvent], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, event) in events.iter().enumerate().skip(start) {
+        match event {
+            XmlEvent::StartElement { .. } => depth += 1,
+            XmlEvent::EndElement { .. } => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            },
+            _ => {},
+        }
+    }
+    events.len() - 1
+}
+
+/// Builds the element from its events
+fn build_element(events: &[XmlEvent]) -> Element {
+    let mut stack: Vec<Element> = Vec::new();
+    for event in events {
+        let node = match event {
+            XmlEvent::StartElement { name, attributes, namespace } => {
+                stack.push(Element { name: name.clone(), attributes: attributes.clone(), namespace: namespace.clone(), children: Vec::new() });
+                continue;
+            },
+            XmlEvent::EndElement { .. } => match stack.pop() {
+                Some(element) if stack.is_empty() => return element,
+                Some(element) => Node::Element(element),
+                None => continue,
+            },
+            XmlEvent::Characters(s) | XmlEvent::Whitespace(s) | XmlEvent::CData(s) => Node::Text(s.clone()),
+            _ => continue,
+        };
+        if let Some(parent) = stack.last_mut() {
+            parent.children.push(node);
+        }
+    }
+    stack.pop().unwrap_or_else(|| Element::new("missing"))
+}
+
+fn is_dsig(element: &Element, local_name: &str) -> bool {
+    element.name.local_name == local_name && element.name.namespace_ref() == Some(NS_XMLDSIG)
+}
+
+fn child<'a>(element: &'a Element, local_name: &str) -> Result<&'a Element, Error> {
+    element.get_child_ns(local_name, NS_XMLDSIG).ok_or_else(|| missing(local_name))
+}
+
+fn attribute<'a>(element: &'a Element, local_name: &str) -> Result<&'a str, Error> {
+    element.attribute(local_name)
+        .ok_or_else(|| Error::Malformed(format!("missing {local_name} attribute of {}", element.name.local_name)))
+}
+
+fn missing(local_name: &str) -> Error {
+    Error::Malformed(format!("missing {local_name} element"))
+}
+
+/// The configuration of a `CanonicalizationMethod` or a canonicalization `Transform`
+fn c14n_config(element: &Element) -> Result<C14nConfig, Error> {
+    let algorithm = attribute(element, "Algorithm")?;
+    let (method, with_comments) = Method::from_uri(algorithm)
+        .ok_or_else(|| Error::Unsupported(format!("canonicalization method {algorithm}")))?;
+    let mut config = C14nConfig::new().method(method).with_comments(with_comments);
+    if let Some(prefixes) = element.get_child_ns("InclusiveNamespaces", NS_EXC_C14N).and_then(|e| e.attribute("PrefixList")) {
+        config = config.inclusive_namespaces(prefixes.split_ascii_whitespace());
+    }
+    Ok(config)
+}
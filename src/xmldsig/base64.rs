@@ -0,0 +1,58 @@
+//! Base64 as used by `DigestValue` and `SignatureValue`
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &b)| bits | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Whitespace is ignored, since values are often split into lines
+pub(crate) fn decode(text: &str) -> Option<Vec<u8>> {
+    let text: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if text.len() % 4 != 0 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for (n, chunk) in text.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&b| b == b'=').count();
+        if padding > 2 || (padding > 0 && n + 1 != text.len() / 4) {
+            return None;
+        }
+        let mut bits = 0u32;
+        for &b in &chunk[..4 - padding] {
+            let value = ALPHABET.iter().position(|&a| a == b)?;
+            bits = bits << 6 | value as u32;
+        }
+        bits <<= 6 * padding;
+        out.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn round_trip() {
+        for (data, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")] {
+            assert_eq!(encode(data.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), data.as_bytes());
+        }
+        assert_eq!(decode(" Zm9v\n YmFy\n").unwrap(), b"foobar");
+        assert_eq!(decode("Zm9"), None);
+        assert_eq!(decode("Zg==Zg=="), None);
+        assert_eq!(decode("Zm9*"), None);
+    }
+}
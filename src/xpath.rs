@@ -0,0 +1,379 @@
+//! Contains an XPath 1.0 implementation for querying documents in memory.
+//!
+//! Expressions are evaluated over a [`CompactDocument`], which is built from
+//! the events of an [`EventReader`](crate::EventReader). All axes, predicates, and the core function
+//! library are supported. Prefixes used in expressions are resolved using the namespace mappings
+//! of the [`Context`].
+//!
+//! ```rust
+//! use xml::dom::compact::CompactDocument;
+//! use xml::xpath::{Context, XPath};
+//!
+//! let doc: CompactDocument = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+//!     <entry><title>First</title><link href="/1"/></entry>
+//!     <entry><title>Second</title><link href="/2"/></entry>
+//! </feed>"#.parse().unwrap();
+//!
+//! let ctx = Context::new().namespace("a", "http://www.w3.org/2005/Atom");
+//!
+//! let hrefs = XPath::compile("/a:feed/a:entry/a:link/@href").unwrap();
+//! let values: Vec<_> = hrefs.evaluate_with(&ctx, doc.root()).unwrap()
+//!     .into_nodes().unwrap()
+//!     .iter().map(|n| n.string_value())
+//!     .collect();
+//! assert_eq!(values, ["/1", "/2"]);
+//!
+//! let title = XPath::compile("string(//a:entry[a:link/@href = '/2']/a:title)").unwrap();
+//! assert_eq!(title.evaluate_with(&ctx, doc.root()).unwrap().string(), "Second");
+//!
+//! let count = XPath::compile("count(//a:entry) * 10").unwrap();
+//! assert_eq!(count.evaluate_with(&ctx, doc.root()).unwrap().number(), 20.);
+//! ```
+//!
+//! Differences from the XPath data model: adjacent text and CDATA nodes are separate text
+//! nodes (enable `cdata_to_characters` in [`ParserConfig`](crate::ParserConfig) to merge them),
+//! and since DTDs are not processed, the `id()` function looks up `id` and `xml:id` attributes.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dom::compact::{CompactDocument, NodeKind, NodeRef};
+use crate::name::Name;
+use crate::namespace::{Namespace, NS_XML_PREFIX, NS_XML_URI};
+
+use self::eval::Evaluator;
+use self::parser::Expr;
+
+mod eval;
+mod parser;
+pub mod streaming;
+
+/// An error in an XPath expression or its evaluation.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The expression is not syntactically valid.
+    Syntax {
+        /// Byte offset in the expression.
+        position: usize,
+        /// What was wrong.
+        message: String,
+    },
+
+    /// The prefix is not bound in the context's namespace mappings.
+    UnboundPrefix(String),
+
+    /// No such function in the core function library.
+    UnknownFunction(String),
+
+    /// The variable has not been set in the context.
+    UnknownVariable(String),
+
+    /// The function has been called with a wrong number of arguments.
+    ArgumentCount(String),
+
+    /// A node-set was required, but the expression evaluated to another type.
+    NotANodeSet,
+
+    /// The expression is valid, but outside of the subset supported by [`StreamingPath`](streaming::StreamingPath).
+    NotStreamable(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("XPath error: ")?;
+        match self {
+            Self::Syntax { position, message } => write!(f, "{message} at offset {position}"),
+            Self::UnboundPrefix(p) => write!(f, "prefix '{p}' is not bound"),
+            Self::UnknownFunction(name) => write!(f, "unknown function {name}()"),
+            Self::UnknownVariable(name) => write!(f, "unknown variable ${name}"),
+            Self::ArgumentCount(name) => write!(f, "wrong number of arguments to {name}()"),
+            Self::NotANodeSet => f.write_str("expression does not evaluate to a node-set"),
+            Self::NotStreamable(reason) => write!(f, "expression is not streamable: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A compiled XPath expression.
+#[derive(Clone, Debug)]
+pub struct XPath {
+    expr: Expr,
+}
+
+impl XPath {
+    /// Parses the expression.
+    pub fn compile(expression: &str) -> Result<Self, Error> {
+        Ok(Self { expr: parser::parse(expression)? })
+    }
+
+    /// Evaluates the expression with the given context node and an empty [`Context`].
+    pub fn evaluate<'a>(&self, node: impl Into<Node<'a>>) -> Result<Value<'a>, Error> {
+        self.evaluate_with(&Context::new(), node)
+    }
+
+    /// Evaluates the expression with the given context node, namespaces and variables.
+    pub fn evaluate_with<'a>(&self, context: &Context<'a>, node: impl Into<Node<'a>>) -> Result<Value<'a>, Error> {
+        Evaluator { ctx: context }.evaluate(&self.expr, node.into())
+    }
+
+    /// Evaluates the expression, which must return a node-set, and returns the nodes in document order.
+    pub fn select<'a>(&self, context: &Context<'a>, node: impl Into<Node<'a>>) -> Result<Vec<Node<'a>>, Error> {
+        self.evaluate_with(context, node)?.into_nodes().ok_or(Error::NotANodeSet)
+    }
+}
+
+impl FromStr for XPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Self::compile(s)
+    }
+}
+
+/// Namespace mappings and variable bindings used when evaluating an expression.
+#[derive(Clone, Debug)]
+pub struct Context<'a> {
+    /// Mappings used to resolve prefixes in name tests.
+    ///
+    /// Unprefixed names in expressions never match namespaced nodes, regardless of
+    /// the default namespace.
+    pub namespaces: Namespace,
+
+    /// Values of `$variables`, by their qualified name.
+    pub variables: HashMap<String, Value<'a>>,
+}
+
+impl<'a> Context<'a> {
+    /// Creates a context in which only the `xml` prefix is bound.
+    #[must_use]
+    pub fn new() -> Self {
+        let mut namespaces = Namespace::empty();
+        namespaces.put(NS_XML_PREFIX, NS_XML_URI);
+        Self { namespaces, variables: HashMap::new() }
+    }
+
+    /// Creates a context using the namespace mappings in scope of the given node.
+    #[must_use]
+    pub fn from_node(node: NodeRef<'a>) -> Self {
+        let mut ctx = Self::new();
+        ctx.namespaces.extend(node.namespace());
+        ctx
+    }
+
+    /// Binds the prefix to a namespace URI.
+    #[must_use]
+    pub fn namespace<P: Into<String>, U: Into<String>>(mut self, prefix: P, uri: U) -> Self {
+        self.namespaces.force_put(prefix, uri);
+        self
+    }
+
+    /// Sets the value of a variable.
+    #[must_use]
+    pub fn variable<N: Into<String>, V: Into<Value<'a>>>(mut self, name: N, value: V) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+}
+
+impl Default for Context<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A node in the XPath data model.
+///
+/// In addition to the nodes of a [`CompactDocument`], XPath has attribute and namespace nodes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Node<'a> {
+    /// Document, element, text, comment or processing instruction node.
+    Node(NodeRef<'a>),
+
+    /// An attribute of the element, by its index.
+    Attribute(NodeRef<'a>, u32),
+
+    /// A namespace binding in scope of the element: prefix and URI.
+    Namespace(NodeRef<'a>, &'a str, &'a str),
+}
+
+impl<'a> From<NodeRef<'a>> for Node<'a> {
+    #[inline]
+    fn from(n: NodeRef<'a>) -> Self {
+        Node::Node(n)
+    }
+}
+
+impl<'a> From<&'a CompactDocument> for Node<'a> {
+    #[inline]
+    fn from(doc: &'a CompactDocument) -> Self {
+        Node::Node(doc.root())
+    }
+}
+
+impl<'a> Node<'a> {
+    /// Returns the document, element, text, comment or processing instruction node.
+    #[inline]
+    #[must_use]
+    pub fn as_node_ref(self) -> Option<NodeRef<'a>> {
+        match self {
+            Node::Node(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// The string-value of the node, as defined by XPath.
+    #[must_use]
+    pub fn string_value(self) -> String {
+        match self {
+            Node::Node(n) => match n.kind() {
+                NodeKind::Document | NodeKind::Element => n.text_content(),
+                _ => n.text().unwrap_or_default().into(),
+            },
+            Node::Attribute(n, i) => n.attributes().nth(i as usize).map(|a| a.value.into()).unwrap_or_default(),
+            Node::Namespace(_, _, uri) => uri.into(),
+        }
+    }
+
+    /// Expanded name of elements, attributes, and processing instructions, and prefix of namespace nodes.
+    #[must_use]
+    pub fn name(self) -> Option<Name<'a>> {
+        match self {
+            Node::Node(n) => n.name().or_else(|| n.pi_target().map(Name::local)),
+            Node::Attribute(n, i) => n.attributes().nth(i as usize).map(|a| a.name),
+            Node::Namespace(_, prefix, _) if !prefix.is_empty() => Some(Name::local(prefix)),
+            Node::Namespace(..) => None,
+        }
+    }
+
+    /// The parent of a tree node, or the element that owns an attribute or namespace node.
+    #[must_use]
+    pub fn parent(self) -> Option<Node<'a>> {
+        match self {
+            Node::Node(n) => n.parent().map(Node::Node),
+            Node::Attribute(n, _) | Node::Namespace(n, ..) => Some(Node::Node(n)),
+        }
+    }
+
+    /// Iterates over the ancestors, ending with the document node.
+    pub fn ancestors(self) -> impl Iterator<Item = Node<'a>> {
+        std::iter::successors(self.parent(), |n| n.parent())
+    }
+
+    /// The document node.
+    #[must_use]
+    pub fn root(self) -> Node<'a> {
+        let (Node::Node(n) | Node::Attribute(n, _) | Node::Namespace(n, ..)) = self;
+        Node::Node(n.document().root())
+    }
+
+    /// Compares positions of nodes in the document.
+    ///
+    /// Namespace nodes of an element come right after the element, followed by its attributes.
+    #[must_use]
+    pub fn document_order(&self, other: &Self) -> Ordering {
+        fn key<'a>(n: &Node<'a>) -> (NodeRef<'a>, u8) {
+            match *n {
+                Node::Node(n) => (n, 0),
+                Node::Namespace(n, ..) => (n, 1),
+                Node::Attribute(n, _) => (n, 2),
+            }
+        }
+        let ((a, ac), (b, bc)) = (key(self), key(other));
+        a.id().cmp(&b.id()).then(ac.cmp(&bc)).then_with(|| match (self, other) {
+            (Node::Namespace(_, a, _), Node::Namespace(_, b, _)) => a.cmp(b),
+            (Node::Attribute(_, a), Node::Attribute(_, b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        })
+    }
+}
+
+/// Result of an XPath expression.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value<'a> {
+    /// Nodes in document order, without duplicates.
+    NodeSet(Vec<Node<'a>>),
+    /// A boolean.
+    Boolean(bool),
+    /// A floating-point number.
+    Number(f64),
+    /// A string.
+    String(String),
+}
+
+impl<'a> Value<'a> {
+    /// Returns the nodes if the value is a node-set.
+    #[must_use]
+    pub fn into_nodes(self) -> Option<Vec<Node<'a>>> {
+        match self {
+            Value::NodeSet(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Converts the value as the `boolean()` function does.
+    #[must_use]
+    pub fn boolean(&self) -> bool {
+        match self {
+            Value::NodeSet(n) => !n.is_empty(),
+            Value::Boolean(b) => *b,
+            Value::Number(n) => *n != 0. && !n.is_nan(),
+            Value::String(s) => !s.is_empty(),
+        }
+    }
+
+    /// Converts the value as the `number()` function does.
+    #[must_use]
+    pub fn number(&self) -> f64 {
+        match self {
+            Value::Boolean(b) => if *b { 1. } else { 0. },
+            Value::Number(n) => *n,
+            v => eval::string_to_number(&v.string()),
+        }
+    }
+
+    /// Converts the value as the `string()` function does.
+    #[must_use]
+    pub fn string(&self) -> String {
+        match self {
+            Value::NodeSet(n) => n.first().map(|n| n.string_value()).unwrap_or_default(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Number(n) => eval::number_to_string(*n),
+            Value::String(s) => s.clone(),
+        }
+    }
+}
+
+impl From<bool> for Value<'_> {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+impl From<f64> for Value<'_> {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<String> for Value<'_> {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value<'_> {
+    fn from(s: &str) -> Self {
+        Value::String(s.into())
+    }
+}
+
+impl<'a> From<Vec<Node<'a>>> for Value<'a> {
+    fn from(mut nodes: Vec<Node<'a>>) -> Self {
+        eval::sort_document_order(&mut nodes);
+        Value::NodeSet(nodes)
+    }
+}
@@ -0,0 +1,440 @@
+//! Contains the evaluator of XPath expressions and the core function library.
+
+use std::cmp::Ordering;
+
+use crate::dom::compact::NodeKind;
+use crate::namespace::{NS_EMPTY_URI, NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XML_URI};
+use crate::xpath::parser::{Axis, BinaryOp, Expr, NodeTest, Step};
+use crate::xpath::{Context, Error, Node, Value};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Dynamic context of evaluation: the context node, its position and the context size
+#[derive(Copy, Clone)]
+struct Focus<'a> {
+    node: Node<'a>,
+    position: usize,
+    size: usize,
+}
+
+pub(crate) struct Evaluator<'c, 'a> {
+    pub ctx: &'c Context<'a>,
+}
+
+impl<'a> Evaluator<'_, 'a> {
+    pub fn evaluate(&self, expr: &Expr, node: Node<'a>) -> Result<Value<'a>> {
+        self.eval(expr, Focus { node, position: 1, size: 1 })
+    }
+
+    fn eval(&self, expr: &Expr, focus: Focus<'a>) -> Result<Value<'a>> {
+        Ok(match expr {
+            Expr::Literal(s) => Value::String(s.clone()),
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Variable(name) => self.ctx.variables.get(name).cloned()
+                .ok_or_else(|| Error::UnknownVariable(name.clone()))?,
+            Expr::Negate(e) => Value::Number(-self.eval(e, focus)?.number()),
+            Expr::Binary(op, left, right) => self.eval_binary(*op, left, right, focus)?,
+            Expr::Function(name, args) => self.call(name, args, focus)?,
+            Expr::Path { absolute, steps } => {
+                let start = if *absolute { focus.node.root() } else { focus.node };
+                Value::NodeSet(self.eval_steps(vec![start], steps)?)
+            },
+            Expr::Filter { primary, predicates, steps } => {
+                let value = self.eval(primary, focus)?;
+                if predicates.is_empty() && steps.is_empty() {
+                    return Ok(value);
+                }
+                let Value::NodeSet(mut nodes) = value else {
+                    return Err(Error::NotANodeSet);
+                };
+                for predicate in predicates {
+                    nodes = self.filter(nodes, predicate)?;
+                }
+                Value::NodeSet(self.eval_steps(nodes, steps)?)
+            },
+        })
+    }
+
+    fn eval_binary(&self, op: BinaryOp, left: &Expr, right: &Expr, focus: Focus<'a>) -> Result<Value<'a>> {
+        Ok(match op {
+            BinaryOp::Or => Value::Boolean(self.eval(left, focus)?.boolean() || self.eval(right, focus)?.boolean()),
+            BinaryOp::And => Value::Boolean(self.eval(left, focus)?.boolean() && self.eval(right, focus)?.boolean()),
+            BinaryOp::Union => {
+                let (Value::NodeSet(mut l), Value::NodeSet(r)) = (self.eval(left, focus)?, self.eval(right, focus)?) else {
+                    return Err(Error::NotANodeSet);
+                };
+                l.extend(r);
+                sort_document_order(&mut l);
+                Value::NodeSet(l)
+            },
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                Value::Boolean(compare(op, &self.eval(left, focus)?, &self.eval(right, focus)?))
+            },
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+                let l = self.eval(left, focus)?.number();
+                let r = self.eval(right, focus)?.number();
+                Value::Number(match op {
+                    BinaryOp::Add => l + r,
+                    BinaryOp::Sub => l - r,
+                    BinaryOp::Mul => l * r,
+                    BinaryOp::Div => l / r,
+                    _ => l % r,
+                })
+            },
+        })
+    }
+
+    fn eval_steps(&self, mut nodes: Vec<Node<'a>>, steps: &[Step]) -> Result<Vec<Node<'a>>> {
+        for step in steps {
+            if let NodeTest::Name { prefix: Some(prefix), .. } = &step.test {
+                if self.ctx.namespaces.get(prefix).is_none() {
+                    return Err(Error::UnboundPrefix(prefix.clone()));
+                }
+            }
+            let mut result = Vec::new();
+            for &node in &nodes {
+                let mut selected: Vec<Node<'a>> = Vec::new();
+                axis_nodes(node, step.axis, &mut |n| if self.matches(n, step) { selected.push(n); });
+                for predicate in &step.predicates {
+                    selected = self.filter(selected, predicate)?;
+                }
+                result.extend(selected);
+            }
+            sort_document_order(&mut result);
+            nodes = result;
+        }
+        Ok(nodes)
+    }
+
+    /// Applies a predicate to nodes given in axis order
+    fn filter(&self, nodes: Vec<Node<'a>>, predicate: &Expr) -> Result<Vec<Node<'a>>> {
+        let size = nodes.len();
+        let mut kept = Vec::with_capacity(size);
+        for (i, node) in nodes.into_iter().enumerate() {
+            let focus = Focus { node, position: i + 1, size };
+            let keep = match self.eval(predicate, focus)? {
+                Value::Number(n) => n == (i + 1) as f64,
+                v => v.boolean(),
+            };
+            if keep {
+                kept.push(node);
+            }
+        }
+        Ok(kept)
+    }
+
+    fn matches(&self, node: Node<'a>, step: &Step) -> bool {
+        match &step.test {
+            NodeTest::Node => true,
+            NodeTest::Text => matches!(node, Node::Node(n) if matches!(n.kind(), NodeKind::Text | NodeKind::CData)),
+            NodeTest::Comment => matches!(node, Node::Node(n) if n.kind() == NodeKind::Comment),
+            NodeTest::ProcessingInstruction(target) => match node {
+                Node::Node(n) if n.kind() == NodeKind::ProcessingInstruction =>
+                    target.as_deref().map_or(true, |t| n.pi_target() == Some(t)),
+                _ => false,
+            },
+            NodeTest::Name { prefix, local } => {
+                let (node_ns, node_local) = match (node, step.axis) {
+                    (Node::Attribute(n, i), Axis::Attribute) => match n.attributes().nth(i as usize) {
+                        Some(a) => (a.name.namespace, a.name.local_name),
+                        None => return false,
+                    },
+                    (Node::Namespace(_, prefix, _), Axis::Namespace) => (None, prefix),
+                    (Node::Node(n), axis) if axis != Axis::Attribute && axis != Axis::Namespace => match n.name() {
+                        Some(name) => (name.namespace, name.local_name),
+                        None => return false,
+                    },
+                    _ => return false,
+                };
+                let uri = prefix.as_ref().and_then(|p| self.ctx.namespaces.get(p));
+                let any_name = prefix.is_none() && local.is_none();
+                if !any_name && node_ns.filter(|ns| !ns.is_empty()) != uri.filter(|ns| !ns.is_empty()) {
+                    return false;
+                }
+                local.as_deref().map_or(true, |l| l == node_local)
+            },
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Expr], focus: Focus<'a>) -> Result<Value<'a>> {
+        let arity = |min: usize, max: usize| -> Result<()> {
+            if args.len() < min || args.len() > max {
+                Err(Error::ArgumentCount(name.into()))
+            } else {
+                Ok(())
+            }
+        };
+        let string_arg = |i: usize| -> Result<String> {
+            match args.get(i) {
+                Some(e) => Ok(self.eval(e, focus)?.string()),
+                None => Ok(focus.node.string_value()),
+            }
+        };
+        let number_arg = |i: usize| -> Result<f64> { Ok(self.eval(&args[i], focus)?.number()) };
+        let nodes_arg = |i: usize| -> Result<Vec<Node<'a>>> {
+            match args.get(i) {
+                Some(e) => match self.eval(e, focus)? {
+                    Value::NodeSet(n) => Ok(n),
+                    _ => Err(Error::NotANodeSet),
+                },
+                None => Ok(vec![focus.node]),
+            }
+        };
+
+        Ok(match name {
+            "last" => { arity(0, 0)?; Value::Number(focus.size as f64) },
+            "position" => { arity(0, 0)?; Value::Number(focus.position as f64) },
+            "count" => { arity(1, 1)?; Value::Number(nodes_arg(0)?.len() as f64) },
+            "id" => {
+                arity(1, 1)?;
+                let ids = match self.eval(&args[0], focus)? {
+                    Value::NodeSet(n) => n.iter().map(|n| n.string_value()).collect::<Vec<_>>().join(" "),
+                    v => v.string(),
+                };
+                let mut found: Vec<Node<'a>> = focus.node.root().as_node_ref().into_iter()
+                    .flat_map(|root| root.descendants())
+                    .filter(|n| n.attributes().any(|a| is_id_attribute(a.name) && ids.split_ascii_whitespace().any(|id| id == a.value)))
+                    .map(Node::Node)
+                    .collect();
+                sort_document_order(&mut found);
+                Value::NodeSet(found)
+            },
+            "local-name" | "namespace-uri" | "name" => {
+                arity(0, 1)?;
+                let nodes = nodes_arg(0)?;
+                let name_of = nodes.first().and_then(|n| n.name());
+                Value::String(match (name, name_of) {
+                    (_, None) => String::new(),
+                    ("local-name", Some(n)) => n.local_name.into(),
+                    ("namespace-uri", Some(n)) => n.namespace.unwrap_or_default().into(),
+                    (_, Some(n)) => n.to_repr(),
+                })
+            },
+            "string" => { arity(0, 1)?; Value::String(string_arg(0)?) },
+            "concat" => {
+                if args.len() < 2 {
+                    return Err(Error::ArgumentCount(name.into()));
+                }
+                let mut s = String::new();
+                for i in 0..args.len() {
+                    s.push_str(&string_arg(i)?);
+                }
+                Value::String(s)
+            },
+            "starts-with" => { arity(2, 2)?; Value::Boolean(string_arg(0)?.starts_with(&string_arg(1)?)) },
+            "contains" => { arity(2, 2)?; Value::Boolean(string_arg(0)?.contains(&string_arg(1)?)) },
+            "substring-before" => {
+                arity(2, 2)?;
+                let s = string_arg(0)?;
+                Value::String(s.split_once(&*string_arg(1)?).map(|(b, _)| b.into()).unwrap_or_default())
+            },
+            "substring-after" => {
+                arity(2, 2)?;
+                let s = string_arg(0)?;
+                Value::String(s.split_once(&*string_arg(1)?).map(|(_, a)| a.into()).unwrap_or_default())
+            },
+            "substring" => {
+                arity(2, 3)?;
+                let s = string_arg(0)?;
+                let start = round(number_arg(1)?);
+                let end = if args.len() == 3 { start + round(number_arg(2)?) } else { f64::INFINITY };
+                Value::String(s.chars().enumerate()
+                    .filter(|&(i, _)| { let p = (i + 1) as f64; p >= start && p < end })
+                    .map(|(_, c)| c)
+                    .collect())
+            },
+            "string-length" => { arity(0, 1)?; Value::Number(string_arg(0)?.chars().count() as f64) },
+            "normalize-space" => {
+                arity(0, 1)?;
+                Value::String(string_arg(0)?.split(crate::common::is_whitespace_char)
+                    .filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" "))
+            },
+            "translate" => {
+                arity(3, 3)?;
+                let from: Vec<char> = string_arg(1)?.chars().collect();
+                let to: Vec<char> = string_arg(2)?.chars().collect();
+                Value::String(string_arg(0)?.chars().filter_map(|c| match from.iter().position(|&f| f == c) {
+                    Some(i) => to.get(i).copied(),
+                    None => Some(c),
+                }).collect())
+            },
+            "boolean" => { arity(1, 1)?; Value::Boolean(self.eval(&args[0], focus)?.boolean()) },
+            "not" => { arity(1, 1)?; Value::Boolean(!self.eval(&args[0], focus)?.boolean()) },
+            "true" => { arity(0, 0)?; Value::Boolean(true) },
+            "false" => { arity(0, 0)?; Value::Boolean(false) },
+            "lang" => {
+                arity(1, 1)?;
+                let lang = string_arg(0)?.to_ascii_lowercase();
+                let node_lang = std::iter::once(focus.node).chain(focus.node.ancestors())
+                    .filter_map(|n| n.as_node_ref())
+                    .find_map(|n| n.attribute_ns("lang", NS_XML_URI))
+                    .map(str::to_ascii_lowercase);
+                Value::Boolean(node_lang.is_some_and(|l| l == lang ||
+                    l.strip_prefix(&*lang).is_some_and(|rest| rest.starts_with('-'))))
+            },
+            "number" => {
+                arity(0, 1)?;
+                Value::Number(match args.first() {
+                    Some(e) => self.eval(e, focus)?.number(),
+                    None => string_to_number(&focus.node.string_value()),
+                })
+            },
+            "sum" => { arity(1, 1)?; Value::Number(nodes_arg(0)?.iter().map(|n| string_to_number(&n.string_value())).sum()) },
+            "floor" => { arity(1, 1)?; Value::Number(number_arg(0)?.floor()) },
+            "ceiling" => { arity(1, 1)?; Value::Number(number_arg(0)?.ceil()) },
+            "round" => { arity(1, 1)?; Value::Number(round(number_arg(0)?)) },
+            _ => return Err(Error::UnknownFunction(name.into())),
+        })
+    }
+}
+
+/// XPath rounding: halves round towards positive infinity
+fn round(n: f64) -> f64 {
+    if n.is_nan() || n.is_infinite() {
+        return n;
+    }
+    let r = (n + 0.5).floor();
+    if r == 0. && n < 0. { -0. } else { r }
+}
+
+/// Without a DTD there are no declared ID attributes, so `id` and `xml:id` are assumed
+fn is_id_attribute(name: crate::name::Name<'_>) -> bool {
+    name.local_name == "id" && (name.namespace.is_none() || name.namespace == Some(NS_XML_URI))
+}
+
+pub(crate) fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(crate::common::is_whitespace_char);
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    let valid = !digits.is_empty() && digits != "." &&
+        digits.chars().all(|c| c.is_ascii_digit() || c == '.') &&
+        digits.matches('.').count() <= 1;
+    if valid { s.parse().unwrap_or(f64::NAN) } else { f64::NAN }
+}
+
+pub(crate) fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".into()
+    } else if n.is_infinite() {
+        if n > 0. { "Infinity".into() } else { "-Infinity".into() }
+    } else if n == 0. {
+        "0".into()
+    } else {
+        n.to_string()
+    }
+}
+
+fn compare(op: BinaryOp, left: &Value<'_>, right: &Value<'_>) -> bool {
+    match (left, right) {
+        (Value::NodeSet(l), Value::NodeSet(r)) => {
+            let r: Vec<String> = r.iter().map(|n| n.string_value()).collect();
+            l.iter().any(|l| {
+                let l = l.string_value();
+                r.iter().any(|r| compare_atomic(op, &Value::String(l.clone()), &Value::String(r.clone())))
+            })
+        },
+        (Value::NodeSet(nodes), other) => match other {
+            Value::Boolean(_) => compare_atomic(op, &Value::Boolean(!nodes.is_empty()), other),
+            _ => nodes.iter().any(|n| compare_atomic(op, &Value::String(n.string_value()), other)),
+        },
+        (other, Value::NodeSet(nodes)) => match other {
+            Value::Boolean(_) => compare_atomic(op, other, &Value::Boolean(!nodes.is_empty())),
+            _ => nodes.iter().any(|n| compare_atomic(op, other, &Value::String(n.string_value()))),
+        },
+        (l, r) => compare_atomic(op, l, r),
+    }
+}
+
+pub(crate) fn compare_atomic(op: BinaryOp, left: &Value<'_>, right: &Value<'_>) -> bool {
+    match op {
+        BinaryOp::Eq | BinaryOp::Ne => {
+            let equal = match (left, right) {
+                (Value::Boolean(_), _) | (_, Value::Boolean(_)) => left.boolean() == right.boolean(),
+                (Value::Number(_), _) | (_, Value::Number(_)) => left.number() == right.number(),
+                _ => left.string() == right.string(),
+            };
+            equal == (op == BinaryOp::Eq)
+        },
+        _ => {
+            let (l, r) = (left.number(), right.number());
+            match op {
+                BinaryOp::Lt => l < r,
+                BinaryOp::Le => l <= r,
+                BinaryOp::Gt => l > r,
+                _ => l >= r,
+            }
+        },
+    }
+}
+
+pub(crate) fn sort_document_order(nodes: &mut Vec<Node<'_>>) {
+    nodes.sort_by(Node::document_order);
+    nodes.dedup_by(|a, b| a.document_order(b) == Ordering::Equal);
+}
+
+/// Calls `f` for every node on the axis, in axis order
+fn axis_nodes<'a>(node: Node<'a>, axis: Axis, f: &mut dyn FnMut(Node<'a>)) {
+    let tree_node = node.as_node_ref();
+    match axis {
+        Axis::Self_ => f(node),
+        Axis::Child => if let Some(n) = tree_node {
+            n.children().for_each(|c| f(Node::Node(c)));
+        },
+        Axis::Descendant => if let Some(n) = tree_node {
+            n.descendants().for_each(|c| f(Node::Node(c)));
+        },
+        Axis::DescendantOrSelf => {
+            f(node);
+            if let Some(n) = tree_node {
+                n.descendants().for_each(|c| f(Node::Node(c)));
+            }
+        },
+        Axis::Parent => if let Some(p) = node.parent() { f(p) },
+        Axis::Ancestor => node.ancestors().for_each(f),
+        Axis::AncestorOrSelf => {
+            f(node);
+            node.ancestors().for_each(f);
+        },
+        Axis::FollowingSibling => if let Some(n) = tree_node {
+            n.following_siblings().for_each(|c| f(Node::Node(c)));
+        },
+        Axis::PrecedingSibling => if let Some(n) = tree_node {
+            n.preceding_siblings().for_each(|c| f(Node::Node(c)));
+        },
+        Axis::Following => {
+            let (owner, start) = match node {
+                Node::Node(n) => (n, n.subtree_end().index()),
+                Node::Attribute(n, _) | Node::Namespace(n, ..) => (n, n.id().index() + 1),
+            };
+            let doc = owner.document();
+            (start..doc.len()).for_each(|i| f(Node::Node(doc.at(i))));
+        },
+        Axis::Preceding => {
+            let owner = match node {
+                Node::Node(n) => n,
+                Node::Attribute(n, _) | Node::Namespace(n, ..) => n,
+            };
+            let ancestors: Vec<_> = owner.ancestors().map(|a| a.id()).collect();
+            let doc = owner.document();
+            (1..owner.id().index()).rev()
+                .map(|i| doc.at(i))
+                .filter(|n| !ancestors.contains(&n.id()))
+                .for_each(|n| f(Node::Node(n)));
+        },
+        Axis::Attribute => if let Some(n) = tree_node {
+            for (i, attr) in n.attributes().enumerate() {
+                // namespace declarations are not attributes in the XPath data model
+                if attr.name.namespace != Some(crate::namespace::NS_XMLNS_URI) {
+                    f(Node::Attribute(n, i as u32));
+                }
+            }
+        },
+        Axis::Namespace => if let Some(n) = tree_node.filter(|n| n.is_element()) {
+            for (prefix, uri) in n.namespace() {
+                if prefix == NS_XMLNS_PREFIX || (prefix == NS_NO_PREFIX && uri == NS_EMPTY_URI) {
+                    continue;
+                }
+                f(Node::Namespace(n, prefix, uri));
+            }
+        },
+    }
+}
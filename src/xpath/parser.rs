@@ -0,0 +1,593 @@
+//! Contains the lexer and the parser of XPath expressions.
+
+use crate::common::{is_name_char, is_name_start_char, is_whitespace_char};
+use crate::xpath::Error;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Axis {
+    Ancestor,
+    AncestorOrSelf,
+    Attribute,
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Following,
+    FollowingSibling,
+    Namespace,
+    Parent,
+    Preceding,
+    PrecedingSibling,
+    Self_,
+}
+
+impl Axis {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ancestor" => Self::Ancestor,
+            "ancestor-or-self" => Self::AncestorOrSelf,
+            "attribute" => Self::Attribute,
+            "child" => Self::Child,
+            "descendant" => Self::Descendant,
+            "descendant-or-self" => Self::DescendantOrSelf,
+            "following" => Self::Following,
+            "following-sibling" => Self::FollowingSibling,
+            "namespace" => Self::Namespace,
+            "parent" => Self::Parent,
+            "preceding" => Self::Preceding,
+            "preceding-sibling" => Self::PrecedingSibling,
+            "self" => Self::Self_,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum NodeTest {
+    /// `prefix:local`, `prefix:*` or `*` (when both are `None`)
+    Name { prefix: Option<String>, local: Option<String> },
+    Node,
+    Text,
+    Comment,
+    ProcessingInstruction(Option<String>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Step {
+    pub axis: Axis,
+    pub test: NodeTest,
+    pub predicates: Vec<Expr>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Union,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Expr {
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Variable(String),
+    Function(String, Vec<Expr>),
+    /// Location path, starting from the root if absolute
+    Path { absolute: bool, steps: Vec<Step> },
+    /// Primary expression with predicates, optionally followed by a relative path
+    Filter { primary: Box<Expr>, predicates: Vec<Expr>, steps: Vec<Step> },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    DotDot,
+    At,
+    Comma,
+    ColonColon,
+    Slash,
+    DoubleSlash,
+    Op(BinaryOp),
+    Minus,
+    Multiply,
+    /// `*`, `prefix:*` or a QName
+    NameTest { prefix: Option<String>, local: Option<String> },
+    NodeType(String),
+    FunctionName(String),
+    AxisName(Axis),
+    Literal(String),
+    Number(f64),
+    Variable(String),
+}
+
+impl Token {
+    /// Whether a `*` or a name after this token is a name test rather than an operator
+    fn precedes_name_test(&self) -> bool {
+        matches!(self, Self::At | Self::ColonColon | Self::LParen | Self::LBracket | Self::Comma |
+            Self::Slash | Self::DoubleSlash | Self::Op(_) | Self::Minus | Self::Multiply)
+    }
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    tokens: Vec<(Token, usize)>,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek_char(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek_char() {
+            if !is_whitespace_char(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Syntax { position: self.pos, message: message.into() }
+    }
+
+    fn read_ncname(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let mut chars = rest.char_indices();
+        match chars.next() {
+            Some((_, c)) if c != ':' && is_name_start_char(c) => {},
+            _ => return None,
+        }
+        let end = chars.find(|&(_, c)| c == ':' || !is_name_char(c)).map_or(rest.len(), |(i, _)| i);
+        self.pos += end;
+        Some(&rest[..end])
+    }
+
+    fn operator_expected(&self) -> bool {
+        self.tokens.last().is_some_and(|(t, _)| !t.precedes_name_test())
+    }
+
+    fn tokenize(mut self) -> Result<Vec<(Token, usize)>, Error> {
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let Some(c) = self.peek_char() else { break };
+            let rest = self.rest();
+            let (token, len) = match c {
+                '(' => (Token::LParen, 1),
+                ')' => (Token::RParen, 1),
+                '[' => (Token::LBracket, 1),
+                ']' => (Token::RBracket, 1),
+                '@' => (Token::At, 1),
+                ',' => (Token::Comma, 1),
+                '|' => (Token::Op(BinaryOp::Union), 1),
+                '+' => (Token::Op(BinaryOp::Add), 1),
+                '-' => (Token::Minus, 1),
+                '=' => (Token::Op(BinaryOp::Eq), 1),
+                ':' if rest.starts_with("::") => (Token::ColonColon, 2),
+                '/' if rest.starts_with("//") => (Token::DoubleSlash, 2),
+                '/' => (Token::Slash, 1),
+                '!' if rest.starts_with("!=") => (Token::Op(BinaryOp::Ne), 2),
+                '<' if rest.starts_with("<=") => (Token::Op(BinaryOp::Le), 2),
+                '<' => (Token::Op(BinaryOp::Lt), 1),
+                '>' if rest.starts_with(">=") => (Token::Op(BinaryOp::Ge), 2),
+                '>' => (Token::Op(BinaryOp::Gt), 1),
+                '.' if rest.starts_with("..") => (Token::DotDot, 2),
+                '.' if !rest[1..].starts_with(|c: char| c.is_ascii_digit()) => (Token::Dot, 1),
+                '*' if self.operator_expected() => (Token::Multiply, 1),
+                '*' => (Token::NameTest { prefix: None, local: None }, 1),
+                '"' | '\'' => {
+                    let end = rest[1..].find(c).ok_or_else(|| self.error("unterminated string literal"))?;
+                    (Token::Literal(rest[1..=end].into()), end + 2)
+                },
+                '$' => {
+                    self.pos += 1;
+                    let name = self.read_qname().ok_or_else(|| self.error("expected variable name"))?;
+                    self.tokens.push((Token::Variable(name), start));
+                    continue;
+                },
+                c if c.is_ascii_digit() || c == '.' => {
+                    let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+                    let number = &rest[..end];
+                    if number.matches('.').count() > 1 {
+                        return Err(self.error("invalid number"));
+                    }
+                    (Token::Number(number.parse().map_err(|_| self.error("invalid number"))?), end)
+                },
+                _ => {
+                    let token = self.name_token()?;
+                    self.tokens.push((token, start));
+                    continue;
+                },
+            };
+            self.pos += len;
+            self.tokens.push((token, start));
+        }
+        Ok(self.tokens)
+    }
+
+    fn read_qname(&mut self) -> Option<String> {
+        let prefix = self.read_ncname()?;
+        if self.rest().starts_with(':') && !self.rest().starts_with("::") {
+            let save = self.pos;
+            self.pos += 1;
+            if let Some(local) = self.read_ncname() {
+                return Some(format!("{prefix}:{local}"));
+            }
+            self.pos = save;
+        }
+        Some(prefix.into())
+    }
+
+    fn name_token(&mut self) -> Result<Token, Error> {
+        let Some(name) = self.read_ncname() else {
+            return Err(self.error("unexpected character"));
+        };
+
+        if self.operator_expected() {
+            return Ok(Token::Op(match name {
+                "and" => BinaryOp::And,
+                "or" => BinaryOp::Or,
+                "mod" => BinaryOp::Mod,
+                "div" => BinaryOp::Div,
+                _ => return Err(self.error("expected an operator")),
+            }));
+        }
+
+        let mut prefix = None;
+        let mut local = Some(name.to_owned());
+        if self.rest().starts_with(':') && !self.rest().starts_with("::") {
+            self.pos += 1;
+            prefix = local.take();
+            if self.rest().starts_with('*') {
+                self.pos += 1;
+                return Ok(Token::NameTest { prefix, local: None });
+            }
+            local = Some(self.read_ncname().ok_or_else(|| self.error("expected local name"))?.into());
+        }
+
+        let after_name = self.pos;
+        self.skip_whitespace();
+        let rest = self.rest();
+        self.pos = after_name;
+
+        if prefix.is_none() && rest.starts_with("::") {
+            return Axis::from_name(name).map(Token::AxisName).ok_or_else(|| self.error("unknown axis"));
+        }
+        if rest.starts_with('(') {
+            if prefix.is_none() && matches!(name, "comment" | "text" | "processing-instruction" | "node") {
+                return Ok(Token::NodeType(name.into()));
+            }
+            let local = local.unwrap_or_default();
+            return Ok(Token::FunctionName(match prefix {
+                Some(p) => format!("{p}:{local}"),
+                None => local,
+            }));
+        }
+        Ok(Token::NameTest { prefix, local })
+    }
+}
+
+/// Parses an XPath expression into an AST
+pub(crate) fn parse(src: &str) -> Result<Expr, Error> {
+    let tokens = Lexer { src, pos: 0, tokens: Vec::new() }.tokenize()?;
+    let mut parser = Parser { tokens, pos: 0, end: src.len() };
+    let expr = parser.parse_or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.error("unexpected token"));
+    }
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        t
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token, what: &str) -> Result<(), Error> {
+        if self.eat(token) { Ok(()) } else { Err(self.error(what)) }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        let position = self.tokens.get(self.pos).map_or(self.end, |&(_, p)| p);
+        Error::Syntax { position, message: message.into() }
+    }
+
+    fn binary(&mut self, ops: &[BinaryOp], next: fn(&mut Self) -> Result<Expr, Error>) -> Result<Expr, Error> {
+        let mut left = next(self)?;
+        while let Some(&Token::Op(op)) = self.peek() {
+            if !ops.contains(&op) {
+                break;
+            }
+            self.pos += 1;
+            let right = next(self)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, Error> {
+        self.binary(&[BinaryOp::Or], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, Error> {
+        self.binary(&[BinaryOp::And], Self::parse_equality)
+    }
+
+    fn parse_equality(&mut self) -> Result<Expr, Error> {
+        self.binary(&[BinaryOp::Eq, BinaryOp::Ne], Self::parse_relational)
+    }
+
+    fn parse_relational(&mut self) -> Result<Expr, Error> {
+        self.binary(&[BinaryOp::Lt, BinaryOp::Le, BinaryOp::Gt, BinaryOp::Ge], Self::parse_additive)
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, Error> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op(BinaryOp::Add)) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Sub,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.parse_multiplicative()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, Error> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Multiply) => BinaryOp::Mul,
+                Some(Token::Op(BinaryOp::Div)) => BinaryOp::Div,
+                Some(Token::Op(BinaryOp::Mod)) => BinaryOp::Mod,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, Error> {
+        if self.eat(&Token::Minus) {
+            return Ok(Expr::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.binary(&[BinaryOp::Union], Self::parse_path)
+    }
+
+    fn parse_path(&mut self) -> Result<Expr, Error> {
+        match self.peek() {
+            Some(Token::LParen | Token::Literal(_) | Token::Number(_) | Token::Variable(_) | Token::FunctionName(_)) => {
+                let primary = self.parse_primary()?;
+                let predicates = self.parse_predicates()?;
+                let mut steps = Vec::new();
+                if self.eat(&Token::Slash) {
+                    self.parse_relative(&mut steps)?;
+                } else if self.eat(&Token::DoubleSlash) {
+                    steps.push(descendant_or_self());
+                    self.parse_relative(&mut steps)?;
+                }
+                if predicates.is_empty() && steps.is_empty() {
+                    return Ok(primary);
+                }
+                Ok(Expr::Filter { primary: Box::new(primary), predicates, steps })
+            },
+            Some(Token::Slash) => {
+                self.pos += 1;
+                let mut steps = Vec::new();
+                if self.starts_step() {
+                    self.parse_relative(&mut steps)?;
+                }
+                Ok(Expr::Path { absolute: true, steps })
+            },
+            Some(Token::DoubleSlash) => {
+                self.pos += 1;
+                let mut steps = vec![descendant_or_self()];
+                self.parse_relative(&mut steps)?;
+                Ok(Expr::Path { absolute: true, steps })
+            },
+            _ => {
+                let mut steps = Vec::new();
+                self.parse_relative(&mut steps)?;
+                Ok(Expr::Path { absolute: false, steps })
+            },
+        }
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(self.peek(), Some(Token::Dot | Token::DotDot | Token::At | Token::AxisName(_) |
+            Token::NameTest { .. } | Token::NodeType(_)))
+    }
+
+    fn parse_relative(&mut self, steps: &mut Vec<Step>) -> Result<(), Error> {
+        loop {
+            steps.push(self.parse_step()?);
+            if self.eat(&Token::DoubleSlash) {
+                steps.push(descendant_or_self());
+            } else if !self.eat(&Token::Slash) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_step(&mut self) -> Result<Step, Error> {
+        if self.eat(&Token::Dot) {
+            return Ok(Step { axis: Axis::Self_, test: NodeTest::Node, predicates: Vec::new() });
+        }
+        if self.eat(&Token::DotDot) {
+            return Ok(Step { axis: Axis::Parent, test: NodeTest::Node, predicates: Vec::new() });
+        }
+        let axis = match self.peek() {
+            Some(Token::At) => {
+                self.pos += 1;
+                Axis::Attribute
+            },
+            Some(&Token::AxisName(axis)) => {
+                self.pos += 1;
+                self.expect(&Token::ColonColon, "expected '::'")?;
+                axis
+            },
+            _ => Axis::Child,
+        };
+        let test = match self.next() {
+            Some(Token::NameTest { prefix, local }) => NodeTest::Name { prefix, local },
+            Some(Token::NodeType(t)) => {
+                self.expect(&Token::LParen, "expected '('")?;
+                let test = match &*t {
+                    "comment" => NodeTest::Comment,
+                    "text" => NodeTest::Text,
+                    "node" => NodeTest::Node,
+                    _ => match self.peek() {
+                        Some(Token::Literal(l)) => {
+                            let l = l.clone();
+                            self.pos += 1;
+                            NodeTest::ProcessingInstruction(Some(l))
+                        },
+                        _ => NodeTest::ProcessingInstruction(None),
+                    },
+                };
+                self.expect(&Token::RParen, "expected ')'")?;
+                test
+            },
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected a location step"));
+            },
+        };
+        let predicates = self.parse_predicates()?;
+        Ok(Step { axis, test, predicates })
+    }
+
+    fn parse_predicates(&mut self) -> Result<Vec<Expr>, Error> {
+        let mut predicates = Vec::new();
+        while self.eat(&Token::LBracket) {
+            predicates.push(self.parse_or()?);
+            self.expect(&Token::RBracket, "expected ']'")?;
+        }
+        Ok(predicates)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Error> {
+        Ok(match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                self.expect(&Token::RParen, "expected ')'")?;
+                expr
+            },
+            Some(Token::Literal(s)) => Expr::Literal(s),
+            Some(Token::Number(n)) => Expr::Number(n),
+            Some(Token::Variable(v)) => Expr::Variable(v),
+            Some(Token::FunctionName(name)) => {
+                self.expect(&Token::LParen, "expected '('")?;
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.parse_or()?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        self.expect(&Token::Comma, "expected ',' or ')'")?;
+                    }
+                }
+                Expr::Function(name, args)
+            },
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected an expression"));
+            },
+        })
+    }
+}
+
+fn descendant_or_self() -> Step {
+    Step { axis: Axis::DescendantOrSelf, test: NodeTest::Node, predicates: Vec::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Axis, BinaryOp, Expr, NodeTest, Step};
+
+    fn child(name: &str) -> Step {
+        Step { axis: Axis::Child, test: NodeTest::Name { prefix: None, local: Some(name.into()) }, predicates: vec![] }
+    }
+
+    #[test]
+    fn operator_disambiguation() {
+        assert_eq!(parse("a * b").unwrap(), Expr::Binary(BinaryOp::Mul,
+            Box::new(Expr::Path { absolute: false, steps: vec![child("a")] }),
+            Box::new(Expr::Path { absolute: false, steps: vec![child("b")] })));
+        assert_eq!(parse("div div div").unwrap(), Expr::Binary(BinaryOp::Div,
+            Box::new(Expr::Path { absolute: false, steps: vec![child("div")] }),
+            Box::new(Expr::Path { absolute: false, steps: vec![child("div")] })));
+        assert_eq!(parse("*").unwrap(), Expr::Path { absolute: false, steps: vec![Step {
+            axis: Axis::Child, test: NodeTest::Name { prefix: None, local: None }, predicates: vec![],
+        }] });
+    }
+
+    #[test]
+    fn steps() {
+        let Expr::Path { absolute: true, steps } = parse("//p:a/@*[1]/ancestor::node()/..").unwrap() else { panic!() };
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[0].axis, Axis::DescendantOrSelf);
+        assert_eq!(steps[1].test, NodeTest::Name { prefix: Some("p".into()), local: Some("a".into()) });
+        assert_eq!(steps[2].axis, Axis::Attribute);
+        assert_eq!(steps[2].predicates, [Expr::Number(1.)]);
+        assert_eq!(steps[3].axis, Axis::Ancestor);
+        assert_eq!(steps[4].axis, Axis::Parent);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("a[").is_err());
+        assert!(parse("a b").is_err());
+        assert!(parse("foo::a").is_err());
+        assert!(parse("'abc").is_err());
+        assert!(parse("1.2.3").is_err());
+        assert!(parse("").is_err());
+    }
+}
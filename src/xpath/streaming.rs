@@ -0,0 +1,442 @@
+//! Contains a streamable subset of XPath, matched against events as they are read.
+//!
+//! Unlike [`XPath`](super::XPath), which needs the whole document in memory,
+//! a [`StreamingPath`] is matched against the stack of currently open elements of an
+//! [`EventReader`], so memory use depends only on the depth of the document and
+//! the size of the selected fragments.
+//!
+//! Supported expressions are absolute location paths using:
+//!
+//! * `/` and `//` with name tests (`name`, `prefix:name`, `prefix:*`, `*`), and the
+//!   `child::` and `descendant::` axes;
+//! * a final `@name` (or `@*`) step selecting attribute values, or `text()` selecting text;
+//! * predicates testing attributes (`[@id]`, `[@type = 'html']`, `[@year > 2000]`) and
+//!   positions among siblings (`[2]`, `[position() < 3]`), combined with `and`, `or` and `not()`.
+//!
+//! ```rust
+//! use xml::EventReader;
+//! use xml::xpath::streaming::{Match, StreamingPath};
+//!
+//! let source = r#"<feed>
+//!     <entry><link href="/1"/><link rel="alternate" href="/1.html"/></entry>
+//!     <entry><link href="/2"/></entry>
+//! </feed>"#;
+//!
+//! let path = StreamingPath::compile("/feed/entry/link[not(@rel)]/@href").unwrap();
+//! let hrefs: Vec<_> = path.select(EventReader::new(source.as_bytes()))
+//!     .map(|m| match m.unwrap() {
+//!         Match::Attribute(attr) => attr.value,
+//!         _ => unreachable!(),
+//!     })
+//!     .collect();
+//! assert_eq!(hrefs, ["/1", "/2"]);
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+use crate::attribute::OwnedAttribute;
+use crate::name::OwnedName;
+use crate::reader::{self, EventReader, XmlEvent};
+use crate::xpath::eval::compare_atomic;
+use crate::xpath::parser::{self, Axis, BinaryOp, Expr, NodeTest, Step};
+use crate::xpath::{Context, Error, Value};
+
+/// A compiled streamable XPath expression.
+#[derive(Clone, Debug)]
+pub struct StreamingPath {
+    steps: Vec<ElementStep>,
+    target: Target,
+    /// The attributes or text are also selected from the element before a final `//`,
+    /// as `descendant-or-self` includes it
+    or_self: bool,
+}
+
+/// A part of the document selected by a [`StreamingPath`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum Match {
+    /// Events of a selected element, from its `StartElement` to its `EndElement`.
+    ///
+    /// When selected elements are nested, the inner one is returned first, as it ends first.
+    Element(Vec<XmlEvent>),
+
+    /// A selected attribute.
+    Attribute(OwnedAttribute),
+
+    /// Contents of a selected text node: adjacent characters, CDATA and whitespace.
+    Text(String),
+}
+
+#[derive(Clone, Debug)]
+enum Target {
+    Element,
+    Attribute(NameTest),
+    Text,
+}
+
+#[derive(Clone, Debug)]
+struct ElementStep {
+    /// Matches any descendant of the previous step, not only children
+    descendant: bool,
+    name: NameTest,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Clone, Debug)]
+enum NameTest {
+    Any,
+    /// Namespace URI and local name, either of which may be a wildcard
+    Name { namespace: Option<String>, local: Option<String> },
+}
+
+impl NameTest {
+    fn matches(&self, name: &OwnedName) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Name { namespace, local } => {
+                let name_ns = name.namespace.as_deref().filter(|ns| !ns.is_empty());
+                name_ns == namespace.as_deref() && local.as_ref().map_or(true, |l| *l == name.local_name)
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Predicate {
+    Exists(NameTest),
+    Compare(BinaryOp, Operand, Operand),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Attribute(NameTest),
+    Position,
+    Literal(Value<'static>),
+}
+
+impl Predicate {
+    fn uses_position(&self) -> bool {
+        match self {
+            Self::Exists(_) => false,
+            Self::Compare(_, l, r) => matches!(l, Operand::Position) || matches!(r, Operand::Position),
+            Self::And(l, r) | Self::Or(l, r) => l.uses_position() || r.uses_position(),
+            Self::Not(p) => p.uses_position(),
+        }
+    }
+
+    fn eval(&self, attributes: &[OwnedAttribute], position: usize) -> bool {
+        match self {
+            Self::Exists(test) => attributes.iter().any(|a| test.matches(&a.name)),
+            Self::Compare(op, l, r) => {
+                let (l, r) = (l.values(attributes, position), r.values(attributes, position));
+                l.iter().any(|l| r.iter().any(|r| compare_atomic(*op, l, r)))
+            },
+            Self::And(l, r) => l.eval(attributes, position) && r.eval(attributes, position),
+            Self::Or(l, r) => l.eval(attributes, position) || r.eval(attributes, position),
+            Self::Not(p) => !p.eval(attributes, position),
+        }
+    }
+}
+
+impl Operand {
+    fn values(&self, attributes: &[OwnedAttribute], position: usize) -> Vec<Value<'static>> {
+        match self {
+            Self::Attribute(test) => attributes.iter()
+                .filter(|a| test.matches(&a.name))
+                .map(|a| Value::String(a.value.clone()))
+                .collect(),
+            Self::Position => vec![Value::Number(position as f64)],
+            Self::Literal(v) => vec![v.clone()],
+        }
+    }
+}
+
+fn not_streamable<T>(message: &str) -> Result<T, Error> {
+    Err(Error::NotStreamable(message.into()))
+}
+
+/// Converts the AST of an XPath expression, resolving prefixes
+struct Compiler<'c, 'a> {
+    ctx: &'c Context<'a>,
+}
+
+impl Compiler<'_, '_> {
+    fn path(&self, expr: Expr) -> Result<StreamingPath, Error> {
+        let Expr::Path { absolute: true, steps } = expr else {
+            return not_streamable("only absolute location paths are supported");
+        };
+        let mut element_steps = Vec::new();
+        let mut descendant = false;
+        let mut target = Target::Element;
+        let mut or_self = false;
+        let count = steps.len();
+        for (i, Step { axis, test, predicates }) in steps.into_iter().enumerate() {
+            let last = i + 1 == count;
+            match (axis, test) {
+                (Axis::DescendantOrSelf, NodeTest::Node) if predicates.is_empty() && !last => {
+                    descendant = true;
+                    continue;
+                },
+                (Axis::Child | Axis::Descendant, NodeTest::Name { prefix, local }) => {
+                    let predicates = predicates.into_iter()
+                        .map(|p| self.predicate(p))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if axis == Axis::Descendant && predicates.iter().any(Predicate::uses_position) {
+                        return not_streamable("positional predicates are not supported on the descendant axis");
+                    }
+                    element_steps.push(ElementStep {
+                        descendant: descendant || axis == Axis::Descendant,
+                        name: self.name_test(prefix, local)?,
+                        predicates,
+                    });
+                },
+                (axis @ (Axis::Attribute | Axis::Child), test) if last && predicates.is_empty() => {
+                    target = match (axis, test) {
+                        (Axis::Attribute, NodeTest::Name { prefix, local }) => Target::Attribute(self.name_test(prefix, local)?),
+                        (Axis::Child, NodeTest::Text) => Target::Text,
+                        _ => return not_streamable("only elements, attributes and text can be selected"),
+                    };
+                    if descendant {
+                        element_steps.push(ElementStep { descendant, name: NameTest::Any, predicates: Vec::new() });
+                        or_self = true;
+                    }
+                },
+                _ => return not_streamable("only child, descendant and attribute axes are supported, and attribute and text steps must be last"),
+            }
+            descendant = false;
+        }
+        if element_steps.is_empty() {
+            return not_streamable("the path must select at least one element");
+        }
+        Ok(StreamingPath { steps: element_steps, target, or_self })
+    }
+
+    fn name_test(&self, prefix: Option<String>, local: Option<String>) -> Result<NameTest, Error> {
+        let namespace = match prefix {
+            Some(p) => match self.ctx.namespaces.get(&p) {
+                Some(uri) if !uri.is_empty() => Some(uri.to_owned()),
+                Some(_) => None,
+                None => return Err(Error::UnboundPrefix(p)),
+            },
+            None if local.is_none() => return Ok(NameTest::Any),
+            None => None,
+        };
+        Ok(NameTest::Name { namespace, local })
+    }
+
+    fn predicate(&self, expr: Expr) -> Result<Predicate, Error> {
+        Ok(match expr {
+            Expr::Number(n) => Predicate::Compare(BinaryOp::Eq, Operand::Position, Operand::Literal(Value::Number(n))),
+            Expr::Binary(BinaryOp::And, l, r) => Predicate::And(Box::new(self.predicate(*l)?), Box::new(self.predicate(*r)?)),
+            Expr::Binary(BinaryOp::Or, l, r) => Predicate::Or(Box::new(self.predicate(*l)?), Box::new(self.predicate(*r)?)),
+            Expr::Binary(op @ (BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge), l, r) => {
+                Predicate::Compare(op, self.operand(*l)?, self.operand(*r)?)
+            },
+            Expr::Function(name, mut args) if name == "not" && args.len() == 1 => {
+                Predicate::Not(Box::new(self.predicate(args.remove(0))?))
+            },
+            expr => match self.operand(expr)? {
+                Operand::Attribute(test) => Predicate::Exists(test),
+                _ => return not_streamable("predicates can only test attributes and positions"),
+            },
+        })
+    }
+
+    fn operand(&self, expr: Expr) -> Result<Operand, Error> {
+        Ok(match expr {
+            Expr::Literal(s) => Operand::Literal(Value::String(s)),
+            Expr::Number(n) => Operand::Literal(Value::Number(n)),
+            Expr::Negate(e) => match *e {
+                Expr::Number(n) => Operand::Literal(Value::Number(-n)),
+                _ => return not_streamable("arithmetic is not supported"),
+            },
+            Expr::Function(name, args) if name == "position" && args.is_empty() => Operand::Position,
+            Expr::Path { absolute: false, mut steps } if steps.len() == 1 => match steps.remove(0) {
+                Step { axis: Axis::Attribute, test: NodeTest::Name { prefix, local }, predicates } if predicates.is_empty() => {
+                    Operand::Attribute(self.name_test(prefix, local)?)
+                },
+                _ => return not_streamable("predicates can only test attributes and positions"),
+            },
+            _ => return not_streamable("predicates can only test attributes and positions"),
+        })
+    }
+}
+
+impl StreamingPath {
+    /// Parses the expression, which must not use namespace prefixes.
+    pub fn compile(expression: &str) -> Result<Self, Error> {
+        Self::compile_with(&Context::new(), expression)
+    }
+
+    /// Parses the expression, resolving prefixes using the namespace mappings of the context.
+    pub fn compile_with(context: &Context<'_>, expression: &str) -> Result<Self, Error> {
+        Compiler { ctx: context }.path(parser::parse(expression)?)
+    }
+
+    /// Reads events from the reader, returning the selected parts of the document as they are found.
+    pub fn select<R: Read>(&self, reader: EventReader<R>) -> Select<'_, R> {
+        Select {
+            path: self,
+            reader,
+            stack: vec![Frame { states: vec![0], ..Frame::default() }],
+            captures: Vec::new(),
+            text: String::new(),
+            queue: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+impl FromStr for StreamingPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Self::compile(s)
+    }
+}
+
+/// State of an open element (or of the document, at the bottom of the stack)
+#[derive(Default)]
+struct Frame {
+    /// Numbers of steps matched by the path to this element
+    states: Vec<usize>,
+    /// Positions of children: step, predicate and count of children that reached the predicate
+    counters: Vec<(usize, usize, usize)>,
+    /// Whether text children of this element are selected
+    select_text: bool,
+}
+
+impl Frame {
+    fn next_position(&mut self, step: usize, predicate: usize) -> usize {
+        if let Some(c) = self.counters.iter_mut().find(|c| c.0 == step && c.1 == predicate) {
+            c.2 += 1;
+            return c.2;
+        }
+        self.counters.push((step, predicate, 1));
+        1
+    }
+}
+
+/// Iterator over the parts of a document selected by a [`StreamingPath`].
+///
+/// Created by [`StreamingPath::select()`].
+pub struct Select<'p, R: Read> {
+    path: &'p StreamingPath,
+    reader: EventReader<R>,
+    stack: Vec<Frame>,
+    /// Depths of selected elements being read, with their events so far
+    captures: Vec<(usize, Vec<XmlEvent>)>,
+    text: String,
+    queue: VecDeque<Match>,
+    finished: bool,
+}
+
+impl<R: Read> Select<'_, R> {
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> EventReader<R> {
+        self.reader
+    }
+
+    fn process(&mut self, event: XmlEvent) {
+        let is_text = matches!(event, XmlEvent::Characters(_) | XmlEvent::CData(_) | XmlEvent::Whitespace(_));
+        if !is_text && !self.text.is_empty() {
+            self.queue.push_back(Match::Text(std::mem::take(&mut self.text)));
+        }
+        for (_, events) in &mut self.captures {
+            events.push(event.clone());
+        }
+
+        match &event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let steps = &self.path.steps;
+                let parent = self.stack.last_mut().expect("document frame");
+                let mut states = Vec::new();
+                for i in 0..parent.states.len() {
+                    let k = parent.states[i];
+                    let Some(step) = steps.get(k) else { continue };
+                    if step.descendant {
+                        states.push(k);
+                    }
+                    if step.name.matches(name) && step.predicates.iter().enumerate()
+                        .all(|(i, p)| p.eval(attributes, parent.next_position(k, i))) {
+                        states.push(k + 1);
+                    }
+                }
+                states.sort_unstable();
+                states.dedup();
+
+                let selected = states.last().is_some_and(|&k| k == steps.len() || (self.path.or_self && k + 1 == steps.len()));
+                let mut frame = Frame { states, ..Frame::default() };
+                if selected {
+                    match &self.path.target {
+                        Target::Element => self.captures.push((self.stack.len(), vec![event.clone()])),
+                        Target::Attribute(test) => self.queue.extend(attributes.iter()
+                            .filter(|a| test.matches(&a.name))
+                            .map(|a| Match::Attribute(a.clone()))),
+                        Target::Text => frame.select_text = true,
+                    }
+                }
+                self.stack.push(frame);
+            },
+            XmlEvent::EndElement { .. } => {
+                self.stack.pop();
+                if self.captures.last().is_some_and(|c| c.0 == self.stack.len()) {
+                    let (_, events) = self.captures.pop().expect("capture");
+                    self.queue.push_back(Match::Element(events));
+                }
+            },
+            XmlEvent::Characters(s) | XmlEvent::CData(s) | XmlEvent::Whitespace(s)
+                if self.stack.last().is_some_and(|f| f.select_text) => self.text.push_str(s),
+            XmlEvent::EndDocument => self.finished = true,
+            _ => {},
+        }
+    }
+}
+
+impl<R: Read> Iterator for Select<'_, R> {
+    type Item = reader::Result<Match>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(m) = self.queue.pop_front() {
+                return Some(Ok(m));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.reader.next() {
+                Ok(event) => self.process(event),
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+    }
+}
+
+impl<R: Read> fmt::Debug for Select<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Select").field("path", &self.path).field("depth", &(self.stack.len() - 1)).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamingPath;
+    use crate::xpath::Error;
+
+    #[test]
+    fn unsupported() {
+        for expr in ["a/b", "/a/..", "/a[last()]", "/a/descendant::b[1]", "/a/@b/c", "/a[b]", "/", "/a[@b + 1]", "count(/a)"] {
+            assert!(matches!(StreamingPath::compile(expr), Err(Error::NotStreamable(_))), "{expr}");
+        }
+        assert_eq!(StreamingPath::compile("/p:a").unwrap_err(), Error::UnboundPrefix("p".into()));
+        assert!(StreamingPath::compile("//a[@b = 'c' or not(@d)][position() > -1]/@*").is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+#![forbid(unsafe_code)]
+
+use xml::c14n::{canonicalize, parser_config, C14nConfig, Canonicalizer, Method};
+use xml::reader::XmlEvent;
+use xml::EventReader;
+
+fn c14n(source: &str, config: &C14nConfig) -> String {
+    let mut output = Vec::new();
+    canonicalize(source.as_bytes(), &mut output, config).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+/// Canonicalizes the element with the given local name and its descendants
+fn c14n_subtree(source: &str, local_name: &str, config: C14nConfig) -> String {
+    let mut reader = EventReader::new_with_config(source.as_bytes(), parser_config());
+    let mut canonicalizer = Canonicalizer::new(Vec::new(), config);
+    let mut depth = 0;
+    loop {
+        let event = reader.next().unwrap();
+        if matches!(&event, XmlEvent::StartElement { name, .. } if name.local_name == local_name) || depth > 0 {
+            match event {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => depth -= 1,
+                _ => {},
+            }
+            canonicalizer.write(&event).unwrap();
+        } else {
+            canonicalizer.skip(&event);
+        }
+        if event == XmlEvent::EndDocument {
+            return String::from_utf8(canonicalizer.into_inner()).unwrap();
+        }
+    }
+}
+
+#[test]
+fn pis_comments_and_outside_of_document_element() {
+    let source = r#"<?xml version="1.0"?>
+
+<?xml-stylesheet   href="doc.xsl"
+   type="text/xsl"   ?>
+
+<!DOCTYPE doc SYSTEM "doc.dtd">
+
+<doc>Hello, world!<!-- Comment 1 --></doc>
+
+<?pi-without-data     ?>
+
+<!-- Comment 2 -->
+
+<!-- Comment 3 -->"#;
+
+    assert_eq!(c14n(source, &C14nConfig::new()), r#"<?xml-stylesheet href="doc.xsl"
+   type="text/xsl"   ?>
+<doc>Hello, world!</doc>
+<?pi-without-data?>"#);
+
+    assert_eq!(c14n(source, &C14nConfig::new().with_comments(true)), r#"<?xml-stylesheet href="doc.xsl"
+   type="text/xsl"   ?>
+<doc>Hello, world!<!-- Comment 1 --></doc>
+<?pi-without-data?>
+<!-- Comment 2 -->
+<!-- Comment 3 -->"#);
+}
+
+#[test]
+fn start_and_end_tags() {
+    let source = r#"<doc>
+   <e1   />
+   <e2   ></e2>
+   <e3   name = "elem3"   id="elem3"   />
+   <e4   name="elem4"   id="elem4"   ></e4>
+   <e5 a:attr="out" b:attr="sorted" attr2="all" attr="I'm"
+      xmlns:b="http://www.ietf.org"
+      xmlns:a="http://www.w3.org"
+      xmlns="http://example.org"/>
+   <e6 xmlns="" xmlns:a="http://www.w3.org">
+      <e7 xmlns="http://www.ietf.org">
+         <e8 xmlns="" xmlns:a="http://www.w3.org">
+            <e9 xmlns="" xmlns:a="http://www.ietf.org"/>
+         </e8>
+      </e7>
+   </e6>
+</doc>"#;
+
+    assert_eq!(c14n(source, &C14nConfig::new()), r#"<doc>
+   <e1></e1>
+   <e2></e2>
+   <e3 id="elem3" name="elem3"></e3>
+   <e4 id="elem4" name="elem4"></e4>
+   <e5 xmlns="http://example.org" xmlns:a="http://www.w3.org" xmlns:b="http://www.ietf.org" attr="I'm" attr2="all" b:attr="sorted" a:attr="out"></e5>
+   <e6 xmlns:a="http://www.w3.org">
+      <e7 xmlns="http://www.ietf.org">
+         <e8 xmlns="">
+            <e9 xmlns:a="http://www.ietf.org"></e9>
+         </e8>
+      </e7>
+   </e6>
+</doc>"#);
+}
+
+#[test]
+fn character_modifications() {
+    let source = r#"<doc>
+   <text>First line&#x0d;&#10;Second line</text>
+   <value>&#x32;</value>
+   <compute><![CDATA[value>"0" && value<"10" ?"valid":"error"]]></compute>
+   <compute expr='value>"0" &amp;&amp; value&lt;"10" ?"valid":"error"'>valid</compute>
+   <norm attr=' &apos;   &#x20;&#13;&#xa;&#9;   &apos; '/>
+</doc>"#;
+
+    assert_eq!(c14n(source, &C14nConfig::new()), "<doc>
+   <text>First line&#xD;
+Second line</text>
+   <value>2</value>
+   <compute>value&gt;\"0\" &amp;&amp; value&lt;\"10\" ?\"valid\":\"error\"</compute>
+   <compute expr=\"value>&quot;0&quot; &amp;&amp; value&lt;&quot;10&quot; ?&quot;valid&quot;:&quot;error&quot;\">valid</compute>
+   <norm attr=\" '    &#xD;&#xA;&#x9;   ' \"></norm>
+</doc>");
+}
+
+#[test]
+fn exclusive_namespaces_and_subsets() {
+    let source = r#"<n0:local xmlns:n0="foo:bar" xmlns:n3="ftp://example.org"><n1:elem2 xmlns:n1="http://example.net" xml:lang="en"><n3:stuff xmlns:n3="ftp://example.org"/></n1:elem2></n0:local>"#;
+    let exclusive = C14nConfig::new().method(Method::Exclusive);
+
+    assert_eq!(c14n(source, &exclusive), r#"<n0:local xmlns:n0="foo:bar"><n1:elem2 xmlns:n1="http://example.net" xml:lang="en"><n3:stuff xmlns:n3="ftp://example.org"></n3:stuff></n1:elem2></n0:local>"#);
+    assert_eq!(c14n_subtree(source, "elem2", exclusive.clone()), r#"<n1:elem2 xmlns:n1="http://example.net" xml:lang="en"><n3:stuff xmlns:n3="ftp://example.org"></n3:stuff></n1:elem2>"#);
+    assert_eq!(c14n_subtree(source, "elem2", exclusive.inclusive_namespaces(["n3"])), r#"<n1:elem2 xmlns:n1="http://example.net" xmlns:n3="ftp://example.org" xml:lang="en"><n3:stuff></n3:stuff></n1:elem2>"#);
+    assert_eq!(c14n_subtree(source, "elem2", C14nConfig::new()), r#"<n1:elem2 xmlns:n0="foo:bar" xmlns:n1="http://example.net" xmlns:n3="ftp://example.org" xml:lang="en"><n3:stuff></n3:stuff></n1:elem2>"#);
+
+    let source = r#"<a xmlns="urn:a" xml:lang="fr" xml:id="x"><b><c xmlns=""/></b></a>"#;
+    assert_eq!(c14n_subtree(source, "b", C14nConfig::new()), r#"<b xmlns="urn:a" xml:id="x" xml:lang="fr"><c xmlns=""></c></b>"#);
+    assert_eq!(c14n_subtree(source, "b", C14nConfig::new().method(Method::C14n11)), r#"<b xmlns="urn:a" xml:lang="fr"><c xmlns=""></c></b>"#);
+    assert_eq!(c14n_subtree(source, "b", C14nConfig::new().method(Method::Exclusive)), r#"<b xmlns="urn:a"><c xmlns=""></c></b>"#);
+    assert_eq!(c14n_subtree(source, "c", C14nConfig::new().method(Method::Exclusive)), r#"<c></c>"#);
+}
+
+#[test]
+fn xml_base_fixup() {
+    let source = r#"<a xml:base="http://example.org/dir/" xml:lang="en"><b xml:base="sub/"><c xml:base="../other/x.xml"/><d/></b></a>"#;
+    let c14n11 = C14nConfig::new().method(Method::C14n11);
+    assert_eq!(c14n_subtree(source, "c", c14n11.clone()), r#"<c xml:base="http://example.org/dir/other/x.xml" xml:lang="en"></c>"#);
+    assert_eq!(c14n_subtree(source, "d", c14n11.clone()), r#"<d xml:base="http://example.org/dir/sub/" xml:lang="en"></d>"#);
+    assert_eq!(c14n_subtree(source, "b", c14n11), r#"<b xml:base="http://example.org/dir/sub/" xml:lang="en"><c xml:base="../other/x.xml"></c><d></d></b>"#);
+    assert_eq!(c14n_subtree(source, "c", C14nConfig::new()), r#"<c xml:base="../other/x.xml" xml:lang="en"></c>"#);
+}
+
+#[test]
+fn entity_references() {
+    let source = r#"<!DOCTYPE doc [<!ENTITY e "text">]><doc>&e;</doc>"#;
+    assert_eq!(c14n(source, &C14nConfig::new()), "<doc>text</doc>");
+
+    let config = parser_config().keep_entity_references(true);
+    let mut canonicalizer = Canonicalizer::new(Vec::new(), C14nConfig::new());
+    let error = EventReader::new_with_config(source.as_bytes(), config)
+        .into_iter()
+        .map(Result::unwrap)
+        .try_for_each(|event| canonicalizer.write(&event))
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn method_uris() {
+    for method in [Method::C14n10, Method::C14n11, Method::Exclusive] {
+        for with_comments in [false, true] {
+            assert_eq!(Method::from_uri(method.uri(with_comments)), Some((method, with_comments)));
+        }
+    }
+    assert_eq!(Method::from_uri("http://www.w3.org/2000/09/xmldsig#"), None);
+}
@@ -0,0 +1,141 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use xml::common::{Position, TextPosition};
+use xml::de::{from_str, Deserializer};
+use xml::EventReader;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Project {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@version")]
+    version: Option<String>,
+    #[serde(rename = "@private", default)]
+    private: bool,
+    description: String,
+    #[serde(rename = "dependency", default)]
+    dependencies: Vec<Dependency>,
+    tags: Tags,
+    license: License,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Dependency {
+    #[serde(rename = "@optional", default)]
+    optional: bool,
+    #[serde(rename = "$text")]
+    name: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Tags {
+    #[serde(rename = "tag")]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+enum License {
+    #[serde(rename = "MIT")]
+    Mit,
+    Apache,
+}
+
+#[test]
+fn structs_attributes_and_sequences() {
+    let project: Project = from_str(r#"<?xml version="1.0"?>
+        <!-- a comment -->
+        <project name="xml-rs" private="1">
+            <description>An XML library <![CDATA[<in Rust>]]></description>
+            <dependency>serde</dependency>
+            <dependency optional="true">other</dependency>
+            <tags><tag>xml</tag><tag>parser</tag></tags>
+            <license> MIT </license>
+            <ignored><nested/></ignored>
+        </project>"#).unwrap();
+
+    assert_eq!(project, Project {
+        name: "xml-rs".into(),
+        version: None,
+        private: true,
+        description: "An XML library <in Rust>".into(),
+        dependencies: vec![
+            Dependency { optional: false, name: "serde".into() },
+            Dependency { optional: true, name: "other".into() },
+        ],
+        tags: Tags { tags: vec!["xml".into(), "parser".into()] },
+        license: License::Mit,
+    });
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Drawing {
+    #[serde(rename = "@size")]
+    size: (u32, u32),
+    #[serde(rename = "$value")]
+    shapes: Vec<Shape>,
+    title: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Shape {
+    Circle {
+        #[serde(rename = "@r")]
+        r: f64,
+    },
+    Label(String),
+    Group(Vec<Shape>),
+    Empty,
+}
+
+#[test]
+fn enums_by_element_name() {
+    let drawing: Drawing = from_str(r#"<drawing size="640 480">
+        <title>Shapes</title>
+        <circle r="1.5"/>
+        <label>Hi</label>
+        <group><empty/><circle r="2"/></group>
+    </drawing>"#).unwrap();
+
+    assert_eq!(drawing, Drawing {
+        size: (640, 480),
+        shapes: vec![
+            Shape::Circle { r: 1.5 },
+            Shape::Label("Hi".into()),
+            Shape::Group(vec![Shape::Empty, Shape::Circle { r: 2. }]),
+        ],
+        title: Some("Shapes".into()),
+    });
+}
+
+#[test]
+fn maps_and_existing_reader() {
+    let source = "<config><a>1</a><b x=\"y\">2</b></config>";
+    let mut de = Deserializer::new(EventReader::new(source.as_bytes()));
+    let map = BTreeMap::<String, String>::deserialize(&mut de).unwrap();
+    assert_eq!(map, BTreeMap::from([("a".to_owned(), "1".to_owned()), ("b".to_owned(), "2".to_owned())]));
+
+    let numbers: Vec<i64> = from_str("<list><n>1</n><n>-2</n><m>3</m></list>").unwrap();
+    assert_eq!(numbers, [1, -2, 3]);
+}
+
+#[test]
+fn errors_have_positions() {
+    let err = from_str::<Project>("<project name=\"x\">\n  <description>d</description>\n  <license>BSD</license>\n</project>").unwrap_err();
+    assert_eq!(err.position(), TextPosition { row: 2, column: 11 });
+    assert!(err.to_string().contains("unknown variant `BSD`"), "{err}");
+
+    let err = from_str::<Dependency>("<d optional=\"maybe\">x</d>").unwrap_err();
+    assert!(err.to_string().contains("invalid bool 'maybe'"), "{err}");
+
+    let err = from_str::<Tags>("<tags>\n<tag>a</tag></tagz>").unwrap_err();
+    assert!(matches!(err.kind(), xml::de::ErrorKind::Reader(_)));
+    assert_eq!(err.position().row, 1);
+
+    let err = from_str::<Tags>("<tags><tag><b/></tag></tags>").unwrap_err();
+    assert!(err.to_string().contains("expected text, found element <b>"), "{err}");
+}
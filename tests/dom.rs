@@ -0,0 +1,135 @@
+#![forbid(unsafe_code)]
+
+use std::fs::File;
+use std::io::BufReader;
+
+use xml::dom::{Document, Element, Node};
+use xml::reader::ParserConfig;
+use xml::writer::EmitterConfig;
+
+#[test]
+fn building_from_reader() {
+    let doc = Document::parse_with_config(
+        BufReader::new(File::open("tests/documents/sample_2.xml").unwrap()),
+        ParserConfig::new().trim_whitespace(true),
+    ).unwrap();
+
+    let root = doc.root().unwrap();
+    assert_eq!(root.name.local_name, "data");
+    assert_eq!(root.name.namespace_ref(), Some("urn:example:namespace"));
+
+    let datum = root.get_child_ns("datum", "urn:example:namespace").unwrap();
+    assert_eq!(datum.attribute("id"), Some("34"));
+    assert_eq!(datum.get_child_ns("name", "urn:example:double").unwrap().text(), "Another name");
+
+    let args: Vec<_> = datum.child_elements()
+        .filter(|e| e.name.local_name == "arg")
+        .map(|e| e.text())
+        .collect();
+    assert_eq!(args, ["0.3", "0.2", "0.1", "0.01"]);
+
+    let header = root.descendants().find(|e| e.attribute("name") == Some("Header-2")).unwrap();
+    assert_eq!(header.text(), "Some bigger value");
+    assert_eq!(header.namespace.get("d"), Some("urn:example:double"));
+}
+
+#[test]
+fn text_content() {
+    let doc: Document = "<a>x<b>y<![CDATA[<z>]]></b><!-- c --><?pi data?>w</a>".parse().unwrap();
+    let root = doc.root().unwrap();
+    assert_eq!(root.text(), "xy<z>w");
+    assert_eq!(root.children.len(), 4);
+    assert_eq!(root.children[0].as_text(), Some("x"));
+    assert!(matches!(&root.children[2], Node::ProcessingInstruction { name, data }
+        if name == "pi" && data.as_deref() == Some("data")));
+}
+
+#[test]
+fn mutation() {
+    let mut doc: Document = r#"<a x="1" y="2"><b/><c/></a>"#.parse().unwrap();
+    let root = doc.root_mut().unwrap();
+
+    root.set_attribute("x", "10");
+    root.set_attribute("z", "3");
+    assert_eq!(root.remove_attribute("y").as_deref(), Some("2"));
+    assert_eq!(root.remove_attribute("y"), None);
+    assert_eq!(root.attribute("x"), Some("10"));
+
+    let removed = root.remove_child(0);
+    assert_eq!(removed.as_element().unwrap().name.local_name, "b");
+    root.insert_child(0, Element::new("d").with_attribute("k", "<v>"));
+    root.get_child_mut("c").unwrap().push_child("text & more");
+
+    let mut out = Vec::new();
+    doc.write_with_config(&mut out, EmitterConfig::new().pad_self_closing(false)).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"<?xml version="1.0" encoding="UTF-8"?><a x="10" z="3"><d k="&lt;v&gt;"/><c>text &amp; more</c></a>"#
+    );
+}
+
+#[test]
+fn namespaces_round_trip() {
+    let source = r#"<p:a xmlns="urn:d" xmlns:p="urn:p"><b p:x="1"><p:c/></b></p:a>"#;
+    let doc: Document = source.parse().unwrap();
+
+    let b = doc.root().unwrap().get_child_ns("b", "urn:d").unwrap();
+    assert_eq!(b.attribute_ns("x", "urn:p"), Some("1"));
+
+    let mut out = Vec::new();
+    doc.write_with_config(&mut out, EmitterConfig::new().pad_self_closing(false)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.strip_prefix(r#"<?xml version="1.0" encoding="UTF-8"?>"#), Some(source));
+
+    let reparsed: Document = out.parse().unwrap();
+    assert_eq!(reparsed, doc);
+}
+
+#[test]
+fn building_new_document() {
+    let doc = Document::new(
+        Element::new("h:root")
+            .with_namespace("h", "urn:h")
+            .with_child(Element::new("h:item").with_text("1"))
+    );
+
+    let mut out = Vec::new();
+    doc.write_with_config(&mut out, EmitterConfig::new().perform_indent(true)).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<h:root xmlns:h=\"urn:h\">\n  <h:item>1</h:item>\n</h:root>"
+    );
+}
+
+#[test]
+fn reading_fragments() {
+    let mut reader = ParserConfig::new().trim_whitespace(true).ignore_comments(false)
+        .create_reader(r#"<feed xmlns:x="urn:x"> <entry><x:title>A</x:title><!-- c --></entry> <entry/> </feed>"#.as_bytes());
+    let mut entries = Vec::new();
+    loop {
+        match reader.next().unwrap() {
+            start @ xml::reader::XmlEvent::StartElement { .. } if start_name(&start) == "entry" => {
+                entries.push(Element::read_from(&mut reader, start).unwrap());
+            },
+            xml::reader::XmlEvent::EndDocument => break,
+            _ => {},
+        }
+    }
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].get_child_ns("title", "urn:x").unwrap().text(), "A");
+    assert!(matches!(&entries[0].children[1], Node::Comment(c) if c == " c "));
+    assert!(entries[1].children.is_empty());
+}
+
+fn start_name(event: &xml::reader::XmlEvent) -> &str {
+    match event {
+        xml::reader::XmlEvent::StartElement { name, .. } => &name.local_name,
+        _ => "",
+    }
+}
+
+#[test]
+fn parse_errors() {
+    assert!("<a><b></a>".parse::<Document>().is_err());
+    assert!("".parse::<Document>().is_err());
+}
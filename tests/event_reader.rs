@@ -7,7 +7,7 @@ use std::path::Path;
 
 use xml::common::Position;
 use xml::name::OwnedName;
-use xml::reader::{EventReader, ParserConfig, Result, XmlEvent};
+use xml::reader::{ErrorCode, EventReader, ParserConfig, Result, XmlEvent};
 
 /// Dummy function that opens a file, parses it, and returns a `Result`.
 /// There can be IO errors (from `File::open`) and XML errors (from the parser).
@@ -830,6 +830,23 @@ fn push_pos_issue() {
     parser.into_iter().for_each(|e| { e.unwrap(); });
 }
 
+#[test]
+fn processing_instruction_data() {
+    test(
+        b"<?pi   a/b c>d ?><r><?empty   ?></r>",
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |ProcessingInstruction(pi="a/b c>d ")
+            |StartElement(r)
+            |ProcessingInstruction(empty="")
+            |EndElement(r)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false,
+    );
+}
+
 #[test]
 fn retrieve_doctype() {
     let source = r#"<?xml version="1.0" encoding="UTF-8"?>
@@ -861,6 +878,256 @@ fn retrieve_doctype() {
     }
 }
 
+#[test]
+fn xml_scope() {
+    let source = r#"<doc xml:lang="en" xml:base="http://example.com/docs/">
+  <p xml:lang="fr" xml:base="fr/index.xml"><img src="a.png"/></p>
+  <p xml:lang=""><a xml:base="../other/"/></p>
+  <pre xml:space="preserve"> a <b> </b><i xml:space="default"> </i></pre>
+</doc>"#;
+    let mut reader = ParserConfig::new().trim_whitespace(true).base_uri("file:///srv/doc.xml").create_reader(source.as_bytes());
+    assert_eq!(reader.scope().base_uri, "file:///srv/doc.xml");
+
+    let mut seen = Vec::new();
+    loop {
+        let event = reader.next().unwrap();
+        let scope = reader.scope();
+        match event {
+            XmlEvent::StartElement { name, .. } => seen.push(format!("{name} {} [{}] {:?}", scope.base_uri, scope.language, scope.space)),
+            XmlEvent::Characters(text) | XmlEvent::Whitespace(text) => seen.push(format!("{text:?} {:?}", scope.space)),
+            XmlEvent::EndDocument => break,
+            _ => {},
+        }
+    }
+    assert_eq!(seen, [
+        "doc http://example.com/docs/ [en] Default",
+        "p http://example.com/docs/fr/index.xml [fr] Default",
+        "img http://example.com/docs/fr/index.xml [fr] Default",
+        "p http://example.com/docs/ [] Default",
+        "a http://example.com/other/ [] Default",
+        "pre http://example.com/docs/ [en] Preserve",
+        "\" a \" Preserve",
+        "b http://example.com/docs/ [en] Preserve",
+        "\" \" Preserve",
+        "i http://example.com/docs/ [en] Default",
+    ]);
+    assert_eq!(reader.scope().base_uri, "file:///srv/doc.xml");
+}
+
+#[test]
+fn read_subtrees() {
+    fn next_start(reader: &mut EventReader<&[u8]>) -> XmlEvent {
+        loop {
+            if let event @ XmlEvent::StartElement { .. } = reader.next().unwrap() {
+                return event;
+            }
+        }
+    }
+
+    let source = r#"<doc xmlns="urn:d" xmlns:x="urn:x"><a id="1">one <b>two</b><![CDATA[ <three> ]]></a><x:c><d/></x:c><x:c/><e>unclosed"#;
+    let mut reader = EventReader::from_str(source);
+    next_start(&mut reader);
+
+    next_start(&mut reader);
+    assert_eq!(reader.read_inner_text().unwrap(), "one two <three> ");
+
+    next_start(&mut reader);
+    let events: Vec<_> = reader.read_subtree_events().unwrap().into_iter().map(|e| Event(&Ok(e)).to_string()).collect();
+    assert_eq!(events, ["StartElement({urn:d}d)", "EndElement({urn:d}d)", "EndElement({urn:x}x:c)"]);
+
+    let c = next_start(&mut reader);
+    assert_eq!(reader.read_outer_xml(&c).unwrap(), r#"<x:c xmlns="urn:d" xmlns:x="urn:x" />"#);
+
+    let e = next_start(&mut reader);
+    assert!(reader.read_outer_xml(&e).is_err());
+}
+
+#[test]
+fn byte_spans() {
+    let source = "\u{feff}<?xml version=\"1.0\"?>\n<!DOCTYPE r [<!ENTITY e \"<i/>\">]>\n<!--c--><r xmlns:a=\"urn:a\" a:x='1'  y = \"&amp;\">t&lt;é<![CDATA[a]]]><e/>&e;  </r> <?end?>";
+    let mut reader = ParserConfig::new().capture_raw(true).ignore_comments(false).create_reader(source.as_bytes());
+    let mut seen = Vec::new();
+    loop {
+        let event = reader.next().unwrap();
+        let span = reader.span().unwrap();
+        assert_eq!(reader.raw().unwrap(), &source.as_bytes()[span.range()]);
+        let attributes: Vec<_> = reader.attribute_spans().iter().map(|a| &source[a.range()]).collect();
+        seen.push(format!("{span} {:?} {attributes:?}", &source[span.range()]));
+        if event == XmlEvent::EndDocument {
+            break;
+        }
+    }
+    assert_eq!(seen, [
+        r#"3..24 "<?xml version=\"1.0\"?>" []"#,
+        r#"25..58 "<!DOCTYPE r [<!ENTITY e \"<i/>\">]>" []"#,
+        r#"59..67 "<!--c-->" []"#,
+        r#"67..107 "<r xmlns:a=\"urn:a\" a:x='1'  y = \"&amp;\">" ["a:x='1'", "y = \"&amp;\""]"#,
+        r#"107..114 "t&lt;é" []"#,
+        r#"114..128 "<![CDATA[a]]]>" []"#,
+        r#"128..132 "<e/>" []"#,
+        r#"132..132 "" []"#,
+        r#"135..135 "" []"#,
+        r#"135..135 "" []"#,
+        r#"135..137 "  " []"#,
+        r#"137..141 "</r>" []"#,
+        r#"142..149 "<?end?>" []"#,
+        r#"149..149 "" []"#,
+    ]);
+
+    let mut reader = ParserConfig::new().record_spans(true).trim_whitespace(true).cdata_to_characters(true)
+        .create_reader("<?pi?><r>  x <![CDATA[ y ]]>&#32;</r>".as_bytes());
+    let spans: Vec<_> = (0..5).map(|_| {
+        reader.next().unwrap();
+        reader.span().unwrap().to_string()
+    }).collect();
+    assert_eq!(spans, ["0..0", "0..6", "6..9", "11..33", "33..37"]);
+    assert!(reader.raw().is_none());
+
+    let mut reader = EventReader::from_str("<r/>");
+    reader.next().unwrap();
+    assert!(reader.span().is_none());
+}
+
+#[test]
+fn keep_entity_references() {
+    let source = "<!DOCTYPE d [<!ENTITY c '(c)'>]><d a='&c;'>x &c;&amp;&extra;<b/>&c; y&#60;</d>";
+    let config = ParserConfig::new().keep_entity_references(true).add_entity("extra", "!").record_spans(true);
+    let mut reader = config.create_reader(source.as_bytes());
+    let mut output = Vec::new();
+    let mut writer = xml::EmitterConfig::new().create_writer(&mut output);
+    let mut seen = Vec::new();
+    loop {
+        let event = reader.next().unwrap();
+        seen.push(format!("{event:?} {:?}", &source[reader.span().unwrap().range()]));
+        match event.as_writer_event() {
+            Some(event) => writer.write(event).unwrap(),
+            None => break,
+        }
+    }
+    assert_eq!(&seen[3..10], [
+        r#"Characters(x ) "x ""#,
+        r#"EntityReference(c) "&c;""#,
+        r#"Characters(&) "&amp;""#,
+        r#"EntityReference(extra) "&extra;""#,
+        r#"StartElement(b, {"": "", "xml": "http://www.w3.org/XML/1998/namespace", "xmlns": "http://www.w3.org/2000/xmlns/"}) "<b/>""#,
+        r#"EndElement(b) """#,
+        r#"EntityReference(c) "&c;""#,
+    ]);
+    assert_eq!(String::from_utf8(output).unwrap(), r#"<?xml version="1.0" encoding="UTF-8"?><!DOCTYPE d [<!ENTITY c '(c)'>]><d a="(c)">x &c;&amp;&extra;<b />&c; y&lt;</d>"#);
+
+    let mut reader = ParserConfig::new().keep_entity_references(true).create_reader("<d>&undeclared;</d>".as_bytes());
+    reader.next().unwrap();
+    reader.next().unwrap();
+    assert!(reader.next().is_err());
+}
+
+#[test]
+fn recover_from_errors() {
+    let source = "<feed><item id=1 name=a&b>Tom & Jerry &nbsp; a < b<b>bold</item><i x='1<2'>x</feed></extra><last>";
+    let mut reader = ParserConfig::new().recover_from_errors(true).create_reader(source.as_bytes());
+    let mut events = Vec::new();
+    loop {
+        let event = reader.next().unwrap();
+        if event == XmlEvent::EndDocument {
+            break;
+        }
+        events.push(match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let attributes: Vec<_> = attributes.iter().map(|a| format!(" {}={}", a.name, a.value)).collect();
+                format!("<{name}{}>", attributes.concat())
+            },
+            XmlEvent::EndElement { name } => format!("</{name}>"),
+            XmlEvent::Characters(text) => text,
+            event => format!("{event:?}"),
+        });
+    }
+    assert_eq!(events.concat(), "StartDocument(1.0, UTF-8, None)<feed><item id=1 name=a&b>Tom & Jerry &nbsp; a < b<b>bold</b></item><i x=1<2>x</i></feed><last></last>");
+
+    let warnings: Vec<_> = reader.take_warnings().iter().map(ToString::to_string).collect();
+    assert_eq!(warnings, [
+        "1:16 Unexpected token: 1",
+        "1:23 Unexpected token: a",
+        "1:26 Unexpected token inside entity: >",
+        "1:32 Unexpected token inside entity:  ",
+        "1:44 Unexpected entity: nbsp",
+        "1:48 Unexpected token '<' before ' '",
+        "1:64 Unexpected closing tag: item != b",
+        "1:72 Unexpected token '<' before '2'",
+        "1:83 Unexpected closing tag: feed != i",
+        "1:91 Unexpected closing tag: extra",
+        "1:98 Unexpected end of stream: still inside the root element",
+    ]);
+    assert!(reader.warnings().is_empty());
+
+    let mut reader = EventReader::from_str("<a><b></a>");
+    reader.next().unwrap();
+    reader.next().unwrap();
+    reader.next().unwrap();
+    assert!(reader.next().is_err());
+    assert!(reader.warnings().is_empty());
+}
+
+#[test]
+fn error_diagnostics() {
+    let source = "<doc>\n\t<a x='1' x='2'/>\n</doc>";
+    let error = EventReader::from_str(source).into_iter().find_map(Result::err).unwrap();
+    assert_eq!(error.code(), "duplicate-attribute");
+    assert_eq!(error.hint(), Some("an attribute can appear only once in a tag"));
+    assert_eq!(error.diagnostic(source).to_string(), "\
+error[duplicate-attribute]: Attribute 'x' is redefined
+ --> <input>:2:12
+  |
+2 | \t<a x='1' x='2'/>
+  | \t          ^
+  = hint: an attribute can appear only once in a tag
+");
+
+    let source = "<b><i>bold</b></i>";
+    let error = ParserConfig::new().record_spans(true).create_reader(source.as_bytes())
+        .into_iter().find_map(Result::err).unwrap();
+    assert_eq!(error.span().map(|s| &source[s.range()]), Some("</b>"));
+    assert_eq!(error.diagnostic(source).to_string(), "\
+error[mismatched-closing-tag]: Unexpected closing tag: b != i
+ --> <input>:1:14
+  |
+1 | <b><i>bold</b></i>
+  |           ^^^^
+  = hint: elements must be closed in the reverse order they were opened
+");
+
+    let error = EventReader::from_str("<doc>").into_iter().find_map(Result::err).unwrap();
+    assert_eq!(error.code(), "unclosed-root-element");
+    let error = EventReader::new(&b"<doc>\xff</doc>"[..]).into_iter().find_map(Result::err).unwrap();
+    assert_eq!((error.code(), error.hint()), ("invalid-utf8", None));
+}
+
+#[test]
+fn error_codes() {
+    fn first_error(source: &str) -> Option<ErrorCode> {
+        let error = EventReader::from_str(source).into_iter().find_map(Result::err).unwrap();
+        let code = error.error_code();
+        assert_eq!(code.as_ref().map_or("syntax", ErrorCode::as_str), error.code());
+        code
+    }
+
+    assert_eq!(first_error("<a>&copy;</a>"), Some(ErrorCode::UndefinedEntity { name: "copy".into() }));
+    assert_eq!(first_error("<a x='1' x='2'/>"), Some(ErrorCode::DuplicateAttribute { name: "x".into() }));
+    assert_eq!(first_error("<p:a/>"), Some(ErrorCode::UnboundElementPrefix { name: "p:a".into() }));
+    assert_eq!(first_error("<a>&#xD800;</a>"), Some(ErrorCode::InvalidCharacter { code_point: 0xD800 }));
+    assert_eq!(first_error("<a xmlns:p='urn:p'><p:b></p:c></a>"), Some(ErrorCode::MismatchedClosingTag {
+        found: "p:c".into(),
+        expected: Some("p:b".into()),
+    }));
+    assert_eq!(first_error("<a>"), Some(ErrorCode::UnclosedRootElement));
+    assert_eq!(ErrorCode::UnclosedRootElement.as_str(), "unclosed-root-element");
+    assert_eq!(EventReader::new(&b"<a>\xff</a>"[..]).into_iter().find_map(Result::err).unwrap().error_code(), None);
+
+    let mut reader = ParserConfig::new().recover_from_errors(true).create_reader("<a></b></a>".as_bytes());
+    while reader.next().unwrap() != XmlEvent::EndDocument {}
+    let codes: Vec<_> = reader.warnings().iter().map(|w| w.error_code().unwrap()).collect();
+    assert_eq!(codes, [ErrorCode::MismatchedClosingTag { found: "b".into(), expected: Some("a".into()) }]);
+}
+
 // clones a lot but that's fine
 fn trim_until_bar(s: String) -> String {
     match s.trim() {
@@ -1016,6 +1283,7 @@ impl fmt::Display for Event<'_> {
                 XmlEvent::Characters(ref data) => write!(f, r#"Characters("{}")"#, data.escape_debug()),
                 XmlEvent::Whitespace(ref data) => write!(f, r#"Whitespace("{}")"#, data.escape_debug()),
                 XmlEvent::Doctype { ref syntax } => write!(f, r#"Doctype("{}")"#, syntax.escape_debug()),
+                XmlEvent::EntityReference { ref name } => write!(f, "EntityReference({name})"),
             },
             Err(ref e) => e.fmt(f),
         }
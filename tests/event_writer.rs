@@ -334,3 +334,373 @@ fn raw_characters() {
         assert!(matches!(r.next().unwrap(), XmlEvent::EndDocument));
     }
 }
+
+#[test]
+fn checking_well_formedness() {
+    use xml::writer::{Error, XmlEvent};
+
+    let config = EmitterConfig::new().write_document_declaration(false).check_well_formedness(true);
+    let check = |events: &[XmlEvent<'_>]| -> Result<String, Error> {
+        let mut b = Vec::new();
+        let mut w = config.clone().create_writer(&mut b);
+        for event in events {
+            w.write(event.clone())?;
+        }
+        Ok(String::from_utf8(b).unwrap())
+    };
+
+    assert_eq!(check(&[
+        XmlEvent::start_element("x:root").ns("x", "urn:x").attr("xml:lang", "en").attr("x:a", "1").into(),
+        XmlEvent::processing_instruction("pi", Some("? >")),
+        XmlEvent::characters("text"),
+        XmlEvent::end_element().into(),
+        XmlEvent::characters("\n"),
+        XmlEvent::comment("trailer"),
+    ]).unwrap(), "<x:root xmlns:x=\"urn:x\" xml:lang=\"en\" x:a=\"1\"><?pi ? >?>text</x:root>\n<!-- trailer -->");
+
+    assert!(matches!(check(&[XmlEvent::start_element("1root").into()]), Err(Error::InvalidName(n)) if n == "1root"));
+    assert!(matches!(check(&[XmlEvent::start_element("root").attr("a b", "").into()]), Err(Error::InvalidName(n)) if n == "a b"));
+    assert!(matches!(check(&[XmlEvent::start_element("root").attr("a", "1").attr("a", "2").into()]), Err(Error::DuplicateAttribute(n)) if n == "a"));
+    assert!(matches!(check(&[XmlEvent::start_element("x:root").into()]), Err(Error::UnboundPrefix(p)) if p == "x"));
+    assert!(matches!(check(&[XmlEvent::start_element("root").attr("y:a", "").into()]), Err(Error::UnboundPrefix(p)) if p == "y"));
+    assert!(matches!(check(&[XmlEvent::start_element("root").into(), XmlEvent::characters("\u{1}")]), Err(Error::InvalidCharacter('\u{1}'))));
+    assert!(matches!(check(&[XmlEvent::start_element("root").attr("a", "\u{FFFF}").into()]), Err(Error::InvalidCharacter('\u{FFFF}'))));
+    assert!(matches!(check(&[XmlEvent::processing_instruction("pi", Some("a?>b"))]), Err(Error::InvalidProcessingInstructionData)));
+    assert!(matches!(check(&[
+        XmlEvent::start_element("root").into(),
+        XmlEvent::processing_instruction("xMl", Some("v")),
+    ]), Err(Error::InvalidName(n)) if n == "xMl"));
+    assert!(matches!(check(&[
+        XmlEvent::start_element("root").ns("p", "urn:x").ns("q", "urn:x").attr("p:x", "1").attr("q:x", "2").into(),
+    ]), Err(Error::DuplicateAttribute(n)) if n == "q:x"));
+    assert!(matches!(check(&[
+        XmlEvent::start_element("root").attr("xmlns:q", "urn:x").ns("p", "urn:x").attr("p:x", "1").attr("q:x", "2").into(),
+    ]), Err(Error::DuplicateAttribute(n)) if n == "q:x"));
+    assert!(check(&[
+        XmlEvent::start_element("root").ns("p", "urn:p").ns("q", "urn:q").attr("p:x", "1").attr("q:x", "2").attr("x", "3").into(),
+    ]).is_ok());
+    assert!(matches!(check(&[
+        XmlEvent::start_element("root").into(),
+        XmlEvent::end_element().into(),
+        XmlEvent::start_element("root").into(),
+    ]), Err(Error::ContentAfterRootElement)));
+    assert!(matches!(check(&[
+        XmlEvent::start_element("root").into(),
+        XmlEvent::end_element().into(),
+        XmlEvent::characters("text"),
+    ]), Err(Error::ContentAfterRootElement)));
+
+    // not checked by default
+    let mut b = Vec::new();
+    let mut w = EmitterConfig::new().write_document_declaration(false).create_writer(&mut b);
+    w.write(XmlEvent::start_element("x:root")).unwrap();
+    w.write(XmlEvent::end_element()).unwrap();
+    w.write(XmlEvent::characters("text")).unwrap();
+    assert_eq!(str::from_utf8(&b).unwrap(), "<x:root />text");
+}
+
+#[test]
+fn writing_undeclared_default_namespace() {
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+    let mut w = EmitterConfig::new().write_document_declaration(false).create_writer(&mut b);
+    unwrap_all! {
+        w.write(XmlEvent::start_element("a").default_ns("urn:a"));
+        w.write(XmlEvent::start_element("b").default_ns(""));
+        w.write(XmlEvent::start_element("c").default_ns(""));
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::start_element("d").default_ns(""));
+        w.write(XmlEvent::end_element())
+    }
+    assert_eq!(str::from_utf8(&b).unwrap(), r#"<a xmlns="urn:a"><b xmlns=""><c /></b></a><d />"#);
+}
+
+#[test]
+fn writing_with_auto_namespace_prefixes() {
+    use xml::name::Name;
+    use xml::writer::XmlEvent;
+
+    let ns = |namespace, local_name| Name { local_name, namespace: Some(namespace), prefix: None };
+    let write = |config: EmitterConfig| {
+        let mut b = Vec::new();
+        let mut w = config.write_document_declaration(false).auto_namespace_prefixes(true).create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element(ns("urn:soap", "Envelope")).attr(ns("urn:x", "id"), "1"));
+            w.write(XmlEvent::start_element(ns("urn:soap", "Body")).default_ns("urn:app"));
+            w.write(XmlEvent::start_element(ns("urn:app", "item")).attr("plain", "a"));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::start_element(ns("urn:other", "item")));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::start_element(ns("urn:other", "item")).attr(ns("http://www.w3.org/XML/1998/namespace", "lang"), "en"));
+            w.write(XmlEvent::end_element().name(ns("urn:other", "item")));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+        String::from_utf8(b).unwrap()
+    };
+
+    assert_eq!(write(EmitterConfig::new()), concat!(
+        r#"<ns0:Envelope xmlns:ns0="urn:soap" xmlns:ns1="urn:x" ns1:id="1">"#,
+        r#"<ns0:Body xmlns="urn:app"><item plain="a" />"#,
+        r#"<ns2:item xmlns:ns2="urn:other" /><ns2:item xmlns:ns2="urn:other" xml:lang="en" />"#,
+        "</ns0:Body></ns0:Envelope>",
+    ));
+
+    let config = EmitterConfig::new()
+        .add_preferred_prefix("urn:soap", "soap")
+        .add_preferred_prefix("urn:other", "o")
+        .hoist_namespace_declarations(true);
+    assert_eq!(write(config), concat!(
+        r#"<soap:Envelope xmlns:ns0="urn:x" xmlns:o="urn:other" xmlns:soap="urn:soap" ns0:id="1">"#,
+        r#"<soap:Body xmlns="urn:app"><item plain="a" />"#,
+        r#"<o:item /><o:item xml:lang="en" />"#,
+        "</soap:Body></soap:Envelope>",
+    ));
+
+    // declarations hoisted to a chosen element are not repeated by its children
+    let mut b = Vec::new();
+    let mut w = EmitterConfig::new()
+        .write_document_declaration(false)
+        .auto_namespace_prefixes(true)
+        .add_preferred_prefix("urn:atom", "atom")
+        .add_preferred_prefix("urn:media", "media")
+        .hoist_namespace_declarations(true)
+        .hoist_namespace_declarations_to(xml::name::OwnedName::qualified("entries", "urn:app", None::<&str>))
+        .create_writer(&mut b);
+    unwrap_all! {
+        w.write(XmlEvent::start_element(ns("urn:app", "feed")));
+        w.write(XmlEvent::start_element(ns("urn:app", "entries")));
+        w.write(XmlEvent::start_element(ns("urn:atom", "entry")).attr(ns("urn:media", "type"), "image"));
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::start_element(ns("urn:atom", "entry")).attr(ns("urn:media", "type"), "video"));
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::start_element(ns("urn:atom", "entry")));
+        w.write(XmlEvent::start_element(ns("urn:app", "entries")));
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::end_element())
+    }
+    assert_eq!(String::from_utf8(b).unwrap(), concat!(
+        r#"<ns0:feed xmlns:ns0="urn:app">"#,
+        r#"<ns0:entries xmlns:atom="urn:atom" xmlns:media="urn:media">"#,
+        r#"<atom:entry media:type="image" /><atom:entry media:type="video" />"#,
+        r#"<atom:entry><ns0:entries /></atom:entry>"#,
+        "</ns0:entries></ns0:feed>",
+    ));
+
+    // a prefix bound to another namespace is redeclared
+    let prefixed = |prefix, namespace, local_name| Name { local_name, namespace: Some(namespace), prefix: Some(prefix) };
+    let mut b = Vec::new();
+    let mut w = EmitterConfig::new()
+        .write_document_declaration(false)
+        .auto_namespace_prefixes(true)
+        .add_preferred_prefix("urn:p", "p")
+        .hoist_namespace_declarations(true)
+        .create_writer(&mut b);
+    unwrap_all! {
+        w.write(XmlEvent::start_element(ns("urn:p", "root")));
+        w.write(XmlEvent::start_element(prefixed("p", "urn:z", "d")));
+        w.write(XmlEvent::end_element().name(prefixed("p", "urn:z", "d")));
+        w.write(XmlEvent::start_element(prefixed("p", "urn:z", "d")).ns("p", "urn:y"));
+        w.write(XmlEvent::end_element().name(prefixed("p", "urn:z", "d")));
+        w.write(XmlEvent::start_element(prefixed("p", "urn:p", "d")));
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::end_element())
+    }
+    assert_eq!(String::from_utf8(b).unwrap(), concat!(
+        r#"<p:root xmlns:p="urn:p">"#,
+        r#"<p:d xmlns:p="urn:z" />"#,
+        r#"<ns0:d xmlns:ns0="urn:z" xmlns:p="urn:y" />"#,
+        r#"<p:d />"#,
+        "</p:root>",
+    ));
+}
+
+#[test]
+fn indenting_with_mixed_content() {
+    let source = concat!(
+        "<doc><title>Mixed</title>  <p>Some <b>bold <i>and</i> italic</b> text</p>\n",
+        "<list><item/> <item>  </item><item><!-- c --></item></list>",
+        "<pre xml:space=\"preserve\"><line> a </line>\n<line/></pre>",
+        "<entry id=\"a-long-identifier\" class=\"first second third\" lang=\"en\"/>",
+        "<port>80</port><!-- http --></doc>"
+    );
+    let format = |config: EmitterConfig| {
+        let mut b = Vec::new();
+        {
+            let mut r = xml::ParserConfig::new().ignore_comments(false).create_reader(source.as_bytes());
+            let mut w = config.perform_indent(true).create_writer(&mut b);
+            loop {
+                let e = r.next().unwrap();
+                match e {
+                    xml::reader::XmlEvent::StartDocument { .. } => continue,
+                    xml::reader::XmlEvent::EndDocument => break,
+                    _ => {},
+                }
+                if let Some(e) = e.as_writer_event() {
+                    w.write(e).unwrap();
+                }
+            }
+        }
+        String::from_utf8(b).unwrap()
+    };
+
+    let config = EmitterConfig::new().write_document_declaration(false).preserve_mixed_content(true);
+    assert_eq!(format(config.clone()), concat!(
+        "<doc>\n",
+        "  <title>Mixed</title>\n",
+        "  <p>Some <b>bold <i>and</i> italic</b> text</p>\n",
+        "  <list>\n",
+        "    <item />\n",
+        "    <item>  </item>\n",
+        "    <item>\n",
+        "      <!-- c -->\n",
+        "    </item>\n",
+        "  </list>\n",
+        "  <pre xml:space=\"preserve\"><line> a </line>\n<line /></pre>\n",
+        "  <entry id=\"a-long-identifier\" class=\"first second third\" lang=\"en\" />\n",
+        "  <port>80</port>\n",
+        "  <!-- http -->\n",
+        "</doc>",
+    ));
+
+    let config = config.collapse_whitespace_only_elements(true).wrap_attributes_at(Some(60)).indent_comments(false);
+    assert_eq!(format(config), concat!(
+        "<doc>\n",
+        "  <title>Mixed</title>\n",
+        "  <p>Some <b>bold <i>and</i> italic</b> text</p>\n",
+        "  <list>\n",
+        "    <item />\n",
+        "    <item />\n",
+        "    <item><!-- c -->\n",
+        "    </item>\n",
+        "  </list>\n",
+        "  <pre xml:space=\"preserve\"><line> a </line>\n<line /></pre>\n",
+        "  <entry\n",
+        "    id=\"a-long-identifier\"\n",
+        "    class=\"first second third\"\n",
+        "    lang=\"en\" />\n",
+        "  <port>80</port><!-- http -->\n",
+        "</doc>",
+    ));
+}
+
+#[test]
+fn indenting_unfinished_mixed_content() {
+    use xml::writer::XmlEvent;
+
+    let config = EmitterConfig::new().write_document_declaration(false).perform_indent(true).preserve_mixed_content(true);
+
+    let mut w = config.clone().create_writer(Vec::new());
+    unwrap_all! {
+        w.write(XmlEvent::start_element("root"));
+        w.write(XmlEvent::start_element("a"));
+        w.write(XmlEvent::end_element())
+    }
+    assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "<root>\n  <a />");
+
+    let mut w = config.create_writer(Vec::new());
+    unwrap_all! {
+        w.write(XmlEvent::start_element("root"));
+        w.write(XmlEvent::start_element("p"));
+        w.write(XmlEvent::characters("text"));
+        w.flush()
+    }
+    assert_eq!(str::from_utf8(w.inner_mut()).unwrap(), "<root>\n  <p>text");
+    unwrap_all! {
+        w.write(XmlEvent::end_element());
+        w.write(XmlEvent::end_element())
+    }
+    assert_eq!(String::from_utf8(w.into_inner()).unwrap(), "<root>\n  <p>text</p>\n</root>");
+}
+
+#[test]
+fn indenting_text_after_nested_children() {
+    let format = |source: &str| {
+        let mut r = xml::ParserConfig::new().create_reader(source.as_bytes());
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .perform_indent(true)
+            .preserve_mixed_content(true)
+            .create_writer(Vec::new());
+        loop {
+            match r.next().unwrap() {
+                xml::reader::XmlEvent::StartDocument { .. } => {},
+                xml::reader::XmlEvent::EndDocument => break,
+                e => w.write(e.as_writer_event().unwrap()).unwrap(),
+            }
+        }
+        String::from_utf8(w.into_inner()).unwrap()
+    };
+
+    assert_eq!(format("<p><b><i>x</i></b> text</p>"), "<p><b><i>x</i></b> text</p>");
+    assert_eq!(format("<doc><p><b><i>x</i> <u/></b><br/> text</p></doc>"),
+        "<doc>\n  <p><b><i>x</i> <u /></b><br /> text</p>\n</doc>");
+    // elements with `xml:space="default"` are still indented
+    assert_eq!(format("<p><f xml:space=\"default\"><i>x</i></f> text</p>"),
+        "<p><f xml:space=\"default\">\n    <i>x</i>\n  </f> text</p>");
+}
+
+#[test]
+fn indenting_default_space_in_preserved_content() {
+    let source = concat!(
+        "<doc><pre xml:space=\"preserve\"><line> a </line> ",
+        "<fig xml:space=\"default\"> <img/> <cap>c</cap> </fig></pre></doc>"
+    );
+    let mut r = xml::ParserConfig::new().create_reader(source.as_bytes());
+    let mut w = EmitterConfig::new()
+        .write_document_declaration(false)
+        .perform_indent(true)
+        .preserve_mixed_content(true)
+        .create_writer(Vec::new());
+    loop {
+        match r.next().unwrap() {
+            xml::reader::XmlEvent::StartDocument { .. } => {},
+            xml::reader::XmlEvent::EndDocument => break,
+            e => w.write(e.as_writer_event().unwrap()).unwrap(),
+        }
+    }
+    assert_eq!(String::from_utf8(w.into_inner()).unwrap(), concat!(
+        "<doc>\n",
+        "  <pre xml:space=\"preserve\"><line> a </line> <fig xml:space=\"default\">\n",
+        "      <img />\n",
+        "      <cap>c</cap>\n",
+        "    </fig></pre>\n",
+        "</doc>",
+    ));
+}
+
+#[test]
+fn writing_sorted_attributes() {
+    use std::cmp::Ordering;
+    use xml::attribute::Attribute;
+    use xml::writer::{AttributeOrder, XmlEvent};
+
+    let write = |config: EmitterConfig| {
+        let mut b = Vec::new();
+        let mut w = config.write_document_declaration(false).create_writer(&mut b);
+        unwrap_all! {
+            w.write(XmlEvent::start_element("root").ns("x", "urn:x").default_ns("urn:d")
+                .attr("z", "1").attr("x:b", "2").attr("id", "3").attr("xml:lang", "en").attr("x:a", "4"));
+            w.write(XmlEvent::end_element())
+        }
+        String::from_utf8(b).unwrap()
+    };
+
+    assert_eq!(write(EmitterConfig::new()),
+        r#"<root xmlns="urn:d" xmlns:x="urn:x" z="1" x:b="2" id="3" xml:lang="en" x:a="4" />"#);
+    assert_eq!(write(EmitterConfig::new().attribute_order(AttributeOrder::Alphabetical)),
+        r#"<root xmlns="urn:d" xmlns:x="urn:x" id="3" x:a="4" x:b="2" xml:lang="en" z="1" />"#);
+    assert_eq!(write(EmitterConfig::new().attribute_order(AttributeOrder::NamespaceFirst).namespace_declarations_last(true)),
+        r#"<root x:a="4" x:b="2" xml:lang="en" id="3" z="1" xmlns="urn:d" xmlns:x="urn:x" />"#);
+
+    fn by_value(a: &Attribute<'_>, b: &Attribute<'_>) -> Ordering {
+        a.value.cmp(b.value)
+    }
+    assert_eq!(write(EmitterConfig::new().attribute_order(AttributeOrder::Custom(by_value))),
+        r#"<root xmlns="urn:d" xmlns:x="urn:x" z="1" x:b="2" id="3" x:a="4" xml:lang="en" />"#);
+}
@@ -0,0 +1,79 @@
+#![forbid(unsafe_code)]
+
+use xml::lossless::{LosslessEvent, LosslessReader};
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
+use xml::reader::XmlEvent;
+use xml::EmitterConfig;
+
+/// Reads the document, passing each event through `edit`, and writes the events back
+fn rewrite(source: &[u8], mut edit: impl FnMut(LosslessEvent) -> Vec<LosslessEvent>) -> String {
+    let mut reader = LosslessReader::new(source);
+    let mut output = Vec::new();
+    let mut writer = EmitterConfig::new().create_writer(&mut output);
+    loop {
+        let event = reader.next().unwrap();
+        let end = *event.event() == XmlEvent::EndDocument;
+        for event in edit(event) {
+            event.write_to(&mut writer).unwrap();
+        }
+        if end {
+            break;
+        }
+    }
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn unmodified_round_trip() {
+    let source = concat!(
+        "\u{feff}<?xml version='1.0'  encoding=\"utf-8\" ?>\n",
+        "<!DOCTYPE doc [\n  <!ENTITY who 'world'>\n]>\n",
+        "<!-- header -->\n",
+        "<doc\n    xmlns:p = 'urn:p'   p:x=\"1\" y='&apos;2&apos;'  >\n",
+        "  Hello, &who;! &#x3C;&#60;&lt;\n",
+        "  <![CDATA[ <raw> ]]><empty/><p:empty ></p:empty >\n",
+        "  <?pi   data ?><!-- inside -->\n",
+        "</doc >\n",
+        "<!-- trailer -->\n",
+    );
+
+    assert_eq!(rewrite(source.as_bytes(), |e| vec![e]), source);
+
+    let mut reader = LosslessReader::new(source.as_bytes());
+    let mut all = Vec::new();
+    loop {
+        let event = reader.next().unwrap();
+        assert!(!event.is_modified());
+        all.extend_from_slice(event.leading());
+        all.extend_from_slice(event.markup());
+        if *event.event() == XmlEvent::EndDocument {
+            break;
+        }
+    }
+    assert_eq!(all, source.as_bytes());
+}
+
+#[test]
+fn modified_events_are_written() {
+    let source = "<doc a = 'x'>\n  <old/>  <old ></old >\n  <keep  b='y'/>\n</doc>";
+
+    let output = rewrite(source.as_bytes(), |mut event| {
+        match event.event() {
+            XmlEvent::StartElement { name, .. } | XmlEvent::EndElement { name } if name.local_name == "old" => {
+                match event.event_mut() {
+                    XmlEvent::StartElement { name, .. } | XmlEvent::EndElement { name } => *name = OwnedName::local("new"),
+                    _ => unreachable!(),
+                }
+                vec![event]
+            },
+            XmlEvent::EndElement { name } if name.local_name == "doc" => vec![
+                XmlEvent::StartElement { name: OwnedName::local("added"), attributes: vec![], namespace: Namespace::empty() }.into(),
+                XmlEvent::EndElement { name: OwnedName::local("added") }.into(),
+                event,
+            ],
+            _ => vec![event],
+        }
+    });
+    assert_eq!(output, "<doc a = 'x'>\n  <new />  <new />\n  <keep  b='y'/>\n<added /></doc>");
+}
@@ -0,0 +1,121 @@
+#![forbid(unsafe_code)]
+
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
+use xml::pipeline::{Error, Pipeline};
+use xml::reader::XmlEvent;
+use xml::{EmitterConfig, EventReader};
+
+/// Runs the pipeline, and returns the output without the XML declaration
+fn run(source: &str, pipeline: impl FnOnce(EventReader<&[u8]>) -> Result<Vec<u8>, Error>) -> String {
+    let output = String::from_utf8(pipeline(EventReader::from_str(source)).unwrap()).unwrap();
+    output.strip_prefix(r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap().to_owned()
+}
+
+fn writer(output: &mut Vec<u8>) -> xml::EventWriter<&mut Vec<u8>> {
+    EmitterConfig::new().create_writer(output)
+}
+
+#[test]
+fn stages_use_element_context() {
+    let source = r#"<?xml version="1.0"?><list><item id="1"><item id="2"><name>a</name></item></item><name>b</name></list>"#;
+
+    let output = run(source, |reader| {
+        let mut output = Vec::new();
+        reader
+            .drop_subtree(|e| e.depth() == 2)
+            .filter_elements(|e| e.attribute("id") != Some("1"))
+            .map_text(|text| format!("[{text}]"))
+            .write_to(&mut writer(&mut output))?;
+        Ok(output)
+    });
+    assert_eq!(output, "<list><name>[b]</name></list>");
+
+    let output = run(source, |reader| {
+        let mut output = Vec::new();
+        reader
+            .insert_after(|e| e.ancestors.iter().any(|a| a.local_name == "item") && e.name.local_name == "name", [
+                XmlEvent::StartElement { name: OwnedName::local("hr"), attributes: vec![], namespace: Namespace::empty() },
+                XmlEvent::EndElement { name: OwnedName::local("hr") },
+            ])
+            .rename(|e| (e.name.local_name == "item").then(|| OwnedName::local(format!("level{}", e.depth()))))
+            .write_to(&mut writer(&mut output))?;
+        Ok(output)
+    });
+    assert_eq!(output, r#"<list><level1 id="1"><level2 id="2"><name>a</name><hr /></level2></level1><name>b</name></list>"#);
+}
+
+#[test]
+fn renames_keep_namespaces() {
+    let source = r#"<doc xmlns="urn:a" xmlns:x="urn:x"><old><x:child/><child/></old></doc>"#;
+    let output = run(source, |reader| {
+        let mut output = Vec::new();
+        reader
+            .rename(|e| match &*e.name.local_name {
+                "old" => Some(OwnedName::qualified("new", "urn:b", Some("b"))),
+                "doc" => Some(OwnedName::local("doc")),
+                _ => None,
+            })
+            .write_to(&mut writer(&mut output))?;
+        Ok(output)
+    });
+    assert_eq!(output, r#"<doc xmlns:x="urn:x"><b:new xmlns="urn:a" xmlns:b="urn:b"><x:child /><child /></b:new></doc>"#);
+
+    let reparsed: Vec<_> = EventReader::from_str(&output).into_iter().filter_map(|e| match e.unwrap() {
+        XmlEvent::StartElement { name, .. } => Some(name.to_string()),
+        _ => None,
+    }).collect();
+    assert_eq!(reparsed, ["doc", "{urn:b}b:new", "{urn:x}x:child", "{urn:a}child"]);
+}
+
+#[test]
+fn renames_to_prefixes_without_uri() {
+    let source = r#"<doc xmlns:x="urn:x"><old/></doc>"#;
+    let output = run(source, |reader| {
+        let mut output = Vec::new();
+        reader
+            .rename(|e| (e.name.local_name == "old").then(|| "x:new".parse().unwrap()))
+            .write_to(&mut writer(&mut output))?;
+        Ok(output)
+    });
+    assert_eq!(output, r#"<doc xmlns:x="urn:x"><x:new /></doc>"#);
+
+    let mut output = Vec::new();
+    let err = EventReader::from_str(source)
+        .rename(|e| (e.name.local_name == "old").then(|| "p:new".parse().unwrap()))
+        .write_to(&mut writer(&mut output))
+        .unwrap_err();
+    assert!(err.to_string().contains("namespace prefix is not bound: p"), "{err}");
+    assert!(!String::from_utf8(output).unwrap().contains("xmlns:p"));
+}
+
+#[test]
+fn insert_after_skips_root() {
+    let output = run("<a><b/></a>", |reader| {
+        let mut output = Vec::new();
+        reader
+            .insert_after(|_| true, [XmlEvent::Comment("after".into())])
+            .write_to(&mut writer(&mut output))?;
+        Ok(output)
+    });
+    assert_eq!(output, "<a><b /><!-- after --></a>");
+}
+
+#[test]
+fn errors_pass_through() {
+    let mut output = Vec::new();
+    let err = EventReader::from_str("<a><b></a>")
+        .drop_subtree(|e| e.name.local_name == "b")
+        .map_text(str::to_owned)
+        .write_to(&mut writer(&mut output))
+        .unwrap_err();
+    assert!(matches!(err, Error::Reader(_)), "{err}");
+}
+
+#[test]
+#[should_panic(expected = "unclosed StartElement")]
+fn insert_after_requires_balanced_events() {
+    let _ = EventReader::from_str("<a/>").insert_after(|_| true, [
+        XmlEvent::StartElement { name: OwnedName::local("hr"), attributes: vec![], namespace: Namespace::empty() },
+    ]);
+}
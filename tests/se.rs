@@ -0,0 +1,135 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use xml::se::{to_string, to_string_with_config, Serializer};
+use xml::{EmitterConfig, EventWriter};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename = "project")]
+struct Project {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@version", skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(rename = "@tags")]
+    tags: Vec<String>,
+    description: String,
+    #[serde(rename = "dependency")]
+    dependencies: Vec<Dependency>,
+    license: License,
+    #[serde(rename = "$value")]
+    extras: Vec<Extra>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Dependency {
+    #[serde(rename = "@optional")]
+    optional: bool,
+    #[serde(rename = "$text")]
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum License {
+    #[serde(rename = "MIT")]
+    Mit,
+    Apache,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Extra {
+    Homepage(String),
+    Badge {
+        #[serde(rename = "@kind")]
+        kind: String,
+    },
+    Unstable,
+}
+
+fn project() -> Project {
+    Project {
+        name: "xml-rs".into(),
+        version: None,
+        tags: vec!["xml".into(), "parser".into()],
+        description: "Reads & writes <XML>".into(),
+        dependencies: vec![
+            Dependency { optional: false, name: "serde".into() },
+            Dependency { optional: true, name: "other".into() },
+        ],
+        license: License::Mit,
+        extras: vec![
+            Extra::Homepage("https://lib.rs/xml".into()),
+            Extra::Badge { kind: "ci".into() },
+            Extra::Unstable,
+        ],
+    }
+}
+
+#[test]
+fn writes_with_conventions() {
+    let config = EmitterConfig::new().perform_indent(true).pad_self_closing(false);
+    assert_eq!(to_string_with_config(&project(), config).unwrap(), r#"<?xml version="1.0" encoding="UTF-8"?>
+<project name="xml-rs" tags="xml parser">
+  <description>Reads &amp; writes &lt;XML&gt;</description>
+  <dependency optional="false">serde</dependency>
+  <dependency optional="true">other</dependency>
+  <license>MIT</license>
+  <homepage>https://lib.rs/xml</homepage>
+  <badge kind="ci"/>
+  <unstable/>
+</project>"#);
+}
+
+#[test]
+fn round_trip() {
+    let xml = to_string(&project()).unwrap();
+    let project2: Project = xml::de::from_str(&xml).unwrap();
+    assert_eq!(project2, project());
+}
+
+#[derive(Serialize)]
+struct Namespaced {
+    #[serde(rename = "@xmlns")]
+    default_ns: &'static str,
+    #[serde(rename = "@xmlns:x")]
+    x_ns: &'static str,
+    #[serde(rename = "@x:attr")]
+    attr: u8,
+    #[serde(rename = "x:child")]
+    child: f64,
+}
+
+#[test]
+fn namespaces_maps_and_roots() {
+    let value = Namespaced { default_ns: "urn:d", x_ns: "urn:x", attr: 1, child: 0.5 };
+    let config = EmitterConfig::new().write_document_declaration(false);
+    assert_eq!(
+        to_string_with_config(&value, config.clone()).unwrap(),
+        r#"<Namespaced xmlns="urn:d" xmlns:x="urn:x" x:attr="1"><x:child>0.5</x:child></Namespaced>"#
+    );
+
+    let map = BTreeMap::from([("@id", "m"), ("a", "1"), ("b", "2")]);
+    let mut writer = EventWriter::new_with_config(Vec::new(), config);
+    map.serialize(&mut Serializer::with_root(&mut writer, "map")).unwrap();
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), r#"<map id="m"><a>1</a><b>2</b></map>"#);
+}
+
+#[derive(Serialize)]
+struct LateAttribute {
+    a: u8,
+    #[serde(rename = "@b")]
+    b: u8,
+}
+
+#[test]
+fn errors() {
+    let err = to_string(&LateAttribute { a: 1, b: 2 }).unwrap_err();
+    assert_eq!(err.to_string(), "attribute 'b' must come before content fields");
+
+    assert!(to_string(&42).is_err(), "a root name is required");
+    assert!(to_string(&vec![1, 2]).is_err());
+}
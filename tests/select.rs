@@ -0,0 +1,81 @@
+#![forbid(unsafe_code)]
+
+use xml::dom::compact::CompactDocument;
+use xml::namespace::Namespace;
+use xml::select::Selector;
+
+const SOURCE: &str = r#"<html xmlns:svg="http://www.w3.org/2000/svg">
+    <body>
+        <ul id="menu" class="nav main">
+            <li lang="en-US">One</li>
+            <li class="active">Two</li>
+            <li><!-- empty --></li>
+            <li lang="en">Four</li>
+        </ul>
+        <p>Intro</p>
+        <svg:svg width="10"><svg:rect x="1"/><rect/></svg:svg>
+        <p class="note">Outro</p>
+    </body>
+</html>"#;
+
+fn texts(doc: &CompactDocument, selector: &str) -> Vec<String> {
+    Selector::parse(selector).unwrap().select(doc.root())
+        .map(|e| format!("{}:{}", e.name().unwrap().local_name, e.text_content().split_whitespace().collect::<Vec<_>>().join(" ")))
+        .collect()
+}
+
+#[test]
+fn simple_selectors() {
+    let doc: CompactDocument = SOURCE.parse().unwrap();
+
+    assert_eq!(texts(&doc, "p"), ["p:Intro", "p:Outro"]);
+    assert_eq!(texts(&doc, "#menu > .active"), ["li:Two"]);
+    assert_eq!(texts(&doc, ".main li[lang|=en]"), ["li:One", "li:Four"]);
+    assert_eq!(texts(&doc, "li[lang^='en-']"), ["li:One"]);
+    assert_eq!(texts(&doc, "li[lang$=n], [class~=note]"), ["li:Four", "p:Outro"]);
+    assert_eq!(texts(&doc, "[class*=\"ai\"]").len(), 1);
+    assert_eq!(texts(&doc, "li:empty"), ["li:"]);
+    assert_eq!(texts(&doc, "*:root > *").len(), 1);
+    assert_eq!(texts(&doc, "body > :not(ul, svg)"), ["p:Intro", "p:Outro"]);
+}
+
+#[test]
+fn structural_pseudo_classes() {
+    let doc: CompactDocument = SOURCE.parse().unwrap();
+
+    assert_eq!(texts(&doc, "li:nth-child(2n)"), ["li:Two", "li:Four"]);
+    assert_eq!(texts(&doc, "li:nth-child(odd)"), ["li:One", "li:"]);
+    assert_eq!(texts(&doc, "li:nth-child(-n+2)"), ["li:One", "li:Two"]);
+    assert_eq!(texts(&doc, "li:nth-last-child(1)"), ["li:Four"]);
+    assert_eq!(texts(&doc, "body > :first-child, body > :last-child"), ["ul:One Two Four", "p:Outro"]);
+    assert_eq!(texts(&doc, "p:first-of-type, p:nth-last-of-type(1)"), ["p:Intro", "p:Outro"]);
+    assert_eq!(texts(&doc, "ul + p"), ["p:Intro"]);
+    assert_eq!(texts(&doc, "ul ~ p"), ["p:Intro", "p:Outro"]);
+    assert!(texts(&doc, "li:only-child").is_empty());
+}
+
+#[test]
+fn namespaces() {
+    let doc: CompactDocument = SOURCE.parse().unwrap();
+    let count = |ns: &Namespace, s: &str| Selector::parse_with(ns, s).unwrap().select(doc.root()).count();
+
+    let mut ns = Namespace::empty();
+    ns.put("s", "http://www.w3.org/2000/svg");
+    assert_eq!(count(&ns, "s|svg > s|*"), 1);
+    assert_eq!(count(&ns, "s|svg > |rect"), 1);
+    assert_eq!(count(&ns, "rect"), 2);
+    assert_eq!(count(&ns, "*|rect[x]"), 1);
+    assert_eq!(count(&ns, "s|svg[|width]"), 1);
+
+    // a default namespace applies to type selectors only
+    ns.put("", "http://www.w3.org/2000/svg");
+    assert_eq!(count(&ns, "rect"), 1);
+    assert_eq!(count(&ns, "svg[width]"), 1);
+
+    let svg = Selector::parse("svg|*").unwrap_err();
+    assert_eq!(svg.to_string(), "selector error: prefix 'svg' is not bound");
+
+    let root = doc.root_element().unwrap();
+    assert!(Selector::parse_with(root.namespace(), "html > body svg|rect").unwrap()
+        .matches(doc.nodes().find(|n| n.attribute("x").is_some()).unwrap()));
+}
@@ -0,0 +1,203 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "derive")]
+
+use xml::common::{Position, TextPosition};
+use xml::typed::{from_str, to_string_with_config, FromXml, ToXml};
+use xml::EmitterConfig;
+
+const NS: &str = "urn:example:project";
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+#[xml(rename = "project", ns = "urn:example:project")]
+struct Project {
+    #[xml(attr)]
+    name: String,
+    #[xml(attr, rename = "xml:lang")]
+    lang: Option<String>,
+    #[xml(attr, rename = "meta:rev", ns = "urn:example:meta")]
+    revision: u32,
+    description: String,
+    #[xml(rename = "dependency")]
+    dependencies: Vec<Dependency>,
+    license: License,
+    homepage: Option<String>,
+    #[xml(default)]
+    stars: u64,
+    #[xml(flatten)]
+    extras: Extras,
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+struct Dependency {
+    #[xml(attr, default)]
+    optional: bool,
+    #[xml(text)]
+    name: String,
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+enum License {
+    #[xml(rename = "MIT")]
+    Mit,
+    Apache,
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+struct Extras {
+    #[xml(attr)]
+    private: Option<bool>,
+    #[xml(rename = "badge")]
+    badges: Vec<Badge>,
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+#[xml(ns = "urn:example:project")]
+enum Badge {
+    #[xml(rename = "ci")]
+    Ci(Link),
+    #[xml(rename = "unstable")]
+    Unstable,
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+struct Link {
+    #[xml(attr)]
+    href: String,
+}
+
+fn project() -> Project {
+    Project {
+        name: "xml-rs".into(),
+        lang: Some("en".into()),
+        revision: 7,
+        description: "Reads & writes <XML>".into(),
+        dependencies: vec![
+            Dependency { optional: false, name: "serde".into() },
+            Dependency { optional: true, name: "other".into() },
+        ],
+        license: License::Mit,
+        homepage: None,
+        stars: 0,
+        extras: Extras {
+            private: Some(false),
+            badges: vec![Badge::Ci(Link { href: "/ci".into() }), Badge::Unstable],
+        },
+    }
+}
+
+#[test]
+fn reads_attributes_text_and_namespaces() {
+    let project: Project = from_str(r#"<?xml version="1.0"?>
+        <p:project xmlns:p="urn:example:project" xmlns:m="urn:example:meta" name="xml-rs" xml:lang="en" m:rev=" 7 " private="0" ignored="x">
+            <p:description>Reads &amp; writes <![CDATA[<XML>]]></p:description>
+            <p:dependency>serde</p:dependency>
+            <p:ci href="/ci"/>
+            <p:dependency optional="true">other</p:dependency>
+            <description>not in the namespace</description>
+            <p:license>MIT</p:license>
+            <p:unstable/>
+            <p:unknown><p:dependency/></p:unknown>
+        </p:project>"#).unwrap();
+    assert_eq!(project, self::project());
+}
+
+#[test]
+fn writes_and_reads_back() {
+    let config = EmitterConfig::new().perform_indent(true).write_document_declaration(false);
+    let xml = to_string_with_config(&project(), config).unwrap();
+    assert_eq!(xml, r#"<project xmlns="urn:example:project" xmlns:meta="urn:example:meta" name="xml-rs" xml:lang="en" meta:rev="7" private="false">
+  <description>Reads &amp; writes &lt;XML&gt;</description>
+  <dependency optional="false">serde</dependency>
+  <dependency optional="true">other</dependency>
+  <license>MIT</license>
+  <stars>0</stars>
+  <ci href="/ci" />
+  <unstable />
+</project>"#);
+    assert_eq!(from_str::<Project>(&xml).unwrap(), project());
+    assert_eq!(xml::typed::to_string(&License::Apache).unwrap_err().to_string(), "writing this value requires a root element name");
+}
+
+#[test]
+fn errors_have_positions() {
+    let doc = |body: &str| format!("<?xml version=\"1.0\"?><project xmlns=\"{NS}\" xmlns:m=\"urn:example:meta\" name=\"x\" m:rev=\"1\">\n{body}\n</project>");
+
+    let err = from_str::<Project>(&doc("<description/><license>BSD</license>")).unwrap_err();
+    assert_eq!(err.position(), TextPosition { row: 1, column: 14 });
+    assert!(err.to_string().contains("unknown value 'BSD', expected one of: MIT, Apache"), "{err}");
+
+    let err = from_str::<Project>(&doc("<license>MIT</license>")).unwrap_err();
+    assert_eq!(err.position(), TextPosition { row: 0, column: 21 });
+    assert!(err.to_string().ends_with("missing element <description>"), "{err}");
+
+    let err = from_str::<Project>(&doc("<description/>\n  <description/>")).unwrap_err();
+    assert_eq!(err.position(), TextPosition { row: 2, column: 2 });
+    assert!(err.to_string().ends_with("duplicate element <description>"), "{err}");
+
+    let err = from_str::<Project>(&doc("<description/><license>MIT</license><dependency optional=\"maybe\"/>")).unwrap_err();
+    assert_eq!(err.position(), TextPosition { row: 1, column: 36 });
+    assert!(err.to_string().ends_with("attribute 'optional': invalid bool 'maybe'"), "{err}");
+
+    let err = from_str::<Project>(&doc("<description>a<b/></description>")).unwrap_err();
+    assert_eq!(err.position(), TextPosition { row: 1, column: 14 });
+    assert!(err.to_string().ends_with("expected text, found element <b>"), "{err}");
+
+    let err = from_str::<Project>("<project name=\"x\"/>").unwrap_err();
+    assert!(err.to_string().ends_with("unexpected element <project>"), "{err}");
+
+    let err = from_str::<Badge>(&format!("<other xmlns=\"{NS}\"/>")).unwrap_err();
+    assert!(err.to_string().ends_with("unexpected element <other>"), "{err}");
+
+    let err = from_str::<Dependency>("<Dependency>x</Dependency").unwrap_err();
+    assert!(matches!(err.kind(), xml::typed::ErrorKind::Reader(_)));
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+#[xml(ns = "urn:x")]
+struct Outer {
+    inner: Inner,
+    #[xml(flatten)]
+    plain: Plain,
+    #[xml(rename = "shape")]
+    shapes: Vec<Shape>,
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+struct Inner {
+    leaf: String,
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+struct Plain {
+    note: Option<String>,
+}
+
+#[derive(FromXml, ToXml, Debug, PartialEq)]
+enum Shape {
+    #[xml(rename = "circle")]
+    Circle(Inner),
+    #[xml(rename = "square")]
+    Square,
+}
+
+#[test]
+fn writes_no_namespace_inside_default_namespace() {
+    let outer = Outer {
+        inner: Inner { leaf: "v".into() },
+        plain: Plain { note: Some("n".into()) },
+        shapes: vec![Shape::Circle(Inner { leaf: "r".into() }), Shape::Square],
+    };
+    let config = EmitterConfig::new().perform_indent(true).write_document_declaration(false);
+    let xml = to_string_with_config(&outer, config).unwrap();
+    assert_eq!(xml, r#"<Outer xmlns="urn:x">
+  <inner>
+    <leaf xmlns="">v</leaf>
+  </inner>
+  <note xmlns="">n</note>
+  <circle xmlns="">
+    <leaf>r</leaf>
+  </circle>
+  <square xmlns="" />
+</Outer>"#);
+    assert_eq!(from_str::<Outer>(&xml).unwrap(), outer);
+}
@@ -0,0 +1,105 @@
+#![forbid(unsafe_code)]
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+
+use xml::reader::XmlEvent;
+use xml::xinclude::{Error, FileResolver, Resolver, XIncludeReader};
+use xml::{EmitterConfig, EventReader, ParserConfig};
+
+/// Writes the events of the reader, without the XML declaration
+fn write<R: Read, S: Resolver>(mut reader: XIncludeReader<R, S>) -> Result<String, Error> {
+    let mut output = Vec::new();
+    let mut writer = EmitterConfig::new().write_document_declaration(false).create_writer(&mut output);
+    loop {
+        match reader.next()? {
+            XmlEvent::EndDocument => break,
+            XmlEvent::StartDocument { .. } => {},
+            event => if let Some(event) = event.as_writer_event() {
+                writer.write(event).unwrap();
+            },
+        }
+    }
+    Ok(String::from_utf8(output).unwrap())
+}
+
+fn include(source: &str, files: &[(&str, &str)]) -> Result<String, Error> {
+    let files: HashMap<_, _> = files.iter().copied().collect();
+    let resolver = |uri: &str| files.get(uri).map(|text| text.as_bytes().to_vec())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"));
+    let reader = EventReader::new_with_config(source.as_bytes(), ParserConfig::new().trim_whitespace(true));
+    write(XIncludeReader::new(reader, resolver))
+}
+
+#[test]
+fn includes_files() {
+    let path = "tests/documents/xinclude/book.xml";
+    let config = ParserConfig::new().trim_whitespace(true).base_uri(path);
+    let reader = EventReader::new_with_config(BufReader::new(File::open(path).unwrap()), config);
+    let output = write(XIncludeReader::new(reader, FileResolver)).unwrap();
+    assert_eq!(output, concat!(
+        r#"<book xmlns:xi="http://www.w3.org/2001/XInclude">"#,
+        r#"<chapter xml:id="intro" xml:base="chapters/intro.xml"><title>Introduction</title><section xml:base="sections/"><img src="a.png" /></section>MIT &amp; friends</chapter>"#,
+        r#"<section xml:base="chapters/sections/"><img src="a.png" /></section>"#,
+        r#"<license>MIT &amp; friends</license>"#,
+        r#"<p>Coming soon</p>"#,
+        r#"</book>"#));
+
+    let path = "tests/documents/xinclude/loop.xml";
+    let reader = EventReader::new_with_config(BufReader::new(File::open(path).unwrap()), ParserConfig::new().base_uri(path));
+    let err = write(XIncludeReader::new(reader, FileResolver)).unwrap_err();
+    assert_eq!(err.to_string(), "1:49 'tests/documents/xinclude/loop.xml' includes itself");
+}
+
+#[test]
+fn xpointers() {
+    let chapter = r#"<chapter><section id="a"><p>1</p><p>2</p></section><section xml:id="b"><p>3</p></section></chapter>"#;
+    let doc = |xpointer: &str| format!(r#"<doc xmlns:xi="http://www.w3.org/2001/XInclude"><xi:include href="c.xml" xpointer="{xpointer}"/></doc>"#);
+    let files = [("c.xml", chapter)];
+    let included = |xpointer| include(&doc(xpointer), &files).unwrap()
+        .replace(r#"<doc xmlns:xi="http://www.w3.org/2001/XInclude">"#, "").replace("</doc>", "");
+
+    assert_eq!(included("b"), r#"<section xml:id="b" xml:base="c.xml"><p>3</p></section>"#);
+    assert_eq!(included("element(a)"), r#"<section id="a" xml:base="c.xml"><p>1</p><p>2</p></section>"#);
+    assert_eq!(included("element(a/2)"), r#"<p xml:base="c.xml">2</p>"#);
+    assert_eq!(included("element(/1/2/1)"), r#"<p xml:base="c.xml">3</p>"#);
+    assert_eq!(included("xmlns(x=urn:x) element(missing) element(/1/1)"), r#"<section id="a" xml:base="c.xml"><p>1</p><p>2</p></section>"#);
+
+    let err = include(&doc("element(/2)"), &files).unwrap_err();
+    assert_eq!(err.to_string(), "can't include 'c.xml': xpointer 'element(/2)' doesn't match");
+}
+
+#[test]
+fn fallbacks_and_base_uris() {
+    let source = r#"<doc xmlns:xi="http://www.w3.org/2001/XInclude" xml:base="parts/">
+        <xi:include href="a.xml"><xi:fallback><xi:include href="b.xml"/></xi:fallback></xi:include>
+        <sub xml:base="sub/"><xi:include href="../c.txt" parse="text"><xi:fallback>none</xi:fallback></xi:include></sub>
+        <xi:include href="c.txt" parse="text" encoding="iso-8859-1"/>
+    </doc>"#;
+    let output = include(source, &[("parts/b.xml", "<b><xi:include xmlns:xi='http://www.w3.org/2001/XInclude' href='c.txt' parse='text'/></b>"), ("parts/c.txt", "é")]).unwrap();
+    assert_eq!(output, r#"<doc xmlns:xi="http://www.w3.org/2001/XInclude" xml:base="parts/"><b xml:base="b.xml">é</b><sub xml:base="sub/">é</sub>Ã©</doc>"#);
+
+    let output = include(source, &[]).unwrap_err();
+    assert!(matches!(&output, Error::Resource { uri, .. } if uri == "parts/b.xml"), "{output}");
+
+    let output = include(source, &[("parts/b.xml", "<b>")]).unwrap_err();
+    assert!(matches!(&output, Error::Included { uri, .. } if uri == "parts/b.xml"), "{output}");
+}
+
+#[test]
+fn invalid_includes() {
+    let doc = |element: &str| format!(r#"<doc xmlns:xi="http://www.w3.org/2001/XInclude">{element}</doc>"#);
+    for (element, msg) in [
+        (r#"<xi:include/>"#, "missing href"),
+        (r#"<xi:include xpointer="a"/>"#, "including parts of the same document is not supported"),
+        (r#"<xi:include href="a.xml#a"/>"#, "href 'a.xml#a' has a fragment identifier"),
+        (r#"<xi:include href="a.xml" parse="html"/>"#, "invalid parse attribute 'html'"),
+        (r#"<xi:include href="a.xml" parse="text" xpointer="a"/>"#, "xpointer is not allowed with parse=\"text\""),
+        (r#"<xi:include href="a.xml"><xi:fallback/><xi:fallback/></xi:include>"#, "unexpected xi:fallback in xi:include"),
+        (r#"<xi:fallback/>"#, "xi:fallback outside of xi:include"),
+    ] {
+        let err = include(&doc(element), &[("a.xml", "<a/>")]).unwrap_err();
+        assert_eq!(err.to_string(), format!("1:49 {msg}"));
+    }
+}
@@ -0,0 +1,130 @@
+#![forbid(unsafe_code)]
+
+use std::fs;
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use xml::c14n::Method;
+use xml::xmldsig::{algorithm, sign, verify, DigestMethod, Error, SignatureConfig, Signer, Verifier};
+
+struct Sha256Digest;
+
+impl DigestMethod for Sha256Digest {
+    fn algorithm(&self) -> &str {
+        algorithm::SHA256
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        Sha256::digest(data).to_vec()
+    }
+}
+
+struct HmacKey(Vec<u8>);
+
+impl HmacKey {
+    fn fixture() -> Self {
+        Self(fs::read("tests/documents/xmldsig-hmac.key").unwrap())
+    }
+
+    fn mac(&self, data: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).unwrap();
+        mac.update(data);
+        mac
+    }
+}
+
+impl Signer for HmacKey {
+    fn algorithm(&self) -> &str {
+        algorithm::HMAC_SHA256
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.mac(data).finalize().into_bytes().to_vec())
+    }
+}
+
+impl Verifier for HmacKey {
+    fn algorithm(&self) -> &str {
+        algorithm::HMAC_SHA256
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        self.mac(data).verify_slice(signature).is_ok()
+    }
+}
+
+fn verify_str(document: &str) -> Result<Vec<String>, Error> {
+    verify(document.as_bytes(), &[&Sha256Digest], &HmacKey::fixture())
+}
+
+fn sign_str(document: &str, config: &SignatureConfig) -> String {
+    let mut output = Vec::new();
+    sign(document.as_bytes(), &mut output, config, &Sha256Digest, &HmacKey::fixture()).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn verifies_fixture() {
+    let signed = fs::read_to_string("tests/documents/xmldsig-signed.xml").unwrap();
+    assert_eq!(verify_str(&signed).unwrap(), [""]);
+
+    // formatting outside of SignedInfo and the signed data doesn't matter
+    let reformatted = signed.replace("ID='inv-1' xmlns=\"urn:example:invoice\"", "xmlns='urn:example:invoice' ID=\"inv-1\"")
+        .replace("<total currency=\"EUR\" >100.00</total>", "<total currency='EUR'><![CDATA[100.00]]></total>");
+    assert_eq!(verify_str(&reformatted).unwrap(), [""]);
+
+    let tampered = signed.replace("100.00", "10.00");
+    assert!(matches!(verify_str(&tampered), Err(Error::DigestMismatch(uri)) if uri.is_empty()));
+
+    let tampered = signed.replace("7oy4", "8oy4");
+    assert!(matches!(verify_str(&tampered), Err(Error::InvalidSignature)));
+
+    let wrong_key = HmacKey(b"another key".to_vec());
+    assert!(matches!(verify(signed.as_bytes(), &[&Sha256Digest], &wrong_key), Err(Error::InvalidSignature)));
+
+    assert!(matches!(verify(signed.as_bytes(), &[], &HmacKey::fixture()), Err(Error::Unsupported(_))));
+}
+
+#[test]
+fn signs_and_verifies() {
+    let document = r#"<?xml version="1.0"?>
+<!DOCTYPE response>
+<r:response xmlns:r="urn:example:response" xmlns:unused="urn:example:unused">
+  <r:assertion ID="a1" xmlns:x="urn:example:x">
+    <r:subject x:type="user">alice</r:subject>
+  </r:assertion>
+  <r:assertion ID="a2"><r:subject>mallory</r:subject></r:assertion>
+</r:response>"#;
+
+    for method in [Method::C14n10, Method::C14n11, Method::Exclusive] {
+        let signed = sign_str(document, &SignatureConfig::new().canonicalization(method));
+        assert!(signed.contains(r#"<ds:Reference URI="">"#), "{signed}");
+        assert_eq!(verify_str(&signed).unwrap(), [""]);
+        assert!(verify_str(&signed.replace("alice", "bob")).is_err());
+
+        let signed = sign_str(document, &SignatureConfig::new().canonicalization(method).reference("#a1"));
+        assert!(signed.contains("alice</r:subject>\n  <ds:Signature"), "{signed}");
+        assert_eq!(verify_str(&signed).unwrap(), ["#a1"]);
+        // only the referenced element is signed
+        assert_eq!(verify_str(&signed.replace("mallory", "eve")).unwrap(), ["#a1"]);
+        assert!(verify_str(&signed.replace("alice", "bob")).is_err());
+    }
+}
+
+#[test]
+fn rejects_unsafe_references() {
+    let signed = sign_str(r#"<doc><item id="x">1</item></doc>"#, &SignatureConfig::new().reference("#x"));
+    assert_eq!(verify_str(&signed).unwrap(), ["#x"]);
+
+    let wrapped = signed.replace("<doc>", r#"<doc><item id="x">2</item>"#);
+    assert!(matches!(verify_str(&wrapped), Err(Error::Malformed(msg)) if msg == "duplicate Id 'x'"));
+
+    let external = signed.replace(r##"URI="#x""##, r#"URI="http://example.com/""#);
+    assert!(matches!(verify_str(&external), Err(Error::Unsupported(_))));
+
+    assert!(matches!(verify_str("<doc/>"), Err(Error::Malformed(msg)) if msg == "no Signature element"));
+
+    let mut output = Vec::new();
+    let err = sign(&b"<doc/>"[..], &mut output, &SignatureConfig::new().reference("#missing"), &Sha256Digest, &HmacKey::fixture()).unwrap_err();
+    assert_eq!(err.to_string(), "malformed signature: no element with Id 'missing'");
+}
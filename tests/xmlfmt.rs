@@ -0,0 +1,100 @@
+#![forbid(unsafe_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Runs `xmlfmt` with the input on stdin
+fn xmlfmt(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_xmlfmt"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Writes a file in the target's temporary directory
+fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn formats_stdin() {
+    let output = xmlfmt(&[], b"<a><b x='1'/><p>Some <i>text</i></p></a>");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\
+<a>
+  <b x=\"1\" />
+  <p>Some <i>text</i></p>
+</a>
+");
+}
+
+#[test]
+fn keeps_text_after_nested_elements() {
+    let output = xmlfmt(&[], b"<p><b><i>x</i></b> text</p>");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "<p><b><i>x</i></b> text</p>\n");
+}
+
+#[test]
+fn check() {
+    let formatted = temp_file("xmlfmt-formatted.xml", b"<a>\n  <b />\n</a>\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_xmlfmt")).arg("--check").arg(&formatted).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+
+    let unformatted = temp_file("xmlfmt-unformatted.xml", b"<a><b/></a>");
+    let output = Command::new(env!("CARGO_BIN_EXE_xmlfmt")).arg("--check").arg(&unformatted).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stdout).unwrap().ends_with("xmlfmt-unformatted.xml: not formatted\n"));
+    // the file is left as it was
+    assert_eq!(fs::read(&unformatted).unwrap(), b"<a><b/></a>");
+
+    let output = xmlfmt(&["--check"], b"<a>\n  <b />\n</a>\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    let output = xmlfmt(&["--check"], b"<a><b/></a>");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, b"<stdin>: not formatted\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_xmlfmt")).arg(&unformatted).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(fs::read(&unformatted).unwrap(), b"<a>\n  <b />\n</a>\n");
+}
+
+#[test]
+fn malformed_input() {
+    let output = xmlfmt(&[], b"<b><i>bold</b></i>");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "\
+error[mismatched-closing-tag]: Unexpected closing tag: b != i
+ --> <stdin>:1:14
+  |
+1 | <b><i>bold</b></i>
+  |              ^
+  = hint: elements must be closed in the reverse order they were opened
+");
+}
+
+#[test]
+fn non_utf8_input() {
+    let output = xmlfmt(&[], b"<?xml version='1.0' encoding='ISO-8859-1'?><a>caf\xe9</a>");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<a>caf\u{e9}</a>\n");
+}
+
+#[test]
+fn crlf() {
+    let output = xmlfmt(&["--crlf"], b"<a><b/><pre>one\ntwo</pre></a>");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "<a>\r\n  <b />\r\n  <pre>one\r\ntwo</pre>\r\n</a>\r\n");
+}
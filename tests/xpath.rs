@@ -0,0 +1,184 @@
+#![forbid(unsafe_code)]
+
+use xml::dom::compact::CompactDocument;
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
+use xml::xpath::streaming::{Match, StreamingPath};
+use xml::xpath::{Context, Error, Node, Value, XPath};
+
+const BOOKS: &str = r#"<?xml version="1.0"?>
+<library xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <!-- catalogue -->
+    <book id="b1" year="1999" xml:lang="en">
+        <dc:title>Alpha</dc:title>
+        <price>10.50</price>
+    </book>
+    <book id="b2" year="2005" xml:lang="en-GB">
+        <dc:title>Beta</dc:title>
+        <price>20</price>
+    </book>
+    <book id="b3" year="2012" xml:lang="de">
+        <dc:title>Gamma</dc:title>
+        <price>5</price>
+        <?note keep?>
+    </book>
+</library>"#;
+
+fn books() -> CompactDocument {
+    CompactDocument::parse_with_config(BOOKS.as_bytes(), ParserConfig::new().ignore_comments(false)).unwrap()
+}
+
+fn ctx() -> Context<'static> {
+    Context::new().namespace("dc", "http://purl.org/dc/elements/1.1/")
+}
+
+fn eval<'a>(doc: &'a CompactDocument, expr: &str) -> Value<'a> {
+    XPath::compile(expr).unwrap().evaluate_with(&ctx(), doc.root()).unwrap()
+}
+
+fn strings(doc: &CompactDocument, expr: &str) -> Vec<String> {
+    XPath::compile(expr).unwrap().select(&ctx(), doc.root()).unwrap()
+        .into_iter().map(Node::string_value).collect()
+}
+
+#[test]
+fn paths_and_predicates() {
+    let doc = books();
+
+    assert_eq!(strings(&doc, "/library/book/dc:title"), ["Alpha", "Beta", "Gamma"]);
+    assert_eq!(strings(&doc, "//book[2]/dc:title"), ["Beta"]);
+    assert_eq!(strings(&doc, "//book[last()]/@id"), ["b3"]);
+    assert_eq!(strings(&doc, "//book[@year > 2000][1]/@id"), ["b2"]);
+    assert_eq!(strings(&doc, "//book[price < 15]/@id"), ["b1", "b3"]);
+    assert_eq!(strings(&doc, "(//dc:title)[position() > 1]"), ["Beta", "Gamma"]);
+    assert_eq!(strings(&doc, "//price/../@id | //book[1]/@year"), ["b1", "1999", "b2", "b3"]);
+    assert_eq!(strings(&doc, "//processing-instruction('note')"), ["keep"]);
+    assert_eq!(strings(&doc, "/library/comment()"), [" catalogue "]);
+    assert!(strings(&doc, "//title").is_empty(), "unprefixed names have no namespace");
+}
+
+#[test]
+fn axes() {
+    let doc = books();
+
+    assert_eq!(strings(&doc, "//book[2]/preceding-sibling::book/@id"), ["b1"]);
+    assert_eq!(strings(&doc, "//book[2]/following-sibling::*[1]/@id"), ["b3"]);
+    assert_eq!(strings(&doc, "//price[. = 20]/ancestor::*[1]/@id"), ["b2"]);
+    assert_eq!(strings(&doc, "//book[3]/preceding::dc:title[1]"), ["Beta"]);
+    assert_eq!(strings(&doc, "//book[1]/dc:title/following::price"), ["10.50", "20", "5"]);
+    assert_eq!(strings(&doc, "//book[1]/descendant-or-self::*/self::price"), ["10.50"]);
+    assert_eq!(eval(&doc, "count(//book[1]/@*)").number(), 3.);
+    assert_eq!(strings(&doc, "//book[1]/namespace::dc"), ["http://purl.org/dc/elements/1.1/"]);
+    assert_eq!(eval(&doc, "name(//book/@xml:lang/..)").string(), "book");
+}
+
+#[test]
+fn functions() {
+    let doc = books();
+
+    assert_eq!(eval(&doc, "sum(//price)").number(), 35.5);
+    assert_eq!(eval(&doc, "concat(id('b2')/dc:title, '-', string(1 div 0))").string(), "Beta-Infinity");
+    assert_eq!(eval(&doc, "normalize-space('  a   b ')").string(), "a b");
+    assert_eq!(eval(&doc, "translate('abc', 'abc', 'AB')").string(), "AB");
+    assert_eq!(eval(&doc, "substring('12345', 1.5, 2.6)").string(), "234");
+    assert_eq!(eval(&doc, "substring-after(name(//dc:title), ':')").string(), "title");
+    assert_eq!(eval(&doc, "namespace-uri(//dc:title)").string(), "http://purl.org/dc/elements/1.1/");
+    assert_eq!(eval(&doc, "round(-2.5) + floor(1.5) + ceiling(1.5)").number(), 1.);
+    assert_eq!(eval(&doc, "count(//book[lang('en')])").number(), 2.);
+    assert_eq!(eval(&doc, "string(0.1 + 0.2 = 0.3)").string(), "false");
+    assert_eq!(eval(&doc, "7 mod -3").string(), "1");
+    assert!(eval(&doc, "//book/@year = '2005' and not(//book/@year = 2006)").boolean());
+}
+
+#[test]
+fn variables_and_context() {
+    let doc = books();
+    let book = XPath::compile("//book[2]").unwrap().select(&ctx(), doc.root()).unwrap()[0];
+
+    let relative = XPath::compile("dc:title").unwrap();
+    assert_eq!(relative.evaluate_with(&ctx(), book).unwrap().string(), "Beta");
+
+    let ctx = Context::from_node(doc.root_element().unwrap())
+        .variable("min", 2000.)
+        .variable("lang", "de");
+    let expr = XPath::compile("//book[@year >= $min and @xml:lang = $lang]/dc:title").unwrap();
+    assert_eq!(expr.evaluate_with(&ctx, &doc).unwrap().string(), "Gamma");
+}
+
+#[test]
+fn errors() {
+    let doc: CompactDocument = "<a/>".parse().unwrap();
+
+    assert!(matches!(XPath::compile("//a["), Err(Error::Syntax { .. })));
+    assert!(matches!(XPath::compile("1 +"), Err(Error::Syntax { .. })));
+
+    let eval = |expr: &str| XPath::compile(expr).unwrap().evaluate(&doc);
+    assert_eq!(eval("x:a"), Err(Error::UnboundPrefix("x".into())));
+    assert_eq!(eval("foo()"), Err(Error::UnknownFunction("foo".into())));
+    assert_eq!(eval("$v"), Err(Error::UnknownVariable("v".into())));
+    assert_eq!(eval("count()"), Err(Error::ArgumentCount("count".into())));
+    assert_eq!(eval("'a' | /a"), Err(Error::NotANodeSet));
+}
+
+fn stream(expr: &str, source: &str) -> Vec<Match> {
+    let path = StreamingPath::compile_with(&ctx(), expr).unwrap();
+    path.select(EventReader::new(source.as_bytes())).map(Result::unwrap).collect()
+}
+
+fn stream_strings(expr: &str, source: &str) -> Vec<String> {
+    stream(expr, source).into_iter().map(|m| match m {
+        Match::Attribute(a) => a.value,
+        Match::Text(t) => t,
+        Match::Element(events) => format!("{} events", events.len()),
+    }).collect()
+}
+
+#[test]
+fn streaming_matches_tree_evaluation() {
+    let doc = books();
+    for expr in [
+        "/library/book/@id",
+        "//dc:title/text()",
+        "//book[2]/@year",
+        "//book[@year > 2000 and @xml:lang != 'de']/price/text()",
+        "/library/*[position() >= 2]/@id",
+        "//@xml:lang",
+        "/library/book[not(@year = 1999)][1]/@id",
+    ] {
+        assert_eq!(stream_strings(expr, BOOKS), strings(&doc, expr), "{expr}");
+    }
+}
+
+#[test]
+fn streaming_descendant_or_self() {
+    let source = r#"<a id="1">top<b id="2">inner</b></a>"#;
+    let doc: CompactDocument = source.parse().unwrap();
+    for (expr, expected) in [
+        ("/a//text()", &["top", "inner"][..]),
+        ("/a//@id", &["1", "2"]),
+        ("/a/b//text()", &["inner"]),
+        ("//@id", &["1", "2"]),
+    ] {
+        assert_eq!(strings(&doc, expr), expected, "{expr}");
+        assert_eq!(stream_strings(expr, source), expected, "{expr}");
+    }
+}
+
+#[test]
+fn streaming_subtrees() {
+    let source = "<a><b id='1'><b id='2'>x</b></b><c><b/></c></a>";
+
+    let matches = stream("/a//b", source);
+    assert_eq!(matches.len(), 3);
+    let Match::Element(inner) = &matches[0] else { panic!() };
+    assert_eq!(inner.len(), 3);
+    assert!(matches!(&inner[0], XmlEvent::StartElement { attributes, .. } if attributes[0].value == "2"));
+    assert!(matches!(&inner[1], XmlEvent::Characters(s) if s == "x"));
+
+    assert_eq!(stream_strings("/a/b/b", source), ["3 events"]);
+    assert_eq!(stream_strings("/a/descendant::b[@id]", source), ["3 events", "5 events"]);
+    assert_eq!(stream_strings("//b[1]/@id", source), ["1", "2"]);
+    assert_eq!(stream_strings("//text()", "<a>x<![CDATA[y]]>z<b>w</b>v</a>"), ["xyz", "w", "v"]);
+
+    let path = StreamingPath::compile("//b").unwrap();
+    assert!(path.select(EventReader::new("<a><b></a>".as_bytes())).any(|m| m.is_err()));
+}
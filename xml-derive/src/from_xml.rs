@@ -0,0 +1,267 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::model::{Field, FieldKind, Input, Occurs, Variant};
+
+pub(crate) fn expand(input: &Input) -> TokenStream {
+    match input {
+        Input::Struct { ident, name, fields } => {
+            let name = name.tokens();
+            let builder_fields = fields.iter().map(builder_field);
+            let start_fields = fields.iter().map(start_field);
+            let attributes = fields.iter().filter_map(read_attribute);
+            let children = fields.iter().filter_map(read_child);
+            let text = read_text(fields);
+            let finish_fields = fields.iter().map(finish_field);
+            quote! {
+                const _: () = {
+                    pub struct __XmlBuilder {
+                        #(#builder_fields,)*
+                    }
+
+                    #[automatically_derived]
+                    #[allow(unused_variables, unused_mut)]
+                    impl ::xml::typed::FromXml for #ident {
+                        type Builder = __XmlBuilder;
+
+                        fn matches(name: &::xml::name::OwnedName, field: ::xml::typed::__private::Option<::xml::name::Name<'_>>) -> bool {
+                            ::xml::typed::__private::is(name, field.unwrap_or(#name))
+                        }
+
+                        fn start(element: &::xml::typed::Element) -> ::xml::typed::__private::Result<__XmlBuilder, ::xml::typed::Error> {
+                            ::xml::typed::__private::Ok(__XmlBuilder { #(#start_fields,)* })
+                        }
+
+                        fn read_attribute(builder: &mut __XmlBuilder, attribute: &::xml::attribute::OwnedAttribute) -> ::xml::typed::__private::Result<bool, ::xml::typed::Error> {
+                            #(#attributes)*
+                            ::xml::typed::__private::Ok(false)
+                        }
+
+                        fn read_child<R: ::xml::typed::__private::Read>(builder: &mut __XmlBuilder, child: &mut ::xml::typed::Child<'_, R>) -> ::xml::typed::__private::Result<bool, ::xml::typed::Error> {
+                            #(#children)*
+                            ::xml::typed::__private::Ok(false)
+                        }
+
+                        fn read_text(builder: &mut __XmlBuilder, text: &str) -> ::xml::typed::__private::Result<bool, ::xml::typed::Error> {
+                            #text
+                        }
+
+                        fn finish(builder: __XmlBuilder, element: &::xml::typed::Element) -> ::xml::typed::__private::Result<Self, ::xml::typed::Error> {
+                            ::xml::typed::__private::Ok(Self { #(#finish_fields,)* })
+                        }
+                    }
+                };
+            }
+        },
+        Input::Choice { ident, variants } => expand_choice(ident, variants),
+        Input::Text { ident, values } => {
+            let expected = values.iter().map(|(_, value)| value.as_str()).collect::<Vec<_>>().join(", ");
+            let arms = values.iter().map(|(variant, value)| quote!(#value => ::xml::typed::__private::Ok(Self::#variant)));
+            quote! {
+                #[automatically_derived]
+                impl ::xml::typed::FromXmlText for #ident {
+                    fn from_xml_text(text: &str) -> ::xml::typed::__private::Result<Self, ::xml::typed::Error> {
+                        match text {
+                            #(#arms,)*
+                            _ => ::xml::typed::__private::Err(::xml::typed::Error::custom(
+                                ::std::format_args!("unknown value '{}', expected one of: {}", text, #expected))),
+                        }
+                    }
+                }
+            }
+        },
+    }
+}
+
+fn builder_field(field: &Field) -> TokenStream {
+    let Field { member, ty, .. } = field;
+    match (&field.kind, &field.occurs) {
+        (FieldKind::Text, _) => quote!(#member: ::xml::typed::__private::String),
+        (FieldKind::Flatten, _) => quote!(#member: <#ty as ::xml::typed::FromXml>::Builder),
+        (_, Occurs::Repeated) => quote!(#member: ::xml::typed::__private::Vec<#ty>),
+        _ => quote!(#member: ::xml::typed::__private::Option<#ty>),
+    }
+}
+
+fn start_field(field: &Field) -> TokenStream {
+    let Field { member, ty, .. } = field;
+    match (&field.kind, &field.occurs) {
+        (FieldKind::Text, _) => quote!(#member: ::xml::typed::__private::String::new()),
+        (FieldKind::Flatten, _) => quote!(#member: <#ty as ::xml::typed::FromXml>::start(element)?),
+        (_, Occurs::Repeated) => quote!(#member: ::xml::typed::__private::Vec::new()),
+        _ => quote!(#member: ::xml::typed::__private::None),
+    }
+}
+
+fn read_attribute(field: &Field) -> Option<TokenStream> {
+    let Field { member, ty, .. } = field;
+    match &field.kind {
+        FieldKind::Attribute(name) => {
+            let name = name.tokens();
+            Some(quote! {
+                if ::xml::typed::__private::is(&attribute.name, #name) {
+                    builder.#member = ::xml::typed::__private::Some(::xml::typed::__private::attribute::<#ty>(attribute)?);
+                    return ::xml::typed::__private::Ok(true);
+                }
+            })
+        },
+        FieldKind::Flatten => Some(quote! {
+            if <#ty as ::xml::typed::FromXml>::read_attribute(&mut builder.#member, attribute)? {
+                return ::xml::typed::__private::Ok(true);
+            }
+        }),
+        _ => None,
+    }
+}
+
+fn read_child(field: &Field) -> Option<TokenStream> {
+    let Field { member, ty, .. } = field;
+    match &field.kind {
+        FieldKind::Child(name) => {
+            let duplicate = format!("duplicate element <{}>", name.display());
+            let name = name.tokens();
+            let read = match field.occurs {
+                Occurs::Repeated => quote!(builder.#member.push(child.read::<#ty>()?);),
+                _ => quote! {
+                    if builder.#member.is_some() {
+                        return ::xml::typed::__private::Err(child.element().error(#duplicate));
+                    }
+                    builder.#member = ::xml::typed::__private::Some(child.read::<#ty>()?);
+                },
+            };
+            Some(quote! {
+                if <#ty as ::xml::typed::FromXml>::matches(child.name(), ::xml::typed::__private::Some(#name)) {
+                    #read
+                    return ::xml::typed::__private::Ok(true);
+                }
+            })
+        },
+        FieldKind::Flatten => Some(quote! {
+            if <#ty as ::xml::typed::FromXml>::read_child(&mut builder.#member, child)? {
+                return ::xml::typed::__private::Ok(true);
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// Text goes to the `text` field, or else to flattened fields
+fn read_text(fields: &[Field]) -> TokenStream {
+    if let Some(field) = fields.iter().find(|f| matches!(f.kind, FieldKind::Text)) {
+        let member = &field.member;
+        return quote! {
+            builder.#member.push_str(text);
+            ::xml::typed::__private::Ok(true)
+        };
+    }
+    let flattened = fields.iter().filter(|f| matches!(f.kind, FieldKind::Flatten)).map(|Field { member, ty, .. }| quote! {
+        if <#ty as ::xml::typed::FromXml>::read_text(&mut builder.#member, text)? {
+            return ::xml::typed::__private::Ok(true);
+        }
+    });
+    quote! {
+        #(#flattened)*
+        ::xml::typed::__private::Ok(false)
+    }
+}
+
+fn finish_field(field: &Field) -> TokenStream {
+    let Field { member, ty, .. } = field;
+    let missing = match &field.kind {
+        FieldKind::Attribute(name) => format!("missing attribute '{}'", name.display()),
+        FieldKind::Child(name) => format!("missing element <{}>", name.display()),
+        FieldKind::Text => {
+            return match field.occurs {
+                Occurs::Optional => quote!(#member: ::xml::typed::__private::optional_text::<#ty>(&builder.#member)?),
+                _ => quote!(#member: <#ty as ::xml::typed::FromXmlText>::from_xml_text(&builder.#member)?),
+            };
+        },
+        FieldKind::Flatten => return quote!(#member: <#ty as ::xml::typed::FromXml>::finish(builder.#member, element)?),
+    };
+    match field.occurs {
+        Occurs::Once if field.default => quote!(#member: builder.#member.unwrap_or_default()),
+        Occurs::Once => quote!(#member: match builder.#member {
+            ::xml::typed::__private::Some(value) => value,
+            ::xml::typed::__private::None => return ::xml::typed::__private::Err(element.error(#missing)),
+        }),
+        Occurs::Optional | Occurs::Repeated => quote!(#member: builder.#member),
+    }
+}
+
+fn expand_choice(ident: &syn::Ident, variants: &[Variant]) -> TokenStream {
+    let expected = variants.iter().map(|v| format!("<{}>", v.name.display())).collect::<Vec<_>>().join(", ");
+    let builder_variants = variants.iter().map(|Variant { ident, ty, .. }| match ty {
+        Some(ty) => quote!(#ident(<#ty as ::xml::typed::FromXml>::Builder)),
+        None => quote!(#ident),
+    });
+    let names = variants.iter().map(|v| v.name.tokens()).collect::<Vec<_>>();
+    let starts = variants.iter().map(|Variant { ident, name, ty }| {
+        let name = name.tokens();
+        let builder = match ty {
+            Some(ty) => quote!(__XmlBuilder::#ident(<#ty as ::xml::typed::FromXml>::start(element)?)),
+            None => quote!(__XmlBuilder::#ident),
+        };
+        quote! {
+            if ::xml::typed::__private::is(element.name(), #name) {
+                return ::xml::typed::__private::Ok(#builder);
+            }
+        }
+    });
+    let newtypes = variants.iter().filter_map(|v| Some((&v.ident, v.ty.as_ref()?))).collect::<Vec<_>>();
+    let forward = |method: TokenStream, arg: TokenStream| {
+        let arms = newtypes.iter().map(|(ident, ty)| quote!(__XmlBuilder::#ident(builder) => <#ty as ::xml::typed::FromXml>::#method(builder, #arg)));
+        quote! {
+            match builder {
+                #(#arms,)*
+                #[allow(unreachable_patterns)]
+                _ => ::xml::typed::__private::Ok(false),
+            }
+        }
+    };
+    let read_attribute = forward(quote!(read_attribute), quote!(attribute));
+    let read_child = forward(quote!(read_child), quote!(child));
+    let read_text = forward(quote!(read_text), quote!(text));
+    let finish = variants.iter().map(|Variant { ident, ty, .. }| match ty {
+        Some(ty) => quote!(__XmlBuilder::#ident(builder) => Self::#ident(<#ty as ::xml::typed::FromXml>::finish(builder, element)?)),
+        None => quote!(__XmlBuilder::#ident => Self::#ident),
+    });
+    quote! {
+        const _: () = {
+            pub enum __XmlBuilder {
+                #(#builder_variants,)*
+            }
+
+            #[automatically_derived]
+            impl ::xml::typed::FromXml for #ident {
+                type Builder = __XmlBuilder;
+
+                fn matches(name: &::xml::name::OwnedName, _: ::xml::typed::__private::Option<::xml::name::Name<'_>>) -> bool {
+                    #(::xml::typed::__private::is(name, #names))||*
+                }
+
+                fn start(element: &::xml::typed::Element) -> ::xml::typed::__private::Result<__XmlBuilder, ::xml::typed::Error> {
+                    #(#starts)*
+                    ::xml::typed::__private::Err(element.error(::std::format_args!("unexpected element <{}>, expected one of: {}", element.name().borrow().repr_display(), #expected)))
+                }
+
+                fn read_attribute(builder: &mut __XmlBuilder, attribute: &::xml::attribute::OwnedAttribute) -> ::xml::typed::__private::Result<bool, ::xml::typed::Error> {
+                    #read_attribute
+                }
+
+                fn read_child<R: ::xml::typed::__private::Read>(builder: &mut __XmlBuilder, child: &mut ::xml::typed::Child<'_, R>) -> ::xml::typed::__private::Result<bool, ::xml::typed::Error> {
+                    #read_child
+                }
+
+                fn read_text(builder: &mut __XmlBuilder, text: &str) -> ::xml::typed::__private::Result<bool, ::xml::typed::Error> {
+                    #read_text
+                }
+
+                fn finish(builder: __XmlBuilder, element: &::xml::typed::Element) -> ::xml::typed::__private::Result<Self, ::xml::typed::Error> {
+                    ::xml::typed::__private::Ok(match builder {
+                        #(#finish,)*
+                    })
+                }
+            }
+        };
+    }
+}
@@ -0,0 +1,31 @@
+//! Derive macros for the `FromXml` and `ToXml` traits of the [`xml`](https://lib.rs/crates/xml) crate.
+//!
+//! Use them through the `derive` feature of the `xml` crate, which re-exports them
+//! from its `typed` module, where the `#[xml(…)]` attributes are documented.
+
+#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod from_xml;
+mod model;
+mod to_xml;
+
+/// Implements `FromXml`, or `FromXmlText` for enums with only unit variants.
+#[proc_macro_derive(FromXml, attributes(xml))]
+pub fn derive_from_xml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    model::Input::parse(&input)
+        .map_or_else(|e| e.to_compile_error(), |input| from_xml::expand(&input))
+        .into()
+}
+
+/// Implements `ToXml`, or `ToXmlText` for enums with only unit variants.
+#[proc_macro_derive(ToXml, attributes(xml))]
+pub fn derive_to_xml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    model::Input::parse(&input)
+        .map_or_else(|e| e.to_compile_error(), |input| to_xml::expand(&input))
+        .into()
+}
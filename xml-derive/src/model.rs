@@ -0,0 +1,268 @@
+//! The `#[xml(…)]` attributes, parsed into a description of the mapping shared by both derives.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Ident, LitStr, PathArguments, Result, Type};
+
+const NS_XML_PREFIX: &str = "xml";
+const NS_XML_URI: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// A qualified name, resolved at compile time
+pub(crate) struct XmlName {
+    local: String,
+    prefix: Option<String>,
+    namespace: Option<String>,
+}
+
+impl XmlName {
+    /// An `xml::name::Name<'static>` expression
+    pub(crate) fn tokens(&self) -> TokenStream {
+        let local = &self.local;
+        let prefix = option(self.prefix.as_deref());
+        let namespace = option(self.namespace.as_deref());
+        quote!(::xml::name::Name { local_name: #local, namespace: #namespace, prefix: #prefix })
+    }
+
+    /// The name as written in documents, for error messages
+    pub(crate) fn display(&self) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}:{}", self.local),
+            None => self.local.clone(),
+        }
+    }
+}
+
+fn option(value: Option<&str>) -> TokenStream {
+    match value {
+        Some(value) => quote!(::xml::typed::__private::Some(#value)),
+        None => quote!(::xml::typed::__private::None),
+    }
+}
+
+/// How many times a child element or attribute can occur
+pub(crate) enum Occurs {
+    Once,
+    Optional,
+    Repeated,
+}
+
+pub(crate) enum FieldKind {
+    Attribute(XmlName),
+    Child(XmlName),
+    Text,
+    Flatten,
+}
+
+pub(crate) struct Field {
+    pub member: Ident,
+    pub kind: FieldKind,
+    pub occurs: Occurs,
+    /// The type without `Option` or `Vec`
+    pub ty: Type,
+    pub default: bool,
+}
+
+pub(crate) struct Variant {
+    pub ident: Ident,
+    pub name: XmlName,
+    /// The type of a newtype variant
+    pub ty: Option<Type>,
+}
+
+pub(crate) enum Input {
+    /// An element with attributes and content
+    Struct { ident: Ident, name: XmlName, fields: Vec<Field> },
+    /// One of the elements named after the variants
+    Choice { ident: Ident, variants: Vec<Variant> },
+    /// Unit variants used as text values
+    Text { ident: Ident, values: Vec<(Ident, String)> },
+}
+
+/// Values of `#[xml(…)]` on a type, variant or field
+#[derive(Default)]
+struct Options {
+    rename: Option<LitStr>,
+    ns: Option<LitStr>,
+    attr: bool,
+    text: bool,
+    flatten: bool,
+    default: bool,
+}
+
+impl Options {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("xml")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    options.rename = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("ns") {
+                    options.ns = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("attr") {
+                    options.attr = true;
+                } else if meta.path.is_ident("text") {
+                    options.text = true;
+                } else if meta.path.is_ident("flatten") {
+                    options.flatten = true;
+                } else if meta.path.is_ident("default") {
+                    options.default = true;
+                } else {
+                    return Err(meta.error("unknown xml attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+
+    /// Only `rename` and `ns` apply to types and variants
+    fn only_names(&self, span: &impl Spanned) -> Result<()> {
+        if self.attr || self.text || self.flatten || self.default {
+            return Err(Error::new(span.span(), "only `rename` and `ns` can be used here"));
+        }
+        Ok(())
+    }
+
+    /// Splits the prefix off `rename`, or uses the identifier
+    fn name(&self, ident: &Ident) -> (String, Option<String>) {
+        match &self.rename {
+            Some(rename) => match rename.value().split_once(':') {
+                Some((prefix, local)) => (local.to_owned(), Some(prefix.to_owned())),
+                None => (rename.value(), None),
+            },
+            None => (ident.unraw().to_string(), None),
+        }
+    }
+
+    /// Name of an element, which is in the namespace of its parent unless it has its own
+    fn element_name(&self, ident: &Ident, parent: Option<&XmlName>) -> Result<XmlName> {
+        let (local, prefix) = self.name(ident);
+        let name = match (&self.ns, parent) {
+            (Some(ns), _) => XmlName { local, prefix, namespace: Some(ns.value()) },
+            (None, Some(parent)) => XmlName {
+                local,
+                prefix: prefix.or_else(|| parent.prefix.clone()),
+                namespace: parent.namespace.clone(),
+            },
+            (None, None) => XmlName { local, prefix, namespace: None },
+        };
+        if name.prefix.is_some() && name.namespace.is_none() {
+            return Err(Error::new(ident.span(), "a prefixed name needs a namespace set with `ns`"));
+        }
+        Ok(name)
+    }
+
+    /// Unprefixed attributes have no namespace
+    fn attribute_name(&self, ident: &Ident) -> Result<XmlName> {
+        let (local, prefix) = self.name(ident);
+        let namespace = match (&self.ns, prefix.as_deref()) {
+            (Some(ns), Some(_)) => Some(ns.value()),
+            (Some(ns), None) => return Err(Error::new(ns.span(), "a namespaced attribute needs a prefix, e.g. `rename = \"p:name\"`")),
+            (None, Some(NS_XML_PREFIX)) => Some(NS_XML_URI.to_owned()),
+            (None, Some(_)) => return Err(Error::new(ident.span(), "a prefixed name needs a namespace set with `ns`")),
+            (None, None) => None,
+        };
+        Ok(XmlName { local, prefix, namespace })
+    }
+}
+
+/// Recognizes `Option<T>` and `Vec<T>` by name
+fn occurs(ty: &Type) -> (Occurs, &Type) {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let (1, Some(GenericArgument::Type(inner))) = (args.args.len(), args.args.first()) {
+                    if segment.ident == "Option" {
+                        return (Occurs::Optional, inner);
+                    }
+                    if segment.ident == "Vec" {
+                        return (Occurs::Repeated, inner);
+                    }
+                }
+            }
+        }
+    }
+    (Occurs::Once, ty)
+}
+
+impl Input {
+    pub(crate) fn parse(input: &DeriveInput) -> Result<Self> {
+        if !input.generics.params.is_empty() {
+            return Err(Error::new(input.generics.span(), "generic types are not supported"));
+        }
+        let options = Options::parse(&input.attrs)?;
+        options.only_names(&input.ident)?;
+        let ident = input.ident.clone();
+        match &input.data {
+            Data::Struct(data) => {
+                let name = options.element_name(&ident, None)?;
+                let fields = match &data.fields {
+                    Fields::Named(fields) => fields.named.iter()
+                        .map(|field| Field::parse(field, &name))
+                        .collect::<Result<Vec<_>>>()?,
+                    Fields::Unit => Vec::new(),
+                    Fields::Unnamed(fields) => return Err(Error::new(fields.span(), "tuple structs are not supported")),
+                };
+                if fields.iter().filter(|f| matches!(f.kind, FieldKind::Text)).count() > 1 {
+                    return Err(Error::new(ident.span(), "only one field can be `text`"));
+                }
+                Ok(Self::Struct { ident, name, fields })
+            },
+            Data::Enum(data) => {
+                let parent = options.element_name(&ident, None)?;
+                let mut variants = Vec::new();
+                for variant in &data.variants {
+                    let options = Options::parse(&variant.attrs)?;
+                    options.only_names(&variant.ident)?;
+                    let ty = match &variant.fields {
+                        Fields::Unit => None,
+                        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Some(fields.unnamed[0].ty.clone()),
+                        fields => return Err(Error::new(fields.span(), "only unit and newtype variants are supported")),
+                    };
+                    let name = options.element_name(&variant.ident, Some(&parent))?;
+                    variants.push(Variant { ident: variant.ident.clone(), name, ty });
+                }
+                if variants.iter().all(|v| v.ty.is_none()) {
+                    let values = variants.into_iter().map(|v| (v.ident, v.name.display())).collect();
+                    return Ok(Self::Text { ident, values });
+                }
+                Ok(Self::Choice { ident, variants })
+            },
+            Data::Union(_) => Err(Error::new(ident.span(), "unions are not supported")),
+        }
+    }
+}
+
+impl Field {
+    fn parse(field: &syn::Field, parent: &XmlName) -> Result<Self> {
+        let options = Options::parse(&field.attrs)?;
+        let member = field.ident.clone().expect("named field");
+        let kinds = usize::from(options.attr) + usize::from(options.text) + usize::from(options.flatten);
+        if kinds > 1 {
+            return Err(Error::new(member.span(), "only one of `attr`, `text` and `flatten` can be used"));
+        }
+        let (occurs, ty) = occurs(&field.ty);
+        let kind = if options.attr {
+            if matches!(occurs, Occurs::Repeated) {
+                return Err(Error::new(field.ty.span(), "an attribute can't be a `Vec`"));
+            }
+            FieldKind::Attribute(options.attribute_name(&member)?)
+        } else if options.text || options.flatten {
+            if options.rename.is_some() || options.ns.is_some() || options.default {
+                return Err(Error::new(member.span(), "`text` and `flatten` fields can't have `rename`, `ns` or `default`"));
+            }
+            if options.flatten {
+                return Ok(Self { member, kind: FieldKind::Flatten, occurs: Occurs::Once, ty: field.ty.clone(), default: false });
+            }
+            if matches!(occurs, Occurs::Repeated) {
+                return Err(Error::new(field.ty.span(), "text can't be a `Vec`"));
+            }
+            FieldKind::Text
+        } else {
+            FieldKind::Child(options.element_name(&member, Some(parent))?)
+        };
+        Ok(Self { member, kind, occurs, ty: ty.clone(), default: options.default })
+    }
+}
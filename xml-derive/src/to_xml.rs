@@ -0,0 +1,135 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::model::{Field, FieldKind, Input, Occurs, Variant};
+
+pub(crate) fn expand(input: &Input) -> TokenStream {
+    match input {
+        Input::Struct { ident, name, fields } => {
+            let name = name.tokens();
+            let attributes = fields.iter().filter_map(write_attribute);
+            let content = fields.iter().filter_map(write_content);
+            quote! {
+                #[automatically_derived]
+                impl ::xml::typed::ToXml for #ident {
+                    fn element_name(&self, field: ::xml::typed::__private::Option<::xml::name::Name<'static>>) -> ::xml::typed::__private::Option<::xml::name::Name<'static>> {
+                        ::xml::typed::__private::Some(field.unwrap_or(#name))
+                    }
+
+                    fn write_attributes<'a>(&'a self, start: &mut ::xml::typed::StartTag<'a>) -> ::xml::typed::__private::Result<(), ::xml::typed::Error> {
+                        #(#attributes)*
+                        ::xml::typed::__private::Ok(())
+                    }
+
+                    fn write_content<W: ::xml::typed::__private::Write>(&self, writer: &mut ::xml::EventWriter<W>) -> ::xml::typed::__private::Result<(), ::xml::typed::Error> {
+                        #(#content)*
+                        ::xml::typed::__private::Ok(())
+                    }
+                }
+            }
+        },
+        Input::Choice { ident, variants } => expand_choice(ident, variants),
+        Input::Text { ident, values } => {
+            let arms = values.iter().map(|(variant, value)| quote!(Self::#variant => #value));
+            quote! {
+                #[automatically_derived]
+                impl ::xml::typed::ToXmlText for #ident {
+                    fn to_xml_text(&self) -> ::std::borrow::Cow<'_, str> {
+                        ::std::borrow::Cow::Borrowed(match self {
+                            #(#arms,)*
+                        })
+                    }
+                }
+            }
+        },
+    }
+}
+
+fn write_attribute(field: &Field) -> Option<TokenStream> {
+    let Field { member, ty, .. } = field;
+    match &field.kind {
+        FieldKind::Attribute(name) => {
+            let name = name.tokens();
+            Some(match field.occurs {
+                Occurs::Optional => quote! {
+                    if let ::xml::typed::__private::Some(value) = &self.#member {
+                        start.attr(#name, ::xml::typed::ToXmlText::to_xml_text(value));
+                    }
+                },
+                _ => quote!(start.attr(#name, ::xml::typed::ToXmlText::to_xml_text(&self.#member));),
+            })
+        },
+        FieldKind::Flatten => Some(quote!(<#ty as ::xml::typed::ToXml>::write_attributes(&self.#member, start)?;)),
+        _ => None,
+    }
+}
+
+fn write_content(field: &Field) -> Option<TokenStream> {
+    let Field { member, ty, .. } = field;
+    let write = match &field.kind {
+        FieldKind::Attribute(_) => return None,
+        FieldKind::Text => quote!(::xml::typed::write_text(writer, value)?;),
+        FieldKind::Child(name) => {
+            let name = name.tokens();
+            quote!(::xml::typed::write_element(writer, #name, value)?;)
+        },
+        FieldKind::Flatten => return Some(quote!(<#ty as ::xml::typed::ToXml>::write_content(&self.#member, writer)?;)),
+    };
+    Some(match field.occurs {
+        Occurs::Once => quote! {
+            let value = &self.#member;
+            #write
+        },
+        Occurs::Optional => quote! {
+            if let ::xml::typed::__private::Some(value) = &self.#member {
+                #write
+            }
+        },
+        Occurs::Repeated => quote! {
+            for value in &self.#member {
+                #write
+            }
+        },
+    })
+}
+
+fn expand_choice(ident: &syn::Ident, variants: &[Variant]) -> TokenStream {
+    let names = variants.iter().map(|Variant { ident, name, ty }| {
+        let name = name.tokens();
+        match ty {
+            Some(_) => quote!(Self::#ident(_) => #name),
+            None => quote!(Self::#ident => #name),
+        }
+    });
+    let newtypes = variants.iter().filter_map(|v| Some((&v.ident, v.ty.as_ref()?))).collect::<Vec<_>>();
+    let forward = |method: TokenStream, arg: TokenStream| {
+        let arms = newtypes.iter().map(|(ident, ty)| quote!(Self::#ident(value) => <#ty as ::xml::typed::ToXml>::#method(value, #arg)));
+        quote! {
+            match self {
+                #(#arms,)*
+                #[allow(unreachable_patterns)]
+                _ => ::xml::typed::__private::Ok(()),
+            }
+        }
+    };
+    let write_attributes = forward(quote!(write_attributes), quote!(start));
+    let write_content = forward(quote!(write_content), quote!(writer));
+    quote! {
+        #[automatically_derived]
+        impl ::xml::typed::ToXml for #ident {
+            fn element_name(&self, _: ::xml::typed::__private::Option<::xml::name::Name<'static>>) -> ::xml::typed::__private::Option<::xml::name::Name<'static>> {
+                ::xml::typed::__private::Some(match self {
+                    #(#names,)*
+                })
+            }
+
+            fn write_attributes<'a>(&'a self, start: &mut ::xml::typed::StartTag<'a>) -> ::xml::typed::__private::Result<(), ::xml::typed::Error> {
+                #write_attributes
+            }
+
+            fn write_content<W: ::xml::typed::__private::Write>(&self, writer: &mut ::xml::EventWriter<W>) -> ::xml::typed::__private::Result<(), ::xml::typed::Error> {
+                #write_content
+            }
+        }
+    }
+}